@@ -0,0 +1,113 @@
+//! Device operations benchmark and correctness check against host references.
+//!
+//! Run with `cargo bench --bench device`, optionally the number of elements and the matrix size
+//! could be passed as arguments: `-- 4194304 512`.
+//!
+//! Each operation is timed on the host and on the device, and the device result is compared with the host one,
+//! so the benchmark also guards against regressions of device kernels. It exits with a nonzero status
+//! if any result differs, and succeeds without running anything if there is no OpenCL device, so it could run in CI.
+
+use std::{env, process, time::{Duration, Instant}};
+use tension::{prelude::*, DeviceContext, DeviceTensor, HostTensor, Shape, Error};
+
+
+/// Minimal time of several runs.
+fn measure(mut f: impl FnMut()) -> Duration {
+    (0..5).map(|_| {
+        let start = Instant::now();
+        f();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+struct Harness {
+    context: DeviceContext,
+    failures: usize,
+}
+
+impl Harness {
+    /// Times `host` and `device` versions of the operation `name` and compares their results.
+    ///
+    /// Device time includes waiting for the queue, so that asynchronous kernels are measured entirely.
+    fn run(
+        &mut self, name: &str,
+        host: impl Fn() -> HostTensor<f32>,
+        device: impl Fn() -> Result<DeviceTensor<f32>, Error>,
+    ) -> Result<(), Error> {
+        let host_time = measure(|| { host(); });
+        let device_time = measure(|| {
+            device().unwrap();
+            self.context.finish().unwrap();
+        });
+        let report = device()?.compare_report(&host(), 1e-4, 1e-4)?;
+        println!(
+            "{}: host {:?}, device {:?}, speedup {:.1}",
+            name, host_time, device_time, host_time.as_secs_f64() / device_time.as_secs_f64(),
+        );
+        if !report.is_close() {
+            println!("{} mismatch: {}", name, report);
+            self.failures += 1;
+        }
+        Ok(())
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let args = env::args().skip(1).filter(|a| !a.starts_with('-')).map(|a| a.parse().unwrap()).collect::<Vec<usize>>();
+    let len = args.first().cloned().unwrap_or(1 << 22);
+    let size = args.get(1).cloned().unwrap_or(256);
+    let context = match DeviceContext::best_available() {
+        Some(context) => context,
+        None => {
+            println!("No OpenCL device available, skipping");
+            return Ok(());
+        }
+    };
+    println!("Device: {}", context.device_name()?);
+
+    let shape = Shape::from([len]);
+    let data = (0..len).map(|i| (i % 251) as f32 / 251.0).collect::<Vec<_>>();
+    let a = HostTensor::from_vec(data.clone(), &shape)?;
+    let b = a.map(|x| 1.0 - x);
+    let (da, db) = (context.from_slice(&data, &shape)?, context.from_vec(b.to_vec()?, &shape)?);
+
+    let square = Shape::from([size, size]);
+    let m = HostTensor::from_vec((0..(size * size)).map(|i| (i % 17) as f32 / 17.0).collect(), &square)?;
+    let dm = context.from_vec(m.to_vec()?, &square)?;
+
+    let mut harness = Harness { context: context.clone(), failures: 0 };
+    harness.run("add", || a.add(&b).unwrap(), || (da.lazy() + db.lazy()).eval())?;
+    harness.run("matmul", || m.matmul(&m).unwrap(), || dm.matmul(&dm))?;
+    let tile = context.matmul_tile_size();
+    context.set_matmul_tile_size(None)?;
+    harness.run("matmul naive", || m.matmul(&m).unwrap(), || dm.matmul(&dm))?;
+    context.set_matmul_tile_size(tile)?;
+    harness.run("sum_axis", || m.sum_axis(0, false), || dm.sum_axis(0, false))?;
+    harness.run("prod_axis", || m.prod_axis(1, false), || dm.prod_axis(1, false))?;
+    // Rectangular products, including ones whose sizes aren't multiples of the work-group or the tile size.
+    for &(rows, inner, cols) in [(1, 513, 1), (300, 1, 257), (300, 129, 1), (1, 129, 300), (67, 3, 129)].iter() {
+        let a = HostTensor::from_vec((0..(rows * inner)).map(|i| (i % 13) as f32 / 13.0).collect(), &Shape::from([inner, rows]))?;
+        let b = HostTensor::from_vec((0..(inner * cols)).map(|i| (i % 7) as f32 / 7.0).collect(), &Shape::from([cols, inner]))?;
+        let (da, db) = (context.from_vec(a.to_vec()?, a.shape())?, context.from_vec(b.to_vec()?, b.shape())?);
+        for &(group, tile) in [(None, None), (Some(64), None), (None, Some(8)), (None, tile)].iter() {
+            context.set_work_group_size(group)?;
+            context.set_matmul_tile_size(tile)?;
+            let name = format!("matmul {}x{}x{} group {:?} tile {:?}", rows, inner, cols, group, tile);
+            harness.run(&name, || a.matmul(&b).unwrap(), || da.matmul(&db))?;
+        }
+        context.set_work_group_size(None)?;
+        context.set_matmul_tile_size(tile)?;
+    }
+    harness.run("transfer", || a.clone(), || {
+        let t = context.from_slice(&data, &shape)?;
+        let mut dst = vec![0.0; len];
+        t.load(&mut dst)?;
+        context.from_vec(dst, &shape)
+    })?;
+
+    if harness.failures > 0 {
+        println!("{} operations differ from host references", harness.failures);
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Host elementwise operations benchmark.
+//!
+//! Run with `cargo bench --no-default-features --bench elementwise`,
+//! optionally the number of elements could be passed as an argument: `-- 4194304`.
+
+use std::{env, time::{Duration, Instant}};
+use tension::{HostTensor, Shape, Prm, Num};
+
+
+/// Minimal time of several runs.
+fn measure(mut f: impl FnMut()) -> Duration {
+    (0..5).map(|_| {
+        let start = Instant::now();
+        f();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+fn compare<T: Prm + Num + From<u8>>(name: &str, len: usize) {
+    let shape = Shape::from([len]);
+    let a = HostTensor::from_vec((0..len).map(|i| T::from((i % 251) as u8)).collect(), &shape).unwrap();
+    let b = HostTensor::from_vec((0..len).map(|i| T::from((i % 13 + 1) as u8)).collect(), &shape).unwrap();
+
+    let base = measure(|| { a.zip_map(&b, |x, y| x * y).unwrap(); });
+    let time = measure(|| { a.mul(&b).unwrap(); });
+    assert!(a.mul(&b).unwrap() == a.zip_map(&b, |x, y| x * y).unwrap());
+    println!("{} x {}: zip_map {:?}, mul {:?}, speedup {:.1}", name, len, base, time, base.as_secs_f64() / time.as_secs_f64());
+}
+
+fn main() {
+    let len = env::args().skip(1).find(|a| !a.starts_with('-')).map(|a| a.parse().unwrap()).unwrap_or(1 << 22);
+    compare::<f32>("f32", len);
+    compare::<f64>("f64", len);
+}
@@ -0,0 +1,48 @@
+//! Host matrix multiplication benchmark.
+//!
+//! Run with `cargo bench --no-default-features --bench matmul`,
+//! optionally the matrix size and block sizes to try could be passed as arguments: `-- 512 16 32 64 128`.
+
+use std::{env, time::{Duration, Instant}};
+use tension::{HostTensor, Shape, MATMUL_BLOCK};
+
+
+/// Reference triple loop over plain matrices in the same layout as `HostTensor::matmul`.
+fn naive(a: &[f32], b: &[f32], m: usize, k: usize, n: usize) -> Vec<f32> {
+    (0..(m * n)).map(|i| {
+        let (q, r) = (i % n, i / n);
+        (0..k).map(|l| a[l + k * r] * b[q + n * l]).sum()
+    }).collect()
+}
+
+/// Minimal time of several runs.
+fn measure(mut f: impl FnMut()) -> Duration {
+    (0..5).map(|_| {
+        let start = Instant::now();
+        f();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+fn main() {
+    let args = env::args().skip(1).filter(|a| !a.starts_with('-')).map(|a| a.parse().unwrap()).collect::<Vec<usize>>();
+    let size = args.first().cloned().unwrap_or(512);
+    let blocks = if args.len() > 1 { args[1..].to_vec() } else { vec![16, 32, MATMUL_BLOCK, 128] };
+
+    let shape = Shape::from([size, size].as_ref());
+    let data = (0..(size * size)).map(|i| (i % 17) as f32 / 17.0).collect::<Vec<_>>();
+    let a = HostTensor::from_vec(data.clone(), &shape).unwrap();
+
+    let base = measure(|| { naive(&data, &data, size, size, size); });
+    println!("{0}x{0} naive: {1:?}", size, base);
+    let expected = naive(&data, &data, size, size, size);
+    for &block in blocks.iter() {
+        let time = measure(|| { a.matmul_blocked(&a, block).unwrap(); });
+        let c = a.matmul_blocked(&a, block).unwrap();
+        let err = c.iter().zip(expected.iter()).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max);
+        println!(
+            "{0}x{0} block {1}: {2:?}, speedup {3:.2}, max deviation {4:e}",
+            size, block, time, base.as_secs_f64() / time.as_secs_f64(), err,
+        );
+    }
+}
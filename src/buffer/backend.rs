@@ -0,0 +1,204 @@
+use crate::{Prm, Num, Interop, Error};
+
+
+/// Memory operations of a device API that `DeviceBuffer` is built on.
+///
+/// `DeviceBuffer<T, B>` allocates, fills, transfers, copies and releases its memory only through the backend `B`,
+/// so another API (e.g. CUDA or wgpu) could provide these operations by implementing it for its own context.
+/// `DeviceContext` is the OpenCL implementation and the default backend of device buffers.
+/// Buffer operations computed by kernels are provided by `BufferKernels`. Kernels of device tensors are written
+/// in OpenCL C and launched by `DeviceContext` directly, so device tensors use only the OpenCL backend for now.
+///
+/// Operations are executed in the order they were issued, transfers to and from host memory are blocking
+/// as required by `DeviceBuffer`. Equal backends execute operations in the same order, e.g. they share a queue.
+pub trait Backend<D: Copy>: Clone + PartialEq {
+    /// Handle to device memory holding elements of type `D`.
+    type Mem;
+
+    /// Checks that the device supports the extension required by the element type, see `Interop::EXTENSION`.
+    ///
+    /// Fails with `Error::ExtensionUnavailable` otherwise, backends without extensions accept all of them.
+    fn check_extension(&self, extension: &str) -> Result<(), Error> {
+        let _ = extension;
+        Ok(())
+    }
+
+    /// Allocates memory for `len` elements.
+    ///
+    /// # Safety
+    ///
+    /// Memory content is uninitialized and must be written before reading.
+    unsafe fn alloc(&self, len: usize) -> Result<Self::Mem, Error>;
+    /// Allocates memory for `len` elements filled with `value`.
+    fn alloc_filled(&self, len: usize, value: D) -> Result<Self::Mem, Error>;
+    /// Releases memory that is no longer used, e.g. to reuse it by later allocations. It is freed by default.
+    fn release(&self, mem: Self::Mem) {
+        drop(mem);
+    }
+    /// Number of elements in the memory.
+    fn mem_len(mem: &Self::Mem) -> usize;
+
+    /// Sets all the elements to `value`.
+    fn fill(&self, mem: &mut Self::Mem, value: D) -> Result<(), Error>;
+    /// Reads `dst.len()` elements starting at `offset` to the host memory.
+    fn read(&self, mem: &Self::Mem, offset: usize, dst: &mut [D]) -> Result<(), Error>;
+    /// Writes elements from the host memory starting at `offset`.
+    fn write(&self, mem: &mut Self::Mem, offset: usize, src: &[D]) -> Result<(), Error>;
+    /// Copies the first `len` elements of `src` to `dst`, both of them belong to the backend.
+    fn copy(&self, src: &Self::Mem, dst: &mut Self::Mem, len: usize) -> Result<(), Error>;
+    /// Copies the first `len` elements of `src` that belongs to the other backend `from` to `dst`, e.g. of another queue.
+    ///
+    /// Returns `false` if the memory isn't accessible by the backend, then it is copied through the host memory.
+    fn copy_across(&self, from: &Self, src: &Self::Mem, dst: &mut Self::Mem, len: usize) -> Result<bool, Error> {
+        let _ = (from, src, dst, len);
+        Ok(false)
+    }
+}
+
+/// Operations of device buffers that the backend computes by kernels rather than by transfers.
+///
+/// `DeviceContext` launches OpenCL kernels for them, see `Buffer::fill_iota` and `Buffer::scatter`.
+pub trait BufferKernels<T: Prm + Interop>: Backend<T::Dev> {
+    /// Fills the memory with arithmetic sequence, each element is `start + i * step`.
+    fn fill_iota(&self, mem: &mut Self::Mem, start: T, step: T) -> Result<(), Error> where T: Num;
+    /// Writes `src` to elements at `indices` of the memory, other elements are left intact.
+    fn scatter(&self, mem: &mut Self::Mem, indices: &[usize], src: &[T]) -> Result<(), Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+    use crate::{Buffer, DeviceBuffer, f16};
+    use super::*;
+
+    /// Backend that stores device memory on the host, counts released allocations.
+    #[derive(Clone, Default)]
+    struct HostMemory {
+        released: Rc<Cell<usize>>,
+    }
+    impl PartialEq for HostMemory {
+        fn eq(&self, other: &Self) -> bool {
+            Rc::ptr_eq(&self.released, &other.released)
+        }
+    }
+
+    impl<D: Copy + Default> Backend<D> for HostMemory {
+        type Mem = Vec<D>;
+
+        unsafe fn alloc(&self, len: usize) -> Result<Vec<D>, Error> {
+            Ok(vec![D::default(); len])
+        }
+        fn alloc_filled(&self, len: usize, value: D) -> Result<Vec<D>, Error> {
+            Ok(vec![value; len])
+        }
+        fn release(&self, _: Vec<D>) {
+            self.released.set(self.released.get() + 1);
+        }
+        fn mem_len(mem: &Vec<D>) -> usize {
+            mem.len()
+        }
+
+        fn fill(&self, mem: &mut Vec<D>, value: D) -> Result<(), Error> {
+            mem.iter_mut().for_each(|x| *x = value);
+            Ok(())
+        }
+        fn read(&self, mem: &Vec<D>, offset: usize, dst: &mut [D]) -> Result<(), Error> {
+            let range = offset..(offset + dst.len());
+            dst.copy_from_slice(mem.get(range).ok_or(Error::BadSize { expected: mem.len(), found: offset + dst.len() })?);
+            Ok(())
+        }
+        fn write(&self, mem: &mut Vec<D>, offset: usize, src: &[D]) -> Result<(), Error> {
+            let (len, range) = (mem.len(), offset..(offset + src.len()));
+            mem.get_mut(range).ok_or(Error::BadSize { expected: len, found: offset + src.len() })?.copy_from_slice(src);
+            Ok(())
+        }
+        fn copy(&self, src: &Vec<D>, dst: &mut Vec<D>, len: usize) -> Result<(), Error> {
+            dst[..len].copy_from_slice(&src[..len]);
+            Ok(())
+        }
+    }
+
+    impl<T: Prm + Interop> BufferKernels<T> for HostMemory {
+        fn fill_iota(&self, mem: &mut Vec<T::Dev>, start: T, step: T) -> Result<(), Error> where T: Num {
+            let mut x = start;
+            for (i, y) in mem.iter_mut().enumerate() {
+                if i > 0 {
+                    x = x + step;
+                }
+                *y = x.to_dev();
+            }
+            Ok(())
+        }
+        fn scatter(&self, mem: &mut Vec<T::Dev>, indices: &[usize], src: &[T]) -> Result<(), Error> {
+            for (&i, &x) in indices.iter().zip(src) {
+                mem[i] = x.to_dev();
+            }
+            Ok(())
+        }
+    }
+
+    type MockBuffer<T> = DeviceBuffer<T, HostMemory>;
+
+    #[test]
+    fn round_trip() {
+        let backend = HostMemory::default();
+        let mut buffer = MockBuffer::new_filled_in(&backend, 4, 1.5f32).unwrap();
+        let mut dst = vec![0.0; 4];
+        buffer.load(&mut dst).unwrap();
+        assert_eq!(dst, [1.5; 4]);
+        buffer.store(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        buffer.load(&mut dst).unwrap();
+        assert_eq!(dst, [1.0, 2.0, 3.0, 4.0]);
+        assert!(matches!(buffer.store(&[1.0]), Err(Error::BadSize { expected: 4, found: 1 })));
+
+        // Types with device representation other than the host one are converted.
+        let mut flags = unsafe { MockBuffer::new_uninit_in(&backend, 3).unwrap() };
+        flags.store(&[true, false, true]).unwrap();
+        let mut dst = vec![false; 3];
+        flags.load(&mut dst).unwrap();
+        assert_eq!(dst, [true, false, true]);
+        let values = [f16::from_f32(0.5), f16::from_f32(-2.0)];
+        let mut dst = [f16::from_f32(0.0); 2];
+        MockBuffer::new_filled_in(&backend, 2, f16::from_f32(0.0)).and_then(|mut buffer| {
+            buffer.store(&values)?;
+            buffer.load(&mut dst)
+        }).unwrap();
+        assert!(dst == values);
+        let mut dst = [0usize; 2];
+        MockBuffer::new_filled_in(&backend, 2, 7usize).unwrap().load(&mut dst).unwrap();
+        assert_eq!(dst, [7; 2]);
+    }
+
+    #[test]
+    fn copy_and_kernels() {
+        let (a, b) = (HostMemory::default(), HostMemory::default());
+        let mut x = MockBuffer::new_filled_in(&a, 5, 0i32).unwrap();
+        x.fill_iota(3, -2).unwrap();
+        let mut dst = vec![0; 5];
+        x.load(&mut dst).unwrap();
+        assert_eq!(dst, [3, 1, -1, -3, -5]);
+
+        // Buffers of the same backend are copied by it, of different ones through the host memory.
+        let mut y = MockBuffer::new_filled_in(&a, 5, 0).unwrap();
+        y.copy_from(&x).unwrap();
+        let mut z = x.clone_to(&b).unwrap();
+        assert!(z.context() == &b && z.context() != x.context());
+        z.scatter(1..4, [3, 1].iter().cloned(), &[7, 8]).unwrap();
+        let mut gathered = vec![0; 3];
+        z.gather(0..5, [4, 3, 1].iter().cloned(), &mut gathered).unwrap();
+        assert_eq!(gathered, [-5, 7, 8]);
+        y.load(&mut dst).unwrap();
+        assert_eq!(dst, [3, 1, -1, -3, -5]);
+
+        y.resize_buffer(7, 9).unwrap();
+        let mut dst = vec![0; 7];
+        y.load(&mut dst).unwrap();
+        assert_eq!(dst, [3, 1, -1, -3, -5, 9, 9]);
+        assert!(matches!(y.copy_from(&x), Err(Error::BadSize { .. })));
+
+        // Memory of dropped buffers is released by their backend.
+        let released = (a.released.get(), b.released.get());
+        drop((x, y, z));
+        assert_eq!((a.released.get(), b.released.get()), (released.0 + 2, released.1 + 1));
+    }
+}
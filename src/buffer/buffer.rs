@@ -1,4 +1,5 @@
-use crate::{Prm};
+use std::ops::Range;
+use crate::{Prm, Num, Error};
 
 
 /// Flat contiguous array.
@@ -8,9 +9,13 @@ pub trait Buffer<T: Prm>: Sized + Clone {
 
     /// Create uninitialzed buffer.
     /// This is unsafe method, but it is helpful for pre-allocation of storage for some operations.
-    unsafe fn new_uninit_in(context: &Self::Context, len: usize) -> Self;
+    ///
+    /// # Safety
+    ///
+    /// Buffer content must be written before reading.
+    unsafe fn new_uninit_in(context: &Self::Context, len: usize) -> Result<Self, Error>;
     /// Create buffer filled with a single value.
-    fn new_filled_in(context: &Self::Context, len: usize, value: T) -> Self;
+    fn new_filled_in(context: &Self::Context, len: usize, value: T) -> Result<Self, Error>;
 
     /// Returns the length of the buffer.
     fn len(&self) -> usize;
@@ -18,12 +23,38 @@ pub trait Buffer<T: Prm>: Sized + Clone {
     fn context(&self) -> &Self::Context;
 
     /// Loads data from buffer to slice.
-    fn load(&self, dst: &mut [T]);
+    /// Fails with `Error::BadSize` if the slice length differs from the buffer one.
+    fn load(&self, dst: &mut [T]) -> Result<(), Error>;
     /// Stores data from slice to buffer.
-    fn store(&mut self, src: &[T]);
+    /// Fails with `Error::BadSize` if the slice length differs from the buffer one.
+    fn store(&mut self, src: &[T]) -> Result<(), Error>;
+
+    /// Loads elements at buffer `indices` to `dst` in order, e.g. elements of a strided view.
+    ///
+    /// All the indices must lie in `span`. Host buffers read the elements in place,
+    /// device buffers transfer only the `span` rather than the whole buffer.
+    fn gather<I: Iterator<Item = usize>>(&self, span: Range<usize>, indices: I, dst: &mut [T]) -> Result<(), Error>;
+    /// Stores `src` to elements at buffer `indices` in order, other elements are left intact.
+    ///
+    /// All the indices must lie in `span`. If an index is repeated, one of its values is stored.
+    fn scatter<I: Iterator<Item = usize>>(&mut self, span: Range<usize>, indices: I, src: &[T]) -> Result<(), Error>;
 
     /// Copies content to `self` from another buffer.
-    fn copy_from(&mut self, src: &Self);
+    /// Fails with `Error::BadSize` if the buffer lengths differ.
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error>;
     /// Copies content from `self` to another buffer.
-    fn copy_to(&self, dst: &mut Self);
+    /// Fails with `Error::BadSize` if the buffer lengths differ.
+    fn copy_to(&self, dst: &mut Self) -> Result<(), Error>;
+
+    /// Changes the length of the buffer keeping the content of the overlapping part.
+    /// New elements are filled with `fill`.
+    fn resize_buffer(&mut self, new_len: usize, fill: T) -> Result<(), Error>;
+
+    /// Fills the buffer with arithmetic sequence `start, start + step, start + 2 * step, ...`.
+    ///
+    /// Host buffers add `step` to the previous element, so integer overflow panics in debug builds as ordinary arithmetic does,
+    /// and rounding errors of floating-point elements accumulate.
+    /// Device buffers compute each element as `start + i * step`, so unsigned integers wrap around on overflow,
+    /// while overflow of signed `int` and `long` is undefined in OpenCL C.
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num;
 }
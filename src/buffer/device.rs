@@ -1,112 +1,719 @@
 use crate::{
-    Prm, Interop,
-    Buffer, HostBuffer,
+    Prm, Num, Interop, Error,
+    Backend, BufferKernels, Buffer, HostBuffer, BufferPool, PoolStats, TraceEvent, check_len, extensions,
+};
+
+#[cfg(feature = "trace")]
+use crate::{TraceHook, TraceSlot};
+#[cfg(feature = "trace")]
+use std::time::Instant;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    ops::Range,
+    mem::{self, ManuallyDrop},
+    sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+    time::Duration,
+};
+use ocl::{
+    OclPrm, Buffer as OclBuffer, Queue, MemFlags, Program, Platform, Device, Context, Kernel, Event,
+    flags::CommandQueueProperties, builders::KernelBuilder,
+    enums::{ProfilingInfo, DeviceInfo, DeviceInfoResult},
 };
 
-use ocl::{Buffer as OclBuffer, Queue, MemFlags};
 
+/// Default tile size of the device matrix product, `256` work items per group are supported by most devices.
+pub const DEFAULT_MATMUL_TILE: usize = 16;
 
-/// Buffer context.
-/// Determines buffer context.
+#[derive(Debug)]
+struct ContextInner {
+    queue: Queue,
+    programs: Mutex<HashMap<String, Program>>,
+    /// Options passed to the OpenCL compiler when programs are built.
+    compiler_options: Mutex<String>,
+    /// Sources of user-provided kernels by name.
+    kernels: Mutex<HashMap<String, String>>,
+    /// Execution times of kernels, `None` if profiling is disabled.
+    profile: Option<Mutex<Vec<(String, Duration)>>>,
+    /// Local work-group size of kernels, zero means that OpenCL implementation chooses it.
+    work_group_size: AtomicUsize,
+    /// Tile size of the matrix product kernel, zero means the naive kernel.
+    matmul_tile: AtomicUsize,
+    /// Released buffers kept for reuse, disabled by default.
+    pool: BufferPool,
+    #[cfg(feature = "trace")]
+    trace_hook: TraceSlot,
+}
+
+/// Handle to the device state: OpenCL queue and compiled programs.
+///
+/// Device tensors and buffers are created in a context and share it,
+/// cloning the context is cheap.
+///
+/// Context is `Send` and `Sync`: compiled programs and profile are guarded by mutexes,
+/// and OpenCL queue could be used from multiple threads. Commands are executed in the order they were enqueued.
 #[derive(Clone, Debug)]
 pub struct DeviceContext {
-    queue: Queue,
+    inner: Arc<ContextInner>,
 }
 impl DeviceContext {
+    fn from_queue(queue: Queue, profiling: bool) -> Self {
+        Self { inner: Arc::new(ContextInner {
+            queue,
+            programs: Mutex::new(HashMap::new()),
+            compiler_options: Mutex::new(String::new()),
+            kernels: Mutex::new(HashMap::new()),
+            profile: if profiling { Some(Mutex::new(Vec::new())) } else { None },
+            work_group_size: AtomicUsize::new(0),
+            matmul_tile: AtomicUsize::new(DEFAULT_MATMUL_TILE),
+            pool: BufferPool::default(),
+            #[cfg(feature = "trace")]
+            trace_hook: TraceSlot::default(),
+        }) }
+    }
+    /// Create context that uses specified queue.
     pub fn new(queue: Queue) -> Self {
-        Self { queue }
+        Self::from_queue(queue, false)
+    }
+    /// Create context with profiling enabled, see `profile`.
+    ///
+    /// Queue must be created with `CommandQueueProperties::PROFILING_ENABLE`.
+    pub fn new_profiling(queue: Queue) -> Self {
+        Self::from_queue(queue, true)
+    }
+    fn device_queue(platform: Platform, device: Device, properties: Option<CommandQueueProperties>) -> Result<Queue, Error> {
+        let context = Context::builder().platform(platform).devices(device).build()?;
+        Ok(Queue::new(&context, device, properties)?)
+    }
+    fn default_queue(properties: Option<CommandQueueProperties>) -> Result<Queue, Error> {
+        let platform = Platform::first().map_err(|_| Error::DeviceUnavailable)?;
+        let device = Device::first(platform).map_err(|_| Error::DeviceUnavailable)?;
+        Self::device_queue(platform, device, properties)
+    }
+    /// All devices of all platforms, ordered by platform and then by device.
+    fn all_devices() -> Vec<(Platform, Device)> {
+        Platform::list().into_iter().flat_map(|platform| {
+            Device::list_all(platform).unwrap_or_default().into_iter().map(move |device| (platform, device))
+        }).collect()
+    }
+    /// Number of available devices, see `new_device`.
+    pub fn device_count() -> usize {
+        Self::all_devices().len()
+    }
+    /// Create context on the `index`-th device, devices of all platforms are counted, see `device_count`.
+    ///
+    /// Fails with `Error::DeviceUnavailable` if there is no such device.
+    pub fn new_device(index: usize) -> Result<Self, Error> {
+        let (platform, device) = Self::all_devices().get(index).cloned().ok_or(Error::DeviceUnavailable)?;
+        Self::device_queue(platform, device, None).map(Self::new)
+    }
+    /// Create separate contexts on all available devices, e.g. to use multiple GPUs.
+    ///
+    /// Tensors of different contexts cannot be used in the same operation, see `DeviceTensor::to_context`.
+    pub fn new_all() -> Result<Vec<Self>, Error> {
+        Self::all_devices().into_iter().map(|(platform, device)| {
+            Self::device_queue(platform, device, None).map(Self::new)
+        }).collect()
+    }
+    /// Name of the device.
+    pub fn device_name(&self) -> Result<String, Error> {
+        Ok(self.queue().device().name()?)
+    }
+    /// Create context on the first device of the first platform.
+    ///
+    /// Fails with `Error::DeviceUnavailable` if there is no OpenCL platform or device.
+    pub fn new_default() -> Result<Self, Error> {
+        Self::default_queue(None).map(Self::new)
     }
+    /// Create context on the first device of the first platform with profiling enabled.
+    pub fn new_default_profiling() -> Result<Self, Error> {
+        Self::default_queue(Some(CommandQueueProperties::PROFILING_ENABLE)).map(Self::new_profiling)
+    }
+    /// Default context if a device is available.
+    ///
+    /// Returns `None` if there is no device or it fails to initialize,
+    /// so that the caller could fall back to host tensors, e.g. on machines without GPU.
+    pub fn best_available() -> Option<Self> {
+        Self::new_default().ok()
+    }
+
     pub fn queue(&self) -> &Queue {
-        return &self.queue
+        &self.inner.queue
+    }
+
+    /// Returns program built from the source.
+    ///
+    /// Programs are cached, so the same source is compiled only once per context.
+    pub(crate) fn program(&self, src: &str) -> Result<Program, Error> {
+        let mut programs = self.inner.programs.lock().unwrap();
+        if let Some(program) = programs.get(src) {
+            return Ok(program.clone());
+        }
+        let program = Program::builder()
+            .src(src)
+            .cmplr_opt(self.inner.compiler_options.lock().unwrap().as_str())
+            .devices(self.queue().device())
+            .build(&self.queue().context())?;
+        programs.insert(src.to_string(), program.clone());
+        Ok(program)
+    }
+
+    /// Sets options passed to the OpenCL compiler, e.g. `-cl-fast-relaxed-math -cl-mad-enable`.
+    ///
+    /// No options are passed by default, so kernels follow the precision requirements of OpenCL.
+    /// Options that relax them trade accuracy (e.g. of `exp` or of NaN and infinity handling) for speed.
+    /// Programs compiled before are discarded and kernels are rebuilt with the new options when they are used next time,
+    /// so invalid options cause `Error::KernelBuildFailed` on the next operation.
+    pub fn set_compiler_options(&self, options: &str) {
+        let mut programs = self.inner.programs.lock().unwrap();
+        *self.inner.compiler_options.lock().unwrap() = options.to_string();
+        programs.clear();
+    }
+    /// Options passed to the OpenCL compiler, see `set_compiler_options`.
+    pub fn compiler_options(&self) -> String {
+        self.inner.compiler_options.lock().unwrap().clone()
+    }
+
+    /// Registers OpenCL C source containing kernel function `name`, replacing previously registered one.
+    ///
+    /// The source is compiled immediately, so it fails with `Error::KernelBuildFailed` if it is invalid.
+    pub fn register_kernel(&self, name: &str, src: &str) -> Result<(), Error> {
+        self.program(src)?;
+        self.inner.kernels.lock().unwrap().insert(name.to_string(), src.to_string());
+        Ok(())
+    }
+    /// Source of the kernel registered under the `name`.
+    pub(crate) fn kernel_source(&self, name: &str) -> Option<String> {
+        self.inner.kernels.lock().unwrap().get(name).cloned()
+    }
+
+    /// Enqueues the kernel.
+    ///
+    /// If profiling is enabled, waits for the kernel to complete and records its execution time under the `name`.
+    ///
+    /// # Safety
+    ///
+    /// Kernel code must be trusted, see `ocl::Kernel::enq`.
+    pub(crate) unsafe fn run(&self, name: &str, kernel: &Kernel) -> Result<(), Error> {
+        let work_size = kernel.default_global_work_size().to_len();
+        self.traced(TraceEvent::Kernel { name, work_size }, || self.enqueue(name, kernel))
+    }
+    unsafe fn enqueue(&self, name: &str, kernel: &Kernel) -> Result<(), Error> {
+        match &self.inner.profile {
+            None => kernel.enq()?,
+            Some(profile) => {
+                let mut event = Event::empty();
+                kernel.cmd().enew(&mut event).enq()?;
+                event.wait_for().map_err(ocl::Error::from)?;
+                let time = |info| event.profiling_info(info).and_then(|r| Ok(r.time()?));
+                let (start, end) = (time(ProfilingInfo::Start)?, time(ProfilingInfo::End)?);
+                let duration = Duration::from_nanos(end.saturating_sub(start));
+                profile.lock().unwrap().push((name.to_string(), duration));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the callback that receives each kernel launch and host-device transfer of the context, `None` removes it.
+    ///
+    /// The callback is called after the command is issued with the host time it took, e.g. to record it
+    /// in a `tracing` span or a log. Transfers are blocking, so their time includes the transfer itself,
+    /// while kernels only are enqueued unless profiling is enabled, see `profile` for the device time.
+    /// Without the `trace` feature commands aren't reported and there is no overhead.
+    #[cfg(feature = "trace")]
+    pub fn set_trace_hook(&self, hook: Option<TraceHook>) {
+        self.inner.trace_hook.set(hook);
+    }
+    /// Runs the command `f`, reporting it to the trace hook if it is set.
+    #[cfg(feature = "trace")]
+    pub(crate) fn traced<R>(&self, event: TraceEvent<'_>, f: impl FnOnce() -> Result<R, Error>) -> Result<R, Error> {
+        match self.inner.trace_hook.get() {
+            None => f(),
+            Some(hook) => {
+                let start = Instant::now();
+                let result = f();
+                hook(&event, start.elapsed());
+                result
+            }
+        }
+    }
+    #[cfg(not(feature = "trace"))]
+    #[inline]
+    pub(crate) fn traced<R>(&self, _event: TraceEvent<'_>, f: impl FnOnce() -> Result<R, Error>) -> Result<R, Error> {
+        f()
+    }
+
+    /// Whether queues of both contexts belong to the same OpenCL context, e.g. they are queues of the same device.
+    ///
+    /// Buffers of such contexts are copied on the device without staging through the host memory.
+    pub fn same_cl_context(&self, other: &Self) -> bool {
+        self.queue().context().as_ptr() == other.queue().context().as_ptr()
+    }
+
+    /// Waits until all commands enqueued in the context complete.
+    ///
+    /// It isn't required for memory safety (see `DeviceBuffer`), but it is needed
+    /// to measure the time of device operations or to release the device memory before allocating more.
+    pub fn finish(&self) -> Result<(), Error> {
+        self.queue().finish().map_err(Error::from)
+    }
+
+    /// Sets local work-group size used by kernels launched in the context.
+    ///
+    /// Optimal size is device-dependent, `None` (default) lets OpenCL implementation choose it.
+    /// Fails with `Error::InvalidWorkGroupSize` if size is zero or exceeds the maximum size supported by the device.
+    pub fn set_work_group_size(&self, size: Option<usize>) -> Result<(), Error> {
+        if let Some(size) = size {
+            if size == 0 || size > self.queue().device().max_wg_size()? {
+                return Err(Error::InvalidWorkGroupSize);
+            }
+        }
+        self.inner.work_group_size.store(size.unwrap_or(0), Ordering::Relaxed);
+        Ok(())
+    }
+    /// Local work-group size used by kernels, see `set_work_group_size`.
+    pub fn work_group_size(&self) -> Option<usize> {
+        match self.inner.work_group_size.load(Ordering::Relaxed) {
+            0 => None,
+            size => Some(size),
+        }
+    }
+    /// Sets the size of square tiles of operands which the device matrix product loads into local memory.
+    ///
+    /// Each work-group has `size * size` items and holds two tiles of accumulator type in local memory,
+    /// `DEFAULT_MATMUL_TILE` by default. The naive kernel reading operands from global memory is used if the size is `None`,
+    /// e.g. to compare their performance, and also if the compiled kernel doesn't support work-groups of this size
+    /// or the tiles don't fit in local memory of the device, which is checked on each product.
+    /// The work-group size set by `set_work_group_size` doesn't affect the tiled kernel.
+    /// Fails with `Error::InvalidWorkGroupSize` if size is zero or its square exceeds the maximum work-group size.
+    pub fn set_matmul_tile_size(&self, size: Option<usize>) -> Result<(), Error> {
+        if let Some(size) = size {
+            if size == 0 || size.saturating_mul(size) > self.queue().device().max_wg_size()? {
+                return Err(Error::InvalidWorkGroupSize);
+            }
+        }
+        self.inner.matmul_tile.store(size.unwrap_or(0), Ordering::Relaxed);
+        Ok(())
+    }
+    /// Tile size of the matrix product, see `set_matmul_tile_size`.
+    pub fn matmul_tile_size(&self) -> Option<usize> {
+        match self.inner.matmul_tile.load(Ordering::Relaxed) {
+            0 => None,
+            size => Some(size),
+        }
+    }
+
+    /// Sets work sizes of a kernel that processes `len` items.
+    ///
+    /// Global size is rounded up to a multiple of work-group size, so the kernel must check item index.
+    pub(crate) fn set_work_size(&self, builder: &mut KernelBuilder, len: usize) {
+        match self.work_group_size() {
+            Some(size) => builder.global_work_size(len.div_ceil(size) * size).local_work_size(size),
+            None => builder.global_work_size(len),
+        };
+    }
+
+    /// Enables or disables reuse of released device buffers, disabled by default.
+    ///
+    /// When enabled, dropped buffers are cached by element type and length instead of being freed,
+    /// and new buffers of the same type and length are taken from the cache, so training loops that allocate
+    /// the same temporaries each step avoid driver allocation calls. Cached buffers aren't freed automatically:
+    /// they are released when the pool is disabled, by `clear_pool`, and when an allocation runs out of device memory.
+    /// Disabling the pool releases cached buffers.
+    ///
+    /// Buffers are reused only within the context, whose queue is in-order, so commands enqueued before a buffer
+    /// was released complete before the commands of its new owner.
+    pub fn set_pool_enabled(&self, enabled: bool) {
+        self.inner.pool.set_enabled(enabled);
+    }
+    /// Whether released buffers are reused, see `set_pool_enabled`.
+    pub fn is_pool_enabled(&self) -> bool {
+        self.inner.pool.is_enabled()
+    }
+    /// Counters of allocations served by the pool and the size of the cache, see `set_pool_enabled`.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.inner.pool.stats()
+    }
+    /// Releases all cached buffers, keeping the pool enabled.
+    pub fn clear_pool(&self) {
+        self.inner.pool.clear();
+    }
+    pub(crate) fn pool(&self) -> &BufferPool {
+        &self.inner.pool
+    }
+
+    /// Whether kernel execution times are recorded.
+    pub fn is_profiling(&self) -> bool {
+        self.inner.profile.is_some()
+    }
+    /// Names and execution times of kernels launched in the context, in the launch order.
+    ///
+    /// Empty if profiling is disabled.
+    /// Note that profiling waits for each kernel to complete, so it adds synchronization overhead.
+    pub fn profile(&self) -> Vec<(String, Duration)> {
+        self.inner.profile.as_ref().map(|p| p.lock().unwrap().clone()).unwrap_or_default()
+    }
+    /// Execution time of the last launched kernel, `None` if profiling is disabled or nothing was launched.
+    pub fn last_op_time(&self) -> Option<Duration> {
+        self.inner.profile.as_ref().and_then(|p| p.lock().unwrap().last().map(|(_, t)| *t))
+    }
+    /// Clears recorded execution times.
+    pub fn clear_profile(&self) {
+        if let Some(p) = &self.inner.profile {
+            p.lock().unwrap().clear();
+        }
+    }
+}
+impl DeviceContext {
+    /// Checks that the device supports the extension.
+    ///
+    /// Fails with `Error::ExtensionUnavailable` otherwise.
+    pub fn check_extension(&self, extension: &str) -> Result<(), Error> {
+        match self.queue().device().info(DeviceInfo::Extensions)? {
+            DeviceInfoResult::Extensions(list) if list.split_whitespace().any(|e| e == extension) => Ok(()),
+            _ => Err(Error::ExtensionUnavailable(extension.to_string())),
+        }
     }
 }
 impl PartialEq for DeviceContext {
     fn eq(&self, other: &Self) -> bool {
-        self.queue.as_ptr() == other.queue.as_ptr()
+        self.queue().as_ptr() == other.queue().as_ptr()
     }
 }
 
-/// Buffer that stores data on device. Wrapper over OpenCL buffer.
-pub struct DeviceBuffer<T: Prm + Interop> {
-    mem: OclBuffer<T::Dev>,
-    ctx: DeviceContext,
+impl DeviceContext {
+    /// Creates new device memory, if the device is out of memory the cached buffers are released and it is retried.
+    fn allocate<M>(&self, build: impl Fn() -> Result<M, Error>) -> Result<M, Error> {
+        match build() {
+            Err(Error::OutOfDeviceMemory) if self.pool().clear() > 0 => build(),
+            result => result,
+        }
+    }
 }
 
-impl<T: Prm + Interop> Buffer<T> for DeviceBuffer<T> {
-    type Context = DeviceContext;
+/// OpenCL implementation of the memory operations, they are enqueued to the queue of the context.
+///
+/// Memory is taken from and released to the buffer pool of the context if it is enabled, see `set_pool_enabled`.
+/// Transfers are reported to the trace hook, see `set_trace_hook`.
+impl<D: OclPrm> Backend<D> for DeviceContext {
+    type Mem = OclBuffer<D>;
 
-    unsafe fn new_uninit_in(context: &DeviceContext, len: usize) -> Self {
-        OclBuffer::builder()
-        .queue(context.queue().clone())
+    fn check_extension(&self, extension: &str) -> Result<(), Error> {
+        DeviceContext::check_extension(self, extension)
+    }
+
+    unsafe fn alloc(&self, len: usize) -> Result<OclBuffer<D>, Error> {
+        if let Some(mem) = self.pool().take(len, mem::size_of::<D>()) {
+            return Ok(mem);
+        }
+        self.allocate(|| Ok(OclBuffer::builder()
+        .queue(self.queue().clone())
         .flags(MemFlags::READ_WRITE)
         .len(len)
-        .build()
-        .map(|mem| DeviceBuffer { mem, ctx: context.clone() })
-        .unwrap()
+        .build()?))
     }
-
-    fn new_filled_in(context: &DeviceContext, len: usize, value: T) -> Self {
-        OclBuffer::builder()
-        .queue(context.queue().clone())
+    fn alloc_filled(&self, len: usize, value: D) -> Result<OclBuffer<D>, Error> {
+        if let Some(mut mem) = self.pool().take(len, mem::size_of::<D>()) {
+            self.fill(&mut mem, value)?;
+            return Ok(mem);
+        }
+        self.allocate(|| Ok(OclBuffer::builder()
+        .queue(self.queue().clone())
         .flags(MemFlags::READ_WRITE)
         .len(len)
-        .fill_val(value.to_dev())
-        .build()
-        .map(|mem| DeviceBuffer { mem, ctx: context.clone() })
-        .unwrap()
+        .fill_val(value)
+        .build()?))
+    }
+    fn release(&self, mem: OclBuffer<D>) {
+        let len = mem.len();
+        self.pool().put(mem, len, mem::size_of::<D>());
+    }
+    fn mem_len(mem: &OclBuffer<D>) -> usize {
+        mem.len()
+    }
+
+    fn fill(&self, mem: &mut OclBuffer<D>, value: D) -> Result<(), Error> {
+        if mem.len() > 0 {
+            mem.cmd().queue(self.queue()).fill(value, None).enq()?;
+        }
+        Ok(())
+    }
+    fn read(&self, mem: &OclBuffer<D>, offset: usize, dst: &mut [D]) -> Result<(), Error> {
+        let bytes = mem::size_of_val(dst);
+        self.traced(TraceEvent::Read { bytes }, || Ok(mem.read(dst).queue(self.queue()).offset(offset).enq()?))
+    }
+    fn write(&self, mem: &mut OclBuffer<D>, offset: usize, src: &[D]) -> Result<(), Error> {
+        let bytes = mem::size_of_val(src);
+        self.traced(TraceEvent::Write { bytes }, || Ok(mem.write(src).queue(self.queue()).offset(offset).enq()?))
+    }
+    fn copy(&self, src: &OclBuffer<D>, dst: &mut OclBuffer<D>, len: usize) -> Result<(), Error> {
+        Ok(src.copy(dst, None, Some(len)).queue(self.queue()).enq()?)
+    }
+    /// Buffers of the same OpenCL context are copied on the device, see `same_cl_context`.
+    fn copy_across(&self, from: &Self, src: &OclBuffer<D>, dst: &mut OclBuffer<D>, len: usize) -> Result<bool, Error> {
+        if !self.same_cl_context(from) {
+            return Ok(false);
+        }
+        // Commands of different queues aren't ordered, so pending writes to the source are waited for.
+        from.finish()?;
+        self.copy(src, dst, len)?;
+        // A pooled source may be reused by its own queue right after it is dropped, so the copy is waited for.
+        if from.is_pool_enabled() {
+            self.finish()?;
+        }
+        Ok(true)
+    }
+}
+
+/// Kernel filling a buffer with arithmetic sequence, each work item computes a single element.
+const IOTA_SRC: &str = "
+__kernel void iota(
+    uint len, T start, T step,
+    __global T *x
+) {
+    uint i = get_global_id(0);
+    if (i >= len) {
+        return;
+    }
+    x[i] = start + (T)i * step;
+}
+";
+
+/// Kernel writing elements of `x` to `y` at `indices`, each work item writes a single element.
+const SCATTER_SRC: &str = "
+__kernel void scatter(
+    uint count,
+    __global const uint *indices,
+    __global const T *x,
+    __global T *y
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    y[indices[i]] = x[i];
+}
+";
+
+/// OpenCL kernels of buffer operations.
+impl<T: Prm + Interop> BufferKernels<T> for DeviceContext {
+    fn fill_iota(&self, mem: &mut OclBuffer<T::Dev>, start: T, step: T) -> Result<(), Error> where T: Num {
+        let len = mem.len();
+        if len == 0 {
+            return Ok(());
+        }
+        if u32::try_from(len).is_err() {
+            return Err(Error::Overflow);
+        }
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, IOTA_SRC);
+        let program = self.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("iota")
+        .queue(self.queue().clone())
+        .arg(len as u32)
+        .arg(start.to_dev())
+        .arg(step.to_dev())
+        .arg(&*mem);
+        self.set_work_size(&mut builder, len);
+        let kernel = builder.build()?;
+        unsafe { self.run("iota", &kernel) }
+    }
+    /// Values and indices are uploaded and written by a kernel, so elements between the indices aren't rewritten
+    /// and stay intact for other users of the buffer.
+    fn scatter(&self, mem: &mut OclBuffer<T::Dev>, indices: &[usize], src: &[T]) -> Result<(), Error> {
+        check_len(src.len(), indices.len())?;
+        let count = src.len();
+        if count == 0 {
+            return Ok(());
+        }
+        if u32::try_from(mem.len()).is_err() {
+            return Err(Error::Overflow);
+        }
+        let index_data = indices.iter().map(|&i| i as u32).collect::<Vec<_>>();
+        let mut index_mem = unsafe { Backend::<u32>::alloc(self, count)? };
+        self.write(&mut index_mem, 0, &index_data)?;
+        let mut values = unsafe { Backend::<T::Dev>::alloc(self, count)? };
+        T::store_to_buffer(self, &mut values, src)?;
+
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, SCATTER_SRC);
+        let program = self.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("scatter")
+        .queue(self.queue().clone())
+        .arg(count as u32)
+        .arg(&index_mem)
+        .arg(&values)
+        .arg(&*mem);
+        self.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        let result = unsafe { self.run("scatter", &kernel) };
+        // The queue is in-order, so the temporaries are reused only after the kernel reads them.
+        self.release(index_mem);
+        self.release(values);
+        result
+    }
+}
+
+/// Buffer that stores data on device. Wrapper over memory of the backend `B`, OpenCL buffer by default.
+///
+/// # Lifetime of pending operations
+///
+/// Kernels are enqueued without waiting for them to complete, so a buffer may be dropped
+/// while a kernel still reads or writes it. This is safe: OpenCL releases the memory object only after it isn't referenced
+/// and all enqueued commands that use it have finished, so `Drop` doesn't have to synchronize.
+/// Transfers between host and device memory (`load`, `store` and the like) are blocking,
+/// so host memory is never accessed by the device after they return.
+/// New asynchronous operations must keep the same contract: they may not borrow host memory beyond the call
+/// unless they also hold it until their event completes. Use `DeviceContext::finish` to wait for all pending commands.
+/// Other backends must provide the same guarantees, see `Backend`.
+///
+/// `Drop` passes the memory to `Backend::release`. If the buffer pool of the context is enabled, it is kept in the pool
+/// and reused by later allocations of the same context, see `DeviceContext::set_pool_enabled`.
+pub struct DeviceBuffer<T: Prm + Interop, B: Backend<T::Dev> = DeviceContext> {
+    /// Taken out on drop to release it to the backend.
+    mem: ManuallyDrop<B::Mem>,
+    ctx: B,
+}
+
+impl<T: Prm + Interop, B: BufferKernels<T>> Buffer<T> for DeviceBuffer<T, B> {
+    type Context = B;
+
+    unsafe fn new_uninit_in(context: &B, len: usize) -> Result<Self, Error> {
+        if let Some(ext) = T::EXTENSION {
+            context.check_extension(ext)?;
+        }
+        Ok(Self::from_mem(context.alloc(len)?, context))
+    }
+
+    fn new_filled_in(context: &B, len: usize, value: T) -> Result<Self, Error> {
+        if let Some(ext) = T::EXTENSION {
+            context.check_extension(ext)?;
+        }
+        Ok(Self::from_mem(context.alloc_filled(len, value.to_dev())?, context))
     }
 
     fn len(&self) -> usize {
-        self.mem.len()
+        B::mem_len(&self.mem)
     }
-    fn context(&self) -> &DeviceContext {
+    fn context(&self) -> &B {
         &self.ctx
     }
 
-    fn load(&self, dst: &mut [T]) {
-        T::load_from_buffer(dst, &self.mem);
+    fn load(&self, dst: &mut [T]) -> Result<(), Error> {
+        T::load_from_buffer(&self.ctx, dst, &self.mem)
+    }
+    fn store(&mut self, src: &[T]) -> Result<(), Error> {
+        T::store_to_buffer(&self.ctx, &mut self.mem, src)
+    }
+
+    fn gather<I: Iterator<Item = usize>>(&self, span: Range<usize>, indices: I, dst: &mut [T]) -> Result<(), Error> {
+        let mut data = vec![T::zero(); span.len()];
+        self.load_range(span.start, &mut data)?;
+        for (d, i) in dst.iter_mut().zip(indices) {
+            *d = data[i - span.start];
+        }
+        Ok(())
     }
-    fn store(&mut self, src: &[T]) {
-        T::store_to_buffer(&mut self.mem, src);
+    fn scatter<I: Iterator<Item = usize>>(&mut self, _: Range<usize>, indices: I, src: &[T]) -> Result<(), Error> {
+        let indices = indices.take(src.len()).collect::<Vec<_>>();
+        self.ctx.scatter(&mut self.mem, &indices, src)
     }
-    fn copy_from(&mut self, src: &Self) {
-        assert_eq!(self.len(), src.len());
-        if self.context() == src.context() {
-            src.mem.copy(&mut self.mem, None, None).enq().unwrap();
+
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error> {
+        check_len(self.len(), src.len())?;
+        let len = self.len();
+        if self.ctx == src.ctx {
+            self.ctx.copy(&src.mem, &mut self.mem, len)
+        } else if self.ctx.copy_across(&src.ctx, &src.mem, &mut self.mem, len)? {
+            Ok(())
         } else {
-            let mut tmp = Vec::<T::Dev>::new();
-            src.mem.read(&mut tmp).enq()
-            .and_then(|_| self.mem.write(tmp.as_slice()).enq())
-            .unwrap();
+            let mut tmp = vec![T::Dev::default(); len];
+            src.ctx.read(&src.mem, 0, &mut tmp)?;
+            self.ctx.write(&mut self.mem, 0, &tmp)
+        }
+    }
+    fn copy_to(&self, dst: &mut Self) -> Result<(), Error> {
+        dst.copy_from(self)
+    }
+
+    /// Device memory cannot be resized, so a new buffer is allocated and the overlapping part is copied to it.
+    fn resize_buffer(&mut self, new_len: usize, fill: T) -> Result<(), Error> {
+        if new_len == self.len() {
+            return Ok(());
+        }
+        let mut dst = Self::new_filled_in(&self.ctx, new_len, fill)?;
+        let overlap = usize::min(self.len(), new_len);
+        if overlap > 0 {
+            self.ctx.copy(&self.mem, &mut dst.mem, overlap)?;
+        }
+        *self = dst;
+        Ok(())
+    }
+
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num {
+        self.ctx.fill_iota(&mut self.mem, start, step)
+    }
+}
+
+impl<T: Prm + Interop, B: Backend<T::Dev>> Drop for DeviceBuffer<T, B> {
+    fn drop(&mut self) {
+        // The memory isn't used after being taken.
+        let mem = unsafe { ManuallyDrop::take(&mut self.mem) };
+        self.ctx.release(mem);
+    }
+}
+
+impl<T: Prm + Interop, B: Backend<T::Dev>> DeviceBuffer<T, B> {
+    fn from_mem(mem: B::Mem, context: &B) -> Self {
+        DeviceBuffer { mem: ManuallyDrop::new(mem), ctx: context.clone() }
+    }
+
+    /// Underlying memory of the backend, OpenCL buffer by default.
+    pub(crate) fn mem(&self) -> &B::Mem {
+        &self.mem
+    }
+
+    /// Copies data from buffer starting at `offset` to slice.
+    pub(crate) fn load_range(&self, offset: usize, dst: &mut [T]) -> Result<(), Error> {
+        let mut tmp = vec![T::Dev::default(); dst.len()];
+        self.ctx.read(&self.mem, offset, &mut tmp)?;
+        for (d, &s) in dst.iter_mut().zip(tmp.iter()) {
+            *d = T::from_dev(s);
         }
+        Ok(())
     }
-    fn copy_to(&self, dst: &mut Self) {
-        dst.copy_from(self);
+    /// Copies data from slice to buffer starting at `offset`.
+    pub(crate) fn store_range(&mut self, offset: usize, src: &[T]) -> Result<(), Error> {
+        let tmp = src.iter().map(|x| x.to_dev()).collect::<Vec<_>>();
+        self.ctx.write(&mut self.mem, offset, &tmp)
     }
 }
 
-impl<T: Prm + Interop> DeviceBuffer<T> {
+impl<T: Prm + Interop, B: BufferKernels<T>> DeviceBuffer<T, B> {
     /// Copies content to `self` from host buffer.
-    pub fn copy_from_host(&mut self, src: &HostBuffer<T>) {
-        assert_eq!(self.len(), src.len());
-        self.store(src.as_slice());
+    pub fn copy_from_host(&mut self, src: &HostBuffer<T>) -> Result<(), Error> {
+        self.store(src.as_slice())
     }
     /// Copies content from `self` to host buffer.
-    pub fn copy_to_host(&self, dst: &mut HostBuffer<T>) {
-        assert_eq!(self.len(), dst.len());
-        self.load(dst.as_mut_slice());
+    pub fn copy_to_host(&self, dst: &mut HostBuffer<T>) -> Result<(), Error> {
+        self.load(dst.as_mut_slice())
     }
 
     /// Creates a new buffer in a specified context and copies the content to it.
-    pub fn clone_to(&self, context: &DeviceContext) -> Self {
-        let mut dst = unsafe { Self::new_uninit_in(context, self.len()) };
-        dst.copy_from(self);
-        dst
+    pub fn clone_to(&self, context: &B) -> Result<Self, Error> {
+        let mut dst = unsafe { Self::new_uninit_in(context, self.len()) }?;
+        dst.copy_from(self)?;
+        Ok(dst)
     }
 }
 
-impl<T: Prm + Interop> Clone for DeviceBuffer<T> {
+impl<T: Prm + Interop, B: BufferKernels<T>> Clone for DeviceBuffer<T, B> {
+    /// Panics if the device fails to allocate or copy the buffer, use `clone_to` to handle this.
     fn clone(&self) -> Self {
-        self.clone_to(&self.context())
+        self.clone_to(self.context()).unwrap()
     }
 }
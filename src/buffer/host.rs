@@ -1,9 +1,19 @@
+use std::ops::Range;
 use crate::{
-    Prm,
+    Prm, Num, Error,
     Buffer
 };
 
 
+/// Checks that lengths of buffer and slice are equal.
+pub(crate) fn check_len(expected: usize, found: usize) -> Result<(), Error> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(Error::BadSize { expected, found })
+    }
+}
+
 /// Buffer that stores data on the host. Simply a wrapper around `Vec`.
 #[derive(Clone)]
 pub struct HostBuffer<T: Prm> {
@@ -13,15 +23,11 @@ pub struct HostBuffer<T: Prm> {
 impl<T: Prm> Buffer<T> for HostBuffer<T> {
     type Context = ();
 
-    unsafe fn new_uninit_in(_: &(), len: usize) -> Self {
-        let mut vec = Vec::<T>::with_capacity(len);
-        vec.set_len(len);
-        Self { vec }
+    unsafe fn new_uninit_in(_: &(), len: usize) -> Result<Self, Error> {
+        Ok(Self::new_uninit(len))
     }
-    fn new_filled_in(_: &(), len: usize, value: T) -> Self {
-        let mut vec = Vec::<T>::new();
-        vec.resize(len, value);
-        Self { vec }
+    fn new_filled_in(_: &(), len: usize, value: T) -> Result<Self, Error> {
+        Ok(Self::new_filled(len, value))
     }
 
     fn len(&self) -> usize {
@@ -31,30 +37,85 @@ impl<T: Prm> Buffer<T> for HostBuffer<T> {
         &()
     }
 
-    fn load(&self, dst: &mut [T]) {
+    fn load(&self, dst: &mut [T]) -> Result<(), Error> {
+        check_len(self.len(), dst.len())?;
         dst.copy_from_slice(self.as_slice());
+        Ok(())
     }
-    fn store(&mut self, src: &[T]) {
+    fn store(&mut self, src: &[T]) -> Result<(), Error> {
+        check_len(self.len(), src.len())?;
         self.as_mut_slice().copy_from_slice(src);
+        Ok(())
     }
 
-    fn copy_from(&mut self, src: &Self) {
-        self.store(src.as_slice());
+    fn gather<I: Iterator<Item = usize>>(&self, _: Range<usize>, indices: I, dst: &mut [T]) -> Result<(), Error> {
+        let data = self.as_slice();
+        for (d, i) in dst.iter_mut().zip(indices) {
+            *d = data[i];
+        }
+        Ok(())
     }
-    fn copy_to(&self, dst: &mut Self) {
-        dst.copy_from(self);
+    fn scatter<I: Iterator<Item = usize>>(&mut self, _: Range<usize>, indices: I, src: &[T]) -> Result<(), Error> {
+        let data = self.as_mut_slice();
+        for (&s, i) in src.iter().zip(indices) {
+            data[i] = s;
+        }
+        Ok(())
+    }
+
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error> {
+        self.store(src.as_slice())
+    }
+    fn copy_to(&self, dst: &mut Self) -> Result<(), Error> {
+        dst.copy_from(self)
+    }
+
+    /// Reuses the capacity of the underlying vector, so shrinking never reallocates.
+    fn resize_buffer(&mut self, new_len: usize, fill: T) -> Result<(), Error> {
+        self.vec.resize(new_len, fill);
+        Ok(())
+    }
+
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num {
+        let mut x = start;
+        for (i, y) in self.vec.iter_mut().enumerate() {
+            if i > 0 {
+                x = x + step;
+            }
+            *y = x;
+        }
+        Ok(())
     }
 }
 
 impl<T: Prm> HostBuffer<T> {
     /// Create uninitialzed buffer.
     /// This is unsafe method, but it is helpful for allocation of storage for some subsequent operation.
+    ///
+    /// # Safety
+    ///
+    /// Buffer content must be written before reading.
+    /// Host buffer is actually zero-filled, but this must not be relied on.
     pub unsafe fn new_uninit(len: usize) -> Self {
-        <Self as Buffer<T>>::new_uninit_in(&(), len)
+        // Vector containing uninitialized values is unsound (e.g. for `bool`), so the memory is zero-filled instead.
+        // This is cheap because allocator provides zeroed memory for primitive types without explicit filling.
+        Self { vec: vec![T::zero(); len] }
     }
     /// Create buffer filled with a single value.
     pub fn new_filled(len: usize, value: T) -> Self {
-        <Self as Buffer<T>>::new_filled_in(&(), len, value)
+        let mut vec = Vec::<T>::new();
+        vec.resize(len, value);
+        Self { vec }
+    }
+
+    /// Create buffer that takes ownership of the vector.
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        Self { vec }
+    }
+
+    /// Takes the underlying vector.
+    pub fn into_vec(self) -> Vec<T> {
+        self.vec
     }
 
     /// Provideas access to underlying memory.
@@ -66,3 +127,29 @@ impl<T: Prm> HostBuffer<T> {
         self.vec.as_mut_slice()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_buffer() {
+        let mut buffer = HostBuffer::from_vec(vec![1, 2, 3]);
+        buffer.resize_buffer(5, 0).unwrap();
+        assert_eq!(buffer.as_slice(), &[1, 2, 3, 0, 0]);
+        let capacity = buffer.vec.capacity();
+        buffer.resize_buffer(2, 0).unwrap();
+        assert_eq!(buffer.as_slice(), &[1, 2]);
+        assert_eq!(buffer.vec.capacity(), capacity);
+    }
+
+    #[test]
+    fn fill_iota() {
+        let mut buffer = HostBuffer::new_filled(4, 0i32);
+        buffer.fill_iota(3, -2).unwrap();
+        assert_eq!(buffer.as_slice(), &[3, 1, -1, -3]);
+        let mut buffer = HostBuffer::new_filled(3, 0.0);
+        buffer.fill_iota(0.5, 0.25).unwrap();
+        assert_eq!(buffer.as_slice(), &[0.5, 0.75, 1.0]);
+    }
+}
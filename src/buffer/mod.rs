@@ -1,9 +1,27 @@
+#[allow(clippy::module_inception)]
 mod buffer;
 pub use buffer::*;
 
 mod host;
 pub use host::*;
 
+#[cfg(feature = "device")]
+mod backend;
+#[cfg(feature = "device")]
+pub use backend::*;
+
+#[cfg(feature = "device")]
+mod pool;
+#[cfg(feature = "device")]
+pub use pool::PoolStats;
+#[cfg(feature = "device")]
+pub(crate) use pool::BufferPool;
+
+#[cfg(feature = "device")]
+mod trace;
+#[cfg(feature = "device")]
+pub use trace::*;
+
 #[cfg(feature = "device")]
 mod device;
 #[cfg(feature = "device")]
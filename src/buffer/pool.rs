@@ -0,0 +1,98 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    sync::{Mutex, atomic::{AtomicBool, Ordering}},
+};
+
+
+/// Statistics of the device buffer pool, see `DeviceContext::pool_stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Number of allocations served by cached buffers.
+    pub hits: usize,
+    /// Number of allocations that created new buffers while the pool was enabled.
+    pub misses: usize,
+    /// Number of buffers currently cached.
+    pub cached_buffers: usize,
+    /// Total size of cached buffers in bytes.
+    pub cached_bytes: usize,
+}
+
+#[derive(Default)]
+struct PoolInner {
+    /// Released buffers by memory type and length.
+    buffers: HashMap<(TypeId, usize), Vec<Box<dyn Any + Send>>>,
+    stats: PoolStats,
+}
+
+/// Cache of released device buffers of a context, reused by allocations of the same type and length.
+#[derive(Default)]
+pub(crate) struct BufferPool {
+    enabled: AtomicBool,
+    inner: Mutex<PoolInner>,
+}
+
+impl BufferPool {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+    /// Enables or disables the pool, cached buffers are released when it is disabled.
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.clear();
+        }
+    }
+    /// Releases all cached buffers and returns their number.
+    pub(crate) fn clear(&self) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let count = inner.stats.cached_buffers;
+        inner.buffers.clear();
+        inner.stats.cached_buffers = 0;
+        inner.stats.cached_bytes = 0;
+        count
+    }
+    pub(crate) fn stats(&self) -> PoolStats {
+        self.inner.lock().unwrap().stats
+    }
+
+    /// Takes a cached buffer of the type and length, `None` if there is no such buffer or the pool is disabled.
+    ///
+    /// Buffers are memory of a backend, e.g. `OclBuffer`, the size of their element is used to count cached bytes.
+    pub(crate) fn take<M: Any + Send>(&self, len: usize, elem_size: usize) -> Option<M> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let mem = inner.buffers.get_mut(&(TypeId::of::<M>(), len)).and_then(|list| list.pop());
+        match mem {
+            Some(mem) => {
+                inner.stats.hits += 1;
+                inner.stats.cached_buffers -= 1;
+                inner.stats.cached_bytes -= len * elem_size;
+                Some(*mem.downcast().unwrap())
+            }
+            None => {
+                inner.stats.misses += 1;
+                None
+            }
+        }
+    }
+    /// Caches the released buffer if the pool is enabled, otherwise it is freed.
+    pub(crate) fn put<M: Any + Send>(&self, mem: M, len: usize, elem_size: usize) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.buffers.entry((TypeId::of::<M>(), len)).or_default().push(Box::new(mem));
+        inner.stats.cached_buffers += 1;
+        inner.stats.cached_bytes += len * elem_size;
+    }
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferPool").field("enabled", &self.is_enabled()).field("stats", &self.stats()).finish()
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "trace")]
+use std::{fmt, sync::{Arc, Mutex}, time::Duration};
+
+
+/// Device command reported to the trace hook of the context, see `DeviceContext::set_trace_hook`.
+///
+/// Commands are reported only if the `trace` feature is enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceEvent<'a> {
+    /// Kernel launch with the kernel name and the number of work items.
+    Kernel { name: &'a str, work_size: usize },
+    /// Transfer of `bytes` from the host memory to the device.
+    Write { bytes: usize },
+    /// Transfer of `bytes` from the device memory to the host.
+    Read { bytes: usize },
+}
+
+/// Callback receiving device commands and the host time spent to issue them.
+#[cfg(feature = "trace")]
+pub type TraceHook = Arc<dyn Fn(&TraceEvent<'_>, Duration) + Send + Sync>;
+
+/// Trace hook of the context, it is cloned before the call, so the callback may replace itself.
+#[cfg(feature = "trace")]
+#[derive(Default)]
+pub(crate) struct TraceSlot {
+    hook: Mutex<Option<TraceHook>>,
+}
+#[cfg(feature = "trace")]
+impl TraceSlot {
+    pub fn set(&self, hook: Option<TraceHook>) {
+        *self.hook.lock().unwrap() = hook;
+    }
+    pub fn get(&self) -> Option<TraceHook> {
+        self.hook.lock().unwrap().clone()
+    }
+}
+#[cfg(feature = "trace")]
+impl fmt::Debug for TraceSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceSlot").field("is_set", &self.get().is_some()).finish()
+    }
+}
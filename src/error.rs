@@ -0,0 +1,166 @@
+use std::{fmt, error};
+#[cfg(feature = "std")]
+use std::io;
+use crate::Shape;
+
+
+/// Error that may occur during tensor operations.
+#[derive(Debug)]
+pub enum Error {
+    /// Number of elements doesn't match the expected one.
+    BadSize {
+        expected: usize,
+        found: usize,
+    },
+    /// Number of elements is too large.
+    Overflow,
+    /// Shapes of operands aren't compatible.
+    ShapeMismatch(Shape, Shape),
+    /// Tensor has more dimensions than expected.
+    BadRank {
+        expected: usize,
+        found: usize,
+    },
+    /// Index is out of tensor bounds.
+    BadIndex(Vec<usize>),
+    /// Operation requires tensor elements to be contiguous in memory.
+    NotContiguous,
+    /// Specified axes are not valid for the operation.
+    BadAxes(Vec<usize>),
+    /// Argument value is out of the allowed range.
+    BadArgument(String),
+    /// Reading or writing of tensor data failed.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// Error of the operation `op`, caused by the `source` one.
+    ///
+    /// Operations that are often called deep inside a chain attach their names, so the message reads like
+    /// `matmul: ShapeMismatch between [3, 2] and [3, 2]`. Use `root` to match the original error.
+    Context {
+        op: &'static str,
+        source: Box<Error>,
+    },
+    /// No OpenCL platform or device is available.
+    #[cfg(feature = "device")]
+    DeviceUnavailable,
+    /// OpenCL program failed to compile, contains the build log.
+    #[cfg(feature = "device")]
+    KernelBuildFailed {
+        log: String,
+    },
+    /// Device failed to allocate memory or other resources.
+    #[cfg(feature = "device")]
+    OutOfDeviceMemory,
+    /// Work-group size isn't supported by the device or the kernel.
+    #[cfg(feature = "device")]
+    InvalidWorkGroupSize,
+    /// Device doesn't support OpenCL extension required for the element type.
+    #[cfg(feature = "device")]
+    ExtensionUnavailable(String),
+    /// Operands belong to different device contexts.
+    #[cfg(feature = "device")]
+    ContextMismatch,
+    /// Other error occured in OpenCL.
+    #[cfg(feature = "device")]
+    OclError(ocl::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::BadSize { expected, found } => {
+                write!(f, "BadSize: expected {} elements, found {}", expected, found)
+            },
+            Error::Overflow => write!(f, "Overflow: number of elements is too large"),
+            Error::ShapeMismatch(a, b) => write!(f, "ShapeMismatch between {} and {}", a, b),
+            Error::BadRank { expected, found } => {
+                write!(f, "BadRank: expected at most {} dimensions, found {}", expected, found)
+            },
+            Error::BadIndex(index) => write!(f, "BadIndex: {:?} is out of bounds", index),
+            Error::NotContiguous => write!(f, "NotContiguous: tensor data is not contiguous"),
+            Error::BadAxes(axes) => write!(f, "BadAxes: {:?}", axes),
+            Error::BadArgument(msg) => write!(f, "BadArgument: {}", msg),
+            #[cfg(feature = "std")]
+            Error::Io(err) => write!(f, "Io: {}", err),
+            Error::Context { op, source } => write!(f, "{}: {}", op, source),
+            #[cfg(feature = "device")]
+            Error::DeviceUnavailable => write!(f, "DeviceUnavailable: no OpenCL device found"),
+            #[cfg(feature = "device")]
+            Error::KernelBuildFailed { log } => write!(f, "KernelBuildFailed:\n{}", log),
+            #[cfg(feature = "device")]
+            Error::OutOfDeviceMemory => write!(f, "OutOfDeviceMemory: device failed to allocate resources"),
+            #[cfg(feature = "device")]
+            Error::InvalidWorkGroupSize => write!(f, "InvalidWorkGroupSize: work-group size is not supported"),
+            #[cfg(feature = "device")]
+            Error::ExtensionUnavailable(ext) => write!(f, "ExtensionUnavailable: device doesn't support {}", ext),
+            #[cfg(feature = "device")]
+            Error::ContextMismatch => write!(f, "ContextMismatch: operands belong to different contexts"),
+            #[cfg(feature = "device")]
+            Error::OclError(err) => write!(f, "OclError: {}", err),
+        }
+    }
+}
+
+impl Error {
+    /// Wraps the error into `Error::Context` of the operation `op`.
+    pub fn context(self, op: &'static str) -> Self {
+        Error::Context { op, source: Box::new(self) }
+    }
+    /// The original error without contexts attached to it.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            err => err,
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            Error::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Attaches the name of an operation to the error of its result, see `Error::Context`.
+pub(crate) trait ResultContext<T> {
+    fn context(self, op: &'static str) -> Result<T, Error>;
+}
+
+impl<T> ResultContext<T> for Result<T, Error> {
+    fn context(self, op: &'static str) -> Result<T, Error> {
+        self.map_err(|err| err.context(op))
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Classifies common OpenCL errors, the rest are wrapped into `Error::OclError`.
+#[cfg(feature = "device")]
+impl From<ocl::Error> for Error {
+    fn from(err: ocl::Error) -> Self {
+        use ocl::{OclCoreError, enums::Status};
+        match &err {
+            ocl::Error::OclCore(OclCoreError::ProgramBuild(build_err)) => {
+                return Error::KernelBuildFailed { log: build_err.to_string() };
+            }
+            // Kernel arguments don't match its signature.
+            ocl::Error::Kernel(_) => return Error::BadArgument(err.to_string()),
+            _ => (),
+        }
+        match err.api_status() {
+            Some(Status::CL_MEM_OBJECT_ALLOCATION_FAILURE) | Some(Status::CL_OUT_OF_RESOURCES) => Error::OutOfDeviceMemory,
+            Some(Status::CL_INVALID_WORK_GROUP_SIZE) => Error::InvalidWorkGroupSize,
+            _ => Error::OclError(err),
+        }
+    }
+}
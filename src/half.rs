@@ -0,0 +1,248 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+};
+use crate::num::{Zero, One};
+
+
+/// Half-precision (binary16) floating-point number.
+///
+/// It's a storage type: elements are converted to `f32` to compute on the host,
+/// while on the device it's the OpenCL `half` which requires `cl_khr_fp16` extension.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Default)]
+pub struct f16(u16);
+
+impl f16 {
+    /// Number from raw binary16 representation.
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+    /// Raw binary16 representation.
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Converts `f32` to the nearest half-precision number, ties are rounded to even.
+    ///
+    /// Values which are too large become infinities, too small become zeros.
+    pub fn from_f32(x: f32) -> Self {
+        let bits = x.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exp = ((bits >> 23) & 0xff) as i32;
+        let man = bits & 0x7fffff;
+        if exp == 0xff {
+            // Infinity or NaN, NaN must remain NaN after truncation of mantissa.
+            let nan = if man != 0 { 0x0200 | (man >> 13) as u16 } else { 0 };
+            return Self(sign | 0x7c00 | nan);
+        }
+        let exp = exp - 127 + 15;
+        if exp >= 0x1f {
+            return Self(sign | 0x7c00);
+        }
+        let (man, shift) = if exp <= 0 {
+            if exp < -10 {
+                return Self(sign);
+            }
+            // Subnormal number, implicit leading bit becomes explicit.
+            (man | 0x800000, (14 - exp) as u32)
+        } else {
+            (man, 13)
+        };
+        let mut half = man >> shift;
+        if exp > 0 {
+            half |= (exp as u32) << 10;
+        }
+        let rem = man & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        // Carry to exponent is correct here, the largest number is rounded up to infinity.
+        if rem > halfway || (rem == halfway && half & 1 != 0) {
+            half += 1;
+        }
+        Self(sign | half as u16)
+    }
+    /// Converts to `f32` exactly.
+    pub fn to_f32(self) -> f32 {
+        let sign = ((self.0 & 0x8000) as u32) << 16;
+        let exp = ((self.0 >> 10) & 0x1f) as u32;
+        let man = (self.0 & 0x3ff) as u32;
+        match exp {
+            0x1f => f32::from_bits(sign | 0x7f800000 | (man << 13)),
+            0 => {
+                let x = man as f32 / (1 << 24) as f32;
+                if sign != 0 { -x } else { x }
+            },
+            _ => f32::from_bits(sign | ((exp + 127 - 15) << 23) | (man << 13)),
+        }
+    }
+
+    pub fn is_nan(self) -> bool {
+        self.0 & 0x7c00 == 0x7c00 && self.0 & 0x3ff != 0
+    }
+}
+
+impl From<f32> for f16 {
+    fn from(x: f32) -> Self {
+        Self::from_f32(x)
+    }
+}
+impl From<f16> for f32 {
+    fn from(x: f16) -> Self {
+        x.to_f32()
+    }
+}
+
+impl PartialEq for f16 {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_f32() == other.to_f32()
+    }
+}
+impl PartialOrd for f16 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.to_f32().partial_cmp(&other.to_f32())
+    }
+}
+
+impl fmt::Debug for f16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f32(), f)
+    }
+}
+impl fmt::Display for f16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f32(), f)
+    }
+}
+
+impl Zero for f16 {
+    fn zero() -> Self {
+        Self(0)
+    }
+}
+impl One for f16 {
+    fn one() -> Self {
+        Self(0x3c00)
+    }
+}
+
+
+/// Brain floating-point number (bfloat16), the upper half of `f32`.
+///
+/// It has the same range as `f32` but only 8 bits of precision.
+/// It's a storage type: elements are converted to `f32` to compute on the host.
+/// OpenCL has no native `bf16`, so on the device it's stored as `ushort` and only conversions are supported,
+/// that is why it doesn't implement `PartialOrd`, otherwise device comparisons would compare raw bits.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Default)]
+pub struct bf16(u16);
+
+impl bf16 {
+    /// Number from raw bfloat16 representation.
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+    /// Raw bfloat16 representation.
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Converts `f32` to the nearest bfloat16 number, ties are rounded to even.
+    pub fn from_f32(x: f32) -> Self {
+        let bits = x.to_bits();
+        if x.is_nan() {
+            // Mantissa must remain nonzero after truncation.
+            return Self((bits >> 16) as u16 | 0x0040);
+        }
+        // Carry to exponent is correct here, the largest numbers are rounded up to infinity.
+        Self(((bits + 0x7fff + ((bits >> 16) & 1)) >> 16) as u16)
+    }
+    /// Converts to `f32` exactly.
+    pub fn to_f32(self) -> f32 {
+        f32::from_bits((self.0 as u32) << 16)
+    }
+
+    pub fn is_nan(self) -> bool {
+        self.0 & 0x7f80 == 0x7f80 && self.0 & 0x7f != 0
+    }
+}
+
+impl From<f32> for bf16 {
+    fn from(x: f32) -> Self {
+        Self::from_f32(x)
+    }
+}
+impl From<bf16> for f32 {
+    fn from(x: bf16) -> Self {
+        x.to_f32()
+    }
+}
+
+impl PartialEq for bf16 {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_f32() == other.to_f32()
+    }
+}
+
+impl fmt::Debug for bf16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f32(), f)
+    }
+}
+impl fmt::Display for bf16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f32(), f)
+    }
+}
+
+impl Zero for bf16 {
+    fn zero() -> Self {
+        Self(0)
+    }
+}
+impl One for bf16 {
+    fn one() -> Self {
+        Self(0x3f80)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact() {
+        for &x in [0.0, -0.0, 1.0, -2.5, 65504.0, 0.000061035156, 5.9604645e-8, f32::INFINITY].iter() {
+            let h = f16::from_f32(x);
+            assert_eq!(h.to_f32(), x);
+            assert_eq!(h.to_f32().is_sign_negative(), x.is_sign_negative());
+        }
+        assert_eq!(f16::one().to_bits(), f16::from_f32(1.0).to_bits());
+        assert!(f16::from_f32(f32::NAN).is_nan());
+        assert!(f16::from_f32(f32::NAN).to_f32().is_nan());
+    }
+
+    #[test]
+    fn rounding() {
+        // Spacing between numbers in `[1, 2)` is `2^-10`.
+        assert_eq!(f16::from_f32(1.0 + 0.5 / 1024.0).to_f32(), 1.0);
+        assert_eq!(f16::from_f32(1.0 + 1.5 / 1024.0).to_f32(), 1.0 + 2.0 / 1024.0);
+        assert_eq!(f16::from_f32(1.0 + 0.6 / 1024.0).to_f32(), 1.0 + 1.0 / 1024.0);
+        assert_eq!(f16::from_f32(65520.0).to_f32(), f32::INFINITY);
+        assert_eq!(f16::from_f32(1e-9).to_f32(), 0.0);
+        assert_eq!(f16::from_f32(3.0 * 5.9604645e-8).to_f32(), 3.0 * 5.9604645e-8);
+    }
+
+    #[test]
+    fn bf16_conversion() {
+        for &x in [0.0, -0.0, 1.0, -2.5, 3.3895314e38, f32::from_bits(0x10000), f32::INFINITY].iter() {
+            assert_eq!(bf16::from_f32(x).to_f32(), x);
+        }
+        assert_eq!(bf16::one().to_bits(), bf16::from_f32(1.0).to_bits());
+        assert!(bf16::from_f32(f32::NAN).is_nan());
+        // Spacing between numbers in `[1, 2)` is `2^-7`.
+        assert_eq!(bf16::from_f32(1.0 + 0.5 / 128.0).to_f32(), 1.0);
+        assert_eq!(bf16::from_f32(1.0 + 1.5 / 128.0).to_f32(), 1.0 + 2.0 / 128.0);
+        assert_eq!(bf16::from_f32(1.0 + 0.6 / 128.0).to_f32(), 1.0 + 1.0 / 128.0);
+        assert_eq!(bf16::from_f32(f32::MAX).to_f32(), f32::INFINITY);
+    }
+}
@@ -1,14 +1,34 @@
+mod half;
+pub use half::{f16, bf16};
+
 mod num;
-pub use num::Prm;
+pub use num::{Prm, Num, Float, LeBytes, Pod};
+pub use num_complex::Complex;
 #[cfg(feature = "device")]
-pub use num::Interop;
+pub use num::{Interop, FloatInterop};
+
+mod random;
+pub use random::{Rng, RngState, GlobalRng, set_global_seed, reservoir_sample};
+pub(crate) use random::*;
+
+mod error;
+pub use error::Error;
+pub(crate) use error::ResultContext;
 
 mod buffer;
 pub(crate) use buffer::*;
+#[cfg(feature = "device")]
+pub use buffer::{Backend, BufferKernels, DeviceContext, PoolStats, TraceEvent, DEFAULT_MATMUL_TILE};
+#[cfg(all(feature = "device", feature = "trace"))]
+pub use buffer::TraceHook;
 
 mod tensor;
 pub use tensor::*;
 
+pub mod metrics;
+
+pub mod optim;
+
 pub mod prelude {
     pub use crate::{
         Tensor as _,
@@ -0,0 +1,70 @@
+//! Metrics for evaluation of model predictions.
+//!
+//! Metrics are computed on the host, because the results are scalars.
+//! Device tensors should be loaded to the host first, e.g. with `DeviceTensor::copy_to_host`.
+
+use num_traits::{Zero, NumCast};
+use crate::{Prm, Float, Error, Tensor, HostTensor};
+
+
+fn check_shapes<T: Prm, U: Prm>(a: &HostTensor<T>, b: &HostTensor<U>) -> Result<(), Error> {
+    if a.shape() != b.shape() {
+        return Err(Error::ShapeMismatch(a.shape().clone(), b.shape().clone()));
+    }
+    Ok(())
+}
+
+/// Mean of values, NaN if there are no values.
+fn mean<T: Float, I: Iterator<Item = T>>(iter: I, len: usize) -> T {
+    iter.fold(Zero::zero(), |s: T, x| s + x) / <T as NumCast>::from(len).unwrap()
+}
+
+/// Fraction of predicted classes which are equal to the target ones.
+///
+/// Fails with `Error::ShapeMismatch` if tensors have different shapes. Accuracy of empty tensors is NaN.
+pub fn accuracy<T: Float>(predictions: &HostTensor<usize>, targets: &HostTensor<usize>) -> Result<T, Error> {
+    check_shapes(predictions, targets)?;
+    let correct = predictions.iter().zip(targets.iter()).filter(|(p, t)| p == t).count();
+    Ok(<T as NumCast>::from(correct).unwrap() / <T as NumCast>::from(targets.shape().content()).unwrap())
+}
+
+/// Mean of squared differences between predictions and targets.
+///
+/// Fails with `Error::ShapeMismatch` if tensors have different shapes. Error of empty tensors is NaN.
+pub fn mean_squared_error<T: Float>(predictions: &HostTensor<T>, targets: &HostTensor<T>) -> Result<T, Error> {
+    check_shapes(predictions, targets)?;
+    let iter = predictions.iter().zip(targets.iter()).map(|(&p, &t)| (p - t) * (p - t));
+    Ok(mean(iter, targets.shape().content()))
+}
+
+/// Mean of absolute differences between predictions and targets.
+///
+/// Fails with `Error::ShapeMismatch` if tensors have different shapes. Error of empty tensors is NaN.
+pub fn mean_absolute_error<T: Float>(predictions: &HostTensor<T>, targets: &HostTensor<T>) -> Result<T, Error> {
+    check_shapes(predictions, targets)?;
+    let iter = predictions.iter().zip(targets.iter()).map(|(&p, &t)| (p - t).abs());
+    Ok(mean(iter, targets.shape().content()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accuracy_() {
+        let p = HostTensor::from(vec![1, 2, 3, 4]);
+        let t = HostTensor::from(vec![1, 0, 3, 0]);
+        assert_eq!(accuracy::<f64>(&p, &t).unwrap(), 0.5);
+        assert!(matches!(accuracy::<f64>(&p, &HostTensor::from(vec![1])), Err(Error::ShapeMismatch(..))));
+        let e = HostTensor::<usize>::from(vec![]);
+        assert!(accuracy::<f64>(&e, &e).unwrap().is_nan());
+    }
+
+    #[test]
+    fn errors() {
+        let p = HostTensor::from(vec![1.0, 2.0, 4.0]);
+        let t = HostTensor::from(vec![1.0, 3.0, 2.0]);
+        assert_eq!(mean_squared_error(&p, &t).unwrap(), 5.0 / 3.0);
+        assert_eq!(mean_absolute_error(&p, &t).unwrap(), 1.0);
+    }
+}
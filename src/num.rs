@@ -1,5 +1,6 @@
 use num_traits as num;
 use num_complex::Complex;
+use crate::{f16, bf16};
 
 /// Analog of `num_traits::Zero` but also implemented for `bool` type.
 pub trait Zero {
@@ -11,7 +12,9 @@ pub trait One {
 }
 
 /// Wrapper for `num_traits::Num`.
-pub trait Num: num::Num {}
+///
+/// Numbers are plain values, so they are required to be `Send` and `Sync` as every `Prm`.
+pub trait Num: num::Num + Send + Sync {}
 
 /// Wrapper for `num_traits::Float`.
 pub trait Float: Num + num::Float {}
@@ -61,53 +64,171 @@ impl One for bool {
 
 
 /// Type that could be put in tensor.
-pub trait Prm : Sized + Copy + PartialEq + Zero + One {}
+///
+/// Elements are always copied by value, so `Copy` is required,
+/// `Zero` and `One` are used to create zeroed tensors and as neutral elements of reductions.
+/// `Send` and `Sync` allow host operations to split the work between threads.
+/// It's implemented for all `Num` types, `bool` and the storage types `f16` and `bf16`,
+/// arithmetic operations additionally require `Num` or `Float`.
+///
+/// To use the type on device it must also implement `Interop` which maps it to a device type by `to_dev` and `from_dev`.
+/// The mapping is a separate trait, because host-only tensors don't need it.
+pub trait Prm : Sized + Copy + PartialEq + Send + Sync + Zero + One {}
 
 impl<T: Num + Copy> Prm for T {}
 
 impl Prm for bool {}
 
+impl Prm for f16 {}
+
+impl Prm for bf16 {}
+
+
+/// Type that has a little-endian binary representation of fixed size, used to read and write tensor data.
+pub trait LeBytes: Prm {
+    /// Size of the representation in bytes.
+    const SIZE: usize;
+    /// Decodes value from `bytes` of length `SIZE`.
+    fn read_le(bytes: &[u8]) -> Self;
+    /// Encodes value into `bytes` of length `SIZE`.
+    fn write_le(self, bytes: &mut [u8]);
+}
+
+macro_rules! impl_le_bytes {
+    ($($t:ty),*) => {$(
+        impl LeBytes for $t {
+            const SIZE: usize = std::mem::size_of::<$t>();
+            fn read_le(bytes: &[u8]) -> Self {
+                let mut array = [0; std::mem::size_of::<$t>()];
+                array.copy_from_slice(bytes);
+                <$t>::from_le_bytes(array)
+            }
+            fn write_le(self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes());
+            }
+        }
+    )*};
+}
+impl_le_bytes!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// Stored as a single byte, any nonzero byte is decoded as `true`.
+impl LeBytes for bool {
+    const SIZE: usize = 1;
+    fn read_le(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+    fn write_le(self, bytes: &mut [u8]) {
+        bytes[0] = self as u8;
+    }
+}
+impl LeBytes for f16 {
+    const SIZE: usize = 2;
+    fn read_le(bytes: &[u8]) -> Self {
+        f16::from_bits(u16::read_le(bytes))
+    }
+    fn write_le(self, bytes: &mut [u8]) {
+        self.to_bits().write_le(bytes)
+    }
+}
+impl LeBytes for bf16 {
+    const SIZE: usize = 2;
+    fn read_le(bytes: &[u8]) -> Self {
+        bf16::from_bits(u16::read_le(bytes))
+    }
+    fn write_le(self, bytes: &mut [u8]) {
+        self.to_bits().write_le(bytes)
+    }
+}
+/// Real part is followed by imaginary one.
+impl<T: Float + LeBytes> LeBytes for Complex<T> {
+    const SIZE: usize = 2 * T::SIZE;
+    fn read_le(bytes: &[u8]) -> Self {
+        Complex::new(T::read_le(&bytes[..T::SIZE]), T::read_le(&bytes[T::SIZE..]))
+    }
+    fn write_le(self, bytes: &mut [u8]) {
+        self.re.write_le(&mut bytes[..T::SIZE]);
+        self.im.write_le(&mut bytes[T::SIZE..]);
+    }
+}
+
+
+/// Type which values are plain bytes in the native representation, so tensor data could be viewed as bytes and back,
+/// see `Tensor::as_bytes` and `Tensor::from_bytes_in`.
+///
+/// # Safety
+///
+/// The type must have no padding and every bit pattern of its size must be a valid value.
+/// That is why `bool` isn't `Pod`, only `0` and `1` bytes are valid for it.
+pub unsafe trait Pod: Prm {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for usize {}
+
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for isize {}
+
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl Pod for f16 {}
+unsafe impl Pod for bf16 {}
+/// `Complex` is `repr(C)`, so it is a pair of parts without padding.
+unsafe impl<T: Pod + Float> Pod for Complex<T> {}
+
 
 #[cfg(feature = "device")]
 mod interop {
     use super::*;
-    use std::mem::transmute;
-    use ocl::{OclPrm, Buffer};
-    use num_complex_v01::{Complex as ComplexV01};
+    use crate::{Error, Backend, check_len};
+    use ocl::{OclPrm, prm::{Float2, Double2}};
 
 
     /// Types that can be transformed from host representation to device one and back.
     pub trait Interop: Copy {
         type Dev: OclPrm + Copy;
+        /// Name of the device type in OpenCL C.
+        const DEV_NAME: &'static str;
+        /// OpenCL extension required to use the type on device.
+        const EXTENSION: Option<&'static str> = None;
 
         /// Transform from host to device type.
         fn to_dev(self) -> Self::Dev;
         /// Transform from device to host type.
         fn from_dev(x: Self::Dev) -> Self;
 
-        /// Copy data from OpenCL buffer to host slice.
-        fn load_from_buffer(dst: &mut [Self], src: &Buffer<Self::Dev>) {
-            assert_eq!(dst.len(), src.len());
-            let mut tmp = Vec::<Self::Dev>::new();
-            src.read(&mut tmp).enq().unwrap();
+        /// Copy data from device memory of the `backend` to host slice.
+        fn load_from_buffer<B: Backend<Self::Dev>>(backend: &B, dst: &mut [Self], src: &B::Mem) -> Result<(), Error> {
+            check_len(B::mem_len(src), dst.len())?;
+            let mut tmp = vec![Self::Dev::default(); dst.len()];
+            backend.read(src, 0, &mut tmp)?;
             for (d, &s) in dst.iter_mut().zip(tmp.iter()) {
                 *d = Self::from_dev(s);
             }
+            Ok(())
         }
 
-        /// Copy data from host slice to OpenCL buffer.
-        fn store_to_buffer(dst: &mut Buffer<Self::Dev>, src: &[Self]) {
-            assert_eq!(dst.len(), src.len());
+        /// Copy data from host slice to device memory of the `backend`.
+        fn store_to_buffer<B: Backend<Self::Dev>>(backend: &B, dst: &mut B::Mem, src: &[Self]) -> Result<(), Error> {
+            check_len(B::mem_len(dst), src.len())?;
             let tmp = src.iter().map(|x| x.to_dev()).collect::<Vec<_>>();
-            dst.write(&tmp).enq().unwrap();
+            backend.write(dst, 0, &tmp)
         }
     }
 
     /// Type which representation remains the same for both host and device.
-    pub trait IdentInterop: Interop<Dev=Self> + OclPrm {}
+    pub trait IdentInterop: Interop<Dev=Self> + OclPrm {
+        /// Name of the type in OpenCL C.
+        const NAME: &'static str;
+    }
 
     impl <T: IdentInterop> Interop for T {
         type Dev = Self;
+        const DEV_NAME: &'static str = T::NAME;
 
         fn to_dev(self) -> Self::Dev {
             self
@@ -115,18 +236,19 @@ mod interop {
         fn from_dev(x: Self::Dev) -> Self {
             x
         }
-        fn load_from_buffer(dst: &mut [Self], src: &Buffer<Self::Dev>) {
-            assert_eq!(dst.len(), src.len());
-            src.read(dst).enq().unwrap();
+        fn load_from_buffer<B: Backend<Self::Dev>>(backend: &B, dst: &mut [Self], src: &B::Mem) -> Result<(), Error> {
+            check_len(B::mem_len(src), dst.len())?;
+            backend.read(src, 0, dst)
         }
-        fn store_to_buffer(dst: &mut Buffer<Self::Dev>, src: &[Self]) {
-            assert_eq!(dst.len(), src.len());
-            dst.write(src).enq().unwrap();
+        fn store_to_buffer<B: Backend<Self::Dev>>(backend: &B, dst: &mut B::Mem, src: &[Self]) -> Result<(), Error> {
+            check_len(B::mem_len(dst), src.len())?;
+            backend.write(dst, 0, src)
         }
     }
 
     impl Interop for bool {
         type Dev = u8;
+        const DEV_NAME: &'static str = "uchar";
         fn to_dev(self) -> Self::Dev {
             if self {
                 0xFF
@@ -139,21 +261,22 @@ mod interop {
         }
     }
 
-    impl IdentInterop for u8 {}
-    impl IdentInterop for u16 {}
-    impl IdentInterop for u32 {}
-    impl IdentInterop for u64 {}
+    impl IdentInterop for u8 { const NAME: &'static str = "uchar"; }
+    impl IdentInterop for u16 { const NAME: &'static str = "ushort"; }
+    impl IdentInterop for u32 { const NAME: &'static str = "uint"; }
+    impl IdentInterop for u64 { const NAME: &'static str = "ulong"; }
 
-    impl IdentInterop for i8 {}
-    impl IdentInterop for i16 {}
-    impl IdentInterop for i32 {}
-    impl IdentInterop for i64 {}
+    impl IdentInterop for i8 { const NAME: &'static str = "char"; }
+    impl IdentInterop for i16 { const NAME: &'static str = "short"; }
+    impl IdentInterop for i32 { const NAME: &'static str = "int"; }
+    impl IdentInterop for i64 { const NAME: &'static str = "long"; }
 
-    impl IdentInterop for f32 {}
-    impl IdentInterop for f64 {}
+    impl IdentInterop for f32 { const NAME: &'static str = "float"; }
+    impl IdentInterop for f64 { const NAME: &'static str = "double"; }
 
     impl Interop for usize {
         type Dev = u32;
+        const DEV_NAME: &'static str = "uint";
         fn to_dev(self) -> Self::Dev {
             self as Self::Dev
         }
@@ -163,6 +286,7 @@ mod interop {
     }
     impl Interop for isize {
         type Dev = i32;
+        const DEV_NAME: &'static str = "int";
         fn to_dev(self) -> Self::Dev {
             self as Self::Dev
         }
@@ -171,25 +295,56 @@ mod interop {
         }
     }
 
-    impl<T: Float> Interop for Complex<T> where ComplexV01<T>: OclPrm {
-        type Dev = ComplexV01<T>;
+    impl Interop for f16 {
+        type Dev = u16;
+        const DEV_NAME: &'static str = "half";
+        const EXTENSION: Option<&'static str> = Some("cl_khr_fp16");
+        fn to_dev(self) -> Self::Dev {
+            self.to_bits()
+        }
+        fn from_dev(x: Self::Dev) -> Self {
+            f16::from_bits(x)
+        }
+    }
+
+    /// Stored as raw bits, there is no native `bf16` type in OpenCL C.
+    impl Interop for bf16 {
+        type Dev = u16;
+        const DEV_NAME: &'static str = "ushort";
+        fn to_dev(self) -> Self::Dev {
+            self.to_bits()
+        }
+        fn from_dev(x: Self::Dev) -> Self {
+            bf16::from_bits(x)
+        }
+    }
+
+    /// Floating-point type that could be a component of complex number on device.
+    pub trait FloatInterop: Float + IdentInterop {
+        /// Device vector type of two components which holds complex number.
+        type Complex: OclPrm + From<[Self; 2]> + Into<[Self; 2]>;
+        /// Name of the complex type in OpenCL C.
+        const COMPLEX_NAME: &'static str;
+    }
+    impl FloatInterop for f32 {
+        type Complex = Float2;
+        const COMPLEX_NAME: &'static str = "float2";
+    }
+    impl FloatInterop for f64 {
+        type Complex = Double2;
+        const COMPLEX_NAME: &'static str = "double2";
+    }
+
+    /// Stored as a two-component vector, real part is `x` and imaginary one is `y`.
+    impl<T: FloatInterop> Interop for Complex<T> {
+        type Dev = T::Complex;
+        const DEV_NAME: &'static str = T::COMPLEX_NAME;
         fn to_dev(self) -> Self::Dev {
-            Self::Dev::new(self.re, self.im)
+            [self.re, self.im].into()
         }
         fn from_dev(x: Self::Dev) -> Self {
-            Self::new(x.re, x.im)
-        }
-        fn load_from_buffer(dst: &mut [Self], src: &Buffer<Self::Dev>) {
-            assert_eq!(dst.len(), src.len());
-            src.read(
-                unsafe { transmute::<_, &mut [Self::Dev]>(dst) }
-            ).enq().unwrap();
-        }
-        fn store_to_buffer(dst: &mut Buffer<Self::Dev>, src: &[Self]) {
-            assert_eq!(dst.len(), src.len());
-            dst.write(
-                unsafe { transmute::<_, &[Self::Dev]>(src) }
-            ).enq().unwrap();
+            let [re, im] = x.into();
+            Self::new(re, im)
         }
     }
 }
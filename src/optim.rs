@@ -0,0 +1,295 @@
+//! Optimizer steps and other updates of training state in place.
+//!
+//! Functions are generic over `OptimTensor`, which is implemented for both host and device tensors.
+
+use std::marker::PhantomData;
+use crate::{Float, Buffer, Shape, Error, Tensor};
+
+
+/// Tensor that supports in-place updates needed by optimizers.
+pub trait OptimTensor<T: Float>: Tensor<T> {
+    /// Replaces each element `y` with `a * y + b * x`, where `x` is the corresponding element of `other`.
+    ///
+    /// Fails with `Error::ShapeMismatch` if tensors have different shapes.
+    fn axpby(&mut self, a: T, other: &Self, b: T) -> Result<(), Error>;
+    /// Replaces each element `y` with `a * y + b * x * x`, see `axpby`.
+    fn axpby_square(&mut self, a: T, other: &Self, b: T) -> Result<(), Error>;
+    /// Adds `alpha * n / (sqrt(d) + eps)` to each element, where `n` and `d` are elements of `num` and `den`.
+    ///
+    /// Fails with `Error::ShapeMismatch` if tensors have different shapes.
+    fn add_scaled_ratio(&mut self, num: &Self, den: &Self, alpha: T, eps: T) -> Result<(), Error>;
+}
+
+/// Checks that the tensor has the same shape as the parameter.
+pub(crate) fn check_param<T: Float, X: Tensor<T>>(param: &X, other: &X) -> Result<(), Error> {
+    if param.shape() != other.shape() {
+        return Err(Error::ShapeMismatch(param.shape().clone(), other.shape().clone()));
+    }
+    Ok(())
+}
+
+/// Adds L2 regularization term to the gradient: `grad += wd * param`.
+///
+/// It should be called before the optimizer step. Note that for Adam it isn't the decoupled weight decay of AdamW,
+/// because the term is normalized with the rest of the gradient.
+/// Fails with `Error::ShapeMismatch` if `grad` has other shape than `param`.
+pub fn add_weight_decay<T: Float, X: OptimTensor<T>>(grad: &mut X, param: &X, wd: T) -> Result<(), Error> {
+    grad.axpby(T::one(), param, wd)
+}
+
+/// Stochastic gradient descent step `param -= lr * grad`.
+///
+/// Fails with `Error::ShapeMismatch` if `grad` has other shape than `param`.
+pub fn sgd_step<T: Float, X: OptimTensor<T>>(param: &mut X, grad: &X, lr: T) -> Result<(), Error> {
+    param.axpby(T::one(), grad, -lr)
+}
+
+/// Stochastic gradient descent step with momentum.
+///
+/// Velocity is updated as `velocity = momentum * velocity + grad` and then `param -= lr * velocity`,
+/// so before the first step `velocity` should be zeroed, e.g. with `Tensor::zeros_like`.
+/// Fails with `Error::ShapeMismatch` if `grad` or `velocity` have other shape than `param`.
+pub fn sgd_momentum_step<T: Float, X: OptimTensor<T>>(
+    param: &mut X, grad: &X, velocity: &mut X, lr: T, momentum: T,
+) -> Result<(), Error> {
+    check_param(param, velocity)?;
+    velocity.axpby(momentum, grad, T::one())?;
+    param.axpby(T::one(), velocity, -lr)
+}
+
+/// Hyperparameters of Adam optimizer.
+#[derive(Clone, Copy, Debug)]
+pub struct Adam<T: Float> {
+    /// Learning rate.
+    pub lr: T,
+    /// Decay rate of the first moment estimate.
+    pub beta1: T,
+    /// Decay rate of the second moment estimate.
+    pub beta2: T,
+    /// Term added to the denominator for stability.
+    pub eps: T,
+}
+
+/// Commonly used values: `lr = 1e-3`, `beta1 = 0.9`, `beta2 = 0.999`, `eps = 1e-8`.
+impl<T: Float> Default for Adam<T> {
+    fn default() -> Self {
+        let c = |x: f64| T::from(x).unwrap();
+        Self { lr: c(1e-3), beta1: c(0.9), beta2: c(0.999), eps: c(1e-8) }
+    }
+}
+
+/// Adam step with bias correction of moment estimates.
+///
+/// Moments are updated as `m = beta1 * m + (1 - beta1) * grad` and `v = beta2 * v + (1 - beta2) * grad^2`,
+/// then `param -= lr * m_hat / (sqrt(v_hat) + eps)` where `m_hat = m / (1 - beta1^step)` and `v_hat = v / (1 - beta2^step)`.
+/// Moments should be zeroed before the first step, and `step` is the number of the step starting from `1`.
+/// Fails with `Error::BadArgument` if `step` is zero, with `Error::ShapeMismatch` if any tensor has other shape than `param`
+/// and with `Error::ContextMismatch` if device tensors belong to different contexts.
+pub fn adam_step<T: Float, X: OptimTensor<T>>(
+    param: &mut X, grad: &X, m: &mut X, v: &mut X, adam: &Adam<T>, step: usize,
+) -> Result<(), Error> {
+    if step == 0 {
+        return Err(Error::BadArgument("Adam steps are counted from 1".into()));
+    }
+    for t in [&*m, &*v, grad].iter() {
+        check_param(param, *t)?;
+    }
+    let one = T::one();
+    let t = T::from(step).unwrap();
+    m.axpby(adam.beta1, grad, one - adam.beta1)?;
+    v.axpby_square(adam.beta2, grad, one - adam.beta2)?;
+    // Bias correction of `v` is moved out of the square root.
+    let (bc1, bc2) = (one - adam.beta1.powf(t), (one - adam.beta2.powf(t)).sqrt());
+    param.add_scaled_ratio(m, v, -adam.lr * bc2 / bc1, adam.eps * bc2)
+}
+
+/// Running mean and variance of batch normalization, which are used instead of batch statistics at inference.
+#[derive(Clone)]
+pub struct BatchNormStats<T: Float, X: OptimTensor<T>> {
+    mean: X,
+    var: X,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Float, X: OptimTensor<T>> BatchNormStats<T, X> {
+    /// Statistics of normalization of features of specified shape, mean is initialized with zeros and variance with ones.
+    pub fn new_in(context: &<X::Buffer as Buffer<T>>::Context, shape: &Shape) -> Result<Self, Error> {
+        Self::from_tensors(X::new_zeroed_in(context, shape)?, X::new_filled_in(context, shape, T::one())?)
+    }
+    /// Statistics with specified initial values.
+    ///
+    /// Fails with `Error::ShapeMismatch` if tensors have different shapes.
+    pub fn from_tensors(mean: X, var: X) -> Result<Self, Error> {
+        check_param(&mean, &var)?;
+        Ok(Self { mean, var, phantom: PhantomData })
+    }
+
+    /// Moves statistics towards the batch ones: `running = (1 - momentum) * running + momentum * batch`.
+    ///
+    /// Fails with `Error::ShapeMismatch` if batch statistics have other shape than the running ones.
+    pub fn update(&mut self, batch_mean: &X, batch_var: &X, momentum: T) -> Result<(), Error> {
+        check_param(&self.mean, batch_mean)?;
+        check_param(&self.var, batch_var)?;
+        let keep = T::one() - momentum;
+        self.mean.axpby(keep, batch_mean, momentum)?;
+        self.var.axpby(keep, batch_var, momentum)
+    }
+
+    /// Running mean.
+    pub fn mean(&self) -> &X {
+        &self.mean
+    }
+    /// Running variance.
+    pub fn var(&self) -> &X {
+        &self.var
+    }
+}
+
+/// Exponential moving average of parameters, its shadow copy is often evaluated instead of the trained parameters.
+#[derive(Clone)]
+pub struct Ema<T: Float, X: OptimTensor<T>> {
+    shadow: Vec<X>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Float, X: OptimTensor<T>> Ema<T, X> {
+    /// Average initialized with copies of the parameters.
+    pub fn new(params: &[&X]) -> Result<Self, Error> {
+        let shadow = params.iter().map(|p| p.clone_contiguous()).collect::<Result<_, _>>()?;
+        Ok(Self { shadow, phantom: PhantomData })
+    }
+    /// Checks that the parameters match the shadow copies in number and shapes.
+    fn check_params<'a>(&self, params: impl ExactSizeIterator<Item = &'a X>) -> Result<(), Error> where X: 'a {
+        if params.len() != self.shadow.len() {
+            return Err(Error::BadSize { expected: self.shadow.len(), found: params.len() });
+        }
+        params.zip(self.shadow.iter()).try_for_each(|(p, s)| check_param(s, p))
+    }
+
+    /// Moves the average towards the parameters: `shadow = decay * shadow + (1 - decay) * param`,
+    /// that is linear interpolation from the parameter to the shadow with weight `decay`.
+    ///
+    /// Parameters must be passed in the same order as to `new`.
+    /// Fails with `Error::BadArgument` if `decay` isn't between zero and one, with `Error::BadSize` if the number
+    /// of parameters differs and with `Error::ShapeMismatch` if any of them has other shape than its shadow copy.
+    pub fn update(&mut self, params: &[&X], decay: T) -> Result<(), Error> {
+        if !(decay >= T::zero() && decay <= T::one()) {
+            return Err(Error::BadArgument("EMA decay must be between 0 and 1".into()));
+        }
+        self.check_params(params.iter().cloned())?;
+        for (s, p) in self.shadow.iter_mut().zip(params.iter()) {
+            s.axpby(decay, p, T::one() - decay)?;
+        }
+        Ok(())
+    }
+    /// Overwrites the parameters with the average, e.g. for evaluation.
+    ///
+    /// Keep a copy of the parameters if training continues afterwards.
+    /// Fails as `update` if the parameters don't match the shadow copies.
+    pub fn copy_to(&self, params: &mut [&mut X]) -> Result<(), Error> {
+        self.check_params(params.iter().map(|p| &**p))?;
+        for (p, s) in params.iter_mut().zip(self.shadow.iter()) {
+            p.copy_from(s)?;
+        }
+        Ok(())
+    }
+
+    /// Averaged parameters in the order they were passed to `new`.
+    pub fn shadow(&self) -> &[X] {
+        &self.shadow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HostTensor;
+
+    #[test]
+    fn sgd() {
+        let mut p = HostTensor::from(vec![1.0, 2.0]);
+        let g = HostTensor::from(vec![0.5, -1.0]);
+        sgd_step(&mut p, &g, 0.1).unwrap();
+        assert!(p.iter().cloned().eq([0.95, 2.1].iter().cloned()));
+        assert!(matches!(sgd_step(&mut p, &HostTensor::from(vec![1.0]), 0.1), Err(Error::ShapeMismatch(..))));
+    }
+
+    #[test]
+    fn batch_norm_stats() {
+        let mut stats = BatchNormStats::<f64, HostTensor<f64>>::new_in(&(), &Shape::from([2])).unwrap();
+        let (mean, var) = (HostTensor::from(vec![1.0, 2.0]), HostTensor::from(vec![3.0, 5.0]));
+        stats.update(&mean, &var, 0.5).unwrap();
+        stats.update(&mean, &var, 0.5).unwrap();
+        assert!(stats.mean().iter().cloned().eq([0.75, 1.5].iter().cloned()));
+        assert!(stats.var().iter().cloned().eq([2.5, 4.0].iter().cloned()));
+        assert!(matches!(stats.update(&mean, &HostTensor::from(vec![1.0]), 0.5), Err(Error::ShapeMismatch(..))));
+        assert!(BatchNormStats::<f64, _>::from_tensors(mean, HostTensor::from(vec![1.0])).is_err());
+    }
+
+    #[test]
+    fn weight_decay() {
+        let p = HostTensor::from(vec![1.0, -2.0]);
+        let mut g = HostTensor::from(vec![0.5, 0.5]);
+        add_weight_decay(&mut g, &p, 0.25).unwrap();
+        assert!(g.iter().cloned().eq([0.75, 0.0].iter().cloned()));
+        assert!(matches!(add_weight_decay(&mut g, &HostTensor::from(vec![1.0]), 0.1), Err(Error::ShapeMismatch(..))));
+    }
+
+    #[test]
+    fn sgd_momentum() {
+        let mut p = HostTensor::from(vec![1.0]);
+        let mut v = p.zeros_like().unwrap();
+        let g = HostTensor::from(vec![1.0]);
+        sgd_momentum_step(&mut p, &g, &mut v, 0.5, 0.5).unwrap();
+        assert_eq!((p[[0]], v[[0]]), (0.5, 1.0));
+        sgd_momentum_step(&mut p, &g, &mut v, 0.5, 0.5).unwrap();
+        assert_eq!((p[[0]], v[[0]]), (-0.25, 1.5));
+        let mut w = HostTensor::from(vec![0.0, 0.0]);
+        assert!(matches!(sgd_momentum_step(&mut p, &g, &mut w, 0.5, 0.5), Err(Error::ShapeMismatch(..))));
+    }
+
+    #[test]
+    fn adam() {
+        let adam = Adam { lr: 0.1, ..Adam::default() };
+        let mut p = HostTensor::from(vec![1.0f64, -1.0, 0.0]);
+        let (mut m, mut v) = (p.zeros_like().unwrap(), p.zeros_like().unwrap());
+        let g = HostTensor::from(vec![2.0, -0.5, 0.0]);
+        adam_step(&mut p, &g, &mut m, &mut v, &adam, 1).unwrap();
+        // After bias correction the first step is `lr * sign(grad)` up to `eps`.
+        let expected = [0.9, -0.9, 0.0];
+        assert!(p.iter().zip(expected.iter()).all(|(x, y)| (x - y).abs() < 1e-6));
+        assert!((m[[0]] - 0.2).abs() < 1e-12 && (v[[0]] - 0.004).abs() < 1e-12);
+
+        // Reference computation of the second step.
+        let p0 = p[[0]];
+        adam_step(&mut p, &g, &mut m, &mut v, &adam, 2).unwrap();
+        let (m1, v1) = (0.9 * 0.2 + 0.1 * 2.0, 0.999 * 0.004 + 0.001 * 4.0);
+        let x = p0 - 0.1 * (m1 / (1.0 - 0.81)) / ((v1 / (1.0 - 0.998001f64)).sqrt() + 1e-8);
+        assert!((p[[0]] - x).abs() < 1e-12);
+
+        assert!(matches!(adam_step(&mut p, &g, &mut m, &mut v, &adam, 0), Err(Error::BadArgument(_))));
+        let mut w = HostTensor::from(vec![0.0]);
+        assert!(matches!(adam_step(&mut p, &g, &mut m, &mut w, &adam, 3), Err(Error::ShapeMismatch(..))));
+    }
+
+    #[test]
+    fn ema() {
+        let (a, b) = (HostTensor::from(vec![1.0, 2.0]), HostTensor::from(vec![4.0]));
+        let mut ema = Ema::new(&[&a, &b]).unwrap();
+        let (a1, b1) = (HostTensor::from(vec![3.0, 0.0]), HostTensor::from(vec![0.0]));
+        ema.update(&[&a1, &b1], 0.75).unwrap();
+        assert!(ema.shadow()[0].iter().cloned().eq([1.5, 1.5].iter().cloned()));
+        assert_eq!(ema.shadow()[1][[0]], 3.0);
+
+        let (mut x, mut y) = (a1.clone(), b1.clone());
+        ema.copy_to(&mut [&mut x, &mut y]).unwrap();
+        assert!(x == ema.shadow()[0] && y == ema.shadow()[1]);
+        // Shadow copies don't share memory with the parameters.
+        assert!(a1.iter().cloned().eq([3.0, 0.0].iter().cloned()));
+
+        assert!(matches!(ema.update(&[&a1], 0.5), Err(Error::BadSize { expected: 2, found: 1 })));
+        assert!(matches!(ema.update(&[&b1, &a1], 0.5), Err(Error::ShapeMismatch(..))));
+        assert!(matches!(ema.update(&[&a1, &b1], 1.5), Err(Error::BadArgument(_))));
+        assert!(matches!(ema.copy_to(&mut [&mut y, &mut x]), Err(Error::ShapeMismatch(..))));
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Mutex,
+    time::SystemTime,
+};
+use num_traits::{NumCast, Zero};
+use crate::{Float, Error};
+
+
+/// Source of random numbers used by tensor operations.
+///
+/// It's intentionally minimal, so that an adapter for any external generator is trivial to write.
+pub trait Rng {
+    /// Next random number, all bits must be uniformly distributed.
+    fn next_u64(&mut self) -> u64;
+
+    /// Random number uniformly distributed in `[0, 1)`.
+    fn uniform<T: Float>(&mut self) -> T {
+        // 53 bits fit into the `f64` mantissa exactly.
+        let x: T = NumCast::from((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64).unwrap();
+        // Casting to less precise type may round the number up to one.
+        if x < T::one() { x } else { T::one() - T::epsilon() }
+    }
+}
+
+/// Seeded pseudo-random generator, so that random operations could be reproduced exactly.
+///
+/// It's xoshiro256** which state is initialized from the seed with SplitMix64.
+/// The sequence for a given seed is the same on all platforms.
+/// Random device operations (e.g. `DeviceTensor::dropout`) generate numbers on the host using the same generator,
+/// so they give the same results as host ones. Generators running on the device itself would produce different sequences.
+#[derive(Clone, Debug)]
+pub struct RngState {
+    s: [u64; 4],
+}
+
+impl RngState {
+    /// Generator with the state derived from the `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut x = seed;
+        let mut splitmix = || {
+            x = x.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Self { s: [splitmix(), splitmix(), splitmix(), splitmix()] }
+    }
+    /// Generator seeded from system entropy, its results are not reproducible.
+    pub fn from_entropy() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        if let Ok(time) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            hasher.write_u128(time.as_nanos());
+        }
+        Self::new(hasher.finish())
+    }
+}
+
+impl Rng for RngState {
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+}
+
+/// Generator shared by the whole program, initialized from entropy on the first use.
+static GLOBAL_RNG: Mutex<Option<RngState>> = Mutex::new(None);
+
+/// Handle to the global generator, e.g. `tensor.dropout(p, true, &mut GlobalRng)`.
+///
+/// The generator is guarded by a mutex, so it could be used from multiple threads,
+/// but then the order of numbers between threads isn't deterministic. Use separate `RngState` for reproducibility.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlobalRng;
+
+impl Rng for GlobalRng {
+    fn next_u64(&mut self) -> u64 {
+        GLOBAL_RNG.lock().unwrap().get_or_insert_with(RngState::from_entropy).next_u64()
+    }
+}
+
+/// Reseeds the global generator, so that the following operations that use `GlobalRng` are reproducible.
+///
+/// The generator becomes `RngState::new(seed)`, so it yields the same sequence as a fresh generator with this seed.
+/// Random operations take the generator as an argument (`dropout` of host and device tensors, `reservoir_sample`),
+/// so only those called with `GlobalRng` are affected, generators passed explicitly keep their own state.
+/// The numbers are taken in the order of calls, so calls from several threads remain nondeterministic.
+///
+/// Other operations don't use random numbers and give the same results on each run with the same inputs:
+/// host ones on all platforms, device ones for the same device, work-group size and compiler options,
+/// because the order of floating-point accumulation in `reduce_device` depends on the work-group size
+/// (see `DeviceContext::set_work_group_size`) and relaxed math options change rounding.
+/// Device results may also differ from host ones in rounding.
+pub fn set_global_seed(seed: u64) {
+    *GLOBAL_RNG.lock().unwrap() = Some(RngState::new(seed));
+}
+
+/// Checks that the dropout probability `p` is in `[0, 1)`.
+pub(crate) fn check_dropout<T: Float>(p: T) -> Result<(), Error> {
+    if p >= Zero::zero() && p < T::one() {
+        Ok(())
+    } else {
+        Err(Error::BadArgument("dropout probability must be in [0, 1)".into()))
+    }
+}
+
+/// Random mask of inverted dropout.
+///
+/// Each element is zero with probability `p`, otherwise it is `1 / (1 - p)`.
+/// Fails with `Error::BadArgument` if `p` is not in `[0, 1)`.
+pub(crate) fn dropout_mask<T: Float>(len: usize, p: T, rng: &mut impl Rng) -> Result<Vec<T>, Error> {
+    check_dropout(p)?;
+    let scale = (T::one() - p).recip();
+    Ok((0..len).map(|_| if rng.uniform::<T>() < p { Zero::zero() } else { scale }).collect())
+}
+
+/// Selects `k` items of the stream uniformly at random without knowing its length in advance, e.g. tensors of a large dataset.
+///
+/// The stream is consumed entirely in a single pass (Algorithm R). Only the `k` selected items are held in memory at once,
+/// so the footprint is `k` tensors regardless of the stream length. Selected items are returned in unspecified order.
+/// If the stream is shorter than `k`, all its items are returned in the original order.
+pub fn reservoir_sample<T, I: IntoIterator<Item = T>>(stream: I, k: usize, rng: &mut impl Rng) -> Vec<T> {
+    let mut stream = stream.into_iter();
+    let mut reservoir = stream.by_ref().take(k).collect::<Vec<_>>();
+    if reservoir.len() < k {
+        return reservoir;
+    }
+    for (i, item) in (k..).zip(stream) {
+        // Uniform index in `[0, i]`, bias of the multiplication is at most `i / 2^64`.
+        let j = ((rng.next_u64() as u128 * (i as u128 + 1)) >> 64) as usize;
+        if j < k {
+            reservoir[j] = item;
+        }
+    }
+    reservoir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded() {
+        let (mut a, mut b) = (RngState::new(42), RngState::new(42));
+        assert!((0..16).all(|_| a.next_u64() == b.next_u64()));
+        assert_ne!(RngState::new(1).next_u64(), RngState::new(2).next_u64());
+        let x: f64 = GlobalRng.uniform();
+        assert!((0.0..1.0).contains(&x));
+        set_global_seed(42);
+        let mut c = RngState::new(42);
+        assert!((0..16).all(|_| GlobalRng.next_u64() == c.next_u64()));
+    }
+
+    #[test]
+    fn reservoir_sample() {
+        let mut rng = RngState::new(7);
+        assert_eq!(super::reservoir_sample(0..3, 5, &mut rng), vec![0, 1, 2]);
+        assert!(super::reservoir_sample(0..3, 0, &mut rng).is_empty());
+        let mut counts = [0; 10];
+        for _ in 0..2000 {
+            let mut sample = super::reservoir_sample(0..10, 3, &mut rng);
+            sample.sort_unstable();
+            sample.dedup();
+            assert_eq!(sample.len(), 3);
+            sample.iter().for_each(|&x| counts[x] += 1);
+        }
+        // Each item is expected to be selected 600 times.
+        assert!(counts.iter().all(|&n| (500..700).contains(&n)), "{:?}", counts);
+    }
+}
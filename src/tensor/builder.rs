@@ -0,0 +1,62 @@
+use crate::{
+    Prm, Buffer, Shape, Tensor, Error,
+};
+
+
+/// Builder that configures the shape, location and content of a tensor before creating it.
+///
+/// E.g. `TensorBuilder::new(&[2, 3]).location(&context).filled(1.0).build::<DeviceTensor<f32>>()`.
+/// Without `location` the tensor is built on the host, and without `filled` or `data` it is zeroed.
+/// Configuration is checked in `build`.
+pub struct TensorBuilder<'a, T: Prm, C = ()> {
+    shape: Shape,
+    context: &'a C,
+    fill: Option<T>,
+    data: Option<Vec<T>>,
+}
+
+impl<T: Prm> TensorBuilder<'static, T> {
+    /// Builder of a tensor of specified shape.
+    pub fn new(shape: &[usize]) -> Self {
+        Self { shape: Shape::from(shape), context: &(), fill: None, data: None }
+    }
+}
+
+impl<'a, T: Prm, C> TensorBuilder<'a, T, C> {
+    /// Hardware where the tensor will be located, e.g. `DeviceContext` of a device.
+    pub fn location<'b, D>(self, context: &'b D) -> TensorBuilder<'b, T, D> {
+        TensorBuilder { shape: self.shape, context, fill: self.fill, data: self.data }
+    }
+    /// Fill the tensor with the value.
+    pub fn filled(mut self, value: T) -> Self {
+        self.fill = Some(value);
+        self
+    }
+    /// Fill the tensor with zeros, it is the default.
+    pub fn zeroed(self) -> Self {
+        self.filled(T::zero())
+    }
+    /// Copy flattened data to the tensor.
+    pub fn data(mut self, data: Vec<T>) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Creates the tensor.
+    ///
+    /// Fails with `Error::BadArgument` if both `filled` and `data` are specified
+    /// and with `Error::BadSize` if the data length isn't equal to the shape content.
+    /// Other errors are the same as of `Tensor` constructors.
+    pub fn build<X: Tensor<T>>(self) -> Result<X, Error> where X::Buffer: Buffer<T, Context = C> {
+        match (self.fill, self.data) {
+            (Some(_), Some(_)) => Err(Error::BadArgument("tensor cannot be both filled and initialized with data".into())),
+            (_, Some(data)) => {
+                let mut tensor = X::new_empty_in(self.context, &self.shape)?;
+                tensor.store(&data)?;
+                Ok(tensor)
+            },
+            (Some(value), None) => X::new_filled_in(self.context, &self.shape, value),
+            (None, None) => X::new_zeroed_in(self.context, &self.shape),
+        }
+    }
+}
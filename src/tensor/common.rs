@@ -1,74 +1,479 @@
 use std::{
     rc::Rc,
+    sync::Arc,
+    ops::{self, Deref},
+    mem, cmp,
     marker::PhantomData,
 };
 use crate::{
-    Prm, Buffer, Shape, Tensor,
+    Prm, Num, Buffer, Shape, Tensor, Error, Index, Range,
+    IndexIter, contiguous_strides, check_len,
 };
 
+/// Reference-counting pointer to a shared buffer.
+///
+/// Implemented for single-threaded `Rc` and thread-safe `Arc`.
+pub trait SharedPtr<B: Clone>: Clone + Deref<Target = B> {
+    fn new(buffer: B) -> Self;
+    /// Clones the buffer if it is shared and provides mutable access to it.
+    fn make_mut(this: &mut Self) -> &mut B;
+    /// Takes the buffer out of the pointer, clones it if it is shared.
+    fn into_owned(this: Self) -> B;
+    /// Takes the buffer out of the pointer if it isn't shared.
+    fn try_unwrap(this: Self) -> Result<B, Self>;
+    /// Number of pointers to the buffer.
+    fn strong_count(this: &Self) -> usize;
+}
+impl<B: Clone> SharedPtr<B> for Rc<B> {
+    fn new(buffer: B) -> Self {
+        Rc::new(buffer)
+    }
+    fn make_mut(this: &mut Self) -> &mut B {
+        Rc::make_mut(this)
+    }
+    fn into_owned(this: Self) -> B {
+        Rc::try_unwrap(this).unwrap_or_else(|rc| (*rc).clone())
+    }
+    fn try_unwrap(this: Self) -> Result<B, Self> {
+        Rc::try_unwrap(this)
+    }
+    fn strong_count(this: &Self) -> usize {
+        Rc::strong_count(this)
+    }
+}
+impl<B: Clone> SharedPtr<B> for Arc<B> {
+    fn new(buffer: B) -> Self {
+        Arc::new(buffer)
+    }
+    fn make_mut(this: &mut Self) -> &mut B {
+        Arc::make_mut(this)
+    }
+    fn into_owned(this: Self) -> B {
+        Arc::try_unwrap(this).unwrap_or_else(|arc| (*arc).clone())
+    }
+    fn try_unwrap(this: Self) -> Result<B, Self> {
+        Arc::try_unwrap(this)
+    }
+    fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(this)
+    }
+}
+
 /// An intermediate structure that contains most of the Tensor functionality.
-pub struct CommonTensor<T: Prm, Buf: Buffer<T>> {
-    buffer: Rc<Buf>,
+///
+/// Tensor elements are placed in the buffer according to strides and offset,
+/// so the same buffer could be shared between different views of the data.
+#[derive(Clone)]
+pub struct CommonTensor<T: Prm, Buf: Buffer<T>, P: SharedPtr<Buf> = Rc<Buf>> {
+    buffer: P,
     pub shape: Shape,
-    phantom: PhantomData<T>,
+    pub strides: Vec<isize>,
+    pub offset: usize,
+    phantom: PhantomData<(T, Buf)>,
 }
 
-impl<T: Prm, Buf: Buffer<T>> CommonTensor<T, Buf> {
+impl<T: Prm, Buf: Buffer<T>, P: SharedPtr<Buf>> CommonTensor<T, Buf, P> {
     /// Create tensor from shared buffer and shape
-    pub fn from_shared_buffer(rc_buffer: Rc<Buf>, shape: &Shape) -> Self {
+    pub fn from_shared_buffer(rc_buffer: P, shape: &Shape) -> Self {
+        Self::from_shared_buffer_strided(rc_buffer, shape, contiguous_strides(shape), 0)
+    }
+    /// Create tensor from shared buffer, shape and location of elements in the buffer.
+    pub fn from_shared_buffer_strided(rc_buffer: P, shape: &Shape, strides: Vec<isize>, offset: usize) -> Self {
         Self {
             buffer: rc_buffer,
             shape: shape.clone(),
-            phantom: PhantomData::<T>,
+            strides,
+            offset,
+            phantom: PhantomData,
         }
     }
     /// Create tensor from specified buffer and shape
+    ///
+    /// Buffer length must be equal to the shape content, it's a caller's responsibility to check that.
     pub fn from_buffer(buffer: Buf, shape: &Shape) -> Self {
-        assert_eq!(buffer.len(), shape.iter().product());
-        Self::from_shared_buffer(Rc::new(buffer), shape)
+        assert_eq!(buffer.len(), shape.content());
+        Self::from_shared_buffer(P::new(buffer), shape)
+    }
+
+    /// Number of elements in tensor of specified shape.
+    /// Checks that both the number and the size in bytes don't overflow.
+    fn checked_len(shape: &Shape) -> Result<usize, Error> {
+        let len = shape.checked_content()?;
+        match len.checked_mul(mem::size_of::<T>()) {
+            Some(size) if size <= isize::MAX as usize => Ok(len),
+            _ => Err(Error::Overflow),
+        }
     }
 
     /// Provides access to inner buffer.
     pub fn buffer(&self) -> &Buf {
-        self.buffer.as_ref()
+        &self.buffer
     }
     /// Clones inner buffer if it is shared and provides mutable access to it.
     pub fn buffer_mut(&mut self) -> &mut Buf {
-        Rc::make_mut(&mut self.buffer)
+        P::make_mut(&mut self.buffer)
+    }
+    /// Takes the buffer out of the tensor if the tensor is plain and the buffer isn't shared.
+    pub fn try_into_buffer(self) -> Result<Buf, Self> {
+        if !self.is_plain() {
+            return Err(self);
+        }
+        let Self { buffer, shape, strides, offset, .. } = self;
+        P::try_unwrap(buffer).map_err(|buffer| Self::from_shared_buffer_strided(buffer, &shape, strides, offset))
+    }
+    /// Same tensor with other kind of pointer to the buffer.
+    ///
+    /// Buffer is cloned if it is shared.
+    pub fn into_ptr<Q: SharedPtr<Buf>>(self) -> CommonTensor<T, Buf, Q> {
+        CommonTensor::from_shared_buffer_strided(Q::new(P::into_owned(self.buffer)), &self.shape, self.strides, self.offset)
+    }
+
+    /// Stride of the specified axis.
+    pub fn stride(&self, axis: usize) -> isize {
+        self.strides.get(axis).cloned().unwrap_or(0)
+    }
+    /// Whether tensor elements are placed in the buffer one after another in flattened order.
+    pub fn is_contiguous(&self) -> bool {
+        let mut expected = 1;
+        for (i, &len) in self.shape.iter().enumerate() {
+            if len != 1 {
+                if self.stride(i) != expected {
+                    return false;
+                }
+                expected *= len as isize;
+            }
+        }
+        true
+    }
+    /// Whether tensor is contiguous and occupies the whole buffer.
+    pub fn is_plain(&self) -> bool {
+        self.is_contiguous() && self.offset == 0 && self.buffer.len() == self.shape.content()
+    }
+    /// Whether different positions of the tensor may refer to the same buffer element, e.g. if it is broadcasted.
+    ///
+    /// Axes are checked from the smallest stride, each of them must step over all the elements of the previous ones,
+    /// so a few layouts interleaving axes are reported as overlapping even if they are not.
+    pub fn is_overlapping(&self) -> bool {
+        if self.shape.content() == 0 {
+            return false;
+        }
+        let mut axes = self.shape.iter().enumerate()
+            .filter(|&(_, &len)| len > 1)
+            .map(|(i, &len)| (self.stride(i).unsigned_abs(), len))
+            .collect::<Vec<_>>();
+        axes.sort_unstable();
+        let mut extent = 0;
+        for (stride, len) in axes {
+            if stride <= extent {
+                return true;
+            }
+            extent += stride * (len - 1);
+        }
+        false
+    }
+
+    /// Buffer index of the tensor element at specified position.
+    ///
+    /// Position may have any length, missing coordinates are zeros.
+    /// Fails with `Error::BadIndex` if position is out of bounds.
+    pub fn buffer_index(&self, pos: &[usize]) -> Result<usize, Error> {
+        let mut index = self.offset as isize;
+        for (i, &x) in pos.iter().enumerate() {
+            if x >= self.shape[i] {
+                return Err(Error::BadIndex(pos.to_vec()));
+            }
+            index += x as isize * self.stride(i);
+        }
+        Ok(index as usize)
+    }
+
+    /// Iterator over buffer indices of tensor elements in flattened order.
+    pub fn indices(&self) -> IndexIter {
+        IndexIter::new(&self.shape, &self.strides, self.offset)
+    }
+
+    /// Range of buffer indices between the first and the last tensor element, empty if the tensor is.
+    pub fn span(&self) -> ops::Range<usize> {
+        if self.shape.content() == 0 {
+            return self.offset..self.offset;
+        }
+        let (mut first, mut last) = (self.offset as isize, self.offset as isize);
+        for (i, &len) in self.shape.iter().enumerate() {
+            let extent = (len as isize - 1) * self.stride(i);
+            if extent < 0 { first += extent } else { last += extent }
+        }
+        first as usize..(last as usize + 1)
+    }
+
+    /// Copies the data to a new buffer if the tensor is not plain.
+    pub fn make_plain(&mut self) -> Result<(), Error> {
+        if !self.is_plain() {
+            *self = self.clone_contiguous()?;
+        }
+        Ok(())
+    }
+}
+
+/// Index of the first element of the range along the axis of length `len` and the number of elements.
+///
+/// Negative bounds count from the end, then they are clamped to the axis as in Python slices.
+fn range_bounds(range: Range, len: isize) -> Result<(isize, isize), Error> {
+    let Range { start, end, step } = range;
+    if step == 0 {
+        return Err(Error::BadArgument("range step must not be zero".into()));
     }
+    let norm = |x: isize| if x < 0 { x + len } else { x };
+    let (start, end) = (norm(start), norm(end));
+    let (start, span) = if step > 0 {
+        let (start, end) = (start.clamp(0, len), end.clamp(0, len));
+        (start, end - start)
+    } else {
+        let (start, end) = (start.clamp(-1, len - 1), end.clamp(-1, len - 1));
+        (start, start - end)
+    };
+    // Span doesn't exceed `len`, so the count is computed in unsigned numbers without overflow even for extreme steps.
+    let count = if span > 0 { (span as usize - 1) / step.unsigned_abs() + 1 } else { 0 };
+    Ok((start, count as isize))
 }
 
-impl<T: Prm, Buf: Buffer<T>> Tensor<T> for CommonTensor<T, Buf> {
+impl<T: Prm, Buf: Buffer<T>, P: SharedPtr<Buf>> Tensor<T> for CommonTensor<T, Buf, P> {
     type Buffer = Buf;
 
-    unsafe fn new_uninit_in(context: &Buf::Context, shape: &Shape) -> Self {
-        Self::from_buffer(
-            Self::Buffer::new_uninit_in(context, shape.iter().product()),
+    unsafe fn new_uninit_in(context: &Buf::Context, shape: &Shape) -> Result<Self, Error> {
+        Ok(Self::from_buffer(
+            Self::Buffer::new_uninit_in(context, Self::checked_len(shape)?)?,
             shape,
-        )
+        ))
     }
-    fn new_filled_in(context: &Buf::Context, shape: &Shape, value: T) -> Self {
-        Self::from_buffer(
-            Self::Buffer::new_filled_in(context, shape.iter().product(), value),
+    fn new_empty_in(context: &Buf::Context, shape: &Shape) -> Result<Self, Error> {
+        Self::new_zeroed_in(context, shape)
+    }
+    fn new_filled_in(context: &Buf::Context, shape: &Shape, value: T) -> Result<Self, Error> {
+        Ok(Self::from_buffer(
+            Self::Buffer::new_filled_in(context, Self::checked_len(shape)?, value)?,
             shape,
-        )
+        ))
     }
-    fn new_zeroed_in(context: &Buf::Context, shape: &Shape) -> Self {
+    fn new_zeroed_in(context: &Buf::Context, shape: &Shape) -> Result<Self, Error> {
         Self::new_filled_in(context, shape, T::zero())
     }
 
+    fn context(&self) -> &Buf::Context {
+        self.buffer.context()
+    }
     fn shape(&self) -> &Shape {
         &self.shape
     }
+    fn buffer_refcount(&self) -> usize {
+        P::strong_count(&self.buffer)
+    }
+
+    fn view(&self, shape: &Shape) -> Result<Self, Error> {
+        let len = shape.checked_content()?;
+        if len != self.shape.content() {
+            return Err(Error::BadSize { expected: self.shape.content(), found: len });
+        }
+        if !self.is_contiguous() {
+            return Err(Error::NotContiguous);
+        }
+        Ok(Self::from_shared_buffer_strided(self.buffer.clone(), shape, contiguous_strides(shape), self.offset))
+    }
+    fn reshape(&self, shape: &Shape) -> Result<Self, Error> {
+        if self.is_contiguous() {
+            self.view(shape)
+        } else {
+            let mut tensor = Self::from_shared_buffer_strided(self.buffer.clone(), &self.shape, self.strides.clone(), self.offset);
+            tensor.make_plain()?;
+            tensor.view(shape)
+        }
+    }
+
+    fn resize(&self, shape: &[usize], fill: T) -> Result<Self, Error> {
+        if shape.len() < self.shape.len() {
+            return Err(Error::BadRank { expected: shape.len(), found: self.shape.len() });
+        }
+        let shape = Shape::from(shape);
+        let overlap = Shape::from((0..shape.len()).map(|i| cmp::min(self.shape[i], shape[i])).collect::<Vec<_>>());
+        let mut data = vec![T::zero(); overlap.content()];
+        Self::from_shared_buffer_strided(self.buffer.clone(), &overlap, self.strides.clone(), self.offset)
+            .load(data.as_mut_slice())?;
+        // Overlapping region of the new tensor is written through a temporary view.
+        let mut tensor = Self::new_filled_in(self.buffer.context(), &shape, fill)?;
+        tensor.shape = overlap;
+        tensor.store(data.as_slice())?;
+        tensor.shape = shape;
+        Ok(tensor)
+    }
 
-    fn reshape(&self, shape: &Shape) -> Self {
-        Self::from_shared_buffer(self.buffer.clone(), shape)
+    fn permute(&self, axes: &[usize]) -> Result<Self, Error> {
+        let mut sorted = axes.to_vec();
+        sorted.sort_unstable();
+        if sorted.iter().enumerate().any(|(i, &a)| i != a) {
+            return Err(Error::BadAxes(axes.to_vec()));
+        }
+        let len = usize::max(axes.len(), self.shape.len());
+        let axis = |i: usize| axes.get(i).cloned().unwrap_or(i);
+        let shape = Shape::from((0..len).map(|i| self.shape[axis(i)]).collect::<Vec<_>>());
+        let strides = (0..len).map(|i| self.stride(axis(i))).collect();
+        Ok(Self::from_shared_buffer_strided(self.buffer.clone(), &shape, strides, self.offset))
+    }
+    fn transpose(&self) -> Self {
+        self.permute(&[1, 0]).unwrap()
+    }
+    fn slice(&self, index: &[Index]) -> Result<Self, Error> {
+        let (mut dims, mut strides) = (Vec::new(), Vec::new());
+        let mut offset = self.offset as isize;
+        let mut axis = 0;
+        for &idx in index {
+            match idx {
+                Index::Single(i) => {
+                    let (len, stride) = (self.shape[axis] as isize, self.stride(axis));
+                    let j = if i < 0 { i + len } else { i };
+                    if j < 0 || j >= len {
+                        // Negative index is reported by its magnitude, which is out of bounds too.
+                        let mut pos = vec![0; axis + 1];
+                        pos[axis] = i.unsigned_abs();
+                        return Err(Error::BadIndex(pos));
+                    }
+                    offset += j * stride;
+                    axis += 1;
+                }
+                Index::Range(range) => {
+                    let (start, count) = range_bounds(range, self.shape[axis] as isize)?;
+                    if count > 0 {
+                        offset += start * self.stride(axis);
+                    }
+                    dims.push(count as usize);
+                    // Step of a range with at most one element doesn't matter, even if the stride overflows.
+                    let step = if count > 1 { range.step } else { 1 };
+                    strides.push(self.stride(axis).checked_mul(step).ok_or(Error::Overflow)?);
+                    axis += 1;
+                }
+                Index::NewAxis => {
+                    dims.push(1);
+                    strides.push(0);
+                }
+            }
+        }
+        for i in axis..self.shape.len() {
+            dims.push(self.shape[i]);
+            strides.push(self.stride(i));
+        }
+        Ok(Self::from_shared_buffer_strided(self.buffer.clone(), &Shape::from(dims), strides, offset as usize))
     }
 
-    fn load(&self, dst: &mut [T]) {
-        self.buffer.load(dst);
+    fn unfold(&self, axis: usize, size: usize, step: usize) -> Result<Self, Error> {
+        let len = self.shape[axis];
+        if size == 0 || step == 0 || size > len {
+            return Err(Error::BadArgument(format!("windows of size {} with step {} don't fit axis {} of size {}", size, step, axis, len)));
+        }
+        let ndim = usize::max(self.shape.len(), axis + 1);
+        let mut dims = (0..ndim).map(|i| self.shape[i]).collect::<Vec<_>>();
+        let mut strides = (0..ndim).map(|i| self.stride(i)).collect::<Vec<_>>();
+        dims[axis] = (len - size) / step + 1;
+        strides[axis] *= step as isize;
+        dims.push(size);
+        strides.push(self.stride(axis));
+        Ok(Self::from_shared_buffer_strided(self.buffer.clone(), &Shape::from(dims), strides, self.offset))
     }
-    fn store(&mut self, src: &[T]) {
-        Rc::make_mut(&mut self.buffer).store(src);
+
+    fn broadcast_to(&self, shape: &Shape) -> Result<Self, Error> {
+        if self.shape.broadcast(shape)? != *shape {
+            return Err(Error::ShapeMismatch(self.shape.clone(), shape.clone()));
+        }
+        let strides = (0..shape.len()).map(|i| {
+            if self.shape[i] == 1 { 0 } else { self.stride(i) }
+        }).collect();
+        Ok(Self::from_shared_buffer_strided(self.buffer.clone(), shape, strides, self.offset))
+    }
+
+    fn clone_contiguous(&self) -> Result<Self, Error> {
+        // The whole buffer is overwritten below.
+        let mut buffer = unsafe { Buf::new_uninit_in(self.buffer.context(), self.shape.content())? };
+        if self.is_plain() {
+            buffer.copy_from(&self.buffer)?;
+        } else {
+            let mut data = vec![T::zero(); self.shape.content()];
+            self.load(data.as_mut_slice())?;
+            buffer.store(data.as_slice())?;
+        }
+        Ok(Self::from_buffer(buffer, &self.shape))
+    }
+
+    fn compact(&self) -> Result<Self, Error> {
+        if 2 * self.shape.content() < self.buffer.len() {
+            self.clone_contiguous()
+        } else {
+            Ok(self.clone())
+        }
+    }
+
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error> {
+        if self.shape != src.shape {
+            return Err(Error::ShapeMismatch(self.shape.clone(), src.shape.clone()));
+        }
+        // Broadcasted elements share memory, so they are separated before writing.
+        if self.is_overlapping() {
+            self.make_plain()?;
+        }
+        if self.is_plain() && src.is_plain() {
+            self.buffer_mut().copy_from(&src.buffer)
+        } else {
+            let mut data = vec![T::zero(); src.shape.content()];
+            src.load(data.as_mut_slice())?;
+            self.store(data.as_slice())
+        }
+    }
+
+    fn assign_slice(&mut self, ranges: &[Range], src: &Self) -> Result<(), Error> {
+        // Broadcasted elements share memory, so they are separated before writing.
+        if !self.is_contiguous() {
+            self.make_plain()?;
+        }
+        let index = ranges.iter().map(|&r| Index::Range(r)).collect::<Vec<_>>();
+        let view = self.slice(&index)?;
+        // Scalars and lower-rank sources are repeated over the sub-region.
+        let src = src.broadcast_to(&view.shape)
+            .map_err(|_| Error::ShapeMismatch(view.shape.clone(), src.shape.clone()))?;
+        let mut values = vec![T::zero(); src.shape.content()];
+        src.load(values.as_mut_slice())?;
+        if values.is_empty() {
+            return Ok(());
+        }
+        let (span, indices) = (view.span(), view.indices());
+        // The view is dropped so that the buffer isn't cloned needlessly.
+        drop(view);
+        self.buffer_mut().scatter(span, indices, &values)
+    }
+
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num {
+        self.make_plain()?;
+        self.buffer_mut().fill_iota(start, step)
+    }
+
+    fn load(&self, dst: &mut [T]) -> Result<(), Error> {
+        check_len(self.shape.content(), dst.len())?;
+        if self.is_plain() {
+            self.buffer.load(dst)
+        } else if dst.is_empty() {
+            Ok(())
+        } else {
+            self.buffer.gather(self.span(), self.indices(), dst)
+        }
+    }
+    fn store(&mut self, src: &[T]) -> Result<(), Error> {
+        check_len(self.shape.content(), src.len())?;
+        // Elements sharing memory would be overwritten by each other, so they are separated first.
+        if self.is_overlapping() {
+            self.make_plain()?;
+        }
+        if self.is_plain() {
+            self.buffer_mut().store(src)
+        } else if src.is_empty() {
+            Ok(())
+        } else {
+            let (span, indices) = (self.span(), self.indices());
+            self.buffer_mut().scatter(span, indices, src)
+        }
     }
 }
@@ -0,0 +1,90 @@
+use std::fmt;
+use crate::{Float, Shape};
+
+
+/// Element-wise comparison of two tensors of the same shape, see `Tensor::compare_report`.
+///
+/// Elements `a` and `b` match if `|a - b| <= atol + rtol * |b|` where `b` is the reference, as in NumPy `allclose`.
+/// NaNs match each other and mismatch any number, infinities match only the infinity of the same sign, as in NumPy `isclose`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComparisonReport<T> {
+    /// Shape of the compared tensors.
+    pub shape: Shape,
+    /// Maximum absolute difference over elements that are both non-NaN.
+    pub max_abs_diff: T,
+    /// Maximum difference relative to the reference element, infinite if only the reference is zero.
+    pub max_rel_diff: T,
+    /// Multi-index of the element with the largest absolute difference, `None` if there are no comparable elements.
+    pub max_index: Option<Vec<usize>>,
+    /// Number of elements that don't match.
+    pub mismatched: usize,
+    /// Number of elements that don't match because exactly one of them is NaN, they are included in `mismatched`.
+    pub nan_mismatched: usize,
+}
+
+impl<T: Float> ComparisonReport<T> {
+    /// Compares flattened elements of a tensor of the `shape` with the reference.
+    pub(crate) fn new(shape: &Shape, data: &[T], reference: &[T], atol: T, rtol: T) -> Self {
+        let zero = T::zero();
+        let mut report = Self {
+            shape: shape.clone(),
+            max_abs_diff: zero, max_rel_diff: zero, max_index: None,
+            mismatched: 0, nan_mismatched: 0,
+        };
+        let mut max_flat = None;
+        for (i, (&a, &b)) in data.iter().zip(reference).enumerate() {
+            match (a.is_nan(), b.is_nan()) {
+                (true, true) => continue,
+                (false, false) => (),
+                _ => {
+                    report.mismatched += 1;
+                    report.nan_mismatched += 1;
+                    continue;
+                }
+            }
+            // Equal infinities have zero difference.
+            let diff = if a == b { zero } else { (a - b).abs() };
+            let rel = if diff == zero { zero } else { diff / b.abs() };
+            if max_flat.is_none() || diff > report.max_abs_diff {
+                report.max_abs_diff = diff;
+                max_flat = Some(i);
+            }
+            report.max_rel_diff = report.max_rel_diff.max(rel);
+            // Infinite reference would make the tolerance infinite, so infinities are compared exactly.
+            let close = if a.is_infinite() || b.is_infinite() { a == b } else { diff <= atol + rtol * b.abs() };
+            if !close {
+                report.mismatched += 1;
+            }
+        }
+        report.max_index = max_flat.map(|mut i| {
+            (0..shape.len()).map(|k| {
+                let j = i % shape[k];
+                i /= shape[k];
+                j
+            }).collect()
+        });
+        report
+    }
+    /// Number of compared elements.
+    pub fn total(&self) -> usize {
+        self.shape.content()
+    }
+    /// Whether all the elements match.
+    pub fn is_close(&self) -> bool {
+        self.mismatched == 0
+    }
+}
+
+impl<T: Float + fmt::Display> fmt::Display for ComparisonReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} elements of shape {} mismatch", self.mismatched, self.total(), self.shape)?;
+        if self.nan_mismatched > 0 {
+            write!(f, " ({} by NaN)", self.nan_mismatched)?;
+        }
+        write!(f, ", max abs diff {}", self.max_abs_diff)?;
+        if let Some(index) = &self.max_index {
+            write!(f, " at {:?}", index)?;
+        }
+        write!(f, ", max rel diff {}", self.max_rel_diff)
+    }
+}
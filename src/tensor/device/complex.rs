@@ -0,0 +1,42 @@
+use num_complex::Complex;
+use crate::{
+    Prm, FloatInterop, Error,
+    DeviceTensor,
+};
+
+
+/// Complex numbers are stored as two-component vectors on the device, `x` is the real part and `y` is the imaginary one.
+///
+/// Vector operators of OpenCL C work componentwise, so only addition and subtraction of complex numbers are native,
+/// multiplication is written out explicitly.
+impl<T: Prm + FloatInterop> DeviceTensor<Complex<T>> {
+    /// Complex tensor from broadcasted real and imaginary parts, see `HostTensor::from_parts`.
+    pub fn from_parts(re: &DeviceTensor<T>, im: &DeviceTensor<T>) -> Result<Self, Error> {
+        re.binary_op(im, &format!("({})(a, b)", T::COMPLEX_NAME))
+    }
+    /// Real part of each element.
+    pub fn real(&self) -> Result<DeviceTensor<T>, Error> {
+        self.unary_op("a.x")
+    }
+    /// Imaginary part of each element.
+    pub fn imag(&self) -> Result<DeviceTensor<T>, Error> {
+        self.unary_op("a.y")
+    }
+    /// Complex conjugate of each element.
+    pub fn conj(&self) -> Result<Self, Error> {
+        self.unary_op(&format!("({})(a.x, -a.y)", T::COMPLEX_NAME))
+    }
+    /// Magnitude of each element.
+    pub fn abs(&self) -> Result<DeviceTensor<T>, Error> {
+        self.unary_op("hypot(a.x, a.y)")
+    }
+
+    /// Elementwise sum of two broadcasted tensors.
+    pub fn add(&self, other: &Self) -> Result<Self, Error> {
+        self.binary_op(other, "a + b")
+    }
+    /// Elementwise complex product of two broadcasted tensors.
+    pub fn mul(&self, other: &Self) -> Result<Self, Error> {
+        self.binary_op(other, &format!("({})(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x)", T::COMPLEX_NAME))
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{
+    Prm, Interop, Error,
+    DeviceContext, Shape, Tensor, DeviceTensor,
+};
+
+
+/// Creation of tensors in the context.
+impl DeviceContext {
+    /// Create tensor filled with value, see `Tensor::new_filled_in`.
+    pub fn filled<T: Prm + Interop>(&self, shape: &Shape, value: T) -> Result<DeviceTensor<T>, Error> {
+        DeviceTensor::new_filled_in(self, shape, value)
+    }
+    /// Create tensor filled with zeros, see `Tensor::new_zeroed_in`.
+    pub fn zeros<T: Prm + Interop>(&self, shape: &Shape) -> Result<DeviceTensor<T>, Error> {
+        DeviceTensor::new_zeroed_in(self, shape)
+    }
+    /// Create tensor of specified shape and copy the data from vector to it.
+    ///
+    /// Fails with `Error::BadSize` if the vector length isn't equal to the shape content.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_vec<T: Prm + Interop>(&self, vec: Vec<T>, shape: &Shape) -> Result<DeviceTensor<T>, Error> {
+        self.from_slice(&vec, shape)
+    }
+    /// Create tensor of specified shape and copy the data from slice to it.
+    ///
+    /// Fails with `Error::BadSize` if the slice length isn't equal to the shape content.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_slice<T: Prm + Interop>(&self, slice: &[T], shape: &Shape) -> Result<DeviceTensor<T>, Error> {
+        let mut tensor = unsafe { DeviceTensor::new_uninit_in(self, shape)? };
+        tensor.store(slice)?;
+        Ok(tensor)
+    }
+}
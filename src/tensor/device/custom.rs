@@ -0,0 +1,76 @@
+use ocl::{Kernel, builders::KernelBuilder};
+use crate::{
+    Prm, Interop, Error,
+    DeviceContext, DeviceTensor, Tensor,
+};
+
+
+/// Argument of a user-provided kernel, see `DeviceContext::run_kernel`.
+pub struct KernelArg<'a> {
+    context: Option<&'a DeviceContext>,
+    /// Passes the argument to the kernel, OpenCL buffers are passed by (reference-counted) value.
+    set: Box<dyn Fn(&mut KernelBuilder) + 'a>,
+}
+
+impl<'a> KernelArg<'a> {
+    /// Tensor passed as `__global const T *` pointer to its elements in flattened order.
+    ///
+    /// Fails with `Error::NotContiguous` if the elements don't occupy the whole buffer,
+    /// use `clone_contiguous` in that case.
+    pub fn tensor<T: Prm + Interop>(tensor: &'a DeviceTensor<T>) -> Result<Self, Error> {
+        if !tensor.inner().is_plain() {
+            return Err(Error::NotContiguous);
+        }
+        Ok(Self {
+            context: Some(tensor.context()),
+            set: Box::new(move |builder| { builder.arg(tensor.inner().buffer().mem().clone()); }),
+        })
+    }
+    /// Tensor passed as `__global T *` pointer, so that the kernel could write to it.
+    ///
+    /// The tensor is copied if its buffer is shared or it isn't contiguous, so other tensors aren't affected.
+    pub fn tensor_mut<T: Prm + Interop>(tensor: &'a mut DeviceTensor<T>) -> Result<Self, Error> {
+        let inner = tensor.inner_mut();
+        inner.make_plain()?;
+        inner.buffer_mut();
+        Self::tensor(tensor)
+    }
+    /// Scalar value passed by value in device representation.
+    pub fn scalar<T: Prm + Interop + 'a>(value: T) -> Self {
+        let dev = value.to_dev();
+        Self { context: None, set: Box::new(move |builder| { builder.arg(dev); }) }
+    }
+}
+
+impl DeviceContext {
+    /// Launches kernel registered by `register_kernel` with global work size `work_size`.
+    ///
+    /// Work size is rounded up to a multiple of work-group size if it is set, so the kernel must check its global id.
+    /// Fails with `Error::BadArgument` if there is no such kernel or arguments don't match its signature,
+    /// and with `Error::ContextMismatch` if tensors belong to other context.
+    ///
+    /// # Safety
+    ///
+    /// Kernel code must be trusted, e.g. it must not access memory out of tensor bounds.
+    pub unsafe fn run_kernel(&self, name: &str, args: &[KernelArg], work_size: usize) -> Result<(), Error> {
+        let src = self.kernel_source(name).ok_or_else(|| Error::BadArgument(format!("kernel '{}' is not registered", name)))?;
+        if args.iter().any(|a| a.context.is_some_and(|c| c != self)) {
+            return Err(Error::ContextMismatch);
+        }
+        if work_size == 0 {
+            return Ok(());
+        }
+        let program = self.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name(name)
+        .queue(self.queue().clone());
+        for arg in args {
+            (arg.set)(&mut builder);
+        }
+        self.set_work_size(&mut builder, work_size);
+        let kernel = builder.build()?;
+        self.run(name, &kernel)
+    }
+}
@@ -0,0 +1,244 @@
+use num_traits::Zero;
+use crate::{
+    Prm, Interop, Num, Float, Error, f16, bf16,
+    Shape, Tensor, DeviceTensor, elementwise, check_diff, diff_views,
+};
+
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    /// Evaluates OpenCL C expression of `a` for each element of `self`.
+    pub(crate) fn unary_op<U: Prm + Interop>(&self, expr: &str) -> Result<DeviceTensor<U>, Error> {
+        elementwise(expr, self.shape(), &[self.inner()]).map(DeviceTensor::from_inner)
+    }
+    /// Evaluates OpenCL C expression of `a` and `b` for each pair of elements of broadcasted `self` and `other`.
+    pub(crate) fn binary_op<U: Prm + Interop, V: Prm + Interop>(&self, other: &DeviceTensor<U>, expr: &str) -> Result<DeviceTensor<V>, Error> {
+        let shape = self.shape().broadcast(other.shape())?;
+        let (a, b) = (self.broadcast_to(&shape)?, other.broadcast_to(&shape)?);
+        elementwise(expr, &shape, &[a.inner(), b.inner()]).map(DeviceTensor::from_inner)
+    }
+}
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    /// Elementwise choice between `a` where `cond` is nonzero and `b` elsewhere, see `HostTensor::select`.
+    pub fn select(cond: &DeviceTensor<u8>, a: &Self, b: &Self) -> Result<Self, Error> {
+        let shape = cond.shape().broadcast(a.shape())?.broadcast(b.shape())?;
+        let (c, a, b) = (cond.broadcast_to(&shape)?, a.broadcast_to(&shape)?, b.broadcast_to(&shape)?);
+        elementwise("a != 0 ? b : c", &shape, &[c.inner(), a.inner(), b.inner()]).map(DeviceTensor::from_inner)
+    }
+}
+
+impl<T: Prm + Interop + Num + PartialOrd> DeviceTensor<T> {
+    /// Elementwise remainder of truncated division of two broadcasted tensors, see `HostTensor::rem` for the sign.
+    ///
+    /// Integers use `%` operator and floating-point numbers use `fmod`, both truncate the quotient as on the host.
+    /// Result of integer division by zero is unspecified in OpenCL C.
+    pub fn rem(&self, other: &Self) -> Result<Self, Error> {
+        let expr = if ["float", "double"].contains(&T::DEV_NAME) { "fmod(a, b)" } else { "a % b" };
+        self.binary_op(other, expr)
+    }
+    /// The `n`-th discrete difference along the `axis`, see `HostTensor::diff`.
+    ///
+    /// Each difference is a subtraction kernel of the views without the first and the last element.
+    pub fn diff(&self, n: usize, axis: usize) -> Result<Self, Error> {
+        check_diff(self.shape(), n, axis)?;
+        let mut x = self.slice(&[])?;
+        for _ in 0..n {
+            let (hi, lo) = diff_views(&x, axis)?;
+            x = hi.binary_op(&lo, "a - b")?;
+        }
+        Ok(x)
+    }
+}
+
+/// Complex numbers are not supported because weights are multiplied componentwise.
+impl<T: Prm + Interop + Num + PartialOrd> DeviceTensor<T> {
+    /// Linear interpolation `self + weight * (other - self)`, see `HostTensor::lerp`.
+    pub fn lerp(&self, other: &Self, weight: T) -> Result<Self, Error> {
+        self.lerp_by(other, &Self::new_filled_in(self.context(), &Shape::from([]), weight)?)
+    }
+    /// Linear interpolation with a tensor of weights, see `HostTensor::lerp_by`.
+    ///
+    /// Computed by a single kernel without temporary tensors.
+    pub fn lerp_by(&self, other: &Self, weight: &Self) -> Result<Self, Error> {
+        let shape = self.shape().broadcast(other.shape())?.broadcast(weight.shape())?;
+        let (a, b, w) = (self.broadcast_to(&shape)?, other.broadcast_to(&shape)?, weight.broadcast_to(&shape)?);
+        elementwise("a + c * (b - a)", &shape, &[a.inner(), b.inner(), w.inner()]).map(DeviceTensor::from_inner)
+    }
+}
+
+impl<T: Prm + Interop + PartialOrd> DeviceTensor<T> {
+    /// Elementwise maximum of two broadcasted tensors.
+    ///
+    /// Result is unspecified if any of elements is NaN.
+    pub fn maximum(&self, other: &Self) -> Result<Self, Error> {
+        self.binary_op(other, "max(a, b)")
+    }
+    /// Elementwise minimum of two broadcasted tensors.
+    ///
+    /// Result is unspecified if any of elements is NaN.
+    pub fn minimum(&self, other: &Self) -> Result<Self, Error> {
+        self.binary_op(other, "min(a, b)")
+    }
+}
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Sign of each element: `1` for positive and `-1` for negative, zeros and NaNs are kept as is.
+    pub fn sign(&self) -> Result<Self, Error> {
+        self.unary_op("isnan(a) ? a : sign(a)")
+    }
+    /// Largest integer not greater than each element.
+    pub fn floor(&self) -> Result<Self, Error> {
+        self.unary_op("floor(a)")
+    }
+    /// Smallest integer not less than each element.
+    pub fn ceil(&self) -> Result<Self, Error> {
+        self.unary_op("ceil(a)")
+    }
+    /// Nearest integer to each element, halfway cases are rounded away from zero as on the host.
+    pub fn round(&self) -> Result<Self, Error> {
+        self.unary_op("round(a)")
+    }
+    /// Integer part of each element.
+    pub fn trunc(&self) -> Result<Self, Error> {
+        self.unary_op("trunc(a)")
+    }
+    /// Fractional part of each element, `x - x.trunc()`, so it has the same sign as `x`.
+    pub fn fract(&self) -> Result<Self, Error> {
+        self.unary_op("a - trunc(a)")
+    }
+}
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Mask of NaN elements: `1` where element is NaN and `0` elsewhere.
+    pub fn isnan(&self) -> Result<DeviceTensor<u8>, Error> {
+        self.unary_op("isnan(a) ? 1 : 0")
+    }
+    /// Mask of infinite elements of any sign.
+    pub fn isinf(&self) -> Result<DeviceTensor<u8>, Error> {
+        self.unary_op("isinf(a) ? 1 : 0")
+    }
+    /// Mask of elements that are neither infinite nor NaN.
+    pub fn isfinite(&self) -> Result<DeviceTensor<u8>, Error> {
+        self.unary_op("isfinite(a) ? 1 : 0")
+    }
+    /// Whether any element is NaN.
+    ///
+    /// The check is done on the device, only the resulting flag is loaded to the host.
+    pub fn any_nan(&self) -> Result<bool, Error> {
+        let mut flag = [false];
+        self.isnan()?.any(None, false)?.load(&mut flag)?;
+        Ok(flag[0])
+    }
+    /// Replaces NaNs with `nan`, positive infinities with `posinf` and negative ones with `neginf`, other elements are kept.
+    pub fn nan_to_num(&self, nan: T, posinf: T, neginf: T) -> Result<Self, Error> {
+        let values = [nan, posinf, neginf].iter()
+            .map(|&x| Self::new_filled_in(self.context(), &Shape::from([]), x)?.broadcast_to(self.shape()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        elementwise(
+            "isnan(a) ? b : (isinf(a) ? (a > 0 ? c : d) : a)",
+            self.shape(), &[self.inner(), values[0].inner(), values[1].inner(), values[2].inner()],
+        ).map(DeviceTensor::from_inner)
+    }
+    /// Replaces NaNs with zeros and infinities with the largest finite numbers of the same sign, see `nan_to_num`.
+    pub fn nan_to_num_default(&self) -> Result<Self, Error> {
+        self.nan_to_num(Zero::zero(), T::max_value(), T::min_value())
+    }
+}
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Sine of each element.
+    pub fn sin(&self) -> Result<Self, Error> {
+        self.unary_op("sin(a)")
+    }
+    /// Cosine of each element.
+    pub fn cos(&self) -> Result<Self, Error> {
+        self.unary_op("cos(a)")
+    }
+    /// Tangent of each element.
+    pub fn tan(&self) -> Result<Self, Error> {
+        self.unary_op("tan(a)")
+    }
+    /// Arcsine of each element.
+    pub fn asin(&self) -> Result<Self, Error> {
+        self.unary_op("asin(a)")
+    }
+    /// Arccosine of each element.
+    pub fn acos(&self) -> Result<Self, Error> {
+        self.unary_op("acos(a)")
+    }
+    /// Arctangent of each element.
+    pub fn atan(&self) -> Result<Self, Error> {
+        self.unary_op("atan(a)")
+    }
+    /// Hyperbolic sine of each element.
+    pub fn sinh(&self) -> Result<Self, Error> {
+        self.unary_op("sinh(a)")
+    }
+    /// Hyperbolic cosine of each element.
+    pub fn cosh(&self) -> Result<Self, Error> {
+        self.unary_op("cosh(a)")
+    }
+    /// Hyperbolic tangent of each element.
+    pub fn tanh(&self) -> Result<Self, Error> {
+        self.unary_op("tanh(a)")
+    }
+    /// Four quadrant arctangent of `self` and `other` elements, see `f64::atan2`.
+    ///
+    /// Tensors are broadcasted to the common shape.
+    pub fn atan2(&self, other: &Self) -> Result<Self, Error> {
+        self.binary_op(other, "atan2(a, b)")
+    }
+    /// Elements with magnitude of `self` and sign of `other`, the sign of zeros and NaNs is copied too.
+    ///
+    /// Tensors are broadcasted to the common shape.
+    pub fn copysign(&self, other: &Self) -> Result<Self, Error> {
+        self.binary_op(other, "copysign(a, b)")
+    }
+    /// Length of hypotenuse `sqrt(x^2 + y^2)` for `self` and `other` elements, without intermediate overflow.
+    ///
+    /// Tensors are broadcasted to the common shape.
+    pub fn hypot(&self, other: &Self) -> Result<Self, Error> {
+        self.binary_op(other, "hypot(a, b)")
+    }
+}
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Reciprocal `1 / x` of each element, zeros give infinities of the same sign.
+    pub fn recip(&self) -> Result<Self, Error> {
+        self.unary_op("1 / a")
+    }
+    /// Reciprocal square root `1 / sqrt(x)` of each element, zeros give infinities of the same sign.
+    ///
+    /// Built-in `rsqrt` is used, its error is within a few ulp.
+    /// Faster `native_rsqrt` is not used because its precision is implementation-defined.
+    pub fn rsqrt(&self) -> Result<Self, Error> {
+        self.unary_op("rsqrt(a)")
+    }
+}
+
+impl DeviceTensor<f32> {
+    /// Converts elements to half precision, ties are rounded to even as on the host.
+    ///
+    /// Requires `cl_khr_fp16` extension, fails with `Error::ExtensionUnavailable` otherwise.
+    pub fn to_f16(&self) -> Result<DeviceTensor<f16>, Error> {
+        self.unary_op("convert_half_rte(a)")
+    }
+    /// Converts elements to bfloat16, ties are rounded to even as on the host.
+    ///
+    /// Conversion is emulated with integer operations, so it doesn't require any extension.
+    pub fn to_bf16(&self) -> Result<DeviceTensor<bf16>, Error> {
+        self.unary_op("isnan(a) ? (ushort)((as_uint(a) >> 16) | 0x40) : (ushort)((as_uint(a) + 0x7fff + ((as_uint(a) >> 16) & 1)) >> 16)")
+    }
+}
+impl DeviceTensor<f16> {
+    /// Converts elements to single precision.
+    pub fn to_f32(&self) -> Result<DeviceTensor<f32>, Error> {
+        self.unary_op("convert_float(a)")
+    }
+}
+impl DeviceTensor<bf16> {
+    /// Converts elements to single precision.
+    pub fn to_f32(&self) -> Result<DeviceTensor<f32>, Error> {
+        self.unary_op("as_float((uint)a << 16)")
+    }
+}
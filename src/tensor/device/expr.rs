@@ -0,0 +1,171 @@
+use std::ops::{Add, Sub, Mul, Div, Neg};
+use crate::{
+    Prm, Num, Float, Interop, Error,
+    Shape, Tensor, DeviceTensor, elementwise, NAMES,
+};
+
+
+/// Node of expression tree.
+#[derive(Clone)]
+enum Node {
+    /// Index of the operand.
+    Leaf(usize),
+    /// Prefix operator or function of one argument.
+    Unary(&'static str, Box<Node>),
+    /// Infix operator or function of two arguments.
+    Binary(&'static str, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn shift(&mut self, n: usize) {
+        match self {
+            Node::Leaf(i) => *i += n,
+            Node::Unary(_, x) => x.shift(n),
+            Node::Binary(_, x, y) => {
+                x.shift(n);
+                y.shift(n);
+            }
+        }
+    }
+    fn source(&self) -> String {
+        match self {
+            Node::Leaf(i) => NAMES[*i].to_string(),
+            Node::Unary(op, x) => format!("{}({})", op, x.source()),
+            Node::Binary(op, x, y) if op.chars().all(char::is_alphanumeric) => {
+                format!("{}({}, {})", op, x.source(), y.source())
+            }
+            Node::Binary(op, x, y) => format!("({} {} {})", x.source(), op, y.source()),
+        }
+    }
+}
+
+/// Lazy elementwise expression over device tensors.
+///
+/// Operations on expression don't launch kernels but build an expression tree,
+/// which is compiled into a single kernel on `eval`, so intermediate results aren't stored in device memory.
+/// For example, `((a.lazy() + b.lazy()) * c.lazy()).eval()` reads each of the tensors once and writes only the result.
+///
+/// Operands are broadcasted to the common shape on `eval`.
+/// Expression may contain a limited number of operand tensors, see `eval`.
+#[derive(Clone)]
+pub struct Expr<T: Prm + Interop> {
+    node: Node,
+    operands: Vec<DeviceTensor<T>>,
+}
+
+impl<T: Prm + Interop> From<&DeviceTensor<T>> for Expr<T> {
+    fn from(tensor: &DeviceTensor<T>) -> Self {
+        Self { node: Node::Leaf(0), operands: vec![tensor.clone()] }
+    }
+}
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    /// Lazy expression consisting of the tensor only, see `Expr`.
+    pub fn lazy(&self) -> Expr<T> {
+        Expr::from(self)
+    }
+}
+
+impl<T: Prm + Interop> Expr<T> {
+    fn unary(self, op: &'static str) -> Self {
+        Self { node: Node::Unary(op, Box::new(self.node)), operands: self.operands }
+    }
+    fn binary(mut self, op: &'static str, mut other: Self) -> Self {
+        other.node.shift(self.operands.len());
+        self.operands.append(&mut other.operands);
+        Self { node: Node::Binary(op, Box::new(self.node), Box::new(other.node)), operands: self.operands }
+    }
+
+    /// Number of operand tensors, the same tensor used twice is counted twice.
+    pub fn operand_count(&self) -> usize {
+        self.operands.len()
+    }
+
+    /// Broadcasted shape of the result.
+    ///
+    /// Fails with `Error::ShapeMismatch` if operands aren't broadcast-compatible.
+    pub fn shape(&self) -> Result<Shape, Error> {
+        let first = self.operands[0].shape().clone();
+        self.operands[1..].iter().try_fold(first, |s, x| s.broadcast(x.shape()))
+    }
+
+    /// Evaluates the expression in a single kernel.
+    ///
+    /// Fails with `Error::BadArgument` if there are more than 8 operands,
+    /// in that case a part of the expression should be evaluated separately.
+    /// Fails with `Error::ContextMismatch` if operands belong to different contexts.
+    pub fn eval(&self) -> Result<DeviceTensor<T>, Error> {
+        if self.operands.len() > NAMES.len() {
+            return Err(Error::BadArgument(format!(
+                "expression has {} operands, at most {} are supported", self.operands.len(), NAMES.len(),
+            )));
+        }
+        let shape = self.shape()?;
+        let operands = self.operands.iter().map(|x| x.broadcast_to(&shape)).collect::<Result<Vec<_>, _>>()?;
+        let inners = operands.iter().map(|x| x.inner() as _).collect::<Vec<_>>();
+        elementwise(&self.node.source(), &shape, &inners).map(DeviceTensor::from_inner)
+    }
+}
+
+impl<T: Prm + Interop + PartialOrd> Expr<T> {
+    /// Lazy elementwise maximum, see `DeviceTensor::maximum`.
+    pub fn maximum(self, other: Self) -> Self {
+        self.binary("max", other)
+    }
+    /// Lazy elementwise minimum, see `DeviceTensor::minimum`.
+    pub fn minimum(self, other: Self) -> Self {
+        self.binary("min", other)
+    }
+}
+
+impl<T: Prm + Interop + Float> Expr<T> {
+    /// Lazy sine, see `DeviceTensor::sin`.
+    pub fn sin(self) -> Self {
+        self.unary("sin")
+    }
+    /// Lazy cosine, see `DeviceTensor::cos`.
+    pub fn cos(self) -> Self {
+        self.unary("cos")
+    }
+    /// Lazy hyperbolic tangent, see `DeviceTensor::tanh`.
+    pub fn tanh(self) -> Self {
+        self.unary("tanh")
+    }
+    /// Lazy reciprocal square root, see `DeviceTensor::rsqrt`.
+    pub fn rsqrt(self) -> Self {
+        self.unary("rsqrt")
+    }
+}
+
+impl<T: Prm + Interop + Num> Add for Expr<T> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        self.binary("+", other)
+    }
+}
+impl<T: Prm + Interop + Num> Sub for Expr<T> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        self.binary("-", other)
+    }
+}
+/// Complex numbers are not supported because OpenCL C multiplies vectors componentwise.
+impl<T: Prm + Interop + Num + PartialOrd> Mul for Expr<T> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        self.binary("*", other)
+    }
+}
+/// Integer division by zero gives unspecified result on device, complex numbers are not supported as for `Mul`.
+impl<T: Prm + Interop + Num + PartialOrd> Div for Expr<T> {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        self.binary("/", other)
+    }
+}
+impl<T: Prm + Interop + Num> Neg for Expr<T> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        self.unary("-")
+    }
+}
@@ -0,0 +1,81 @@
+//! In-place operations, named as on the host.
+//!
+//! Elementwise kernels write to a new buffer, so in-place operations replace the buffer of the tensor with the result.
+//! The old buffer is released at once, unless it is shared with other tensors.
+use crate::{
+    Prm, Num, Float, Interop, Error,
+    Shape, Tensor, DeviceTensor, elementwise,
+};
+
+
+impl<T: Prm + Interop + Num> DeviceTensor<T> {
+    fn zip_assign(&mut self, other: &Self, expr: &str) -> Result<(), Error> {
+        let other = other.broadcast_to(self.shape())?;
+        *self = self.binary_op(&other, expr)?;
+        Ok(())
+    }
+    /// Adds `other` broadcasted to the shape of `self`, see `HostTensor::add_assign`.
+    pub fn add_assign(&mut self, other: &Self) -> Result<(), Error> {
+        self.zip_assign(other, "a + b")
+    }
+    /// Subtracts `other` broadcasted to the shape of `self`, see `HostTensor::sub_assign`.
+    pub fn sub_assign(&mut self, other: &Self) -> Result<(), Error> {
+        self.zip_assign(other, "a - b")
+    }
+}
+
+/// Complex numbers are not supported because vector operators of OpenCL C multiply and divide componentwise.
+impl<T: Prm + Interop + Num + PartialOrd> DeviceTensor<T> {
+    /// Multiplies by `other` broadcasted to the shape of `self`, see `HostTensor::mul_assign`.
+    pub fn mul_assign(&mut self, other: &Self) -> Result<(), Error> {
+        self.zip_assign(other, "a * b")
+    }
+    /// Divides by `other` broadcasted to the shape of `self`, see `HostTensor::div_assign`.
+    ///
+    /// Result of integer division by zero is unspecified in OpenCL C.
+    pub fn div_assign(&mut self, other: &Self) -> Result<(), Error> {
+        self.zip_assign(other, "a / b")
+    }
+}
+
+impl<T: Prm + Interop + PartialOrd> DeviceTensor<T> {
+    /// Elementwise clamping to `[min, max]`, see `HostTensor::clamp`.
+    pub fn clamp(&self, min: T, max: T) -> Result<Self, Error> {
+        let bounds = Self::new_filled_in(self.context(), &Shape::from([]), min)?;
+        let upper = Self::new_filled_in(self.context(), &Shape::from([]), max)?;
+        self.binary_op::<T, T>(&bounds, "a < b ? b : a")?.binary_op(&upper, "a > b ? b : a")
+    }
+    /// Elementwise clamping along with the mask of clipped elements, see `HostTensor::clamp_with_mask`.
+    pub fn clamp_with_mask(&self, min: T, max: T) -> Result<(Self, DeviceTensor<u8>), Error> {
+        let lower = Self::new_filled_in(self.context(), &Shape::from([]), min)?.broadcast_to(self.shape())?;
+        let upper = Self::new_filled_in(self.context(), &Shape::from([]), max)?.broadcast_to(self.shape())?;
+        let mask = elementwise("a < b || a > c", self.shape(), &[self.inner(), lower.inner(), upper.inner()])?;
+        Ok((self.clamp(min, max)?, DeviceTensor::from_inner(mask)))
+    }
+    /// Clamps elements in place, see `HostTensor::clamp`.
+    pub fn clamp_assign(&mut self, min: T, max: T) -> Result<(), Error> {
+        *self = self.clamp(min, max)?;
+        Ok(())
+    }
+    /// Elementwise rectified linear unit, see `HostTensor::relu`.
+    pub fn relu(&self) -> Result<Self, Error> {
+        self.unary_op("a < 0 ? 0 : a")
+    }
+    /// Applies rectified linear unit in place, see `HostTensor::relu`.
+    pub fn relu_assign(&mut self) -> Result<(), Error> {
+        *self = self.relu()?;
+        Ok(())
+    }
+}
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Elementwise exponent.
+    pub fn exp(&self) -> Result<Self, Error> {
+        self.unary_op("exp(a)")
+    }
+    /// Computes exponent in place, see `exp`.
+    pub fn exp_assign(&mut self) -> Result<(), Error> {
+        *self = self.exp()?;
+        Ok(())
+    }
+}
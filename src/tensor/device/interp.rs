@@ -0,0 +1,88 @@
+use std::convert::TryFrom;
+use ocl::Kernel;
+use crate::{
+    Prm, Float, Interop, Error,
+    Tensor, DeviceTensor, InterpMode, InterpLayout, extensions,
+};
+
+
+/// Kernels of 2D interpolation, each work item computes a single output pixel. See `InterpLayout`.
+///
+/// Source positions are computed as in `nearest_source` and `bilinear_source`.
+const INTERP_SRC: &str = "
+__kernel void interpolate_nearest(
+    uint count, uint w, uint h, uint out_w, uint out_h,
+    __global const T *a,
+    __global T *b
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    uint x = i % out_w, y = (i / out_w) % out_h, p = i / (out_w * out_h);
+    b[i] = a[x * w / out_w + w * (y * h / out_h + h * p)];
+}
+
+__kernel void interpolate_bilinear(
+    uint count, uint w, uint h, uint out_w, uint out_h,
+    __global const T *a,
+    __global T *b
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    uint x = i % out_w, y = (i / out_w) % out_h, p = i / (out_w * out_h);
+    float sx = max(((float)x + 0.5f) * w / out_w - 0.5f, 0.0f);
+    float sy = max(((float)y + 0.5f) * h / out_h - 0.5f, 0.0f);
+    uint x0 = min((uint)sx, w - 1), y0 = min((uint)sy, h - 1);
+    uint x1 = min(x0 + 1, w - 1), y1 = min(y0 + 1, h - 1);
+    T lx = (T)(sx - x0), ly = (T)(sy - y0);
+    __global const T *plane = a + w * h * p;
+    T r0 = plane[x0 + w * y0] * (1 - lx) + plane[x1 + w * y0] * lx;
+    T r1 = plane[x0 + w * y1] * (1 - lx) + plane[x1 + w * y1] * lx;
+    b[i] = r0 * (1 - ly) + r1 * ly;
+}
+";
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Resizes images to `out_h` rows and `out_w` columns, see `HostTensor::interpolate2d`.
+    ///
+    /// Source positions are computed in single precision, so for large images they may slightly differ from the host ones.
+    pub fn interpolate2d(&self, out_h: usize, out_w: usize, mode: InterpMode) -> Result<Self, Error> {
+        let context = self.context();
+        let layout = InterpLayout::new(self.shape(), out_h, out_w)?;
+        let output = Self::new_zeroed_in(context, &layout.shape)?;
+        let count = layout.shape.content();
+        if count == 0 {
+            return Ok(output);
+        }
+        if [self.shape().content(), count].iter().any(|&x| u32::try_from(x).is_err()) {
+            return Err(Error::Overflow);
+        }
+
+        let a = self.plain_inner()?;
+        let name = match mode {
+            InterpMode::Nearest => "interpolate_nearest",
+            InterpMode::Bilinear => "interpolate_bilinear",
+        };
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, INTERP_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name(name)
+        .queue(context.queue().clone())
+        .arg(count as u32)
+        .arg(layout.w as u32)
+        .arg(layout.h as u32)
+        .arg(layout.out_w as u32)
+        .arg(layout.out_h as u32)
+        .arg(a.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run(name, &kernel)?; }
+        Ok(output)
+    }
+}
@@ -0,0 +1,33 @@
+use std::io::{Read, Write};
+use crate::{
+    Prm, Interop, LeBytes, Error, Buffer,
+    DeviceContext, Shape, Tensor, DeviceTensor, read_chunks, write_elements, CHUNK_LEN,
+};
+
+
+impl<T: Prm + Interop + LeBytes> DeviceTensor<T> {
+    /// Reads tensor of the `shape` from raw little-endian elements, see `HostTensor::load_from_reader`.
+    ///
+    /// Data is uploaded to the device by chunks, so the whole tensor is never stored in the host memory.
+    pub fn load_from_reader<R: Read>(reader: &mut R, shape: &Shape, context: &DeviceContext) -> Result<Self, Error> {
+        // The whole buffer is overwritten below.
+        let mut inner = unsafe { Self::new_uninit_in(context, shape)? }.into_inner();
+        let buffer = inner.buffer_mut();
+        read_chunks(reader, shape.content(), |offset, chunk| buffer.store_range(offset, chunk))?;
+        Ok(Self::from_inner(inner))
+    }
+    /// Writes elements as raw little-endian values in flattened order, see `HostTensor::load_from_reader`.
+    ///
+    /// Data is downloaded from the device by chunks.
+    pub fn store_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let inner = self.plain_inner()?;
+        let len = inner.buffer().len();
+        let mut chunk = Vec::with_capacity(usize::min(len, CHUNK_LEN));
+        for offset in (0..len).step_by(CHUNK_LEN) {
+            chunk.resize(usize::min(len - offset, CHUNK_LEN), T::zero());
+            inner.buffer().load_range(offset, &mut chunk)?;
+            write_elements(writer, chunk.iter().cloned())?;
+        }
+        Ok(())
+    }
+}
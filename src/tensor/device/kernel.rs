@@ -0,0 +1,158 @@
+use std::{
+    convert::TryFrom,
+    fmt::Write,
+};
+use ocl::{Buffer as OclBuffer, Kernel, MemFlags, OclPrm, builders::KernelBuilder};
+use crate::{
+    Prm, Interop, Error,
+    Buffer, DeviceBuffer, DeviceContext,
+    Shape, InnerTensor,
+};
+
+
+/// Names of operands in kernel expression.
+pub(crate) const NAMES: [&str; 8] = ["a", "b", "c", "d", "e", "f", "g", "h"];
+
+/// Tensor that could be passed to elementwise kernel.
+pub(crate) trait Operand {
+    /// Name of the element type in OpenCL C.
+    fn dev_name(&self) -> &'static str;
+    fn context(&self) -> &DeviceContext;
+    fn shape(&self) -> &Shape;
+    /// Strides and offset of the elements in the buffer.
+    fn layout(&self) -> (&[isize], usize);
+    /// Length of the underlying buffer.
+    fn buffer_len(&self) -> usize;
+    /// Passes the underlying buffer to the kernel.
+    fn set_arg<'b>(&'b self, builder: &mut KernelBuilder<'b>);
+}
+
+impl<T: Prm + Interop> Operand for InnerTensor<T> {
+    fn dev_name(&self) -> &'static str {
+        T::DEV_NAME
+    }
+    fn context(&self) -> &DeviceContext {
+        self.buffer().context()
+    }
+    fn shape(&self) -> &Shape {
+        &self.shape
+    }
+    fn layout(&self) -> (&[isize], usize) {
+        (&self.strides, self.offset)
+    }
+    fn buffer_len(&self) -> usize {
+        self.buffer().len()
+    }
+    fn set_arg<'b>(&'b self, builder: &mut KernelBuilder<'b>) {
+        builder.arg(self.buffer().mem());
+    }
+}
+
+/// Extensions required by kernel operating on specified types.
+pub(crate) fn extensions<'a, I: IntoIterator<Item = &'a str>>(types: I) -> String {
+    let mut src = String::new();
+    for ty in types {
+        for (prefix, ext) in [("double", "cl_khr_fp64"), ("half", "cl_khr_fp16")].iter() {
+            let pragma = format!("#pragma OPENCL EXTENSION {} : enable\n", ext);
+            if ty.starts_with(prefix) && !src.contains(&pragma) {
+                src += &pragma;
+            }
+        }
+    }
+    src
+}
+
+/// Generates source of the kernel that evaluates `expr` for each element.
+fn elementwise_source(expr: &str, types: &[&str], out: &str) -> String {
+    let mut src = extensions(types.iter().cloned().chain(Some(out)));
+    src += "__kernel void elementwise(\n    uint len, uint ndim,\n    __global const uint *shape,\n";
+    for (name, ty) in NAMES.iter().zip(types) {
+        writeln!(src, "    __global const {1} *{0}_ptr, __global const int *{0}_strides, int {0}_offset,", name, ty).unwrap();
+    }
+    writeln!(src, "    __global {} *y\n) {{", out).unwrap();
+    src += "    uint i = get_global_id(0);\n    if (i >= len) {\n        return;\n    }\n    uint r = i;\n";
+    for name in NAMES.iter().take(types.len()) {
+        writeln!(src, "    int {0}_index = {0}_offset;", name).unwrap();
+    }
+    src += "    for (uint k = 0; k < ndim; ++k) {\n        int x = (int)(r % shape[k]);\n        r /= shape[k];\n";
+    for name in NAMES.iter().take(types.len()) {
+        writeln!(src, "        {0}_index += x * {0}_strides[k];", name).unwrap();
+    }
+    src += "    }\n";
+    for (name, ty) in NAMES.iter().zip(types) {
+        writeln!(src, "    {1} {0} = {0}_ptr[{0}_index];", name, ty).unwrap();
+    }
+    writeln!(src, "    y[i] = {};\n}}", expr).unwrap();
+    src
+}
+
+/// Creates read-only device buffer with specified content.
+///
+/// Zero-sized buffers are not allowed, so empty slice is replaced with a single default element.
+fn const_buffer<T: OclPrm>(context: &DeviceContext, data: &[T]) -> Result<OclBuffer<T>, Error> {
+    let default = [T::default()];
+    let data = if data.is_empty() { &default[..] } else { data };
+    OclBuffer::builder()
+    .queue(context.queue().clone())
+    .flags(MemFlags::READ_ONLY)
+    .len(data.len())
+    .copy_host_slice(data)
+    .build()
+    .map_err(Error::from)
+}
+
+fn to_int<T, U: TryFrom<T>>(x: T) -> Result<U, Error> {
+    U::try_from(x).map_err(|_| Error::Overflow)
+}
+
+/// Evaluates OpenCL C expression for each element of the operands.
+///
+/// Operands are named `a`, `b`, `c` and so on (see `NAMES`) in the expression and must be already broadcasted to the `shape`.
+/// Fails with `Error::ContextMismatch` if operands belong to different contexts.
+pub(crate) fn elementwise<U: Prm + Interop>(expr: &str, shape: &Shape, operands: &[&dyn Operand]) -> Result<InnerTensor<U>, Error> {
+    assert!(!operands.is_empty() && operands.len() <= NAMES.len());
+    let context = operands[0].context();
+    if operands.iter().any(|x| x.context() != context) {
+        return Err(Error::ContextMismatch);
+    }
+    if let Some(x) = operands.iter().find(|x| x.shape() != shape) {
+        return Err(Error::ShapeMismatch(x.shape().clone(), shape.clone()));
+    }
+    let output = unsafe { InnerTensor::<U>::from_buffer(DeviceBuffer::new_uninit_in(context, shape.checked_content()?)?, shape) };
+    if shape.content() == 0 {
+        return Ok(output);
+    }
+    to_int::<_, u32>(shape.content())?;
+
+    let dims = shape.iter().map(|&x| to_int(x)).collect::<Result<Vec<u32>, _>>()?;
+    let dims_buffer = const_buffer(context, &dims)?;
+    let mut layouts = Vec::new();
+    for x in operands {
+        to_int::<_, i32>(x.buffer_len())?;
+        let (strides, offset) = x.layout();
+        let strides = (0..dims.len())
+            .map(|i| to_int(strides.get(i).cloned().unwrap_or(0)))
+            .collect::<Result<Vec<i32>, _>>()?;
+        layouts.push((const_buffer(context, &strides)?, to_int::<_, i32>(offset)?));
+    }
+
+    let types = operands.iter().map(|x| x.dev_name()).collect::<Vec<_>>();
+    let program = context.program(&elementwise_source(expr, &types, U::DEV_NAME))?;
+    let mut builder = Kernel::builder();
+    builder
+    .program(&program)
+    .name("elementwise")
+    .queue(context.queue().clone())
+    .arg(shape.content() as u32)
+    .arg(dims.len() as u32)
+    .arg(&dims_buffer);
+    for (x, (strides, offset)) in operands.iter().zip(layouts.iter()) {
+        x.set_arg(&mut builder);
+        builder.arg(strides).arg(*offset);
+    }
+    builder.arg(output.buffer().mem());
+    context.set_work_size(&mut builder, shape.content());
+    let kernel = builder.build()?;
+    unsafe { context.run(expr, &kernel)?; }
+    Ok(output)
+}
@@ -0,0 +1,341 @@
+use std::{convert::TryFrom, mem};
+use num_traits::One;
+use ocl::{
+    Kernel,
+    enums::{DeviceInfo, DeviceInfoResult, KernelWorkGroupInfo, KernelWorkGroupInfoResult},
+};
+use crate::{
+    Prm, Num, Float, Interop, Error, ResultContext, f16,
+    Tensor, DeviceTensor, MatmulLayout, CdistLayout, KronLayout, check_cdist_p, extensions,
+};
+
+
+/// Kernel of matrix product, each work item computes a single element. See `MatmulLayout`.
+///
+/// If `TILE` is defined, the kernel runs in two-dimensional work-groups of `TILE` by `TILE` items
+/// (the first dimension is the column of the result), which load square tiles of both operands into local memory
+/// and multiply them, so each element is read from global memory `TILE` times less often. Items beyond the result
+/// load zeros and write nothing, so dimensions don't have to be multiples of the tile. Otherwise the naive kernel
+/// reads operands directly from global memory with plain one-dimensional work size.
+///
+/// Sums are accumulated in type `A`. Operands stored transposed are read with swapped strides if `TRANS_A` or `TRANS_B` is defined.
+/// If `GEMM` is defined, the product is scaled by `alpha` and added to `c` scaled by `beta`, `c` isn't read if `beta` is zero.
+const MATMUL_SRC: &str = "
+#ifdef TRANS_A
+#define A_AT(r, l) a[(r) + m * (l)]
+#else
+#define A_AT(r, l) a[(l) + k * (r)]
+#endif
+#ifdef TRANS_B
+#define B_AT(l, q) b[(l) + k * (q)]
+#else
+#define B_AT(l, q) b[(q) + n * (l)]
+#endif
+
+__kernel void matmul(
+    uint m, uint k, uint n,
+    __global const T *a, __global const T *b,
+    __global T *c
+#ifdef GEMM
+    , T alpha, T beta
+#endif
+) {
+#ifdef TILE
+    __local A ta[TILE][TILE], tb[TILE][TILE];
+    uint lq = get_local_id(0), lr = get_local_id(1);
+    uint q = get_group_id(0) * TILE + lq, r = get_group_id(1) * TILE + lr;
+    A s = 0;
+    // All items of the group reach the barriers, even those beyond the result.
+    for (uint t = 0; t < k; t += TILE) {
+        ta[lr][lq] = r < m && t + lq < k ? (A)A_AT(r, t + lq) : 0;
+        tb[lr][lq] = t + lr < k && q < n ? (A)B_AT(t + lr, q) : 0;
+        barrier(CLK_LOCAL_MEM_FENCE);
+        for (uint l = 0; l < TILE; ++l) {
+            s += ta[lr][l] * tb[l][lq];
+        }
+        barrier(CLK_LOCAL_MEM_FENCE);
+    }
+    if (r >= m || q >= n) {
+        return;
+    }
+    uint i = q + n * r;
+#else
+    uint i = get_global_id(0);
+    if (i >= m * n) {
+        return;
+    }
+    uint q = i % n, r = i / n;
+    A s = 0;
+    for (uint l = 0; l < k; ++l) {
+        s += (A)A_AT(r, l) * (A)B_AT(l, q);
+    }
+#endif
+#ifdef GEMM
+    c[i] = beta == 0 ? alpha * (T)s : alpha * (T)s + beta * c[i];
+#else
+    c[i] = (T)s;
+#endif
+}
+";
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    /// Matrix product of operands transposed according to the flags accumulated in OpenCL C type `acc`.
+    fn matmul_acc(&self, other: &Self, acc: &str, trans_a: bool, trans_b: bool) -> Result<Self, Error> {
+        let layout = MatmulLayout::with_flags(self.shape(), other.shape(), trans_a, trans_b)?;
+        let mut output = Self::new_zeroed_in(self.context(), &layout.shape())?;
+        self.matmul_into(other, &mut output, acc, trans_a, trans_b, None)?;
+        Ok(output)
+    }
+    /// Writes the product to `output`, or accumulates it as `alpha * product + beta * output` if `scale` is `(alpha, beta)`.
+    fn matmul_into(
+        &self, other: &Self, output: &mut Self, acc: &str,
+        trans_a: bool, trans_b: bool, scale: Option<(T, T)>,
+    ) -> Result<(), Error> {
+        let context = self.context();
+        if other.context() != context || output.context() != context {
+            return Err(Error::ContextMismatch);
+        }
+        let layout = MatmulLayout::with_flags(self.shape(), other.shape(), trans_a, trans_b)?;
+        if *output.shape() != layout.shape() {
+            return Err(Error::ShapeMismatch(output.shape().clone(), layout.shape()));
+        }
+        let count = layout.shape().content();
+        if count == 0 {
+            return Ok(());
+        }
+        if [self.shape().content(), other.shape().content(), count].iter().any(|&x| u32::try_from(x).is_err()) {
+            return Err(Error::Overflow);
+        }
+
+        // Tiles of both operands must fit in local memory of the device, otherwise the naive kernel is used.
+        // Accumulators are either of the element type or `float`, see `matmul_mixed`.
+        let acc_size = if acc == T::DEV_NAME { mem::size_of::<T::Dev>() } else { mem::size_of::<f32>() };
+        let device = context.queue().device();
+        let local_mem = match device.info(DeviceInfo::LocalMemSize)? {
+            DeviceInfoResult::LocalMemSize(size) => usize::try_from(size).unwrap_or(usize::MAX),
+            _ => 0,
+        };
+        let max_group = device.max_wg_size()?;
+        let tile = context.matmul_tile_size().filter(|&t| {
+            t * t <= max_group && 2 * t * t * acc_size <= local_mem
+        });
+        let (a, b) = (self.plain_inner()?, other.plain_inner()?);
+        let inner = output.inner_mut();
+        inner.make_plain()?;
+        let c = inner.buffer_mut().mem().clone();
+        let build = |tile: Option<usize>| -> Result<Kernel, Error> {
+            let flags = [(trans_a, "#define TRANS_A\n"), (trans_b, "#define TRANS_B\n"), (scale.is_some(), "#define GEMM\n")];
+            let src = format!(
+                "{}#define T {}\n#define A {}\n{}{}{}",
+                extensions([T::DEV_NAME, acc].iter().cloned()), T::DEV_NAME, acc,
+                flags.iter().filter(|(f, _)| *f).map(|(_, d)| *d).collect::<String>(),
+                tile.map_or(String::new(), |t| format!("#define TILE {}\n", t)), MATMUL_SRC,
+            );
+            let program = context.program(&src)?;
+            let mut builder = Kernel::builder();
+            builder
+            .program(&program)
+            .name("matmul")
+            .queue(context.queue().clone())
+            .arg(layout.m as u32)
+            .arg(layout.k as u32)
+            .arg(layout.n as u32)
+            .arg(a.buffer().mem())
+            .arg(b.buffer().mem())
+            .arg(&c);
+            if let Some((alpha, beta)) = scale {
+                builder.arg(alpha.to_dev()).arg(beta.to_dev());
+            }
+            if let Some(t) = tile {
+                builder.global_work_size([layout.n.div_ceil(t) * t, layout.m.div_ceil(t) * t]).local_work_size([t, t]);
+            } else {
+                context.set_work_size(&mut builder, count);
+            }
+            Ok(builder.build()?)
+        };
+        let mut kernel = build(tile)?;
+        // The compiled kernel may support smaller work-groups than the device, e.g. because of the registers it uses.
+        if let Some(t) = tile {
+            match kernel.wg_info(device, KernelWorkGroupInfo::WorkGroupSize)? {
+                KernelWorkGroupInfoResult::WorkGroupSize(size) if t * t <= size => (),
+                _ => kernel = build(None)?,
+            }
+        }
+        unsafe { context.run("matmul", &kernel) }
+    }
+}
+
+impl<T: Prm + Interop + Num + PartialOrd> DeviceTensor<T> {
+    /// Matrix product, see `HostTensor::matmul`.
+    ///
+    /// Complex numbers are not supported because OpenCL C has no complex multiplication.
+    pub fn matmul(&self, other: &Self) -> Result<Self, Error> {
+        self.matmul_acc(other, T::DEV_NAME, false, false).context("matmul")
+    }
+    /// Matrix product of `self` and `other` transposed according to the flags, see `HostTensor::matmul_flags`.
+    ///
+    /// The kernel reads transposed operands with swapped strides, so plain operands aren't copied.
+    pub fn matmul_flags(&self, other: &Self, trans_a: bool, trans_b: bool) -> Result<Self, Error> {
+        self.matmul_acc(other, T::DEV_NAME, trans_a, trans_b).context("matmul_flags")
+    }
+    /// General matrix product `out = alpha * (self @ other) + beta * out`, see `HostTensor::gemm`.
+    ///
+    /// The product is accumulated into `out` by the same kernel, so no temporary matrix is allocated.
+    /// Fails with `Error::ContextMismatch` if any operand belongs to other context.
+    pub fn gemm(&self, other: &Self, out: &mut Self, alpha: T, beta: T) -> Result<(), Error> {
+        self.matmul_into(other, out, T::DEV_NAME, false, false, Some((alpha, beta))).context("gemm")
+    }
+}
+
+/// Kernel of Kronecker product, each work item computes a single element. See `KronLayout`.
+const KRON_SRC: &str = "
+__kernel void kron(
+    uint m1, uint n1, uint m2, uint n2,
+    __global const T *a, __global const T *b,
+    __global T *c
+) {
+    uint i = get_global_id(0);
+    uint n = n1 * n2;
+    if (i >= m1 * m2 * n) {
+        return;
+    }
+    uint row = i / n, col = i % n;
+    c[i] = a[col / n2 + n1 * (row / m2)] * b[col % n2 + n2 * (row % m2)];
+}
+";
+
+/// Complex numbers are not supported because OpenCL C has no complex multiplication.
+impl<T: Prm + Interop + Num + PartialOrd> DeviceTensor<T> {
+    /// Kronecker product of matrices, see `HostTensor::kron`.
+    pub fn kron(&self, other: &Self) -> Result<Self, Error> {
+        let context = self.context();
+        if other.context() != context {
+            return Err(Error::ContextMismatch);
+        }
+        let layout = KronLayout::new(self.shape(), other.shape())?;
+        let output = Self::new_zeroed_in(context, &layout.shape())?;
+        let count = layout.shape().content();
+        if count == 0 {
+            return Ok(output);
+        }
+        if u32::try_from(count).is_err() {
+            return Err(Error::Overflow);
+        }
+
+        let (a, b) = (self.plain_inner()?, other.plain_inner()?);
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, KRON_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("kron")
+        .queue(context.queue().clone())
+        .arg(layout.m1 as u32)
+        .arg(layout.n1 as u32)
+        .arg(layout.m2 as u32)
+        .arg(layout.n2 as u32)
+        .arg(a.buffer().mem())
+        .arg(b.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run("kron", &kernel)?; }
+        Ok(output)
+    }
+}
+
+impl DeviceTensor<f16> {
+    /// Matrix product of half-precision matrices with sums accumulated in single precision, see `HostTensor::matmul_mixed`.
+    pub fn matmul_mixed(&self, other: &Self) -> Result<Self, Error> {
+        self.matmul_acc(other, "float", false, false).context("matmul_mixed")
+    }
+}
+
+/// Kernel of pairwise distances, each work item computes a single element. See `CdistLayout`.
+///
+/// Distances of order 2, 1 and infinity are selected by `EUCLIDEAN`, `MANHATTAN` and `CHEBYSHEV` definitions,
+/// otherwise the general power formula is used.
+const CDIST_SRC: &str = "
+__kernel void cdist(
+    uint n, uint d, uint m, T p,
+    __global const T *a, __global const T *b,
+    __global T *y
+) {
+    uint i = get_global_id(0);
+    if (i >= n * m) {
+        return;
+    }
+    uint j = i % m, r = i / m;
+    T s = 0;
+    for (uint l = 0; l < d; ++l) {
+        T x = fabs(a[l + d * r] - b[l + d * j]);
+#if defined(EUCLIDEAN)
+        s += x * x;
+#elif defined(MANHATTAN)
+        s += x;
+#elif defined(CHEBYSHEV)
+        s = fmax(s, x);
+#else
+        s += pow(x, p);
+#endif
+    }
+#if defined(EUCLIDEAN)
+    y[i] = sqrt(s);
+#elif defined(MANHATTAN) || defined(CHEBYSHEV)
+    y[i] = s;
+#else
+    y[i] = pow(s, 1 / p);
+#endif
+}
+";
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Pairwise distances of order `p` between rows of `self` and `other`, see `HostTensor::cdist`.
+    pub fn cdist(&self, other: &Self, p: T) -> Result<Self, Error> {
+        let context = self.context();
+        if other.context() != context {
+            return Err(Error::ContextMismatch);
+        }
+        let layout = CdistLayout::new(self.shape(), other.shape())?;
+        check_cdist_p(p)?;
+        let output = Self::new_zeroed_in(context, &layout.shape())?;
+        let count = layout.shape().content();
+        if count == 0 {
+            return Ok(output);
+        }
+        if [self.shape().content(), other.shape().content(), count].iter().any(|&x| u32::try_from(x).is_err()) {
+            return Err(Error::Overflow);
+        }
+
+        let one: T = One::one();
+        let two = one + one;
+        let mode = if p == two {
+            "#define EUCLIDEAN\n"
+        } else if p == one {
+            "#define MANHATTAN\n"
+        } else if p.is_infinite() {
+            "#define CHEBYSHEV\n"
+        } else {
+            ""
+        };
+        let (a, b) = (self.plain_inner()?, other.plain_inner()?);
+        let src = format!("{}#define T {}\n{}{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, mode, CDIST_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("cdist")
+        .queue(context.queue().clone())
+        .arg(layout.n as u32)
+        .arg(layout.d as u32)
+        .arg(layout.m as u32)
+        .arg(p.to_dev())
+        .arg(a.buffer().mem())
+        .arg(b.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run("cdist", &kernel)?; }
+        Ok(output)
+    }
+}
@@ -0,0 +1,44 @@
+mod tensor;
+pub use tensor::*;
+
+mod sync;
+pub use sync::*;
+
+mod context;
+
+mod custom;
+pub use custom::*;
+
+mod kernel;
+pub(crate) use kernel::*;
+
+mod elementwise;
+
+mod inplace;
+
+mod expr;
+pub use expr::*;
+
+mod nn;
+
+mod select;
+
+mod reduce;
+
+mod matmul;
+
+mod io;
+
+mod sparse;
+
+mod interp;
+
+mod complex;
+
+mod optim;
+
+mod prefetch;
+pub use prefetch::*;
+
+#[cfg(test)]
+mod tests;
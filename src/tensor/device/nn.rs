@@ -0,0 +1,299 @@
+use std::convert::TryFrom;
+use num_traits::Zero;
+use ocl::Kernel;
+use crate::{
+    Prm, Interop, Num, Float, Error, Rng, ReduceOp, quant_params, check_grad,
+    Shape, Tensor, DeviceTensor, check_bias, dropout_mask, check_dropout, clip_scale, check_layer_norm, extensions,
+    embedding_shape, check_embedding_indices, check_triangle,
+};
+
+
+impl<T: Prm + Interop + Num> DeviceTensor<T> {
+    /// Adds `bias` vector to each slice of the tensor along the first axis, see `HostTensor::add_bias`.
+    ///
+    /// Bias is broadcasted with zero stride, so each work item reads it directly from the same buffer.
+    pub fn add_bias(&self, bias: &Self) -> Result<Self, Error> {
+        check_bias(self.shape(), bias.shape())?;
+        self.binary_op(bias, "a + b")
+    }
+}
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Inverted dropout, see `HostTensor::dropout`.
+    ///
+    /// The mask is generated on the host and uploaded to the device,
+    /// so the result is the same as on the host for the same random numbers.
+    pub fn dropout(&self, p: T, training: bool, rng: &mut impl Rng) -> Result<Self, Error> {
+        check_dropout(p)?;
+        if !training {
+            return Ok(self.clone());
+        }
+        let mask_data = dropout_mask(self.shape().content(), p, rng)?;
+        let mut mask = Self::new_empty_in(self.context(), self.shape())?;
+        mask.store(&mask_data)?;
+        self.binary_op(&mask, "a * b")
+    }
+
+    /// Scales gradients so that their global L2 norm doesn't exceed `max_norm`, see `HostTensor::clip_grad_norm`.
+    ///
+    /// Squared norms of tensors are summed on the device, only the sums are loaded to the host.
+    pub fn clip_grad_norm(grads: &mut [Self], max_norm: T) -> Result<T, Error> {
+        let mut sum: T = Zero::zero();
+        for g in grads.iter() {
+            let squares = g.unary_op::<T>("a * a")?.reshape(&Shape::from([g.shape().content()]))?;
+            let mut s = [Zero::zero()];
+            squares.sum_axis(0, false)?.load(&mut s)?;
+            sum = sum + s[0];
+        }
+        let norm = sum.sqrt();
+        if let Some(scale) = clip_scale(norm, max_norm)? {
+            for g in grads.iter_mut() {
+                let scale = Self::new_filled_in(g.context(), &Shape::from([]), scale)?;
+                *g = g.binary_op(&scale, "a * b")?;
+            }
+        }
+        Ok(norm)
+    }
+}
+
+/// Kernel of layer normalization, each work item normalizes a single group.
+const LAYER_NORM_SRC: &str = "
+__kernel void layer_norm(
+    uint groups, uint n, T eps,
+    __global const T *x, __global const T *gamma, __global const T *beta,
+    __global T *y
+) {
+    if (get_global_id(0) >= groups) {
+        return;
+    }
+    uint offset = get_global_id(0) * n;
+    T mean = 0;
+    for (uint i = 0; i < n; ++i) {
+        mean += x[offset + i];
+    }
+    mean /= n;
+    T var = 0;
+    for (uint i = 0; i < n; ++i) {
+        T d = x[offset + i] - mean;
+        var += d * d;
+    }
+    var /= n;
+    T scale = rsqrt(var + eps);
+    for (uint i = 0; i < n; ++i) {
+        T v = (x[offset + i] - mean) * scale;
+#ifdef GAMMA
+        v *= gamma[i];
+#endif
+#ifdef BETA
+        v += beta[i];
+#endif
+        y[offset + i] = v;
+    }
+}
+";
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Layer normalization, see `HostTensor::layer_norm`.
+    ///
+    /// Computed by a single fused kernel, each group is normalized by a separate work item.
+    pub fn layer_norm(&self, normalized_shape: &[usize], gamma: Option<&Self>, beta: Option<&Self>, eps: T) -> Result<Self, Error> {
+        let n = check_layer_norm(self, normalized_shape, gamma, beta)?;
+        let context = self.context();
+        if [Some(self), gamma, beta].iter().flatten().any(|t| t.context() != context) {
+            return Err(Error::ContextMismatch);
+        }
+        let x = self.plain_inner()?;
+        let (gamma, beta) = (gamma.map(Self::plain_inner).transpose()?, beta.map(Self::plain_inner).transpose()?);
+        let output = Self::new_empty_in(context, self.shape())?;
+        let groups = self.shape().content() / n.max(1);
+        if groups == 0 {
+            return Ok(output);
+        }
+        if u32::try_from(self.shape().content()).is_err() {
+            return Err(Error::Overflow);
+        }
+
+        let mut src = extensions(Some(T::DEV_NAME));
+        src += &format!("#define T {}\n", T::DEV_NAME);
+        if gamma.is_some() {
+            src += "#define GAMMA\n";
+        }
+        if beta.is_some() {
+            src += "#define BETA\n";
+        }
+        src += LAYER_NORM_SRC;
+        let program = context.program(&src)?;
+        // Missing parameters are replaced with input buffer, they are not accessed anyway.
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("layer_norm")
+        .queue(context.queue().clone())
+        .arg(groups as u32)
+        .arg(n as u32)
+        .arg(eps.to_dev())
+        .arg(x.buffer().mem())
+        .arg(gamma.as_ref().unwrap_or(&x).buffer().mem())
+        .arg(beta.as_ref().unwrap_or(&x).buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, groups);
+        let kernel = builder.build()?;
+        unsafe { context.run("layer_norm", &kernel)?; }
+        Ok(output)
+    }
+}
+
+/// Kernel of embedding lookup, each work item copies a single element.
+const EMBEDDING_SRC: &str = "
+__kernel void embedding(
+    uint len, uint dim,
+    __global const T *weight, __global const uint *indices,
+    __global T *y
+) {
+    uint i = get_global_id(0);
+    if (i >= len) {
+        return;
+    }
+    y[i] = weight[indices[i / dim] * dim + i % dim];
+}
+";
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    /// Embedding lookup, see `HostTensor::embedding`.
+    ///
+    /// Indices are loaded to the host to check bounds, then embeddings are gathered by a device kernel.
+    pub fn embedding(weight: &Self, indices: &DeviceTensor<usize>) -> Result<Self, Error> {
+        let (dim, num, shape) = embedding_shape(weight.shape(), indices.shape())?;
+        let context = weight.context();
+        if indices.context() != context {
+            return Err(Error::ContextMismatch);
+        }
+        let mut index_data = vec![0; indices.shape().content()];
+        indices.load(&mut index_data)?;
+        check_embedding_indices(index_data.iter(), num)?;
+        let output = Self::new_empty_in(context, &shape)?;
+        if shape.content() == 0 {
+            return Ok(output);
+        }
+        if u32::try_from(weight.shape().content()).is_err() || u32::try_from(shape.content()).is_err() {
+            return Err(Error::Overflow);
+        }
+
+        let (weight, indices) = (weight.plain_inner()?, indices.plain_inner()?);
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, EMBEDDING_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("embedding")
+        .queue(context.queue().clone())
+        .arg(shape.content() as u32)
+        .arg(dim as u32)
+        .arg(weight.buffer().mem())
+        .arg(indices.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, shape.content());
+        let kernel = builder.build()?;
+        unsafe { context.run("embedding", &kernel)?; }
+        Ok(output)
+    }
+}
+
+/// Kernel of triangular masking, each work item copies or zeroes a single element. See `in_triangle`.
+const TRIANGLE_SRC: &str = "
+__kernel void triangle(
+    uint count, uint cols, uint rows, int diagonal, uint upper,
+    __global const T *x,
+    __global T *y
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    int d = (int)(i % cols) - (int)((i / cols) % rows);
+    y[i] = (upper ? d >= diagonal : d <= diagonal) ? x[i] : (T)0;
+}
+";
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    /// Upper triangle of a matrix, see `HostTensor::triu`.
+    pub fn triu(&self, diagonal: isize) -> Result<Self, Error> {
+        self.triangle(diagonal, true)
+    }
+    /// Lower triangle of a matrix, see `HostTensor::tril`.
+    pub fn tril(&self, diagonal: isize) -> Result<Self, Error> {
+        self.triangle(diagonal, false)
+    }
+    fn triangle(&self, diagonal: isize, upper: bool) -> Result<Self, Error> {
+        let (cols, rows) = check_triangle(self.shape())?;
+        let context = self.context();
+        let output = Self::new_empty_in(context, self.shape())?;
+        let count = self.shape().content();
+        if count == 0 {
+            return Ok(output);
+        }
+        if i32::try_from(count).is_err() {
+            return Err(Error::Overflow);
+        }
+        // Diagonals beyond the matrix bounds select either all or none of the elements, so they are clamped to fit in `int`.
+        let diagonal = diagonal.clamp(-(rows as isize), cols as isize);
+
+        let x = self.plain_inner()?;
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, TRIANGLE_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("triangle")
+        .queue(context.queue().clone())
+        .arg(count as u32)
+        .arg(cols as u32)
+        .arg(rows as u32)
+        .arg(diagonal as i32)
+        .arg(upper as u32)
+        .arg(x.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run("triangle", &kernel)?; }
+        Ok(output)
+    }
+}
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Affine quantization to signed bytes, see `HostTensor::quantize_int8`.
+    pub fn quantize_int8(&self, scale: T, zero_point: i32) -> Result<DeviceTensor<i8>, Error> {
+        let scale = Self::new_filled_in(self.context(), &Shape::from([]), scale)?;
+        self.binary_op(&scale, &format!("convert_char_sat(round(a / b) + {})", zero_point))
+    }
+    /// Restores values from affine quantization, see `HostTensor::dequantize`.
+    pub fn dequantize(q: &DeviceTensor<i8>, scale: T, zero_point: i32) -> Result<Self, Error> {
+        let scale = Self::new_filled_in(q.context(), &Shape::from([]), scale)?;
+        q.binary_op(&scale, &format!("({})((int)a - {}) * b", T::DEV_NAME, zero_point))
+    }
+    /// Scale and zero point that map the range of the tensor onto `[-128, 127]`, see `HostTensor::compute_quant_params`.
+    ///
+    /// The range is found by `reduce_device`, only its bounds are loaded to the host.
+    pub fn compute_quant_params(&self) -> Result<(T, i32), Error> {
+        let zero = Zero::zero();
+        Ok(quant_params(self.reduce_device(zero, ReduceOp::Min)?, self.reduce_device(zero, ReduceOp::Max)?))
+    }
+}
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Gradient of ReLU, see `HostTensor::relu_backward`.
+    pub fn relu_backward(grad_out: &Self, input: &Self) -> Result<Self, Error> {
+        check_grad(grad_out.shape(), input.shape())?;
+        grad_out.binary_op(input, "b > 0 ? a : 0")
+    }
+    /// Gradient of the logistic sigmoid, see `HostTensor::sigmoid_backward`.
+    pub fn sigmoid_backward(grad_out: &Self, input: &Self) -> Result<Self, Error> {
+        check_grad(grad_out.shape(), input.shape())?;
+        grad_out.binary_op(input, "a / (1 + exp(-b)) * (1 - 1 / (1 + exp(-b)))")
+    }
+    /// Gradient of the hyperbolic tangent, see `HostTensor::tanh_backward`.
+    pub fn tanh_backward(grad_out: &Self, input: &Self) -> Result<Self, Error> {
+        check_grad(grad_out.shape(), input.shape())?;
+        grad_out.binary_op(input, "a * (1 - tanh(b) * tanh(b))")
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{
+    Prm, Interop, Float, Error,
+    Shape, Tensor, DeviceTensor, elementwise, optim::{OptimTensor, check_param},
+};
+
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Scalars broadcasted to the shape of the tensor, to pass them to elementwise kernels without recompilation.
+    fn scalars(&self, values: &[T]) -> Result<Vec<Self>, Error> {
+        values.iter()
+            .map(|&x| Self::new_filled_in(self.context(), &Shape::from([]), x)?.broadcast_to(self.shape()))
+            .collect()
+    }
+}
+
+/// The tensor is replaced with the result, so its buffer is reallocated.
+impl<T: Prm + Interop + Float> OptimTensor<T> for DeviceTensor<T> {
+    fn axpby(&mut self, a: T, other: &Self, b: T) -> Result<(), Error> {
+        check_param(self, other)?;
+        let s = self.scalars(&[a, b])?;
+        let inner = elementwise("c * a + d * b", self.shape(), &[self.inner(), other.inner(), s[0].inner(), s[1].inner()])?;
+        *self = Self::from_inner(inner);
+        Ok(())
+    }
+    fn axpby_square(&mut self, a: T, other: &Self, b: T) -> Result<(), Error> {
+        check_param(self, other)?;
+        let s = self.scalars(&[a, b])?;
+        let inner = elementwise("c * a + d * b * b", self.shape(), &[self.inner(), other.inner(), s[0].inner(), s[1].inner()])?;
+        *self = Self::from_inner(inner);
+        Ok(())
+    }
+    fn add_scaled_ratio(&mut self, num: &Self, den: &Self, alpha: T, eps: T) -> Result<(), Error> {
+        check_param(self, num)?;
+        check_param(self, den)?;
+        let s = self.scalars(&[alpha, eps])?;
+        let inner = elementwise(
+            "a + d * b / (sqrt(c) + e)", self.shape(),
+            &[self.inner(), num.inner(), den.inner(), s[0].inner(), s[1].inner()],
+        )?;
+        *self = Self::from_inner(inner);
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+use ocl::Event;
+use crate::{
+    Prm, Interop, Error,
+    Tensor, HostTensor, DeviceTensor, DeviceContext,
+};
+
+
+/// Host copy of an uploaded tensor, it must live until the transfer completes.
+struct Staging<T: Interop> {
+    _data: Vec<T::Dev>,
+    /// `None` if nothing was transferred.
+    event: Option<Event>,
+}
+
+impl<T: Interop> Drop for Staging<T> {
+    fn drop(&mut self) {
+        if let Some(event) = &self.event {
+            // Errors can't be reported from `drop`, the memory is released anyway then.
+            let _ = event.wait_for();
+        }
+    }
+}
+
+/// Iterator that uploads host tensors to the device one batch ahead.
+///
+/// When a batch is taken, the transfer of the next one is already enqueued without blocking,
+/// so the host thread produces batches while the device copies and computes the previous ones.
+/// At most two host copies are kept in flight (the current batch and the next one), and the oldest is awaited
+/// before the third is enqueued, see the lifetime contract of `DeviceBuffer`.
+///
+/// Transfers are enqueued to the context queue, so kernels using a batch are ordered after its upload.
+pub struct Prefetcher<T: Prm + Interop, I: Iterator<Item = HostTensor<T>>> {
+    iter: I,
+    context: DeviceContext,
+    next: Option<Result<DeviceTensor<T>, Error>>,
+    in_flight: VecDeque<Staging<T>>,
+}
+
+impl<T: Prm + Interop, I: Iterator<Item = HostTensor<T>>> Prefetcher<T, I> {
+    /// Prefetcher of tensors produced by `iter` to the `context`.
+    ///
+    /// Nothing is uploaded until the first batch is requested.
+    pub fn new<J: IntoIterator<IntoIter = I>>(iter: J, context: &DeviceContext) -> Self {
+        Self { iter: iter.into_iter(), context: context.clone(), next: None, in_flight: VecDeque::new() }
+    }
+
+    fn upload(&mut self) -> Option<Result<DeviceTensor<T>, Error>> {
+        let host = self.iter.next()?;
+        Some(self.enqueue(&host))
+    }
+    fn enqueue(&mut self, host: &HostTensor<T>) -> Result<DeviceTensor<T>, Error> {
+        let tensor = DeviceTensor::new_empty_in(&self.context, host.shape())?;
+        let data = host.iter().map(|x| x.to_dev()).collect::<Vec<_>>();
+        let event = if data.is_empty() {
+            None
+        } else {
+            let mut event = Event::empty();
+            // The memory of `data` stays in place when the vector is moved to `Staging`,
+            // and it is freed only after the transfer completes.
+            unsafe { tensor.inner().buffer().mem().write(&data).block(false).enew(&mut event).enq()?; }
+            Some(event)
+        };
+        self.in_flight.push_back(Staging { _data: data, event });
+        while self.in_flight.len() > 2 {
+            self.in_flight.pop_front();
+        }
+        Ok(tensor)
+    }
+}
+
+impl<T: Prm + Interop, I: Iterator<Item = HostTensor<T>>> Iterator for Prefetcher<T, I> {
+    type Item = Result<DeviceTensor<T>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = match self.next.take() {
+            Some(current) => current,
+            None => self.upload()?,
+        };
+        self.next = self.upload();
+        Some(current)
+    }
+}
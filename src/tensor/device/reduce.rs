@@ -0,0 +1,354 @@
+use std::convert::TryFrom;
+use num_traits::{Zero, One, PrimInt};
+use ocl::Kernel;
+use crate::{
+    Prm, Num, Float, Interop, Error,
+    Shape, Tensor, DeviceTensor, ReduceLayout, ReduceOp, COSINE_EPS, descending_axes, check_mask, accumulate_layout, bincount_len, check_confusion, extensions,
+};
+
+
+/// Kernel of reduction along an axis with operation `OP` starting from `INIT`, each work item computes a single element.
+/// See `ReduceLayout`.
+const REDUCE_AXIS_SRC: &str = "
+__kernel void reduce_axis(
+    uint count, uint inner, uint len,
+    __global const T *x,
+    __global T *y
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    uint j = i % inner, o = i / inner;
+    T s = INIT;
+    for (uint k = 0; k < len; ++k) {
+        s = OP(s, x[j + inner * (k + len * o)]);
+    }
+    y[i] = s;
+}
+";
+
+impl<T: Prm + Interop + Num> DeviceTensor<T> {
+    /// Sum along the `axis`, see `HostTensor::sum_axis` for `keepdims`.
+    pub fn sum_axis(&self, axis: usize, keepdims: bool) -> Result<Self, Error> {
+        self.reduce_axis(axis, keepdims, ReduceOp::Sum, "sum_axis")
+    }
+    /// Reduction along the `axis` with `op` starting from its identity, `name` is reported to the profile.
+    ///
+    /// Only `Sum` and `Prod` have an identity, so `op` must be one of them.
+    fn reduce_axis(&self, axis: usize, keepdims: bool, op: ReduceOp, name: &str) -> Result<Self, Error> {
+        let context = self.context();
+        let layout = ReduceLayout::new(self.shape(), axis, keepdims);
+        let count = layout.shape.content();
+        let init: T = if op == ReduceOp::Prod { One::one() } else { Zero::zero() };
+        let output = Self::new_filled_in(context, &layout.shape, init)?;
+        if count == 0 || layout.len == 0 {
+            return Ok(output);
+        }
+        if u32::try_from(self.shape().content()).is_err() {
+            return Err(Error::Overflow);
+        }
+
+        let x = self.plain_inner()?;
+        let src = format!(
+            "{}#define T {}\n#define INIT (T){}\n#define OP(a, b) ({})\n{}",
+            extensions(Some(T::DEV_NAME)), T::DEV_NAME, if op == ReduceOp::Prod { 1 } else { 0 }, op.dev_expr(), REDUCE_AXIS_SRC,
+        );
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("reduce_axis")
+        .queue(context.queue().clone())
+        .arg(count as u32)
+        .arg(layout.inner as u32)
+        .arg(layout.len as u32)
+        .arg(x.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run(name, &kernel)?; }
+        Ok(output)
+    }
+    /// Sum along all the `axes` at once, see `HostTensor::sum_axes`.
+    pub fn sum_axes(&self, axes: &[usize], keepdims: bool) -> Result<Self, Error> {
+        descending_axes(axes)?.into_iter().try_fold(self.clone(), |s, axis| s.sum_axis(axis, keepdims))
+    }
+
+    /// Adds `other` to `self` summing over broadcasted axes, see `HostTensor::accumulate`.
+    ///
+    /// The tensor is replaced with the result, so its buffer is reallocated.
+    pub fn accumulate(&mut self, other: &Self) -> Result<(), Error> {
+        let (axes, kept) = accumulate_layout(self.shape(), other.shape())?;
+        let mut sum = other.clone();
+        for &axis in axes.iter() {
+            sum = sum.sum_axis(axis, false)?;
+        }
+        *self = self.binary_op(&sum.reshape(&kept)?, "a + b")?;
+        Ok(())
+    }
+
+    /// Sum along the `axis` over positions where `mask` is nonzero, see `HostTensor::masked_sum`.
+    pub fn masked_sum(&self, mask: &DeviceTensor<u8>, axis: usize, keepdims: bool) -> Result<Self, Error> {
+        check_mask(self.shape(), mask.shape())?;
+        self.binary_op::<u8, T>(mask, "b != 0 ? a : 0")?.sum_axis(axis, keepdims)
+    }
+}
+
+/// Kernel of cosine similarity along an axis, each work item computes a single element. See `ReduceLayout`.
+const COSINE_SRC: &str = "
+__kernel void cosine_similarity(
+    uint count, uint inner, uint len, T eps,
+    __global const T *a, __global const T *b,
+    __global T *y
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    uint j = i % inner, o = i / inner;
+    T d = 0, aa = 0, bb = 0;
+    for (uint k = 0; k < len; ++k) {
+        uint s = j + inner * (k + len * o);
+        d += a[s] * b[s];
+        aa += a[s] * a[s];
+        bb += b[s] * b[s];
+    }
+    y[i] = d / fmax(sqrt(aa * bb), eps);
+}
+";
+
+impl<T: Prm + Interop + Float> DeviceTensor<T> {
+    /// Cosine similarity along the `axis`, see `HostTensor::cosine_similarity`.
+    ///
+    /// Computed by a single fused kernel, broadcasted operands are copied to plain buffers first.
+    pub fn cosine_similarity(&self, other: &Self, axis: usize) -> Result<Self, Error> {
+        let context = self.context();
+        if other.context() != context {
+            return Err(Error::ContextMismatch);
+        }
+        let shape = self.shape().broadcast(other.shape())?;
+        let layout = ReduceLayout::new(&shape, axis, false);
+        let count = layout.shape.content();
+        let output = Self::new_zeroed_in(context, &layout.shape)?;
+        if count == 0 || layout.len == 0 {
+            return Ok(output);
+        }
+        if u32::try_from(shape.content()).is_err() {
+            return Err(Error::Overflow);
+        }
+
+        let (a, b) = (self.broadcast_to(&shape)?.plain_inner()?, other.broadcast_to(&shape)?.plain_inner()?);
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, COSINE_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("cosine_similarity")
+        .queue(context.queue().clone())
+        .arg(count as u32)
+        .arg(layout.inner as u32)
+        .arg(layout.len as u32)
+        .arg(T::from(COSINE_EPS).unwrap().to_dev())
+        .arg(a.buffer().mem())
+        .arg(b.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run("cosine_similarity", &kernel)?; }
+        Ok(output)
+    }
+
+    /// Mean along the `axis`, see `HostTensor::mean_axis`.
+    pub fn mean_axis(&self, axis: usize, keepdims: bool) -> Result<Self, Error> {
+        self.sum_axis(axis, keepdims)?.unary_op(&format!("a / ({}){}", T::DEV_NAME, self.shape()[axis]))
+    }
+    /// Mean along all the `axes` at once, see `HostTensor::mean_axes`.
+    pub fn mean_axes(&self, axes: &[usize], keepdims: bool) -> Result<Self, Error> {
+        let len = axes.iter().map(|&axis| self.shape()[axis]).product::<usize>();
+        self.sum_axes(axes, keepdims)?.unary_op(&format!("a / ({}){}", T::DEV_NAME, len))
+    }
+    /// Mean along the `axis` over positions where `mask` is nonzero, see `HostTensor::masked_mean`.
+    pub fn masked_mean(&self, mask: &DeviceTensor<u8>, axis: usize, keepdims: bool) -> Result<Self, Error> {
+        check_mask(self.shape(), mask.shape())?;
+        let count = mask.broadcast_to(self.shape())?.unary_op::<T>("a != 0 ? 1 : 0")?.sum_axis(axis, keepdims)?;
+        self.masked_sum(mask, axis, keepdims)?.binary_op(&count, "a / b")
+    }
+}
+
+/// Kernel of full reduction, each work-group combines its elements in local memory by a binary tree.
+///
+/// The operation is defined as `OP(a, b)` and the work-group size as `GROUP`, which must be a power of two.
+const REDUCE_SRC: &str = "
+__kernel void reduce(
+    uint count,
+    __global const T *x,
+    __global T *y
+) {
+    __local T buf[GROUP];
+    uint i = get_global_id(0), l = get_local_id(0);
+    if (i < count) {
+        buf[l] = x[i];
+    }
+    barrier(CLK_LOCAL_MEM_FENCE);
+    for (uint s = GROUP / 2; s > 0; s /= 2) {
+        if (l < s && i + s < count) {
+            T a = buf[l], b = buf[l + s];
+            buf[l] = OP(a, b);
+        }
+        barrier(CLK_LOCAL_MEM_FENCE);
+    }
+    if (l == 0) {
+        y[get_group_id(0)] = buf[0];
+    }
+}
+";
+
+impl<T: Prm + Interop + Num + PartialOrd> DeviceTensor<T> {
+    /// Reduces all the elements with the associative `op` starting from `init`, see `HostTensor::reduce`.
+    ///
+    /// All the operations share a single tree-reduction kernel, which is launched repeatedly
+    /// until one element per work-group is left, only the last element is loaded to the host.
+    pub fn reduce_device(&self, init: T, op: ReduceOp) -> Result<T, Error> {
+        let context = self.context();
+        let mut count = self.shape().content();
+        if count == 0 {
+            return Ok(init);
+        }
+        if u32::try_from(count).is_err() {
+            return Err(Error::Overflow);
+        }
+        // Work-group size is rounded down to a power of two, as required by the tree.
+        let max = context.work_group_size().unwrap_or(64).min(context.queue().device().max_wg_size()?);
+        let group = 1 << (usize::BITS - 1 - max.leading_zeros());
+
+        let src = format!(
+            "{}#define T {}\n#define GROUP {}\n#define OP(a, b) ({})\n{}",
+            extensions(Some(T::DEV_NAME)), T::DEV_NAME, group, op.dev_expr(), REDUCE_SRC,
+        );
+        let program = context.program(&src)?;
+        let mut x = self.plain_inner()?;
+        while count > 1 {
+            let groups = count.div_ceil(group);
+            let y = Self::new_empty_in(context, &Shape::from([groups]))?;
+            let kernel = Kernel::builder()
+            .program(&program)
+            .name("reduce")
+            .queue(context.queue().clone())
+            .global_work_size(groups * group)
+            .local_work_size(group)
+            .arg(count as u32)
+            .arg(x.buffer().mem())
+            .arg(y.inner().buffer().mem())
+            .build()?;
+            unsafe { context.run("reduce", &kernel)?; }
+            x = y.plain_inner()?;
+            count = groups;
+        }
+        let mut value = [init];
+        Self::from_inner(x).load(&mut value)?;
+        Ok(op.apply(init, value[0]))
+    }
+    /// Product of all the elements by the tree-reduction kernel, see `HostTensor::prod` and `prod_axis` for overflow.
+    pub fn prod(&self) -> Result<T, Error> {
+        self.reduce_device(One::one(), ReduceOp::Prod)
+    }
+    /// Product along the `axis`, see `HostTensor::prod_axis`.
+    ///
+    /// Unsigned integers wrap around on overflow, while overflow of signed `int` and `long` is undefined in OpenCL C.
+    /// Complex numbers are not supported because the kernel multiplies componentwise.
+    pub fn prod_axis(&self, axis: usize, keepdims: bool) -> Result<Self, Error> {
+        self.reduce_axis(axis, keepdims, ReduceOp::Prod, "prod_axis")
+    }
+}
+
+impl<T: Prm + Interop + PartialOrd> DeviceTensor<T> {
+    /// Number of nonzero elements along the `axis` or in the whole tensor, see `HostTensor::count_nonzero`.
+    pub fn count_nonzero(&self, axis: Option<usize>, keepdims: bool) -> Result<DeviceTensor<usize>, Error> {
+        let flags = self.unary_op::<usize>("a != 0 ? 1 : 0")?;
+        match axis {
+            Some(axis) => flags.sum_axis(axis, keepdims),
+            None => flags.reshape(&Shape::from([self.shape().content()]))?.sum_axis(0, keepdims),
+        }
+    }
+}
+
+/// Kernel of counting values, each work item increments the bin of a single element atomically.
+const BINCOUNT_SRC: &str = "
+__kernel void bincount(
+    uint count,
+    __global const T *x,
+    __global uint *y
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    atomic_inc(&y[(uint)x[i]]);
+}
+";
+
+impl<T: Prm + Interop + Num + PartialOrd + PrimInt> DeviceTensor<T> {
+    /// Number of occurrences of each value of a tensor of non-negative integers, see `HostTensor::bincount`.
+    ///
+    /// Values are checked by device reductions, so only the minimum and the maximum are loaded to the host.
+    pub fn bincount(&self, minlength: usize) -> Result<DeviceTensor<usize>, Error> {
+        let context = self.context();
+        let count = self.shape().content();
+        let min = self.reduce_device(T::max_value(), ReduceOp::Min)?;
+        let max = self.reduce_device(T::min_value(), ReduceOp::Max)?;
+        let len = bincount_len(count, min, max, minlength)?;
+        if u32::try_from(count).is_err() || u32::try_from(len).is_err() {
+            return Err(Error::Overflow);
+        }
+        let output = DeviceTensor::new_zeroed_in(context, &Shape::from([len]))?;
+        if count == 0 {
+            return Ok(output);
+        }
+
+        let x = self.plain_inner()?;
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, BINCOUNT_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("bincount")
+        .queue(context.queue().clone())
+        .arg(count as u32)
+        .arg(x.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run("bincount", &kernel)?; }
+        Ok(output)
+    }
+}
+
+impl DeviceTensor<usize> {
+    /// Confusion matrix of classification, see `HostTensor::confusion_matrix`.
+    ///
+    /// Classes are checked by device reductions and counted by `bincount`.
+    pub fn confusion_matrix(predictions: &Self, targets: &Self, num_classes: usize) -> Result<Self, Error> {
+        if predictions.context() != targets.context() {
+            return Err(Error::ContextMismatch);
+        }
+        let max = usize::max(predictions.reduce_device(0, ReduceOp::Max)?, targets.reduce_device(0, ReduceOp::Max)?);
+        let len = check_confusion(predictions.shape(), targets.shape(), max, num_classes)?;
+        if u32::try_from(len).is_err() {
+            return Err(Error::Overflow);
+        }
+        let flat: Self = predictions.binary_op(targets, &format!("a + {}u * b", num_classes))?;
+        flat.bincount(len)?.reshape(&Shape::from([num_classes, num_classes]))
+    }
+}
+
+impl DeviceTensor<u8> {
+    /// Whether all elements of the mask are nonzero, see `HostTensor::all`.
+    pub fn all(&self, axis: Option<usize>, keepdims: bool) -> Result<DeviceTensor<bool>, Error> {
+        self.unary_op::<u8>("a == 0")?.count_nonzero(axis, keepdims)?.unary_op("a == 0")
+    }
+    /// Whether any element of the mask is nonzero, see `HostTensor::any`.
+    pub fn any(&self, axis: Option<usize>, keepdims: bool) -> Result<DeviceTensor<bool>, Error> {
+        self.count_nonzero(axis, keepdims)?.unary_op("a != 0")
+    }
+}
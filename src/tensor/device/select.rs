@@ -0,0 +1,157 @@
+use std::convert::TryFrom;
+use ocl::Kernel;
+use crate::{
+    Prm, Num, Interop, Error,
+    Shape, Tensor, DeviceTensor, SelectLayout, SegmentLayout, interleave_indices, extensions,
+};
+
+
+/// Kernel of index selection, each work item copies a single element. See `SelectLayout::source_index`.
+const INDEX_SELECT_SRC: &str = "
+__kernel void index_select(
+    uint count, uint inner, uint len, uint n,
+    __global const T *x, __global const uint *indices,
+    __global T *y
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    uint j = i % inner, r = i / inner;
+    uint k = r % n, o = r / n;
+    y[i] = x[j + inner * (indices[k] + len * o)];
+}
+";
+
+/// Kernel of segment summation, each work item computes a single element of the result. See `SegmentLayout`.
+///
+/// Instead of scattering the source with atomic additions, which OpenCL doesn't provide for floating-point types,
+/// each work item gathers the source slices of its segment, so the result is also deterministic.
+const SEGMENT_SUM_SRC: &str = "
+__kernel void segment_sum(
+    uint count, uint inner, uint len, uint segments,
+    __global const T *x, __global const uint *ids,
+    __global T *y
+) {
+    uint i = get_global_id(0);
+    if (i >= count) {
+        return;
+    }
+    uint j = i % inner, r = i / inner;
+    uint s = r % segments, o = r / segments;
+    T acc = 0;
+    for (uint k = 0; k < len; ++k) {
+        if (ids[k] == s) {
+            acc += x[j + inner * (k + len * o)];
+        }
+    }
+    y[i] = acc;
+}
+";
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    /// Selects whole slices along the `axis` by indices, see `HostTensor::index_select`.
+    ///
+    /// Indices are loaded to the host to check bounds, then elements are gathered by a device kernel.
+    pub fn index_select(&self, axis: usize, indices: &DeviceTensor<usize>) -> Result<Self, Error> {
+        let context = self.context();
+        if indices.context() != context {
+            return Err(Error::ContextMismatch);
+        }
+        let mut index_data = vec![0; indices.shape().content()];
+        indices.load(&mut index_data)?;
+        let layout = SelectLayout::new(self.shape(), axis, indices.shape(), &index_data)?;
+        let output = Self::new_empty_in(context, &layout.shape)?;
+        let count = layout.shape.content();
+        if count == 0 {
+            return Ok(output);
+        }
+        if u32::try_from(self.shape().content()).is_err() || u32::try_from(count).is_err() {
+            return Err(Error::Overflow);
+        }
+
+        let (x, indices) = (self.plain_inner()?, indices.plain_inner()?);
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, INDEX_SELECT_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("index_select")
+        .queue(context.queue().clone())
+        .arg(count as u32)
+        .arg(layout.inner as u32)
+        .arg(layout.len as u32)
+        .arg(index_data.len() as u32)
+        .arg(x.buffer().mem())
+        .arg(indices.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run("index_select", &kernel)?; }
+        Ok(output)
+    }
+
+    /// Repeats each slice along the `axis` consecutively `repeats` times, see `HostTensor::repeat_interleave`.
+    pub fn repeat_interleave(&self, repeats: usize, axis: usize) -> Result<Self, Error> {
+        let len = self.shape()[axis];
+        let indices = interleave_indices(self.shape(), axis, &Shape::from([len]), &vec![repeats; len])?;
+        self.select_interleaved(axis, &indices)
+    }
+    /// Repeats `k`-th slice along the `axis` consecutively `repeats[k]` times, see `HostTensor::repeat_interleave_by`.
+    ///
+    /// Repeats are loaded to the host to compute indices of the slices.
+    pub fn repeat_interleave_by(&self, repeats: &DeviceTensor<usize>, axis: usize) -> Result<Self, Error> {
+        let mut data = vec![0; repeats.shape().content()];
+        repeats.load(&mut data)?;
+        let indices = interleave_indices(self.shape(), axis, repeats.shape(), &data)?;
+        self.select_interleaved(axis, &indices)
+    }
+    fn select_interleaved(&self, axis: usize, indices: &[usize]) -> Result<Self, Error> {
+        let mut tensor = DeviceTensor::new_empty_in(self.context(), &Shape::from([indices.len()]))?;
+        tensor.store(indices)?;
+        self.index_select(axis, &tensor)
+    }
+}
+
+impl<T: Prm + Interop + Num> DeviceTensor<T> {
+    /// Sums slices along the `axis` grouped by segment ids, see `HostTensor::segment_sum`.
+    ///
+    /// Ids are loaded to the host to check bounds, then the sums are computed by a device kernel.
+    pub fn segment_sum(&self, axis: usize, segment_ids: &DeviceTensor<usize>, num_segments: usize) -> Result<Self, Error> {
+        let context = self.context();
+        if segment_ids.context() != context {
+            return Err(Error::ContextMismatch);
+        }
+        let mut id_data = vec![0; segment_ids.shape().content()];
+        segment_ids.load(&mut id_data)?;
+        let layout = SegmentLayout::new(self.shape(), axis, segment_ids.shape(), &id_data, num_segments)?;
+        let output = Self::new_zeroed_in(context, &layout.shape)?;
+        let count = layout.shape.content();
+        if count == 0 || layout.len == 0 {
+            return Ok(output);
+        }
+        if u32::try_from(self.shape().content()).is_err() || u32::try_from(count).is_err() {
+            return Err(Error::Overflow);
+        }
+
+        let (x, ids) = (self.plain_inner()?, segment_ids.plain_inner()?);
+        let src = format!("{}#define T {}\n{}", extensions(Some(T::DEV_NAME)), T::DEV_NAME, SEGMENT_SUM_SRC);
+        let program = context.program(&src)?;
+        let mut builder = Kernel::builder();
+        builder
+        .program(&program)
+        .name("segment_sum")
+        .queue(context.queue().clone())
+        .arg(count as u32)
+        .arg(layout.inner as u32)
+        .arg(layout.len as u32)
+        .arg(layout.segments as u32)
+        .arg(x.buffer().mem())
+        .arg(ids.buffer().mem())
+        .arg(output.inner().buffer().mem());
+        context.set_work_size(&mut builder, count);
+        let kernel = builder.build()?;
+        unsafe { context.run("segment_sum", &kernel)?; }
+        Ok(output)
+    }
+}
@@ -0,0 +1,23 @@
+use crate::{
+    Prm, Interop, Num, Error,
+    DeviceContext, Tensor, HostTensor, DeviceTensor, SparseTensor,
+};
+
+
+impl<T: Prm + Interop + Num> SparseTensor<T> {
+    /// Converts to dense tensor in the context, see `to_dense`.
+    ///
+    /// Dense tensor is assembled on the host and then copied to the device.
+    pub fn to_dense_in(&self, context: &DeviceContext) -> Result<DeviceTensor<T>, Error> {
+        context.from_vec(self.to_dense().to_vec()?, self.shape())
+    }
+}
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    /// Converts to sparse tensor keeping only nonzero elements, see `HostTensor::to_sparse`.
+    ///
+    /// The whole tensor is loaded to the host.
+    pub fn to_sparse(&self) -> Result<SparseTensor<T>, Error> {
+        Ok(HostTensor::from_vec(self.to_vec()?, self.shape())?.to_sparse())
+    }
+}
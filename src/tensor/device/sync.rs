@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use crate::{
+    Prm, Num, Interop,
+    DeviceBuffer, DeviceContext,
+    Shape, Tensor, CommonTensor, Error, DeviceTensor, Index, Range,
+};
+
+
+type SyncInnerTensor<T> = CommonTensor<T, DeviceBuffer<T>, Arc<DeviceBuffer<T>>>;
+
+/// Device tensor which buffer is shared via `Arc`, so that it could be sent to other threads.
+///
+/// Operations could be called from any thread: OpenCL queue accepts commands from multiple threads,
+/// and commands of the same context are executed in the order they were enqueued.
+/// Transfers between host and device (`load`, `store`) are blocking, so data uploaded in one thread
+/// is visible to kernels launched later from another thread.
+/// Among different contexts there is no ordering, the tensor used in other context must be copied to it first.
+///
+/// Atomic reference counting makes cloning and dropping slightly slower than for `DeviceTensor`.
+#[derive(Clone)]
+pub struct SyncDeviceTensor<T: Prm + Interop> {
+    inner: SyncInnerTensor<T>,
+}
+
+impl<T: Prm + Interop> SyncDeviceTensor<T> {
+    /// Converts to `DeviceTensor`, the buffer is copied if it is shared with other tensors.
+    pub fn into_device(self) -> DeviceTensor<T> {
+        DeviceTensor::from_inner(self.inner.into_ptr())
+    }
+}
+
+/// The buffer is moved without copying if it isn't shared with other tensors.
+impl<T: Prm + Interop> From<DeviceTensor<T>> for SyncDeviceTensor<T> {
+    fn from(tensor: DeviceTensor<T>) -> Self {
+        Self { inner: tensor.into_inner().into_ptr() }
+    }
+}
+impl<T: Prm + Interop> From<SyncDeviceTensor<T>> for DeviceTensor<T> {
+    fn from(tensor: SyncDeviceTensor<T>) -> Self {
+        tensor.into_device()
+    }
+}
+
+impl<T: Prm + Interop> Tensor<T> for SyncDeviceTensor<T> {
+    type Buffer = DeviceBuffer<T>;
+
+    unsafe fn new_uninit_in(context: &DeviceContext, shape: &Shape) -> Result<Self, Error> {
+        SyncInnerTensor::<T>::new_uninit_in(context, shape).map(|inner| Self { inner })
+    }
+    fn new_empty_in(context: &DeviceContext, shape: &Shape) -> Result<Self, Error> {
+        SyncInnerTensor::<T>::new_empty_in(context, shape).map(|inner| Self { inner })
+    }
+    fn new_filled_in(context: &DeviceContext, shape: &Shape, value: T) -> Result<Self, Error> {
+        SyncInnerTensor::<T>::new_filled_in(context, shape, value).map(|inner| Self { inner })
+    }
+    fn new_zeroed_in(context: &DeviceContext, shape: &Shape) -> Result<Self, Error> {
+        SyncInnerTensor::<T>::new_zeroed_in(context, shape).map(|inner| Self { inner })
+    }
+
+    fn context(&self) -> &DeviceContext {
+        self.inner.context()
+    }
+    fn shape(&self) -> &Shape {
+        self.inner.shape()
+    }
+    fn buffer_refcount(&self) -> usize {
+        self.inner.buffer_refcount()
+    }
+
+    fn view(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.view(shape).map(|inner| Self { inner })
+    }
+    fn reshape(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.reshape(shape).map(|inner| Self { inner })
+    }
+
+    fn resize(&self, shape: &[usize], fill: T) -> Result<Self, Error> {
+        self.inner.resize(shape, fill).map(|inner| Self { inner })
+    }
+
+    fn permute(&self, axes: &[usize]) -> Result<Self, Error> {
+        self.inner.permute(axes).map(|inner| Self { inner })
+    }
+    fn transpose(&self) -> Self {
+        Self { inner: self.inner.transpose() }
+    }
+    fn slice(&self, index: &[Index]) -> Result<Self, Error> {
+        self.inner.slice(index).map(|inner| Self { inner })
+    }
+    fn unfold(&self, axis: usize, size: usize, step: usize) -> Result<Self, Error> {
+        self.inner.unfold(axis, size, step).map(|inner| Self { inner })
+    }
+    fn broadcast_to(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.broadcast_to(shape).map(|inner| Self { inner })
+    }
+
+    fn clone_contiguous(&self) -> Result<Self, Error> {
+        self.inner.clone_contiguous().map(|inner| Self { inner })
+    }
+    fn compact(&self) -> Result<Self, Error> {
+        self.inner.compact().map(|inner| Self { inner })
+    }
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error> {
+        self.inner.copy_from(&src.inner)
+    }
+    fn assign_slice(&mut self, ranges: &[Range], src: &Self) -> Result<(), Error> {
+        self.inner.assign_slice(ranges, &src.inner)
+    }
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num {
+        self.inner.fill_iota(start, step)
+    }
+
+    fn load(&self, dst: &mut [T]) -> Result<(), Error> {
+        self.inner.load(dst)
+    }
+    fn store(&mut self, src: &[T]) -> Result<(), Error> {
+        self.inner.store(src)
+    }
+}
+
+/// Buffers and contexts must stay thread-safe for `SyncDeviceTensor` to be `Send` and `Sync`.
+const _: fn() = || {
+    fn check<X: Send + Sync>() {}
+    check::<DeviceContext>();
+    check::<SyncDeviceTensor<f32>>();
+};
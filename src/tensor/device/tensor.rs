@@ -0,0 +1,183 @@
+use std::mem;
+use crate::{
+    Prm, Num, Interop,
+    DeviceBuffer, DeviceContext,
+    Shape, Tensor, CommonTensor, Error, HostTensor, Index, Range,
+};
+
+pub(crate) type InnerTensor<T> = CommonTensor<T, DeviceBuffer<T>>;
+
+/// Tensor structure.
+/// It consists of a contiguous one-dimensional array and a shape.
+/// Tensor tries to reuse resources as long as possible and implements copy-on-write mechanism.
+///
+/// The buffer is shared via `Rc`, so the tensor cannot be sent to other threads,
+/// convert it to `SyncDeviceTensor` for that.
+#[derive(Clone)]
+pub struct DeviceTensor<T: Prm + Interop> {
+    inner: InnerTensor<T>,
+}
+
+impl<T: Prm + Interop> DeviceTensor<T> {
+    pub(crate) fn from_inner(inner: InnerTensor<T>) -> Self {
+        Self { inner }
+    }
+    pub(crate) fn inner(&self) -> &InnerTensor<T> {
+        &self.inner
+    }
+    pub(crate) fn inner_mut(&mut self) -> &mut InnerTensor<T> {
+        &mut self.inner
+    }
+    pub(crate) fn into_inner(self) -> InnerTensor<T> {
+        self.inner
+    }
+    /// Inner tensor which elements occupy the whole buffer in flattened order, copied if needed.
+    pub(crate) fn plain_inner(&self) -> Result<InnerTensor<T>, Error> {
+        let mut inner = self.inner.clone();
+        inner.make_plain()?;
+        Ok(inner)
+    }
+    /// Copies the tensor to other context, e.g. to other device.
+    ///
+    /// Contexts that share the OpenCL context (see `DeviceContext::same_cl_context`) copy the data on the device,
+    /// otherwise it is transferred through the host memory.
+    /// Returns a cheap clone if the tensor already belongs to the context.
+    pub fn to_context(&self, context: &DeviceContext) -> Result<Self, Error> {
+        if self.context() == context {
+            return Ok(self.clone());
+        }
+        let inner = self.plain_inner()?;
+        let buffer = inner.buffer().clone_to(context)?;
+        Ok(Self { inner: InnerTensor::<T>::from_buffer(buffer, self.shape()) })
+    }
+
+    /// Reinterprets device representation of each element as a device representation of `U`, e.g. `int` as `uint`.
+    ///
+    /// Unlike `HostTensor::reinterpret` sizes of device types are compared, e.g. `usize` is `uint` on the device,
+    /// so it could be reinterpreted as `f32`. Elements are copied to a new buffer by the OpenCL `as_<type>` conversion
+    /// without loading them to the host. It is safe because `U` is obtained from its device representation on loading,
+    /// e.g. any nonzero byte is loaded as `true`.
+    /// Fails with `Error::BadSize` containing sizes of device types in bytes if they are different.
+    pub fn reinterpret<U: Prm + Interop>(&self) -> Result<DeviceTensor<U>, Error> {
+        let (from, to) = (mem::size_of::<T::Dev>(), mem::size_of::<U::Dev>());
+        if from != to {
+            return Err(Error::BadSize { expected: from, found: to });
+        }
+        self.unary_op(&format!("as_{}(a)", U::DEV_NAME))
+    }
+
+    /// Overwrites elements of the tensor with elements of the host tensor, see `Tensor::copy_from`.
+    ///
+    /// Fails with `Error::ShapeMismatch` if tensors have different shapes.
+    pub fn copy_from_host(&mut self, src: &HostTensor<T>) -> Result<(), Error> {
+        if self.shape() != src.shape() {
+            return Err(Error::ShapeMismatch(self.shape().clone(), src.shape().clone()));
+        }
+        self.store(&src.iter().cloned().collect::<Vec<_>>())
+    }
+    /// Overwrites elements of the host tensor with elements of the tensor, see `Tensor::copy_from`.
+    ///
+    /// Fails with `Error::ShapeMismatch` if tensors have different shapes.
+    pub fn copy_to_host(&self, dst: &mut HostTensor<T>) -> Result<(), Error> {
+        if self.shape() != dst.shape() {
+            return Err(Error::ShapeMismatch(dst.shape().clone(), self.shape().clone()));
+        }
+        let mut data = vec![T::zero(); self.shape().content()];
+        self.load(&mut data)?;
+        dst.store(&data)
+    }
+}
+
+/// Tensors are equal if they have the same shape and elements, see `PartialEq` for `HostTensor`.
+///
+/// Both tensors are copied to the host to compare them, so it is expensive.
+/// Panics if the device fails to read the tensors.
+impl<T: Prm + Interop> PartialEq for DeviceTensor<T> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.shape() != other.shape() {
+            return false;
+        }
+        let len = self.shape().content();
+        let (mut a, mut b) = (vec![T::zero(); len], vec![T::zero(); len]);
+        self.load(&mut a).unwrap();
+        other.load(&mut b).unwrap();
+        a == b
+    }
+}
+
+impl<T: Prm + Interop> Tensor<T> for DeviceTensor<T> {
+    type Buffer = DeviceBuffer<T>;
+
+    unsafe fn new_uninit_in(context: &DeviceContext, shape: &Shape) -> Result<Self, Error> {
+        InnerTensor::<T>::new_uninit_in(context, shape).map(|inner| Self { inner })
+    }
+    fn new_empty_in(context: &DeviceContext, shape: &Shape) -> Result<Self, Error> {
+        InnerTensor::<T>::new_empty_in(context, shape).map(|inner| Self { inner })
+    }
+    fn new_filled_in(context: &DeviceContext, shape: &Shape, value: T) -> Result<Self, Error> {
+        InnerTensor::<T>::new_filled_in(context, shape, value).map(|inner| Self { inner })
+    }
+    fn new_zeroed_in(context: &DeviceContext, shape: &Shape) -> Result<Self, Error> {
+        InnerTensor::<T>::new_zeroed_in(context, shape).map(|inner| Self { inner })
+    }
+
+    fn context(&self) -> &DeviceContext {
+        self.inner.context()
+    }
+    fn shape(&self) -> &Shape {
+        self.inner.shape()
+    }
+    fn buffer_refcount(&self) -> usize {
+        self.inner.buffer_refcount()
+    }
+
+    fn view(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.view(shape).map(|inner| Self { inner })
+    }
+    fn reshape(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.reshape(shape).map(|inner| Self { inner })
+    }
+
+    fn resize(&self, shape: &[usize], fill: T) -> Result<Self, Error> {
+        self.inner.resize(shape, fill).map(|inner| Self { inner })
+    }
+
+    fn permute(&self, axes: &[usize]) -> Result<Self, Error> {
+        self.inner.permute(axes).map(|inner| Self { inner })
+    }
+    fn transpose(&self) -> Self {
+        Self { inner: self.inner.transpose() }
+    }
+    fn slice(&self, index: &[Index]) -> Result<Self, Error> {
+        self.inner.slice(index).map(|inner| Self { inner })
+    }
+    fn unfold(&self, axis: usize, size: usize, step: usize) -> Result<Self, Error> {
+        self.inner.unfold(axis, size, step).map(|inner| Self { inner })
+    }
+    fn broadcast_to(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.broadcast_to(shape).map(|inner| Self { inner })
+    }
+
+    fn clone_contiguous(&self) -> Result<Self, Error> {
+        self.inner.clone_contiguous().map(|inner| Self { inner })
+    }
+    fn compact(&self) -> Result<Self, Error> {
+        self.inner.compact().map(|inner| Self { inner })
+    }
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error> {
+        self.inner.copy_from(&src.inner)
+    }
+    fn assign_slice(&mut self, ranges: &[Range], src: &Self) -> Result<(), Error> {
+        self.inner.assign_slice(ranges, &src.inner)
+    }
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num {
+        self.inner.fill_iota(start, step)
+    }
+
+    fn load(&self, dst: &mut [T]) -> Result<(), Error> {
+        self.inner.load(dst)
+    }
+    fn store(&mut self, src: &[T]) -> Result<(), Error> {
+        self.inner.store(src)
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{Prm, Interop, DeviceContext, Tensor, HostTensor, DeviceTensor, Shape, Index, Range, ReduceOp, Error};
+
+
+/// Context of the default device, tests return early if there is no device.
+fn context() -> Option<DeviceContext> {
+    DeviceContext::best_available()
+}
+
+/// Copy of the host tensor on the device.
+fn upload<T: Prm + Interop>(context: &DeviceContext, x: &HostTensor<T>) -> DeviceTensor<T> {
+    context.from_vec(x.to_vec().unwrap(), x.shape()).unwrap()
+}
+
+/// Fails if the device result isn't close to the host one, as in the device benchmark.
+fn assert_close(device: &DeviceTensor<f32>, host: &HostTensor<f32>) {
+    let report = device.compare_report(host, 1e-5, 1e-5).unwrap();
+    assert!(report.is_close(), "{}", report);
+}
+
+#[test]
+fn matmul_tiled() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    // Dimensions aren't multiples of any tested tile, so boundary tiles are partially filled.
+    let (m, k, n) = (37, 19, 23);
+    let a = HostTensor::from_vec((0..(m * k)).map(|i| (i % 7) as f32 - 3.0).collect(), &Shape::from([k, m])).unwrap();
+    let b = HostTensor::from_vec((0..(k * n)).map(|i| (i % 5) as f32 - 2.0).collect(), &Shape::from([n, k])).unwrap();
+    let c = a.matmul(&b).unwrap();
+    let (da, db) = (context.from_vec(a.to_vec().unwrap(), a.shape()).unwrap(), context.from_vec(b.to_vec().unwrap(), b.shape()).unwrap());
+    // Operands stored transposed are read by the same kernel with swapped strides.
+    let dat = context.from_vec(a.transpose().to_vec().unwrap(), &Shape::from([m, k])).unwrap();
+    let dbt = context.from_vec(b.transpose().to_vec().unwrap(), &Shape::from([k, n])).unwrap();
+    for &tile in [Some(16), Some(4), Some(1), None].iter() {
+        context.set_matmul_tile_size(tile).unwrap();
+        // Small integers are summed exactly, so results of both kernels are equal to the host one.
+        assert_eq!(da.matmul(&db).unwrap().to_vec().unwrap(), c.to_vec().unwrap());
+        assert_eq!(dat.matmul_flags(&dbt, true, true).unwrap().to_vec().unwrap(), c.to_vec().unwrap());
+    }
+    assert_eq!(context.matmul_tile_size(), None);
+    assert!(matches!(context.set_matmul_tile_size(Some(0)), Err(Error::InvalidWorkGroupSize)));
+    assert!(matches!(context.set_matmul_tile_size(Some(1 << 16)), Err(Error::InvalidWorkGroupSize)));
+}
+
+#[test]
+fn complex() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    let shape = Shape::from([3, 2]);
+    let re = HostTensor::from_vec(vec![1.0f32, -2.0, 0.5, 3.0, 0.0, -1.5], &shape).unwrap();
+    let im = HostTensor::from_vec(vec![2.0f32, 0.0, -4.0, 1.0, -0.5, 2.5], &shape).unwrap();
+    let a = HostTensor::from_parts(&re, &im).unwrap();
+    let b = a.conj().add(&HostTensor::from_parts(&im, &re).unwrap()).unwrap();
+    let (dre, dim) = (context.from_vec(re.to_vec().unwrap(), &shape).unwrap(), context.from_vec(im.to_vec().unwrap(), &shape).unwrap());
+    let da = DeviceTensor::from_parts(&dre, &dim).unwrap();
+    let db = context.from_vec(b.to_vec().unwrap(), &shape).unwrap();
+    // Values are round-tripped through `float2`, so real and imaginary parts keep their places.
+    assert_eq!(da.to_vec().unwrap(), a.to_vec().unwrap());
+    assert_eq!(da.real().unwrap().to_vec().unwrap(), re.to_vec().unwrap());
+    assert_eq!(da.imag().unwrap().to_vec().unwrap(), im.to_vec().unwrap());
+    assert_eq!(da.conj().unwrap().to_vec().unwrap(), a.conj().to_vec().unwrap());
+    assert_eq!(da.add(&db).unwrap().to_vec().unwrap(), a.add(&b).unwrap().to_vec().unwrap());
+    // Parts are small multiples of a half, so products are exact and must match the host ones.
+    assert_eq!(da.mul(&db).unwrap().to_vec().unwrap(), a.mul(&b).unwrap().to_vec().unwrap());
+    for (x, y) in da.abs().unwrap().to_vec().unwrap().into_iter().zip(a.abs().to_vec().unwrap()) {
+        assert!((x - y).abs() <= 1e-6 * y);
+    }
+}
+
+#[test]
+fn reductions() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    // Small integers are summed and multiplied exactly, so results of the kernels are equal to the host ones.
+    let x = HostTensor::from_vec((0..20).map(|i| (i % 7) as f32 - 2.0).collect(), &Shape::from([4, 5])).unwrap();
+    let dx = upload(&context, &x);
+    for &axis in [0, 1].iter() {
+        for &keepdims in [false, true].iter() {
+            assert_eq!(dx.sum_axis(axis, keepdims).unwrap().to_vec().unwrap(), x.sum_axis(axis, keepdims).to_vec().unwrap());
+            assert_eq!(dx.prod_axis(axis, keepdims).unwrap().to_vec().unwrap(), x.prod_axis(axis, keepdims).to_vec().unwrap());
+            assert_close(&dx.mean_axis(axis, keepdims).unwrap(), &x.mean_axis(axis, keepdims));
+        }
+    }
+    // Strided views are reduced as well as the plain tensors.
+    let t = x.transpose();
+    assert_eq!(dx.transpose().sum_axis(0, false).unwrap().to_vec().unwrap(), t.sum_axis(0, false).to_vec().unwrap());
+    for &op in [ReduceOp::Sum, ReduceOp::Max, ReduceOp::Min].iter() {
+        assert_eq!(dx.reduce_device(0.0, op).unwrap(), x.reduce(0.0, op));
+    }
+    let small = HostTensor::from_vec(vec![1.0f32, -2.0, 0.5, 3.0, -1.0, 2.0], &Shape::from([3, 2])).unwrap();
+    assert_eq!(upload(&context, &small).prod().unwrap(), small.prod());
+
+    let mask = HostTensor::from_vec((0..20).map(|i| (i % 3 == 0) as u8).collect(), x.shape()).unwrap();
+    assert_eq!(dx.masked_sum(&upload(&context, &mask), 1, false).unwrap().to_vec().unwrap(), x.masked_sum(&mask, 1, false).unwrap().to_vec().unwrap());
+    assert_eq!(dx.count_nonzero(Some(0), true).unwrap().to_vec().unwrap(), x.count_nonzero(Some(0), true).unwrap().to_vec().unwrap());
+    assert_eq!(dx.count_nonzero(None, false).unwrap().to_vec().unwrap(), x.count_nonzero(None, false).unwrap().to_vec().unwrap());
+    let ids = HostTensor::from(vec![2, 0, 2, 1, 0]);
+    assert_eq!(dx.segment_sum(1, &upload(&context, &ids), 3).unwrap().to_vec().unwrap(), x.segment_sum(1, &ids, 3).unwrap().to_vec().unwrap());
+}
+
+/// Views of a tensor of shape `[4, 3]` which elements aren't contiguous in memory.
+fn strided_views<X: Tensor<i32>>(x: &X) -> Vec<X> {
+    vec![
+        x.transpose(),
+        x.slice(&[Index::Range(Range::new(None, None, -2))]).unwrap(),
+        x.slice(&[Index::Range(Range::new(Some(1), None, 2)), Index::Single(-1)]).unwrap(),
+    ]
+}
+
+#[test]
+fn store_views() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    let x = HostTensor::from_vec((0..12).collect::<Vec<i32>>(), &Shape::from([4, 3])).unwrap();
+    let data = (100..112).collect::<Vec<i32>>();
+    for k in 0..3 {
+        let view = |x: &HostTensor<i32>| strided_views(x).remove(k);
+        let device_view = |x: &DeviceTensor<i32>| strided_views(x).remove(k);
+        // Elements of a strided view are scattered to their places in the buffer by the kernel.
+        let (mut h, mut d) = (view(&x), device_view(&upload(&context, &x)));
+        let src = &data[..h.numel()];
+        h.store(src).unwrap();
+        d.store(src).unwrap();
+        assert_eq!(d.to_vec().unwrap(), h.to_vec().unwrap());
+
+        let (mut h, mut d) = (view(&x), device_view(&upload(&context, &x)));
+        h.fill(-1).unwrap();
+        d.fill(-1).unwrap();
+        assert_eq!(d.to_vec().unwrap(), h.to_vec().unwrap());
+
+        let (mut h, mut d) = (view(&x), device_view(&upload(&context, &x)));
+        // Source is strided as well, so the elements are gathered from it.
+        let src = x.map(|v| v * 10);
+        h.copy_from(&view(&src)).unwrap();
+        d.copy_from(&device_view(&upload(&context, &src))).unwrap();
+        assert_eq!(d.to_vec().unwrap(), h.to_vec().unwrap());
+    }
+
+    // Broadcasted elements share memory, so they are separated before the store.
+    let row = HostTensor::from(vec![1, 2, 3]);
+    let (mut h, mut d) = (row.broadcast_to(&Shape::from([3, 2])).unwrap(), upload(&context, &row).broadcast_to(&Shape::from([3, 2])).unwrap());
+    h.store(&data[..6]).unwrap();
+    d.store(&data[..6]).unwrap();
+    assert_eq!(d.to_vec().unwrap(), h.to_vec().unwrap());
+
+    let (mut h, mut d) = (x.clone(), upload(&context, &x));
+    let ranges = [Range::new(Some(1), Some(3), 1), Range::new(None, None, -2)];
+    h.assign_slice(&ranges, &row.slice(&[Index::Range(Range::new(None, Some(2), 1))]).unwrap()).unwrap();
+    d.assign_slice(&ranges, &upload(&context, &row).slice(&[Index::Range(Range::new(None, Some(2), 1))]).unwrap()).unwrap();
+    assert_eq!(d.to_vec().unwrap(), h.to_vec().unwrap());
+}
+
+/// In-place operation with a broadcasted operand, e.g. `add_assign`.
+type AssignOp<X> = fn(&mut X, &X) -> Result<(), Error>;
+/// The same in-place operation on the device and on the host.
+type AssignOps = (AssignOp<DeviceTensor<f32>>, AssignOp<HostTensor<f32>>);
+
+#[test]
+fn elementwise() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    let shape = Shape::from([3, 4]);
+    let x = HostTensor::from_vec((0..12).map(|i| i as f32 * 0.75 - 4.0).collect(), &shape).unwrap();
+    let y = HostTensor::from_vec((0..12).map(|i| ((i * 5) % 12) as f32 * 0.5 - 2.5).collect(), &shape).unwrap();
+    let (dx, dy) = (upload(&context, &x), upload(&context, &y));
+    // Row is broadcasted over the other axis, as for the host operations.
+    let row = HostTensor::from(vec![0.5f32, -1.0, 2.0]);
+    let drow = upload(&context, &row);
+
+    // Arithmetic is done in place on the device, the other operand is broadcasted to the shape of `self`.
+    let ops: [AssignOps; 4] = [
+        (DeviceTensor::add_assign, HostTensor::add_assign),
+        (DeviceTensor::sub_assign, HostTensor::sub_assign),
+        (DeviceTensor::mul_assign, HostTensor::mul_assign),
+        (DeviceTensor::div_assign, HostTensor::div_assign),
+    ];
+    for &(device_op, host_op) in ops.iter() {
+        let (mut d, mut h) = (dx.clone(), x.clone());
+        device_op(&mut d, &drow).unwrap();
+        host_op(&mut h, &row).unwrap();
+        assert_close(&d, &h);
+    }
+    assert_eq!(dx.maximum(&dy).unwrap().to_vec().unwrap(), x.maximum(&y).unwrap().to_vec().unwrap());
+    assert_eq!(dx.minimum(&dy).unwrap().to_vec().unwrap(), x.minimum(&y).unwrap().to_vec().unwrap());
+    assert_eq!(dx.clamp(-1.0, 2.0).unwrap().to_vec().unwrap(), x.clamp(-1.0, 2.0).to_vec().unwrap());
+    assert_eq!(dx.relu().unwrap().to_vec().unwrap(), x.relu().to_vec().unwrap());
+    assert_eq!(dx.floor().unwrap().to_vec().unwrap(), x.floor().to_vec().unwrap());
+    assert_eq!(dx.sign().unwrap().to_vec().unwrap(), x.sign().to_vec().unwrap());
+    assert_close(&dx.lerp(&dy, 0.25).unwrap(), &x.lerp(&y, 0.25).unwrap());
+    assert_close(&dx.exp().unwrap(), &x.exp());
+    assert_close(&dx.tanh().unwrap(), &x.tanh());
+    assert_close(&dx.atan2(&dy).unwrap(), &x.atan2(&y).unwrap());
+    assert_close(&dx.hypot(&dy).unwrap(), &x.hypot(&y).unwrap());
+    assert_eq!(dx.diff(1, 0).unwrap().to_vec().unwrap(), x.diff(1, 0).unwrap().to_vec().unwrap());
+
+    let nan = HostTensor::from(vec![1.0f32, f32::NAN, f32::INFINITY, -2.0]);
+    let dnan = upload(&context, &nan);
+    assert_eq!(dnan.isnan().unwrap().to_vec().unwrap(), nan.isnan().to_vec().unwrap());
+    assert_eq!(dnan.isfinite().unwrap().to_vec().unwrap(), nan.isfinite().to_vec().unwrap());
+    assert_eq!(dnan.nan_to_num(0.0, 9.0, -9.0).unwrap().to_vec().unwrap(), nan.nan_to_num(0.0, 9.0, -9.0).to_vec().unwrap());
+}
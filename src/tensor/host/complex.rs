@@ -0,0 +1,30 @@
+use num_complex::Complex;
+use crate::{
+    Prm, Float, Error,
+    HostTensor,
+};
+
+
+/// Complex numbers are `Num`, so they are added and multiplied by the generic `add` and `mul`.
+impl<T: Prm + Float> HostTensor<Complex<T>> {
+    /// Complex tensor from broadcasted real and imaginary parts.
+    pub fn from_parts(re: &HostTensor<T>, im: &HostTensor<T>) -> Result<Self, Error> {
+        re.zip_map(im, Complex::new)
+    }
+    /// Real part of each element.
+    pub fn real(&self) -> HostTensor<T> {
+        self.map(|x| x.re)
+    }
+    /// Imaginary part of each element.
+    pub fn imag(&self) -> HostTensor<T> {
+        self.map(|x| x.im)
+    }
+    /// Complex conjugate of each element.
+    pub fn conj(&self) -> Self {
+        self.map(|x| x.conj())
+    }
+    /// Magnitude of each element, computed without intermediate overflow as in `hypot`.
+    pub fn abs(&self) -> HostTensor<T> {
+        self.map(|x| x.re.hypot(x.im))
+    }
+}
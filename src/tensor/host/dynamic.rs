@@ -0,0 +1,100 @@
+use std::{any::{Any, type_name}, fmt};
+use crate::{Prm, Error, Shape, Tensor, HostTensor, f16, bf16};
+
+
+macro_rules! def_dyn {
+    ($($var:ident: $type:ident),* $(,)?) => {
+        /// Element type of `DynTensor`.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum DType {
+            $($var,)*
+        }
+
+        impl DType {
+            /// Element type corresponding to `T`, `None` if it cannot be stored in `DynTensor` (e.g. complex numbers).
+            pub fn of<T: Prm + Any>() -> Option<Self> {
+                let id = std::any::TypeId::of::<T>();
+                $(if id == std::any::TypeId::of::<$type>() {
+                    return Some(DType::$var);
+                })*
+                None
+            }
+            /// Name of the type in Rust, e.g. `f32`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(DType::$var => stringify!($type),)*
+                }
+            }
+        }
+
+        /// Host tensor which element type is known only at runtime, e.g. loaded from a file of arbitrary type.
+        ///
+        /// It is a plain enum over tensors of each element type, so it costs the same as the wrapped tensor.
+        #[derive(Clone)]
+        pub enum DynTensor {
+            $($var(HostTensor<$type>),)*
+        }
+
+        impl DynTensor {
+            /// Element type of the wrapped tensor.
+            pub fn dtype(&self) -> DType {
+                match self {
+                    $(DynTensor::$var(_) => DType::$var,)*
+                }
+            }
+            /// Shape of the wrapped tensor.
+            pub fn shape(&self) -> &Shape {
+                match self {
+                    $(DynTensor::$var(t) => t.shape(),)*
+                }
+            }
+            fn into_any(self) -> Box<dyn Any> {
+                match self {
+                    $(DynTensor::$var(t) => Box::new(t),)*
+                }
+            }
+            fn as_any(&self) -> &dyn Any {
+                match self {
+                    $(DynTensor::$var(t) => t,)*
+                }
+            }
+        }
+
+        $(
+            impl From<HostTensor<$type>> for DynTensor {
+                fn from(t: HostTensor<$type>) -> Self {
+                    DynTensor::$var(t)
+                }
+            }
+        )*
+    };
+}
+
+def_dyn! {
+    U8: u8, U16: u16, U32: u32, U64: u64, Usize: usize,
+    I8: i8, I16: i16, I32: i32, I64: i64, Isize: isize,
+    F16: f16, Bf16: bf16, F32: f32, F64: f64,
+    Bool: bool,
+}
+
+impl fmt::Display for DType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl DynTensor {
+    /// Takes the wrapped tensor if its element type is `T`.
+    ///
+    /// Fails with `Error::BadArgument` naming both types otherwise, elements are never converted.
+    pub fn downcast<T: Prm + Any>(self) -> Result<HostTensor<T>, Error> {
+        let dtype = self.dtype();
+        self.into_any().downcast::<HostTensor<T>>().map(|t| *t).map_err(|_| {
+            Error::BadArgument(format!("tensor of type {} cannot be downcast to {}", dtype, type_name::<T>()))
+        })
+    }
+    /// Reference to the wrapped tensor if its element type is `T`.
+    pub fn downcast_ref<T: Prm + Any>(&self) -> Option<&HostTensor<T>> {
+        self.as_any().downcast_ref()
+    }
+}
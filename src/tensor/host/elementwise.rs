@@ -0,0 +1,361 @@
+use num_traits::{Zero, CheckedAdd, CheckedSub, CheckedMul, SaturatingAdd, SaturatingSub, SaturatingMul};
+use crate::{
+    Prm, Num, Float, Error, Shape, f16, bf16,
+    Tensor, HostTensor, for_each_part, check_diff, diff_views,
+};
+
+
+/// Number of elements processed at once by chunked loops, a chunk of `f32` fills an AVX register.
+const LANES: usize = 8;
+
+/// Applies `f` to pairs of elements of slices of the same length.
+///
+/// Elements are processed by fixed-size chunks, so the compiler unrolls the inner loop and vectorizes it,
+/// the remaining elements are processed one by one. Large slices are split between threads, see `for_each_part`.
+fn chunked<T: Copy + Send + Sync, F: Fn(T, T) -> T + Sync>(a: &[T], b: &[T], f: F) -> Vec<T> {
+    let mut vec = a.to_vec();
+    for_each_part(&mut vec, LANES, |start, part| {
+        let b = &b[start..(start + part.len())];
+        let mut chunks = part.chunks_exact_mut(LANES);
+        let b_chunks = b.chunks_exact(LANES);
+        let b_tail = b_chunks.remainder();
+        for (x, y) in (&mut chunks).zip(b_chunks) {
+            for (x, &y) in x.iter_mut().zip(y) {
+                *x = f(*x, y);
+            }
+        }
+        for (x, &y) in chunks.into_remainder().iter_mut().zip(b_tail) {
+            *x = f(*x, y);
+        }
+    });
+    vec
+}
+
+
+impl<T: Prm> HostTensor<T> {
+    /// Returns a new tensor of the same shape with function applied to each element.
+    pub fn map<U: Prm, F: FnMut(T) -> U>(&self, f: F) -> HostTensor<U> {
+        HostTensor::from_plain_vec(self.iter().cloned().map(f).collect(), self.shape())
+    }
+    /// Applies `f` to each one-dimensional lane of the tensor along `axis` and assembles the results along the same axis.
+    ///
+    /// All calls must return vectors of the same length, which becomes the length of the axis, e.g. a running filter
+    /// keeps it and a custom reduction may return a single element. If the tensor has no lanes, `f` isn't called
+    /// and the axis keeps its length. Lanes are passed in flattened order of the other axes.
+    /// Fails with `Error::BadAxes` if `axis` isn't less than the number of dimensions
+    /// and with `Error::BadSize` if `f` returns vectors of different lengths.
+    pub fn apply_along_axis<F: FnMut(&[T]) -> Vec<T>>(&self, axis: usize, mut f: F) -> Result<Self, Error> {
+        if axis >= self.shape().len() {
+            return Err(Error::BadAxes(vec![axis]));
+        }
+        let lanes = self.moveaxis(axis, 0)?.clone_contiguous()?;
+        let mut dims = lanes.shape().as_slice().to_vec();
+        let (len, count) = (dims[0], lanes.shape().slice(1..).content());
+        let data = lanes.as_slice().unwrap();
+        let mut out = Vec::new();
+        let mut out_len = None;
+        for i in 0..count {
+            let r = f(&data[(i * len)..((i + 1) * len)]);
+            let n = *out_len.get_or_insert(r.len());
+            if r.len() != n {
+                return Err(Error::BadSize { expected: n, found: r.len() });
+            }
+            out.extend(r);
+        }
+        dims[0] = out_len.unwrap_or(len);
+        HostTensor::from_plain_vec(out, &Shape::from(dims)).moveaxis(0, axis)?.clone_contiguous()
+    }
+    /// Returns a new tensor with function applied to each pair of elements of `self` and `other`.
+    ///
+    /// Tensors are broadcasted to the common shape, see `Shape::broadcast`.
+    /// Fails with `Error::ShapeMismatch` if shapes aren't compatible.
+    pub fn zip_map<U: Prm, V: Prm, F: FnMut(T, U) -> V>(&self, other: &HostTensor<U>, mut f: F) -> Result<HostTensor<V>, Error> {
+        let shape = self.shape().broadcast(other.shape())?;
+        let (a, b) = (self.broadcast_to(&shape)?, other.broadcast_to(&shape)?);
+        let vec = a.iter().zip(b.iter()).map(|(&x, &y)| f(x, y)).collect();
+        Ok(HostTensor::from_plain_vec(vec, &shape))
+    }
+    /// Elementwise choice between `a` where `cond` is nonzero and `b` elsewhere, as NumPy `where`.
+    ///
+    /// All three tensors are broadcasted to the common shape.
+    /// Fails with `Error::ShapeMismatch` if any pair of shapes isn't compatible.
+    pub fn select(cond: &HostTensor<u8>, a: &Self, b: &Self) -> Result<Self, Error> {
+        let shape = cond.shape().broadcast(a.shape())?.broadcast(b.shape())?;
+        let (c, a, b) = (cond.broadcast_to(&shape)?, a.broadcast_to(&shape)?, b.broadcast_to(&shape)?);
+        let vec = c.iter().zip(a.iter().zip(b.iter())).map(|(&c, (&x, &y))| if c != 0 { x } else { y }).collect();
+        Ok(HostTensor::from_plain_vec(vec, &shape))
+    }
+}
+
+impl<T: Prm + Num> HostTensor<T> {
+    /// Elementwise sum of two broadcasted tensors.
+    ///
+    /// Contiguous tensors of the same shape are processed by vectorized chunks, the others as in `zip_map`.
+    pub fn add(&self, other: &Self) -> Result<Self, Error> {
+        self.chunked_op(other, |x, y| x + y)
+    }
+    /// Elementwise difference of two broadcasted tensors, see `add`.
+    pub fn sub(&self, other: &Self) -> Result<Self, Error> {
+        self.chunked_op(other, |x, y| x - y)
+    }
+    /// Elementwise product of two broadcasted tensors, see `add`.
+    pub fn mul(&self, other: &Self) -> Result<Self, Error> {
+        self.chunked_op(other, |x, y| x * y)
+    }
+    /// Elementwise quotient of two broadcasted tensors, see `add`.
+    ///
+    /// Panics on integer division by zero as the `/` operator does.
+    pub fn div(&self, other: &Self) -> Result<Self, Error> {
+        self.chunked_op(other, |x, y| x / y)
+    }
+    /// Elementwise remainder of truncated division of two broadcasted tensors, see `add`.
+    ///
+    /// It matches Rust `%` operator for both integer and floating-point types: the result has the sign of `self` (dividend),
+    /// e.g. `-7 % 3 == -1`, unlike Python where it has the sign of the divisor. So `x == (x / y) * y + x % y` for integers.
+    /// Panics on integer division by zero, floating-point remainder of division by zero is NaN.
+    pub fn rem(&self, other: &Self) -> Result<Self, Error> {
+        self.chunked_op(other, |x, y| x % y)
+    }
+    /// The `n`-th discrete difference along the `axis` as NumPy `diff`, the axis becomes shorter by `n`.
+    ///
+    /// The first difference is `x[i + 1] - x[i]`, higher ones are computed by taking it repeatedly.
+    /// Fails with `Error::BadAxes` if `axis` isn't less than the number of dimensions
+    /// and with `Error::BadArgument` if `n` is greater than the length of the axis.
+    pub fn diff(&self, n: usize, axis: usize) -> Result<Self, Error> {
+        check_diff(self.shape(), n, axis)?;
+        let mut x = self.slice(&[])?;
+        for _ in 0..n {
+            let (hi, lo) = diff_views(&x, axis)?;
+            x = hi.sub(&lo)?;
+        }
+        Ok(x)
+    }
+    /// Linear interpolation `self + weight * (other - self)` of two broadcasted tensors, e.g. for moving averages of weights.
+    ///
+    /// It is computed in one pass without temporary tensors, see `add`.
+    /// The expression is evaluated as written, so for unsigned integers `other - self` overflows where `other` is less.
+    pub fn lerp(&self, other: &Self, weight: T) -> Result<Self, Error> {
+        self.chunked_op(other, |a, b| a + weight * (b - a))
+    }
+    /// Linear interpolation with a tensor of weights, all three tensors are broadcasted to the common shape, see `lerp`.
+    ///
+    /// Fails with `Error::ShapeMismatch` if any pair of shapes isn't compatible.
+    pub fn lerp_by(&self, other: &Self, weight: &Self) -> Result<Self, Error> {
+        let shape = self.shape().broadcast(other.shape())?.broadcast(weight.shape())?;
+        let (a, b, w) = (self.broadcast_to(&shape)?, other.broadcast_to(&shape)?, weight.broadcast_to(&shape)?);
+        let vec = a.iter().zip(b.iter().zip(w.iter())).map(|(&x, (&y, &t))| x + t * (y - x)).collect();
+        Ok(Self::from_plain_vec(vec, &shape))
+    }
+    fn chunked_op<F: Fn(T, T) -> T + Sync>(&self, other: &Self, f: F) -> Result<Self, Error> {
+        match (self.as_slice(), other.as_slice()) {
+            (Some(a), Some(b)) if self.shape() == other.shape() => Ok(Self::from_plain_vec(chunked(a, b, f), self.shape())),
+            _ => self.zip_map(other, f),
+        }
+    }
+}
+
+impl<T: Prm + Num> HostTensor<T> {
+    /// Elementwise sum of two broadcasted integer tensors that fails with `Error::Overflow` if any element overflows.
+    ///
+    /// Unlike `add`, which panics on overflow in debug builds and silently wraps around in release builds.
+    pub fn checked_add(&self, other: &Self) -> Result<Self, Error> where T: CheckedAdd {
+        self.checked_op(other, |x, y| x.checked_add(&y))
+    }
+    /// Elementwise difference of two broadcasted integer tensors, see `checked_add`.
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, Error> where T: CheckedSub {
+        self.checked_op(other, |x, y| x.checked_sub(&y))
+    }
+    /// Elementwise product of two broadcasted integer tensors, see `checked_add`.
+    pub fn checked_mul(&self, other: &Self) -> Result<Self, Error> where T: CheckedMul {
+        self.checked_op(other, |x, y| x.checked_mul(&y))
+    }
+    /// Elementwise sum of two broadcasted integer tensors clamped to the bounds of the type, e.g. `250u8 + 10 == 255`.
+    pub fn saturating_add(&self, other: &Self) -> Result<Self, Error> where T: SaturatingAdd {
+        self.chunked_op(other, |x, y| x.saturating_add(&y))
+    }
+    /// Elementwise difference of two broadcasted integer tensors clamped to the bounds of the type, see `saturating_add`.
+    pub fn saturating_sub(&self, other: &Self) -> Result<Self, Error> where T: SaturatingSub {
+        self.chunked_op(other, |x, y| x.saturating_sub(&y))
+    }
+    /// Elementwise product of two broadcasted integer tensors clamped to the bounds of the type, see `saturating_add`.
+    pub fn saturating_mul(&self, other: &Self) -> Result<Self, Error> where T: SaturatingMul {
+        self.chunked_op(other, |x, y| x.saturating_mul(&y))
+    }
+    fn checked_op<F: Fn(T, T) -> Option<T>>(&self, other: &Self, f: F) -> Result<Self, Error> {
+        let mut overflow = false;
+        let out = self.zip_map(other, |x, y| f(x, y).unwrap_or_else(|| {
+            overflow = true;
+            Zero::zero()
+        }))?;
+        if overflow {
+            return Err(Error::Overflow);
+        }
+        Ok(out)
+    }
+}
+
+impl<T: Prm + PartialOrd> HostTensor<T> {
+    /// Elementwise maximum of two broadcasted tensors.
+    ///
+    /// Result is unspecified if any of elements is NaN.
+    pub fn maximum(&self, other: &Self) -> Result<Self, Error> {
+        self.zip_map(other, |x, y| if x < y { y } else { x })
+    }
+    /// Elementwise minimum of two broadcasted tensors.
+    ///
+    /// Result is unspecified if any of elements is NaN.
+    pub fn minimum(&self, other: &Self) -> Result<Self, Error> {
+        self.zip_map(other, |x, y| if y < x { y } else { x })
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Sign of each element: `1` for positive and `-1` for negative, zeros and NaNs are kept as is.
+    pub fn sign(&self) -> Self {
+        self.map(|x| if x.is_nan() || x.is_zero() { x } else { x.signum() })
+    }
+    /// Largest integer not greater than each element.
+    pub fn floor(&self) -> Self {
+        self.map(T::floor)
+    }
+    /// Smallest integer not less than each element.
+    pub fn ceil(&self) -> Self {
+        self.map(T::ceil)
+    }
+    /// Nearest integer to each element, halfway cases are rounded away from zero.
+    pub fn round(&self) -> Self {
+        self.map(T::round)
+    }
+    /// Integer part of each element.
+    pub fn trunc(&self) -> Self {
+        self.map(T::trunc)
+    }
+    /// Fractional part of each element, `x - x.trunc()`, so it has the same sign as `x`.
+    pub fn fract(&self) -> Self {
+        self.map(T::fract)
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Mask of NaN elements: `1` where element is NaN and `0` elsewhere.
+    pub fn isnan(&self) -> HostTensor<u8> {
+        self.map(|x| x.is_nan() as u8)
+    }
+    /// Mask of infinite elements of any sign.
+    pub fn isinf(&self) -> HostTensor<u8> {
+        self.map(|x| x.is_infinite() as u8)
+    }
+    /// Mask of elements that are neither infinite nor NaN.
+    pub fn isfinite(&self) -> HostTensor<u8> {
+        self.map(|x| x.is_finite() as u8)
+    }
+    /// Whether any element is NaN, stops on the first one.
+    pub fn any_nan(&self) -> bool {
+        self.iter().any(|x| x.is_nan())
+    }
+    /// Replaces NaNs with `nan`, positive infinities with `posinf` and negative ones with `neginf`, other elements are kept.
+    pub fn nan_to_num(&self, nan: T, posinf: T, neginf: T) -> Self {
+        self.map(|x| if x.is_nan() {
+            nan
+        } else if x.is_infinite() {
+            if x.is_sign_positive() { posinf } else { neginf }
+        } else {
+            x
+        })
+    }
+    /// Replaces NaNs with zeros and infinities with the largest finite numbers of the same sign, see `nan_to_num`.
+    pub fn nan_to_num_default(&self) -> Self {
+        self.nan_to_num(Zero::zero(), T::max_value(), T::min_value())
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Sine of each element.
+    pub fn sin(&self) -> Self {
+        self.map(T::sin)
+    }
+    /// Cosine of each element.
+    pub fn cos(&self) -> Self {
+        self.map(T::cos)
+    }
+    /// Tangent of each element.
+    pub fn tan(&self) -> Self {
+        self.map(T::tan)
+    }
+    /// Arcsine of each element.
+    pub fn asin(&self) -> Self {
+        self.map(T::asin)
+    }
+    /// Arccosine of each element.
+    pub fn acos(&self) -> Self {
+        self.map(T::acos)
+    }
+    /// Arctangent of each element.
+    pub fn atan(&self) -> Self {
+        self.map(T::atan)
+    }
+    /// Hyperbolic sine of each element.
+    pub fn sinh(&self) -> Self {
+        self.map(T::sinh)
+    }
+    /// Hyperbolic cosine of each element.
+    pub fn cosh(&self) -> Self {
+        self.map(T::cosh)
+    }
+    /// Hyperbolic tangent of each element.
+    pub fn tanh(&self) -> Self {
+        self.map(T::tanh)
+    }
+    /// Four quadrant arctangent of `self` and `other` elements, see `f64::atan2`.
+    ///
+    /// Tensors are broadcasted to the common shape.
+    pub fn atan2(&self, other: &Self) -> Result<Self, Error> {
+        self.zip_map(other, T::atan2)
+    }
+    /// Elements with magnitude of `self` and sign of `other`, the sign of zeros and NaNs is copied too.
+    ///
+    /// Tensors are broadcasted to the common shape.
+    pub fn copysign(&self, other: &Self) -> Result<Self, Error> {
+        self.zip_map(other, T::copysign)
+    }
+    /// Length of hypotenuse `sqrt(x^2 + y^2)` for `self` and `other` elements, without intermediate overflow.
+    ///
+    /// Tensors are broadcasted to the common shape.
+    pub fn hypot(&self, other: &Self) -> Result<Self, Error> {
+        self.zip_map(other, T::hypot)
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Reciprocal `1 / x` of each element, zeros give infinities of the same sign.
+    pub fn recip(&self) -> Self {
+        self.map(T::recip)
+    }
+    /// Reciprocal square root `1 / sqrt(x)` of each element, zeros give infinities of the same sign.
+    pub fn rsqrt(&self) -> Self {
+        self.map(|x| x.sqrt().recip())
+    }
+}
+
+impl HostTensor<f32> {
+    /// Converts elements to half precision, see `f16::from_f32`.
+    pub fn to_f16(&self) -> HostTensor<f16> {
+        self.map(f16::from_f32)
+    }
+    /// Converts elements to bfloat16, see `bf16::from_f32`.
+    pub fn to_bf16(&self) -> HostTensor<bf16> {
+        self.map(bf16::from_f32)
+    }
+}
+impl HostTensor<f16> {
+    /// Converts elements to single precision.
+    pub fn to_f32(&self) -> HostTensor<f32> {
+        self.map(f16::to_f32)
+    }
+}
+impl HostTensor<bf16> {
+    /// Converts elements to single precision.
+    pub fn to_f32(&self) -> HostTensor<f32> {
+        self.map(bf16::to_f32)
+    }
+}
@@ -0,0 +1,92 @@
+use num_traits::{NumCast, Zero};
+use crate::{
+    Prm, Float, Error,
+    Tensor, HostTensor, ReduceLayout,
+};
+
+
+/// In-place radix-2 Cooley-Tukey transform of a single sequence, its length must be a power of two.
+///
+/// The exponent sign is positive in the `inverse` transform, the result is not normalized.
+fn fft_lane<T: Float>(re: &mut [T], im: &mut [T], inverse: bool) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    if bits == 0 {
+        return;
+    }
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let pi = <T as NumCast>::from(std::f64::consts::PI).unwrap();
+    let sign = if inverse { pi } else { -pi };
+    let mut size = 2;
+    while size <= n {
+        let half = size / 2;
+        for k in 0..half {
+            // Each twiddle is computed directly to avoid accumulation of rounding errors.
+            let angle = sign * <T as NumCast>::from(2 * k).unwrap() / <T as NumCast>::from(size).unwrap();
+            let (wr, wi) = (angle.cos(), angle.sin());
+            for start in (0..n).step_by(size) {
+                let (a, b) = (start + k, start + k + half);
+                let (br, bi) = (re[b] * wr - im[b] * wi, re[b] * wi + im[b] * wr);
+                re[b] = re[a] - br;
+                im[b] = im[a] - bi;
+                re[a] = re[a] + br;
+                im[a] = im[a] + bi;
+            }
+        }
+        size *= 2;
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Discrete Fourier transform of real sequences along the `axis`, returns real and imaginary parts of the spectrum.
+    ///
+    /// Length of the axis must be a power of two, otherwise `Error::BadArgument` is returned.
+    /// Circular convolution of `a` and `b` along the axis is the inverse transform of the product of their spectra.
+    pub fn fft(&self, axis: usize) -> Result<(Self, Self), Error> {
+        self.transform(&self.zeros_like()?, axis, false)
+    }
+    /// Forward transform of complex sequences with real part `self` and imaginary part `imag`.
+    ///
+    /// Fails with `Error::ShapeMismatch` if parts have different shapes.
+    pub fn fft_complex(&self, imag: &Self, axis: usize) -> Result<(Self, Self), Error> {
+        self.transform(imag, axis, false)
+    }
+    /// Inverse of `fft_complex`, the result is divided by the length of the axis, so that `ifft` of `fft` gives the source back.
+    pub fn ifft(&self, imag: &Self, axis: usize) -> Result<(Self, Self), Error> {
+        let (re, im) = self.transform(imag, axis, true)?;
+        let len = <T as NumCast>::from(self.shape()[axis]).unwrap();
+        Ok((re.map(|x| x / len), im.map(|x| x / len)))
+    }
+
+    fn transform(&self, imag: &Self, axis: usize, inverse: bool) -> Result<(Self, Self), Error> {
+        if self.shape() != imag.shape() {
+            return Err(Error::ShapeMismatch(self.shape().clone(), imag.shape().clone()));
+        }
+        let len = self.shape()[axis];
+        if !len.is_power_of_two() {
+            return Err(Error::BadArgument(format!("FFT length {} is not a power of two", len)));
+        }
+        let layout = ReduceLayout::new(self.shape(), axis, false);
+        let (src_re, src_im) = (self.iter().cloned().collect::<Vec<_>>(), imag.iter().cloned().collect::<Vec<_>>());
+        let (mut re, mut im) = (vec![Zero::zero(); src_re.len()], vec![Zero::zero(); src_im.len()]);
+        let (mut lane_re, mut lane_im) = (vec![Zero::zero(); len], vec![Zero::zero(); len]);
+        for i in 0..layout.shape.content() {
+            for (k, s) in layout.source_indices(i).enumerate() {
+                lane_re[k] = src_re[s];
+                lane_im[k] = src_im[s];
+            }
+            fft_lane(&mut lane_re, &mut lane_im, inverse);
+            for (k, s) in layout.source_indices(i).enumerate() {
+                re[s] = lane_re[k];
+                im[s] = lane_im[k];
+            }
+        }
+        Ok((Self::from_plain_vec(re, self.shape()), Self::from_plain_vec(im, self.shape())))
+    }
+}
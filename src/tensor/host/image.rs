@@ -0,0 +1,38 @@
+use crate::{Shape, Tensor, HostTensor, Error, check_len};
+
+
+impl HostTensor<u8> {
+    /// Create image tensor from interleaved bytes of `h` rows of `w` pixels with `c` channels each, e.g. decoded RGB data.
+    ///
+    /// Channels of a pixel are adjacent in memory, so the shape is `[c, w, h]`, see `to_chw` for planar layout.
+    /// Fails with `Error::BadSize` if the number of bytes isn't `h * w * c`.
+    pub fn from_image_bytes(bytes: &[u8], h: usize, w: usize, c: usize) -> Result<Self, Error> {
+        let shape = Shape::from([c, w, h]);
+        check_len(shape.checked_content()?, bytes.len())?;
+        Ok(Self::from_plain_vec(bytes.to_vec(), &shape))
+    }
+
+    /// Converts planar image of shape `[w, h, c]` to `f32` and normalizes each channel,
+    /// `(x / 255 - mean[k]) / std[k]` for the channel `k`, as in common preprocessing of pretrained networks.
+    ///
+    /// Fails with `Error::BadRank` if the tensor has more than three dimensions,
+    /// with `Error::BadSize` if `mean` or `std` length isn't the number of channels
+    /// and with `Error::BadArgument` if any `std` is zero.
+    pub fn normalize_image(&self, mean: &[f32], std: &[f32]) -> Result<HostTensor<f32>, Error> {
+        let shape = self.shape();
+        if shape.len() > 3 {
+            return Err(Error::BadRank { expected: 3, found: shape.len() });
+        }
+        check_len(shape[2], mean.len())?;
+        check_len(shape[2], std.len())?;
+        if std.contains(&0.0) {
+            return Err(Error::BadArgument("image std must not be zero".into()));
+        }
+        let plane = shape[0] * shape[1];
+        let vec = self.iter().enumerate().map(|(i, &x)| {
+            let k = i / plane;
+            (x as f32 / 255.0 - mean[k]) / std[k]
+        }).collect();
+        Ok(HostTensor::from_plain_vec(vec, shape))
+    }
+}
@@ -0,0 +1,97 @@
+//! In-place operations.
+//!
+//! Operations that modify the tensor instead of returning a new one are named after their out-of-place counterparts
+//! with the `_assign` suffix, as `AddAssign::add_assign` is named after `Add::add`.
+//! Operations that exist only in place (e.g. `fill`) have no suffix.
+//! The buffer is cloned first if it is shared with other tensors, so they never observe the modification.
+use crate::{
+    Prm, Num, Float, Error,
+    Tensor, HostTensor,
+};
+
+
+impl<T: Prm + Num> HostTensor<T> {
+    /// Applies `f` to each element of `self` and of `other` broadcasted to the shape of `self`.
+    fn zip_assign<F: Fn(T, T) -> T>(&mut self, other: &Self, f: F) -> Result<(), Error> {
+        let other = other.broadcast_to(self.shape())?;
+        for (x, &y) in self.iter_mut().zip(other.iter()) {
+            *x = f(*x, y);
+        }
+        Ok(())
+    }
+    /// Adds `other` broadcasted to the shape of `self`, see `add`.
+    ///
+    /// Unlike `accumulate` the shape of `self` is never reduced to, so `other` must be broadcastable to it.
+    /// Fails with `Error::ShapeMismatch` otherwise, the same applies to other binary in-place operations.
+    pub fn add_assign(&mut self, other: &Self) -> Result<(), Error> {
+        self.zip_assign(other, |x, y| x + y)
+    }
+    /// Subtracts `other` broadcasted to the shape of `self`, see `add_assign`.
+    pub fn sub_assign(&mut self, other: &Self) -> Result<(), Error> {
+        self.zip_assign(other, |x, y| x - y)
+    }
+    /// Multiplies by `other` broadcasted to the shape of `self`, see `add_assign`.
+    pub fn mul_assign(&mut self, other: &Self) -> Result<(), Error> {
+        self.zip_assign(other, |x, y| x * y)
+    }
+    /// Divides by `other` broadcasted to the shape of `self`, see `add_assign`.
+    ///
+    /// Panics on integer division by zero as the `/` operator does.
+    pub fn div_assign(&mut self, other: &Self) -> Result<(), Error> {
+        self.zip_assign(other, |x, y| x / y)
+    }
+}
+
+impl<T: Prm + PartialOrd> HostTensor<T> {
+    /// Elementwise clamping to `[min, max]`.
+    ///
+    /// NaN elements are kept as is, and the result is unspecified if `min > max`.
+    pub fn clamp(&self, min: T, max: T) -> Self {
+        self.map(|x| if x < min { min } else if x > max { max } else { x })
+    }
+    /// Elementwise clamping to `[min, max]` along with the mask which is `1` where the element was clipped, see `clamp`.
+    ///
+    /// Elements that are already equal to a bound aren't clipped, so they are `0` in the mask as NaN elements.
+    /// The mask shows saturation, e.g. the fraction of clipped gradients is its mean.
+    pub fn clamp_with_mask(&self, min: T, max: T) -> (Self, HostTensor<u8>) {
+        let mask = self.map(|x| (x < min || x > max) as u8);
+        (self.clamp(min, max), mask)
+    }
+    /// Clamps elements in place, see `clamp`.
+    pub fn clamp_assign(&mut self, min: T, max: T) {
+        for x in self.iter_mut() {
+            if *x < min {
+                *x = min;
+            } else if *x > max {
+                *x = max;
+            }
+        }
+    }
+    /// Elementwise rectified linear unit, `max(x, 0)`.
+    ///
+    /// NaN elements are kept as is.
+    pub fn relu(&self) -> Self {
+        self.map(|x| if x < T::zero() { T::zero() } else { x })
+    }
+    /// Applies rectified linear unit in place, see `relu`.
+    pub fn relu_assign(&mut self) {
+        for x in self.iter_mut() {
+            if *x < T::zero() {
+                *x = T::zero();
+            }
+        }
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Elementwise exponent.
+    pub fn exp(&self) -> Self {
+        self.map(|x| x.exp())
+    }
+    /// Computes exponent in place, see `exp`.
+    pub fn exp_assign(&mut self) {
+        for x in self.iter_mut() {
+            *x = x.exp();
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use num_traits::{NumCast, One};
+use crate::{
+    Prm, Float, Error,
+    Tensor, HostTensor, InterpMode, InterpLayout, nearest_source, bilinear_source,
+};
+
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Resizes images to `out_h` rows and `out_w` columns.
+    ///
+    /// Tensor has shape `[w, h, channels, batch]`, that is the reversed `[batch, channels, h, w]` layout, because the first axis is the fastest.
+    /// Lesser ranks are allowed, e.g. a single image of shape `[w, h]`. Pixel centers are aligned,
+    /// so the source position of output pixel `x` is `(x + 0.5) * w / out_w - 0.5` (`align_corners = false` in PyTorch).
+    /// Fails with `Error::BadRank` if the tensor has more than four dimensions.
+    pub fn interpolate2d(&self, out_h: usize, out_w: usize, mode: InterpMode) -> Result<Self, Error> {
+        let layout = InterpLayout::new(self.shape(), out_h, out_w)?;
+        let InterpLayout { w, h, out_w, out_h, .. } = layout;
+        let data = self.iter().cloned().collect::<Vec<_>>();
+        let one: T = One::one();
+        let mut vec = Vec::with_capacity(layout.shape.content());
+        for p in 0..layout.planes {
+            let plane = &data[(p * w * h)..((p + 1) * w * h)];
+            for y in 0..out_h {
+                for x in 0..out_w {
+                    vec.push(match mode {
+                        InterpMode::Nearest => plane[nearest_source(x, w, out_w) + w * nearest_source(y, h, out_h)],
+                        InterpMode::Bilinear => {
+                            let (x0, x1, lx) = bilinear_source(x, w, out_w);
+                            let (y0, y1, ly) = bilinear_source(y, h, out_h);
+                            let (lx, ly) = (<T as NumCast>::from(lx).unwrap(), <T as NumCast>::from(ly).unwrap());
+                            let row = |y: usize| plane[x0 + w * y] * (one - lx) + plane[x1 + w * y] * lx;
+                            row(y0) * (one - ly) + row(y1) * ly
+                        },
+                    });
+                }
+            }
+        }
+        Ok(Self::from_plain_vec(vec, &layout.shape))
+    }
+}
@@ -0,0 +1,26 @@
+use std::io::{Read, Write};
+use crate::{
+    LeBytes, Error,
+    Shape, HostTensor, read_chunks, write_elements,
+};
+
+
+impl<T: LeBytes> HostTensor<T> {
+    /// Reads tensor of the `shape` from raw little-endian elements in flattened order.
+    ///
+    /// Exactly the amount of bytes needed is read, so the reader could contain other data after the tensor.
+    /// Fails with `Error::BadSize` (in bytes) if the data ends earlier and with `Error::Io` if reading fails.
+    pub fn load_from_reader<R: Read>(reader: &mut R, shape: &Shape) -> Result<Self, Error> {
+        let len = shape.checked_content()?;
+        let mut vec = Vec::with_capacity(len);
+        read_chunks(reader, len, |_, chunk: &[T]| {
+            vec.extend_from_slice(chunk);
+            Ok(())
+        })?;
+        Ok(Self::from_plain_vec(vec, shape))
+    }
+    /// Writes elements as raw little-endian values in flattened order, see `load_from_reader`.
+    pub fn store_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        write_elements(writer, self.iter().cloned())
+    }
+}
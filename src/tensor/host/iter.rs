@@ -3,15 +3,13 @@ use std::{
 };
 use crate::{
     Prm,
-    Tensor, HostTensor,
+    HostTensor, IndexIter,
 };
 
 /// Iterator over host tensor content.
 pub struct HostTensorIter<'a, T: Prm> {
     tensor: &'a HostTensor<T>,
-    position: Vec<usize>,
-    first: bool,
-    exhausted: bool,
+    indices: IndexIter,
 }
 
 /// Mutable iterator over host tensor content.
@@ -20,65 +18,23 @@ pub type HostTensorIterMut<'a, T> = slice::IterMut<'a, T>;
 impl <'a, T: Prm> HostTensorIter<'a, T> {
     /// Create iterator over specified tensor.
     pub(crate) fn new(tensor: &'a HostTensor<T>) -> Self {
-        let mut position = Vec::<usize>::new();
-        position.resize(tensor.shape().len(), 0);
         Self {
             tensor,
-            position,
-            first: true,
-            exhausted: false,
+            indices: tensor.indices(),
         }
     }
-
-    fn next_position(&mut self) -> Option<&[usize]> {
-        let shape = self.tensor.shape();
-        if self.exhausted {
-            None
-        } else if self.first {
-            if shape.content() > 0 {
-                self.first = false;
-                Some(self.position.as_slice())
-            } else {
-                self.exhausted = true;
-                None
-            }
-        } else {
-            let mut carry = true;
-            for i in 0..shape.len() {
-                if carry {
-                    carry = false;
-                    let mut pos = self.position[i] + 1;
-                    if pos >= shape[i] {
-                        pos = 0;
-                        carry = true;
-                    }
-                    self.position[i] = pos;
-                }
-            }
-            if carry {
-                self.exhausted = true;
-                None
-            } else {
-                Some(self.position.as_slice())
-            }
-        }
-    }
-
-    fn next_index(&mut self) -> Option<usize> {
-        let shape = self.tensor.shape().clone();
-        self.next_position().map(|slice| {
-            slice.iter().zip(shape.iter())
-            .fold((1, 0), |(stride, pos), (x, len)| {
-                (stride*len, pos + stride*x)
-            }).1
-        })
-    }
 }
 
 impl<'a, T: Prm> Iterator for HostTensorIter<'a, T> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
-        self.next_index()
-        .map(|pos| &self.tensor.buffer().as_slice()[pos])
+        let tensor = self.tensor;
+        self.indices.next()
+        .map(|pos| &tensor.buffer().as_slice()[pos])
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
     }
 }
+
+impl<'a, T: Prm> ExactSizeIterator for HostTensorIter<'a, T> {}
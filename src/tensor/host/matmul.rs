@@ -0,0 +1,227 @@
+use std::{any::Any, cmp::min, sync::{RwLock, atomic::{AtomicUsize, Ordering}}};
+use num_traits::{Zero, One};
+use crate::{
+    Prm, Num, Float, Error, ResultContext, f16,
+    Tensor, HostTensor, MatmulLayout, CdistLayout, KronLayout, check_cdist_p, for_each_part,
+};
+
+
+/// Default size of square blocks the matrices are split into by `matmul`.
+///
+/// Blocks of three `f64` matrices of this size fit in a typical 128 KiB L2 cache.
+pub const MATMUL_BLOCK: usize = 64;
+
+/// Matrix product routine of an external library used by host matrix products, e.g. a wrapper of `cblas_sgemm`.
+///
+/// It's called as `hook(m, k, n, a, b, c)` with plain matrices `a` of `m` rows and `k` columns, `b` of `k` rows and `n` columns
+/// and `c` of `m` rows and `n` columns, all of them row-major, as tensors of shapes `[k, m]`, `[n, k]` and `[n, m]` are stored.
+/// The hook overwrites `c` with the product, as BLAS `gemm` without transposition, with `alpha = 1` and `beta = 0`, does.
+pub type GemmHook<T> = fn(m: usize, k: usize, n: usize, a: &[T], b: &[T], c: &mut [T]);
+
+static SGEMM: RwLock<Option<GemmHook<f32>>> = RwLock::new(None);
+static DGEMM: RwLock<Option<GemmHook<f64>>> = RwLock::new(None);
+static GEMM_THRESHOLD: AtomicUsize = AtomicUsize::new(1 << 18);
+
+/// Sets the routine computing single-precision `matmul`, `matmul_flags` and `gemm` of host tensors, `None` removes it.
+///
+/// The crate doesn't depend on a BLAS library, so it is plugged in by the hook, e.g. `cblas_sgemm` or `matrixmultiply::sgemm`.
+/// The hook is used for products of at least `gemm_threshold()` multiplications, smaller ones and `matmul_blocked` are computed
+/// by the pure-Rust blocked loop. Results of the hook may differ from the pure-Rust ones by rounding.
+pub fn set_sgemm_hook(hook: Option<GemmHook<f32>>) {
+    *SGEMM.write().unwrap() = hook;
+}
+/// Sets the routine computing double-precision host matrix products, see `set_sgemm_hook`.
+pub fn set_dgemm_hook(hook: Option<GemmHook<f64>>) {
+    *DGEMM.write().unwrap() = hook;
+}
+/// Hook registered for the element type, only `f32` and `f64` have them.
+pub(crate) fn gemm_hook<T: 'static>() -> Option<GemmHook<T>> {
+    let (sgemm, dgemm) = (*SGEMM.read().unwrap(), *DGEMM.read().unwrap());
+    let hooks: [&dyn Any; 2] = [&sgemm, &dgemm];
+    hooks.iter().find_map(|hook| hook.downcast_ref::<Option<GemmHook<T>>>()).cloned().flatten()
+}
+
+/// Minimal number of multiplications `m * k * n` of a host matrix product to compute it by the hook, see `set_sgemm_hook`.
+///
+/// The default is `262144`, i.e. square matrices of size `64`, smaller products don't gain from an optimized library
+/// because of the call overhead.
+pub fn gemm_threshold() -> usize {
+    GEMM_THRESHOLD.load(Ordering::Relaxed)
+}
+/// Sets the threshold returned by `gemm_threshold`.
+pub fn set_gemm_threshold(threshold: usize) {
+    GEMM_THRESHOLD.store(threshold, Ordering::Relaxed)
+}
+
+/// Computes matrix product of plain matrices accumulating sums in type `A`.
+///
+/// Matrices are processed by square blocks of size `block` to reuse the data while it is in cache,
+/// and the innermost loop goes along rows of `b` and the result, so it accesses memory sequentially.
+/// Rows of the result are split between threads, see `for_each_part`.
+fn matmul_acc<T: Prm, A: Num + Copy + Send>(
+    a: &[T], b: &[T], layout: &MatmulLayout, block: usize,
+    up: impl Fn(T) -> A + Sync, down: impl Fn(A) -> T,
+) -> Vec<T> {
+    let MatmulLayout { m, k, n } = *layout;
+    let mut c = vec![A::zero(); m * n];
+    for_each_part(&mut c, n, |start, c| {
+        let (rows, offset) = (c.len() / usize::max(n, 1), start / usize::max(n, 1));
+        for r0 in (0..rows).step_by(block) {
+            for l0 in (0..k).step_by(block) {
+                for q0 in (0..n).step_by(block) {
+                    let q1 = min(q0 + block, n);
+                    for r in r0..min(r0 + block, rows) {
+                        for l in l0..min(l0 + block, k) {
+                            let x = up(a[l + k * (r + offset)]);
+                            for (s, &y) in c[(q0 + n * r)..(q1 + n * r)].iter_mut().zip(&b[(q0 + n * l)..(q1 + n * l)]) {
+                                *s = *s + x * up(y);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    });
+    c.into_iter().map(down).collect()
+}
+
+fn check_block(block: usize) -> Result<(), Error> {
+    if block == 0 {
+        Err(Error::BadArgument("matmul block size must not be zero".into()))
+    } else {
+        Ok(())
+    }
+}
+
+impl<T: Prm + Num + 'static> HostTensor<T> {
+    /// Matrix product of `self` and `other`.
+    ///
+    /// Matrix of `m` rows and `k` columns has shape `[k, m]` as in `from_2d`, so that the product of `[k, m]` and `[n, k]` has shape `[n, m]`.
+    /// Fails with `Error::BadRank` if operands aren't matrices and with `Error::ShapeMismatch` if their inner dimensions are different.
+    ///
+    /// Large products of `f32` and `f64` matrices could be computed by an external library, see `set_sgemm_hook`.
+    pub fn matmul(&self, other: &Self) -> Result<Self, Error> {
+        self.matmul_hooked(other, gemm_hook(), gemm_threshold()).context("matmul")
+    }
+    /// Matrix product computed by square blocks of specified size, see `matmul`.
+    ///
+    /// The best size depends on the cache of the processor and on the element type, `MATMUL_BLOCK` by default.
+    /// The hook set by `set_sgemm_hook` isn't used, so it is the pure-Rust reference of the hook.
+    /// Fails with `Error::BadArgument` if `block` is zero.
+    pub fn matmul_blocked(&self, other: &Self, block: usize) -> Result<Self, Error> {
+        self.matmul_impl(other, block).context("matmul_blocked")
+    }
+    /// Matrix product of `self` and `other` transposed according to the flags, e.g. `grad @ W^T`, see `matmul`.
+    ///
+    /// Host operands are gathered into contiguous matrices anyway, so it is the same as multiplying transposed views.
+    /// Fails with `Error::ShapeMismatch` if inner dimensions of the transposed operands are different.
+    pub fn matmul_flags(&self, other: &Self, trans_a: bool, trans_b: bool) -> Result<Self, Error> {
+        let transpose = |t: &Self, flag: bool| if flag { t.transpose() } else { t.clone() };
+        transpose(self, trans_a).matmul_hooked(&transpose(other, trans_b), gemm_hook(), gemm_threshold()).context("matmul_flags")
+    }
+    /// Matrix product computed by the `hook` if it has at least `threshold` multiplications, otherwise by blocks.
+    pub(crate) fn matmul_hooked(&self, other: &Self, hook: Option<GemmHook<T>>, threshold: usize) -> Result<Self, Error> {
+        let layout = MatmulLayout::new(self.shape(), other.shape())?;
+        let MatmulLayout { m, k, n } = layout;
+        match hook {
+            Some(hook) if m.saturating_mul(k).saturating_mul(n) >= threshold && m * n > 0 => {
+                let (a, b) = (self.iter().cloned().collect::<Vec<_>>(), other.iter().cloned().collect::<Vec<_>>());
+                let mut c = vec![Zero::zero(); m * n];
+                hook(m, k, n, &a, &b, &mut c);
+                Ok(Self::from_plain_vec(c, &layout.shape()))
+            }
+            _ => self.matmul_impl(other, MATMUL_BLOCK),
+        }
+    }
+    /// General matrix product as in BLAS, `out = alpha * (self @ other) + beta * out`, see `matmul`.
+    ///
+    /// It accumulates the product into existing matrix, e.g. a residual, without a separate addition.
+    /// If `beta` is zero, the contents of `out` are ignored, so they may be uninitialized or NaN.
+    /// Fails with `Error::ShapeMismatch` if `out` doesn't have the shape of the product.
+    pub fn gemm(&self, other: &Self, out: &mut Self, alpha: T, beta: T) -> Result<(), Error> {
+        self.gemm_impl(other, out, alpha, beta).context("gemm")
+    }
+    fn gemm_impl(&self, other: &Self, out: &mut Self, alpha: T, beta: T) -> Result<(), Error> {
+        let product = self.matmul_hooked(other, gemm_hook(), gemm_threshold())?;
+        if out.shape() != product.shape() {
+            return Err(Error::ShapeMismatch(out.shape().clone(), product.shape().clone()));
+        }
+        let ignore_out = beta == Zero::zero();
+        for (c, &p) in out.iter_mut().zip(product.iter()) {
+            *c = if ignore_out { alpha * p } else { alpha * p + beta * *c };
+        }
+        Ok(())
+    }
+    /// Kronecker product of matrices, a block matrix with `other` scaled by each element of `self` in place of the element.
+    ///
+    /// Product of matrices of `m1` by `n1` and `m2` by `n2` elements (shapes `[n1, m1]` and `[n2, m2]`, see `matmul`)
+    /// has `m1 * m2` rows and `n1 * n2` columns, its element at row `i1 * m2 + i2` and column `j1 * n2 + j2`
+    /// is `self[i1, j1] * other[i2, j2]` in row-column terms.
+    /// Fails with `Error::BadRank` if operands aren't matrices and with `Error::Overflow` if the result is too large.
+    pub fn kron(&self, other: &Self) -> Result<Self, Error> {
+        let layout = KronLayout::new(self.shape(), other.shape())?;
+        let (a, b) = (self.to_vec()?, other.to_vec()?);
+        let shape = layout.shape();
+        let vec = (0..shape.content()).map(|i| {
+            let (x, y) = layout.sources(i);
+            a[x] * b[y]
+        }).collect();
+        Ok(Self::from_plain_vec(vec, &shape))
+    }
+    fn matmul_impl(&self, other: &Self, block: usize) -> Result<Self, Error> {
+        check_block(block)?;
+        let layout = MatmulLayout::new(self.shape(), other.shape())?;
+        let (a, b) = (self.iter().cloned().collect::<Vec<_>>(), other.iter().cloned().collect::<Vec<_>>());
+        Ok(Self::from_plain_vec(matmul_acc(&a, &b, &layout, block, |x| x, |x| x), &layout.shape()))
+    }
+}
+
+impl HostTensor<f16> {
+    /// Matrix product of half-precision matrices with sums accumulated in single precision, see `matmul`.
+    ///
+    /// Only the result is rounded to half precision, so its error is about one half-precision ulp
+    /// instead of growing with the inner dimension, and intermediate sums don't overflow `f16` range.
+    pub fn matmul_mixed(&self, other: &Self) -> Result<Self, Error> {
+        let layout = MatmulLayout::new(self.shape(), other.shape()).context("matmul_mixed")?;
+        let (a, b) = (self.iter().cloned().collect::<Vec<_>>(), other.iter().cloned().collect::<Vec<_>>());
+        Ok(Self::from_plain_vec(matmul_acc(&a, &b, &layout, MATMUL_BLOCK, f32::from, f16::from_f32), &layout.shape()))
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Pairwise Minkowski distances of order `p` between rows of `self` and `other`.
+    ///
+    /// Operands are matrices of `n` and `m` rows with `d` columns, i.e. of shapes `[d, n]` and `[d, m]`,
+    /// and the result has shape `[m, n]`, so that its row `i` contains distances from the row `i` of `self`.
+    /// Euclidean (`p = 2`), Manhattan (`p = 1`) and Chebyshev (infinite `p`) distances are computed without powers.
+    /// Fails with `Error::BadRank` if operands aren't matrices, with `Error::ShapeMismatch` if their numbers of columns differ
+    /// and with `Error::BadArgument` if `p` isn't positive.
+    pub fn cdist(&self, other: &Self, p: T) -> Result<Self, Error> {
+        let layout = CdistLayout::new(self.shape(), other.shape())?;
+        check_cdist_p(p)?;
+        let CdistLayout { n, d, m } = layout;
+        let (a, b) = (self.iter().cloned().collect::<Vec<_>>(), other.iter().cloned().collect::<Vec<_>>());
+        let (zero, one): (T, T) = (Zero::zero(), One::one());
+        let two = one + one;
+        let dist = |x: &[T], y: &[T]| {
+            let diffs = x.iter().zip(y).map(|(&x, &y)| (x - y).abs());
+            if p == two {
+                diffs.fold(zero, |s, x| s + x * x).sqrt()
+            } else if p == one {
+                diffs.fold(zero, |s, x| s + x)
+            } else if p.is_infinite() {
+                diffs.fold(zero, T::max)
+            } else {
+                diffs.fold(zero, |s, x| s + x.powf(p)).powf(p.recip())
+            }
+        };
+        let mut c = vec![zero; n * m];
+        for_each_part(&mut c, usize::max(m, 1), |start, c| {
+            for (i, y) in (start..).zip(c.iter_mut()) {
+                let (j, r) = (i % m, i / m);
+                *y = dist(&a[(d * r)..(d * (r + 1))], &b[(d * j)..(d * (j + 1))]);
+            }
+        });
+        Ok(Self::from_plain_vec(c, &layout.shape()))
+    }
+}
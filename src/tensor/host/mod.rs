@@ -1,8 +1,53 @@
 mod iter;
 pub use iter::*;
 
+mod parallel;
+pub use parallel::{parallel_threshold, set_parallel_threshold};
+pub(crate) use parallel::for_each_part;
+
 mod tensor;
 pub use tensor::*;
 
+mod sync;
+pub use sync::*;
+
+mod view;
+pub use view::*;
+
+mod dynamic;
+pub use dynamic::*;
+
+mod elementwise;
+
+mod inplace;
+
+mod nn;
+
+mod select;
+
+mod reduce;
+
+mod matmul;
+pub use matmul::{MATMUL_BLOCK, GemmHook, set_sgemm_hook, set_dgemm_hook, gemm_threshold, set_gemm_threshold};
+
+#[cfg(feature = "std")]
+mod io;
+
+mod interp;
+
+mod image;
+
+mod fft;
+
+mod complex;
+
+mod optim;
+
+mod sparse;
+pub use sparse::*;
+
+mod ring;
+pub use ring::*;
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,236 @@
+use num_traits::{NumCast, Zero, One};
+use crate::{
+    Prm, Num, Float, Error, Rng, ReduceOp,
+    Tensor, HostTensor, quant_params, quantize_int8, check_grad, check_bias, dropout_mask, check_dropout, clip_scale, check_layer_norm, embedding_shape, check_embedding_indices, in_triangle, check_triangle,
+    Conv2dLayout,
+};
+
+
+impl<T: Prm + Num> HostTensor<T> {
+    /// Adds `bias` vector to each slice of the tensor along the first axis, e.g. to the output of a dense layer.
+    ///
+    /// Features are placed along the first axis, so the output of shape `[features, batch]` takes bias of shape `[features]`.
+    /// It's the same as broadcasted addition but adds the bias to contiguous chunks directly.
+    /// Fails with `Error::BadRank` if `bias` isn't a vector and with `Error::ShapeMismatch` if its size differs from the first axis.
+    pub fn add_bias(&self, bias: &Self) -> Result<Self, Error> {
+        let len = check_bias(self.shape(), bias.shape())?;
+        let bias = bias.iter().cloned().collect::<Vec<_>>();
+        let mut vec = self.iter().cloned().collect::<Vec<_>>();
+        if len > 0 {
+            for chunk in vec.chunks_mut(len) {
+                for (x, &b) in chunk.iter_mut().zip(bias.iter()) {
+                    *x = *x + b;
+                }
+            }
+        }
+        Ok(Self::from_plain_vec(vec, self.shape()))
+    }
+}
+
+impl<T: Prm> HostTensor<T> {
+    /// Upper triangle of a matrix, elements below the `diagonal` are replaced with zeros.
+    ///
+    /// The axis `0` is the column and the axis `1` is the row, as the shape of a matrix is the reversed `[rows, cols]`.
+    /// Element is kept if `col - row >= diagonal`, so a positive diagonal is above the main one.
+    /// Trailing unit axes aren't stored in the shape, so a vector is a single-row matrix.
+    /// Fails with `Error::BadRank` if the tensor has more than two axes.
+    pub fn triu(&self, diagonal: isize) -> Result<Self, Error> {
+        self.triangle(diagonal, true)
+    }
+    /// Lower triangle of a matrix, elements above the `diagonal` are replaced with zeros, see `triu`.
+    ///
+    /// For example, `tril(0)` of ones is a causal attention mask.
+    pub fn tril(&self, diagonal: isize) -> Result<Self, Error> {
+        self.triangle(diagonal, false)
+    }
+    fn triangle(&self, diagonal: isize, upper: bool) -> Result<Self, Error> {
+        let (cols, _) = check_triangle(self.shape())?;
+        let vec = self.iter().enumerate().map(|(i, &x)| {
+            if in_triangle(i % cols, i / cols, diagonal, upper) { x } else { T::zero() }
+        }).collect();
+        Ok(Self::from_plain_vec(vec, self.shape()))
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Inverted dropout.
+    ///
+    /// When `training`, each element is zeroed with probability `p` and the rest are scaled by `1 / (1 - p)`,
+    /// so the expected value of each element is preserved. Otherwise returns the tensor as is.
+    /// Fails with `Error::BadArgument` if `p` is not in `[0, 1)`, regardless of `training`.
+    pub fn dropout(&self, p: T, training: bool, rng: &mut impl Rng) -> Result<Self, Error> {
+        check_dropout(p)?;
+        if !training {
+            return Ok(self.clone());
+        }
+        let mask = dropout_mask(self.shape().content(), p, rng)?;
+        Ok(Self::from_plain_vec(self.iter().zip(mask).map(|(&x, m)| x * m).collect(), self.shape()))
+    }
+
+    /// Scales gradients in place so that their global L2 norm doesn't exceed `max_norm`, returns the original norm.
+    ///
+    /// The norm is computed over elements of all tensors together. If it is zero or not greater than `max_norm`
+    /// gradients are left as is, and if it is NaN they are left as is too, so that the caller could detect it.
+    /// Fails with `Error::BadArgument` if `max_norm` is negative or NaN.
+    pub fn clip_grad_norm(grads: &mut [Self], max_norm: T) -> Result<T, Error> {
+        let norm = grads.iter().flat_map(|g| g.iter()).fold(Zero::zero(), |s: T, &x| s + x * x).sqrt();
+        if let Some(scale) = clip_scale(norm, max_norm)? {
+            for x in grads.iter_mut().flat_map(|g| g.iter_mut()) {
+                *x = *x * scale;
+            }
+        }
+        Ok(norm)
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Layer normalization.
+    ///
+    /// Normalizes each group of elements over the first axes given by `normalized_shape` to zero mean and unit variance,
+    /// then optionally multiplies by `gamma` and adds `beta`, both of `normalized_shape`.
+    /// Biased variance is used and `eps` is added to it for numerical stability.
+    ///
+    /// Fails with `Error::ShapeMismatch` if `normalized_shape` doesn't match the first axes of the tensor
+    /// or shapes of `gamma` and `beta` aren't equal to `normalized_shape`.
+    pub fn layer_norm(&self, normalized_shape: &[usize], gamma: Option<&Self>, beta: Option<&Self>, eps: T) -> Result<Self, Error> {
+        let n = check_layer_norm(self, normalized_shape, gamma, beta)?;
+        let data = self.iter().cloned().collect::<Vec<_>>();
+        let gamma = gamma.map(|t| t.iter().cloned().collect::<Vec<_>>());
+        let beta = beta.map(|t| t.iter().cloned().collect::<Vec<_>>());
+        let len: T = NumCast::from(n).unwrap();
+        let mut vec = Vec::with_capacity(data.len());
+        for group in data.chunks(n.max(1)) {
+            let mean = group.iter().fold(Zero::zero(), |s: T, &x| s + x) / len;
+            let var = group.iter().fold(Zero::zero(), |s: T, &x| s + (x - mean) * (x - mean)) / len;
+            let scale = (var + eps).sqrt().recip();
+            vec.extend(group.iter().enumerate().map(|(i, &x)| {
+                let mut y = (x - mean) * scale;
+                if let Some(g) = &gamma {
+                    y = y * g[i];
+                }
+                if let Some(b) = &beta {
+                    y = y + b[i];
+                }
+                y
+            }));
+        }
+        Ok(Self::from_plain_vec(vec, self.shape()))
+    }
+}
+
+impl<T: Prm> HostTensor<T> {
+    /// Embedding lookup.
+    ///
+    /// `weight` has shape `[dim, num_embeddings]`, so each embedding is placed along the first axis.
+    /// Result has shape `[dim, S...]` where `S` is the shape of `indices`.
+    /// Fails with `Error::BadRank` if `weight` isn't a matrix and `Error::BadIndex` if any index is out of bounds.
+    pub fn embedding(weight: &Self, indices: &HostTensor<usize>) -> Result<Self, Error> {
+        let (dim, num, shape) = embedding_shape(weight.shape(), indices.shape())?;
+        check_embedding_indices(indices.iter(), num)?;
+        let data = weight.iter().cloned().collect::<Vec<_>>();
+        let mut vec = Vec::with_capacity(shape.content());
+        for &i in indices.iter() {
+            vec.extend_from_slice(&data[(i * dim)..((i + 1) * dim)]);
+        }
+        Ok(Self::from_plain_vec(vec, &shape))
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Affine quantization to signed bytes, each element becomes `round(x / scale) + zero_point` saturated to `[-128, 127]`.
+    ///
+    /// Halfway cases are rounded away from zero and NaN is quantized to zero, as on the device.
+    /// `scale` is expected to be positive, see `compute_quant_params`.
+    pub fn quantize_int8(&self, scale: T, zero_point: i32) -> HostTensor<i8> {
+        self.map(|x| quantize_int8(x, scale, zero_point))
+    }
+    /// Restores values from affine quantization, each element becomes `(q - zero_point) * scale`.
+    pub fn dequantize(q: &HostTensor<i8>, scale: T, zero_point: i32) -> Self {
+        q.map(|q| T::from(q as i32 - zero_point).unwrap() * scale)
+    }
+    /// Scale and zero point that map the range of the tensor onto `[-128, 127]`.
+    ///
+    /// The range is extended to include zero, so that zero is quantized exactly,
+    /// and the scale of a tensor that contains only zeros (or nothing) is `1`.
+    /// Parameters are unspecified if the tensor contains NaN.
+    pub fn compute_quant_params(&self) -> (T, i32) {
+        let zero = Zero::zero();
+        quant_params(self.reduce(zero, ReduceOp::Min), self.reduce(zero, ReduceOp::Max))
+    }
+}
+
+impl<T: Prm + Num> HostTensor<T> {
+    /// 2D convolution (cross-correlation, as in most frameworks) of images of shape `[w, h, c_in, n]`
+    /// with weight of shape `[kw, kh, c_in, c_out]`, the result has shape `[out_w, out_h, c_out, n]`.
+    ///
+    /// Input is padded with `padding` zeros on each side and the kernel moves by `stride` pixels,
+    /// so `out_w = (w + 2 * padding - kw) / stride + 1` and similarly for the height.
+    /// Fails with `Error::BadRank` if any operand has more than four dimensions,
+    /// with `Error::ShapeMismatch` if the numbers of input channels differ
+    /// and with `Error::BadArgument` if `stride` is zero or the kernel is larger than the padded input.
+    pub fn conv2d(&self, weight: &Self, stride: usize, padding: usize) -> Result<Self, Error> {
+        let layout = Conv2dLayout::new(self.shape(), weight.shape(), stride, padding)?;
+        let (x, w) = (self.to_vec()?, weight.to_vec()?);
+        let shape = layout.output_shape();
+        let mut out = vec![Zero::zero(); shape.content()];
+        layout.for_each_tap(|o, i, k| out[o] = out[o] + x[i] * w[k]);
+        Ok(Self::from_plain_vec(out, &shape))
+    }
+    /// Gradient of `conv2d` with respect to its input, given the gradient of its output.
+    ///
+    /// It is the transposed convolution of `grad_out` with `weight`, `input` is only used for its shape.
+    /// Fails as `conv2d` for `input` and `weight`, and with `Error::ShapeMismatch` if `grad_out` doesn't have
+    /// the shape of the output, the same applies to `conv2d_backward_weight`.
+    pub fn conv2d_backward_input(grad_out: &Self, input: &Self, weight: &Self, stride: usize, padding: usize) -> Result<Self, Error> {
+        let layout = Conv2dLayout::new(input.shape(), weight.shape(), stride, padding)?;
+        check_grad(grad_out.shape(), &layout.output_shape())?;
+        let (g, w) = (grad_out.to_vec()?, weight.to_vec()?);
+        let mut grad = vec![Zero::zero(); input.numel()];
+        layout.for_each_tap(|o, i, k| grad[i] = grad[i] + g[o] * w[k]);
+        Ok(Self::from_plain_vec(grad, input.shape()))
+    }
+    /// Gradient of `conv2d` with respect to its weight, given the gradient of its output.
+    ///
+    /// It is the correlation of `input` with `grad_out` summed over the batch, `weight` is only used for its shape.
+    pub fn conv2d_backward_weight(grad_out: &Self, input: &Self, weight: &Self, stride: usize, padding: usize) -> Result<Self, Error> {
+        let layout = Conv2dLayout::new(input.shape(), weight.shape(), stride, padding)?;
+        check_grad(grad_out.shape(), &layout.output_shape())?;
+        let (g, x) = (grad_out.to_vec()?, input.to_vec()?);
+        let mut grad = vec![Zero::zero(); weight.numel()];
+        layout.for_each_tap(|o, i, k| grad[k] = grad[k] + g[o] * x[i]);
+        Ok(Self::from_plain_vec(grad, weight.shape()))
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Gradient of ReLU with respect to its `input`, given the gradient of its output.
+    ///
+    /// The derivative at zero is taken to be zero.
+    /// Fails with `Error::ShapeMismatch` if shapes of `grad_out` and `input` are different, the same applies to other activations.
+    pub fn relu_backward(grad_out: &Self, input: &Self) -> Result<Self, Error> {
+        check_grad(grad_out.shape(), input.shape())?;
+        grad_out.zip_map(input, |g, x| if x > Zero::zero() { g } else { Zero::zero() })
+    }
+    /// Gradient of the logistic sigmoid with respect to its `input`, given the gradient of its output.
+    ///
+    /// The sigmoid `s` is recomputed from the input, the derivative is `s * (1 - s)`.
+    pub fn sigmoid_backward(grad_out: &Self, input: &Self) -> Result<Self, Error> {
+        check_grad(grad_out.shape(), input.shape())?;
+        let one: T = One::one();
+        grad_out.zip_map(input, |g, x| {
+            let s = (one + (-x).exp()).recip();
+            g * s * (one - s)
+        })
+    }
+    /// Gradient of the hyperbolic tangent with respect to its `input`, given the gradient of its output.
+    ///
+    /// The derivative is `1 - tanh(x)^2`.
+    pub fn tanh_backward(grad_out: &Self, input: &Self) -> Result<Self, Error> {
+        check_grad(grad_out.shape(), input.shape())?;
+        let one: T = One::one();
+        grad_out.zip_map(input, |g, x| {
+            let t = x.tanh();
+            g * (one - t * t)
+        })
+    }
+}
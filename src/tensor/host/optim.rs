@@ -0,0 +1,30 @@
+use crate::{
+    Prm, Float, Error,
+    HostTensor, optim::{OptimTensor, check_param},
+};
+
+
+impl<T: Prm + Float> OptimTensor<T> for HostTensor<T> {
+    fn axpby(&mut self, a: T, other: &Self, b: T) -> Result<(), Error> {
+        check_param(self, other)?;
+        for (y, &x) in self.iter_mut().zip(other.iter()) {
+            *y = a * *y + b * x;
+        }
+        Ok(())
+    }
+    fn axpby_square(&mut self, a: T, other: &Self, b: T) -> Result<(), Error> {
+        check_param(self, other)?;
+        for (y, &x) in self.iter_mut().zip(other.iter()) {
+            *y = a * *y + b * x * x;
+        }
+        Ok(())
+    }
+    fn add_scaled_ratio(&mut self, num: &Self, den: &Self, alpha: T, eps: T) -> Result<(), Error> {
+        check_param(self, num)?;
+        check_param(self, den)?;
+        for ((y, &n), &d) in self.iter_mut().zip(num.iter()).zip(den.iter()) {
+            *y = *y + alpha * n / (d.sqrt() + eps);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use std::{cell::Cell, sync::atomic::{AtomicUsize, Ordering}};
+
+
+static THRESHOLD: AtomicUsize = AtomicUsize::new(1 << 16);
+
+thread_local! {
+    static THREADS: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Minimal number of elements of a host operation result to compute it with several threads.
+///
+/// The default is `65536`, an elementwise operation of that size takes tens of microseconds on a single core,
+/// which is comparable to the time of spawning the threads.
+/// Only has effect with the `parallel` feature, which uses `std::thread::scope` rather than `rayon` to avoid the dependency.
+pub fn parallel_threshold() -> usize {
+    THRESHOLD.load(Ordering::Relaxed)
+}
+/// Sets the threshold returned by `parallel_threshold`, e.g. raise it if other threads of the program are busy.
+pub fn set_parallel_threshold(threshold: usize) {
+    THRESHOLD.store(threshold, Ordering::Relaxed)
+}
+
+/// Runs `f` with host operations of the current thread split into `threads` parts,
+/// regardless of their size and the `parallel` feature.
+#[cfg(test)]
+pub(crate) fn with_threads<R>(threads: usize, f: impl FnOnce() -> R) -> R {
+    let prev = THREADS.with(|t| t.replace(Some(threads)));
+    let result = f();
+    THREADS.with(|t| t.set(prev));
+    result
+}
+
+/// Number of threads to process `len` elements with.
+fn threads_for(len: usize) -> usize {
+    if let Some(threads) = THREADS.with(Cell::get) {
+        return threads;
+    }
+    #[cfg(feature = "parallel")]
+    if len >= parallel_threshold() {
+        return std::thread::available_parallelism().map_or(1, |n| n.get());
+    }
+    let _ = len;
+    1
+}
+
+/// Splits `data` into parts consisting of whole `unit`s and calls `f` for each part along with the offset of the part.
+///
+/// With the `parallel` feature the parts are processed by scoped threads, one per core,
+/// if `data` is not shorter than `parallel_threshold()`, otherwise `f` is called once for the whole data.
+pub(crate) fn for_each_part<T: Send, F: Fn(usize, &mut [T]) + Sync>(data: &mut [T], unit: usize, f: F) {
+    split_parts(data, unit, threads_for(data.len()), f)
+}
+
+/// Splits `data` into at most `threads` parts consisting of whole `unit`s, the last one may be shorter,
+/// and processes them by scoped threads. Calls `f` once for the whole data if there is a single thread.
+pub(crate) fn split_parts<T: Send, F: Fn(usize, &mut [T]) + Sync>(data: &mut [T], unit: usize, threads: usize, f: F) {
+    if threads > 1 && unit > 0 && !data.is_empty() {
+        let units = data.len().div_ceil(unit);
+        let part = units.div_ceil(threads) * unit;
+        let f = &f;
+        std::thread::scope(|scope| {
+            for (i, chunk) in data.chunks_mut(part).enumerate() {
+                scope.spawn(move || f(i * part, chunk));
+            }
+        });
+    } else {
+        f(0, data)
+    }
+}
@@ -0,0 +1,245 @@
+use std::cmp::Ordering;
+use num_traits::{Zero, One, PrimInt};
+use crate::{
+    Prm, Num, Float, Error,
+    Shape, Tensor, HostTensor, ReduceLayout, ReduceOp, COSINE_EPS, descending_axes, check_mask, accumulate_layout, bincount_len, check_confusion, for_each_part,
+};
+
+
+impl<T: Prm + Num> HostTensor<T> {
+    /// Sum along the `axis`.
+    ///
+    /// The axis is removed from the result, or kept with size `1` if `keepdims` is set, so that the result
+    /// could be broadcasted back to the tensor shape. This applies to all the axis reductions.
+    /// Note that trailing axes of size `1` are implicit, so keeping the last axis doesn't change the shape.
+    pub fn sum_axis(&self, axis: usize, keepdims: bool) -> Self {
+        self.fold_axis(axis, keepdims, Zero::zero(), |s, x| s + x)
+    }
+    /// Product along the `axis`, see `sum_axis` for `keepdims`. Product over an empty axis is one.
+    ///
+    /// Elements are multiplied as ordinary values, so integer overflow panics in debug builds and wraps in release ones,
+    /// and floating-point products of many small (or large) factors underflow to zero (or overflow to infinity).
+    /// Summing logarithms is more robust for long chains of probabilities or geometric means.
+    pub fn prod_axis(&self, axis: usize, keepdims: bool) -> Self {
+        self.fold_axis(axis, keepdims, One::one(), |s, x| s * x)
+    }
+    /// Product of all the elements, one for an empty tensor, see `prod_axis` for overflow.
+    pub fn prod(&self) -> T {
+        self.iter().fold(One::one(), |s, &x| s * x)
+    }
+    fn fold_axis(&self, axis: usize, keepdims: bool, init: T, f: impl Fn(T, T) -> T + Sync) -> Self {
+        let data = self.iter().cloned().collect::<Vec<_>>();
+        let layout = ReduceLayout::new(self.shape(), axis, keepdims);
+        let mut vec = vec![init; layout.shape.content()];
+        for_each_part(&mut vec, 1, |start, part| {
+            for (i, y) in (start..).zip(part.iter_mut()) {
+                *y = layout.source_indices(i).fold(init, |s, k| f(s, data[k]));
+            }
+        });
+        Self::from_plain_vec(vec, &layout.shape)
+    }
+    /// Sum along all the `axes` at once, see `sum_axis` for `keepdims`.
+    ///
+    /// Axes beyond the number of dimensions are the implicit axes of size `1`.
+    /// Fails with `Error::BadAxes` if axes are repeated.
+    pub fn sum_axes(&self, axes: &[usize], keepdims: bool) -> Result<Self, Error> {
+        Ok(descending_axes(axes)?.into_iter().fold(self.clone(), |s, axis| s.sum_axis(axis, keepdims)))
+    }
+
+    /// Adds `other` to `self` in place, e.g. to accumulate gradients.
+    ///
+    /// Shapes must be broadcast-compatible. Axes where `self` has size `1` and `other` doesn't are summed over,
+    /// so that `self` keeps its shape, e.g. a tensor of shape `[3]` accumulates all the columns of `other` of shape `[3, b]`.
+    /// Axes where `other` has size `1` are broadcasted.
+    /// Fails with `Error::ShapeMismatch` if shapes aren't compatible.
+    pub fn accumulate(&mut self, other: &Self) -> Result<(), Error> {
+        let (axes, kept) = accumulate_layout(self.shape(), other.shape())?;
+        let sum = axes.iter().fold(other.clone(), |s, &axis| s.sum_axis(axis, false)).reshape(&kept)?;
+        let sum = sum.broadcast_to(self.shape())?;
+        for (x, &y) in self.iter_mut().zip(sum.iter()) {
+            *x = *x + y;
+        }
+        Ok(())
+    }
+
+    /// Sum along the `axis` over positions where `mask` is nonzero, see `sum_axis` for `keepdims`.
+    ///
+    /// Mask is broadcasted to the shape of the tensor.
+    /// Fails with `Error::ShapeMismatch` if it cannot be broadcasted.
+    pub fn masked_sum(&self, mask: &HostTensor<u8>, axis: usize, keepdims: bool) -> Result<Self, Error> {
+        check_mask(self.shape(), mask.shape())?;
+        let mask = mask.broadcast_to(self.shape())?;
+        let data = self.iter().zip(mask.iter()).map(|(&x, &m)| if m != 0 { x } else { Zero::zero() }).collect::<Vec<_>>();
+        Ok(Self::from_plain_vec(data, self.shape()).sum_axis(axis, keepdims))
+    }
+}
+
+impl<T: Prm + Num + PartialOrd> HostTensor<T> {
+    /// Reduces all the elements with the associative `op`, starting from `init`.
+    ///
+    /// The result of an empty tensor is `init`, so it is usually the identity of the operation.
+    /// Elements are folded sequentially, while `DeviceTensor::reduce_device` combines them in a tree,
+    /// so floating-point results may differ in rounding.
+    pub fn reduce(&self, init: T, op: ReduceOp) -> T {
+        self.iter().fold(init, |s, &x| op.apply(s, x))
+    }
+}
+
+/// Moves the `k`-th smallest element of `lane` to position `k` with quickselect, smaller elements go before it
+/// and larger ones after it. NaNs compare equal to everything, so their placement is unspecified.
+fn select_nth<T: Prm + PartialOrd>(lane: &mut [T], k: usize) -> T {
+    *lane.select_nth_unstable_by(k, |a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal)).1
+}
+
+impl<T: Prm + PartialOrd> HostTensor<T> {
+    /// Partially sorts each lane along the `axis`, so that the element at position `kth` is the one that would be there
+    /// if the lane was sorted, all the elements before it are not greater and all the elements after it are not less.
+    ///
+    /// The order within both parts is unspecified. Each lane is processed by quickselect in linear average time.
+    /// Fails with `Error::BadAxes` if `axis` isn't less than the number of dimensions
+    /// and with `Error::BadIndex` if `kth` isn't less than the length of the axis.
+    pub fn partition(&self, kth: usize, axis: usize) -> Result<Self, Error> {
+        if axis < self.shape().len() && kth >= self.shape()[axis] {
+            return Err(Error::BadIndex(vec![kth]));
+        }
+        self.apply_along_axis(axis, |lane| {
+            let mut lane = lane.to_vec();
+            select_nth(&mut lane, kth);
+            lane
+        })
+    }
+}
+
+impl<T: Prm + Num + PartialOrd> HostTensor<T> {
+    /// Median along the `axis`, which is removed from the result.
+    ///
+    /// For lanes of even length the two middle elements are averaged. For integer types the lower of them is taken
+    /// instead, so the median is always one of the elements. Unlike `mean_axis` it isn't affected by a few outliers.
+    /// Fails with `Error::BadAxes` if `axis` isn't less than the number of dimensions
+    /// and with `Error::BadArgument` if the axis is empty.
+    pub fn median_axis(&self, axis: usize) -> Result<Self, Error> {
+        let rank = self.shape().len();
+        if axis < rank && self.shape()[axis] == 0 {
+            return Err(Error::BadArgument("median of an empty axis".to_string()));
+        }
+        let one: T = One::one();
+        let two = one + one;
+        // Halving of one yields zero only for integer types.
+        let average = one / two != Zero::zero();
+        let median = self.apply_along_axis(axis, |lane| {
+            let mut lane = lane.to_vec();
+            let n = lane.len();
+            let upper = select_nth(&mut lane, n / 2);
+            if n % 2 == 1 {
+                return vec![upper];
+            }
+            let lower = select_nth(&mut lane[..(n / 2)], n / 2 - 1);
+            vec![if average { (lower + upper) / two } else { lower }]
+        })?;
+        let mut dims = median.shape().as_slice().to_vec();
+        dims.resize(rank, 1);
+        dims.remove(axis);
+        median.reshape(&Shape::from(dims))
+    }
+}
+
+impl<T: Prm> HostTensor<T> {
+    /// Number of nonzero elements along the `axis` or in the whole tensor if it is `None`, see `sum_axis` for `keepdims`.
+    ///
+    /// Element is nonzero if it isn't equal to `T::zero()`, so for floating-point types `-0.0` is zero and NaN is nonzero.
+    pub fn count_nonzero(&self, axis: Option<usize>, keepdims: bool) -> Result<HostTensor<usize>, Error> {
+        let flags = self.map(|x| if x == T::zero() { 0 } else { 1 });
+        match axis {
+            Some(axis) => Ok(flags.sum_axis(axis, keepdims)),
+            None => Ok(flags.reshape(&Shape::from([self.shape().content()]))?.sum_axis(0, keepdims)),
+        }
+    }
+}
+
+impl<T: Prm + PrimInt> HostTensor<T> {
+    /// Number of occurrences of each value of a tensor of non-negative integers, e.g. to check the balance of class labels.
+    ///
+    /// The result is a vector of length `max + 1` where `max` is the greatest value, or of `minlength` if it is greater,
+    /// so that counts of different tensors could be compared. Fails with `Error::BadArgument` if any value is negative.
+    pub fn bincount(&self, minlength: usize) -> Result<HostTensor<usize>, Error> {
+        let (min, max) = self.iter().fold((T::max_value(), T::min_value()), |(a, b), &x| (a.min(x), b.max(x)));
+        let len = bincount_len(self.shape().content(), min, max, minlength)?;
+        let mut counts = vec![0; len];
+        for &x in self.iter() {
+            counts[x.to_usize().unwrap()] += 1;
+        }
+        Ok(HostTensor::from(counts))
+    }
+}
+
+impl HostTensor<usize> {
+    /// Confusion matrix of classification, the element in row `t` and column `p` is the number of positions
+    /// where the target class is `t` and the predicted one is `p`. Like other matrices it has shape `[columns, rows]`,
+    /// so the element is at `[p, t]`. Both tensors contain class indices and must have the same shape.
+    ///
+    /// Computed as `bincount` of the flat indices of the matrix.
+    /// Fails with `Error::ShapeMismatch` if shapes differ and `Error::BadIndex` if any class isn't less than `num_classes`.
+    pub fn confusion_matrix(predictions: &Self, targets: &Self, num_classes: usize) -> Result<Self, Error> {
+        let max = predictions.iter().chain(targets.iter()).cloned().max().unwrap_or(0);
+        let len = check_confusion(predictions.shape(), targets.shape(), max, num_classes)?;
+        let flat = predictions.zip_map(targets, |p, t| p + num_classes * t)?;
+        flat.bincount(len)?.reshape(&Shape::from([num_classes, num_classes]))
+    }
+}
+
+impl HostTensor<u8> {
+    /// Whether all elements of the mask along the `axis` (or in the whole tensor if it is `None`) are nonzero.
+    ///
+    /// Reduction over empty axis is `true`.
+    pub fn all(&self, axis: Option<usize>, keepdims: bool) -> Result<HostTensor<bool>, Error> {
+        Ok(self.map(|x| (x == 0) as u8).count_nonzero(axis, keepdims)?.map(|n| n == 0))
+    }
+    /// Whether any element of the mask along the `axis` (or in the whole tensor if it is `None`) is nonzero.
+    ///
+    /// Reduction over empty axis is `false`.
+    pub fn any(&self, axis: Option<usize>, keepdims: bool) -> Result<HostTensor<bool>, Error> {
+        Ok(self.count_nonzero(axis, keepdims)?.map(|n| n != 0))
+    }
+}
+
+impl<T: Prm + Float> HostTensor<T> {
+    /// Mean along the `axis`, see `sum_axis` for `keepdims`.
+    ///
+    /// Mean over an empty axis is NaN.
+    pub fn mean_axis(&self, axis: usize, keepdims: bool) -> Self {
+        let len = T::from(self.shape()[axis]).unwrap();
+        self.sum_axis(axis, keepdims).map(|s| s / len)
+    }
+    /// Mean along all the `axes` at once, see `sum_axes`.
+    pub fn mean_axes(&self, axes: &[usize], keepdims: bool) -> Result<Self, Error> {
+        let len = T::from(axes.iter().map(|&axis| self.shape()[axis]).product::<usize>()).unwrap();
+        Ok(self.sum_axes(axes, keepdims)?.map(|s| s / len))
+    }
+    /// Mean along the `axis` over positions where `mask` is nonzero, see `masked_sum`.
+    ///
+    /// The sum is divided by the number of valid positions, so a lane without them is NaN.
+    pub fn masked_mean(&self, mask: &HostTensor<u8>, axis: usize, keepdims: bool) -> Result<Self, Error> {
+        let count = self.map(|_| One::one()).masked_sum(mask, axis, keepdims)?;
+        self.masked_sum(mask, axis, keepdims)?.zip_map(&count, |s, n| s / n)
+    }
+
+    /// Cosine similarity of broadcasted `self` and `other` along the `axis`, which is removed from the result.
+    ///
+    /// Dot product and both norms are accumulated in a single pass, and the product of norms is bounded below by `1e-8`,
+    /// so the similarity with a zero vector is zero rather than NaN.
+    /// Fails with `Error::ShapeMismatch` if shapes aren't broadcast-compatible.
+    pub fn cosine_similarity(&self, other: &Self, axis: usize) -> Result<Self, Error> {
+        let shape = self.shape().broadcast(other.shape())?;
+        let a = self.broadcast_to(&shape)?.iter().cloned().collect::<Vec<_>>();
+        let b = other.broadcast_to(&shape)?.iter().cloned().collect::<Vec<_>>();
+        let layout = ReduceLayout::new(&shape, axis, false);
+        let (zero, eps): (T, T) = (Zero::zero(), T::from(COSINE_EPS).unwrap());
+        let vec = (0..layout.shape.content()).map(|i| {
+            let (dot, aa, bb) = layout.source_indices(i).fold((zero, zero, zero), |(d, x, y), k| {
+                (d + a[k] * b[k], x + a[k] * a[k], y + b[k] * b[k])
+            });
+            dot / (aa * bb).sqrt().max(eps)
+        }).collect();
+        Ok(Self::from_plain_vec(vec, &layout.shape))
+    }
+}
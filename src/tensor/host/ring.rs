@@ -0,0 +1,88 @@
+use crate::{
+    Prm, Error,
+    Shape, Tensor, HostTensor,
+};
+
+
+/// Fixed-size window of the latest frames of a stream, stored on the host as a circular buffer.
+///
+/// Frames have the same shape and are stacked along a new time axis placed after the axes of the frame,
+/// so each frame is contiguous and a window of frames of shape `[features]` has shape `[features, time]`.
+/// When the window is full, pushing a new frame drops the oldest one, no data is moved in the buffer.
+#[derive(Clone)]
+pub struct RingTensor<T: Prm> {
+    frame: Shape,
+    capacity: usize,
+    data: Vec<T>,
+    /// Slot of the oldest frame.
+    start: usize,
+    len: usize,
+}
+
+impl<T: Prm> RingTensor<T> {
+    /// Creates empty window for `capacity` frames of the `frame` shape.
+    ///
+    /// Fails with `Error::BadArgument` if `capacity` is zero and with `Error::Overflow` if the buffer is too large.
+    pub fn new(frame: &Shape, capacity: usize) -> Result<Self, Error> {
+        if capacity == 0 {
+            return Err(Error::BadArgument("RingTensor capacity must be nonzero".to_string()));
+        }
+        let size = frame.checked_content()?.checked_mul(capacity).ok_or(Error::Overflow)?;
+        Ok(Self { frame: frame.clone(), capacity, data: vec![T::zero(); size], start: 0, len: 0 })
+    }
+
+    /// Shape of a single frame.
+    pub fn frame_shape(&self) -> &Shape {
+        &self.frame
+    }
+    /// Maximum number of frames in the window.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Number of frames currently in the window.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Whether the next push drops the oldest frame.
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity
+    }
+
+    /// Appends a new frame to the window, dropping the oldest one if the window is full.
+    ///
+    /// Fails with `Error::ShapeMismatch` if the frame has a different shape.
+    pub fn push(&mut self, frame: &HostTensor<T>) -> Result<(), Error> {
+        if frame.shape() != &self.frame {
+            return Err(Error::ShapeMismatch(frame.shape().clone(), self.frame.clone()));
+        }
+        let slot = (self.start + self.len) % self.capacity;
+        let size = self.frame.content();
+        for (d, &x) in self.data[(slot * size)..((slot + 1) * size)].iter_mut().zip(frame.iter()) {
+            *d = x;
+        }
+        if self.is_full() {
+            self.start = (self.start + 1) % self.capacity;
+        } else {
+            self.len += 1;
+        }
+        Ok(())
+    }
+    /// Removes all frames from the window.
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+
+    /// Copy of the frames in the window from the oldest to the latest, it has `len` frames along the time axis.
+    pub fn as_tensor(&self) -> HostTensor<T> {
+        let size = self.frame.content();
+        let (head, tail) = self.data.split_at(self.start * size);
+        let vec = tail.iter().chain(head.iter()).take(self.len * size).cloned().collect();
+        let mut dims = self.frame.as_slice().to_vec();
+        dims.push(self.len);
+        HostTensor::from_plain_vec(vec, &Shape::from(dims))
+    }
+}
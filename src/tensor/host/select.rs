@@ -0,0 +1,99 @@
+use num_traits::Zero;
+use crate::{
+    Prm, Num, Error,
+    Shape, Tensor, HostTensor, SelectLayout, SegmentLayout, interleave_indices,
+};
+
+
+impl<T: Prm> HostTensor<T> {
+    /// Selects whole slices along the `axis` by indices.
+    ///
+    /// Result has the same shape except that the length of `axis` equals the number of `indices`.
+    /// Fails with `Error::BadRank` if `indices` isn't a vector and `Error::BadIndex` if any index is out of bounds.
+    pub fn index_select(&self, axis: usize, indices: &HostTensor<usize>) -> Result<Self, Error> {
+        let indices_data = indices.iter().cloned().collect::<Vec<_>>();
+        let layout = SelectLayout::new(self.shape(), axis, indices.shape(), &indices_data)?;
+        let data = self.iter().cloned().collect::<Vec<_>>();
+        let vec = (0..layout.shape.content()).map(|i| data[layout.source_index(i, &indices_data)]).collect();
+        Ok(Self::from_plain_vec(vec, &layout.shape))
+    }
+
+    /// Repeats each slice along the `axis` consecutively `repeats` times, so `[1, 2]` becomes `[1, 1, 2, 2]`.
+    ///
+    /// Fails with `Error::BadAxes` if `axis` isn't less than the rank of the tensor.
+    pub fn repeat_interleave(&self, repeats: usize, axis: usize) -> Result<Self, Error> {
+        let len = self.shape()[axis];
+        let indices = interleave_indices(self.shape(), axis, &Shape::from([len]), &vec![repeats; len])?;
+        self.index_select(axis, &HostTensor::from(indices))
+    }
+    /// Repeats `k`-th slice along the `axis` consecutively `repeats[k]` times.
+    ///
+    /// Fails with `Error::BadRank` if `repeats` isn't a vector and `Error::BadSize` if its length differs from the length of the axis.
+    pub fn repeat_interleave_by(&self, repeats: &HostTensor<usize>, axis: usize) -> Result<Self, Error> {
+        let data = repeats.iter().cloned().collect::<Vec<_>>();
+        let indices = interleave_indices(self.shape(), axis, repeats.shape(), &data)?;
+        self.index_select(axis, &HostTensor::from(indices))
+    }
+}
+
+impl<T: Prm + Num> HostTensor<T> {
+    /// Sums slices along the `axis` grouped by segment ids, like `segment_sum` in TensorFlow.
+    ///
+    /// Slice `k` is added to the slice `segment_ids[k]` of the result, which has `num_segments` slices along the axis.
+    /// Ids don't have to be sorted, segments without elements are zeros.
+    /// Fails with `Error::BadRank` if `segment_ids` isn't a vector, `Error::BadSize` if its length differs from the length of the axis,
+    /// and `Error::BadIndex` if any id isn't less than `num_segments`.
+    pub fn segment_sum(&self, axis: usize, segment_ids: &HostTensor<usize>, num_segments: usize) -> Result<Self, Error> {
+        let ids = segment_ids.iter().cloned().collect::<Vec<_>>();
+        let layout = SegmentLayout::new(self.shape(), axis, segment_ids.shape(), &ids, num_segments)?;
+        let mut vec = vec![Zero::zero(); layout.shape.content()];
+        for (i, &x) in self.iter().enumerate() {
+            let t = layout.target_index(i, &ids);
+            vec[t] = vec[t] + x;
+        }
+        Ok(Self::from_plain_vec(vec, &layout.shape))
+    }
+}
+
+impl<T: Prm + PartialOrd> HostTensor<T> {
+    /// Sorted distinct values with the number of occurrences of each one and the position of each element among them.
+    fn unique_parts(&self) -> (Vec<T>, Vec<usize>, Vec<usize>) {
+        let data = self.iter().cloned().collect::<Vec<_>>();
+        let mut order = (0..data.len()).collect::<Vec<_>>();
+        // Only NaNs are unordered, even with themselves, so the order is total with NaNs last.
+        let is_nan = |x: &T| x.partial_cmp(x).is_none();
+        order.sort_by(|&a, &b| {
+            let (x, y) = (&data[a], &data[b]);
+            x.partial_cmp(y).unwrap_or_else(|| is_nan(x).cmp(&is_nan(y)))
+        });
+        let (mut values, mut counts, mut inverse) = (Vec::new(), Vec::new(), vec![0; data.len()]);
+        for i in order {
+            if values.last() != Some(&data[i]) {
+                values.push(data[i]);
+                counts.push(0);
+            }
+            *counts.last_mut().unwrap() += 1;
+            inverse[i] = values.len() - 1;
+        }
+        (values, counts, inverse)
+    }
+
+    /// Distinct values of the tensor sorted in ascending order as a vector, e.g. to collect the labels of a dataset.
+    ///
+    /// Values are compared with `==`, so each NaN is a distinct value, NaNs are placed after all the other values
+    /// in the order of their positions in the tensor.
+    pub fn unique(&self) -> Self {
+        HostTensor::from(self.unique_parts().0)
+    }
+    /// Distinct values as in `unique` and the number of occurrences of each of them.
+    pub fn unique_counts(&self) -> (Self, HostTensor<usize>) {
+        let (values, counts, _) = self.unique_parts();
+        (HostTensor::from(values), HostTensor::from(counts))
+    }
+    /// Distinct values as in `unique` and the tensor of the same shape as `self` containing the index of each element
+    /// in the distinct values, so that `values.index_select(0, &inverse)` reconstructs the elements.
+    pub fn unique_inverse(&self) -> (Self, HostTensor<usize>) {
+        let (values, _, inverse) = self.unique_parts();
+        (HostTensor::from(values), HostTensor::from_plain_vec(inverse, self.shape()))
+    }
+}
@@ -0,0 +1,100 @@
+use num_traits::Zero;
+use crate::{
+    Prm, Num, Error,
+    Shape, Tensor, HostTensor,
+};
+
+
+/// Sparse tensor in coordinate (COO) format, stored on the host.
+///
+/// Contains positions and values of `nnz` nonzero elements of a dense tensor of the specified shape.
+/// Positions are stored in `indices` tensor of shape `[ndim, nnz]`, so coordinates of each element are contiguous,
+/// and values in `values` tensor of shape `[nnz]`. The order of elements is arbitrary,
+/// repeated positions are allowed and their values are summed on conversion to dense tensor.
+#[derive(Clone)]
+pub struct SparseTensor<T: Prm> {
+    shape: Shape,
+    indices: HostTensor<usize>,
+    values: HostTensor<T>,
+}
+
+impl<T: Prm> SparseTensor<T> {
+    /// Creates sparse tensor from positions and values, see `SparseTensor` for their layout.
+    ///
+    /// Fails with `Error::ShapeMismatch` if `indices` or `values` have wrong shapes
+    /// and with `Error::BadIndex` if a position is out of bounds of `shape`.
+    pub fn new(shape: &Shape, indices: HostTensor<usize>, values: HostTensor<T>) -> Result<Self, Error> {
+        let nnz = values.shape()[0];
+        if values.shape().len() > 1 {
+            return Err(Error::ShapeMismatch(values.shape().clone(), Shape::from([nnz])));
+        }
+        let expected = Shape::from([shape.len(), nnz]);
+        if indices.shape() != &expected {
+            return Err(Error::ShapeMismatch(indices.shape().clone(), expected));
+        }
+        let tensor = Self { shape: shape.clone(), indices, values };
+        for j in 0..nnz {
+            let pos = tensor.position(j);
+            if pos.iter().zip(shape.iter()).any(|(&x, &len)| x >= len) {
+                return Err(Error::BadIndex(pos));
+            }
+        }
+        Ok(tensor)
+    }
+
+    /// Shape of the dense tensor.
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+    /// Number of stored elements.
+    pub fn nnz(&self) -> usize {
+        self.values.shape()[0]
+    }
+    /// Positions of stored elements, tensor of shape `[ndim, nnz]`.
+    pub fn indices(&self) -> &HostTensor<usize> {
+        &self.indices
+    }
+    /// Values of stored elements, tensor of shape `[nnz]`.
+    pub fn values(&self) -> &HostTensor<T> {
+        &self.values
+    }
+
+    /// Position of `j`-th stored element.
+    fn position(&self, j: usize) -> Vec<usize> {
+        (0..self.shape.len()).map(|i| self.indices[[i, j]]).collect()
+    }
+}
+
+impl<T: Prm + Num> SparseTensor<T> {
+    /// Converts to dense tensor, missing elements are zeros and repeated ones are summed.
+    pub fn to_dense(&self) -> HostTensor<T> {
+        let mut vec = vec![Zero::zero(); self.shape.content()];
+        for (j, &x) in self.values.iter().enumerate() {
+            let index = self.position(j).iter().zip(self.shape.iter()).rev().fold(0, |s, (&x, &len)| s * len + x);
+            vec[index] = vec[index] + x;
+        }
+        HostTensor::from_plain_vec(vec, &self.shape)
+    }
+}
+
+impl<T: Prm> HostTensor<T> {
+    /// Converts to sparse tensor keeping only nonzero elements in flattened order.
+    pub fn to_sparse(&self) -> SparseTensor<T> {
+        let (mut indices, mut values) = (Vec::new(), Vec::new());
+        for (mut r, &x) in self.iter().enumerate() {
+            if x != T::zero() {
+                for &len in self.shape().iter() {
+                    indices.push(r % len);
+                    r /= len;
+                }
+                values.push(x);
+            }
+        }
+        let nnz = values.len();
+        SparseTensor {
+            shape: self.shape().clone(),
+            indices: HostTensor::from_plain_vec(indices, &Shape::from([self.shape().len(), nnz])),
+            values: HostTensor::from_plain_vec(values, &Shape::from([nnz])),
+        }
+    }
+}
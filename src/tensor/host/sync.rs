@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use crate::{
+    Prm, Num, HostBuffer,
+    Shape, Tensor, CommonTensor, Error, HostTensor, Index, Range,
+};
+
+
+type SyncInnerTensor<T> = CommonTensor<T, HostBuffer<T>, Arc<HostBuffer<T>>>;
+
+/// Host tensor which buffer is shared via `Arc`, so that it could be sent to other threads.
+///
+/// Views of the same buffer could be used from different threads simultaneously,
+/// modification of the shared buffer clones it as in `HostTensor`.
+/// Atomic reference counting makes cloning and dropping slightly slower than for `HostTensor`,
+/// so it is better to convert the tensor back to `HostTensor` for the heavy single-threaded work.
+#[derive(Clone)]
+pub struct SyncHostTensor<T: Prm> {
+    inner: SyncInnerTensor<T>,
+}
+
+impl<T: Prm> SyncHostTensor<T> {
+    /// Converts to `HostTensor`, the buffer is cloned if it is shared with other tensors.
+    pub fn into_host(self) -> HostTensor<T> {
+        HostTensor::from_inner(self.inner.into_ptr())
+    }
+}
+
+/// The buffer is moved without copying if it isn't shared with other tensors.
+impl<T: Prm> From<HostTensor<T>> for SyncHostTensor<T> {
+    fn from(tensor: HostTensor<T>) -> Self {
+        Self { inner: tensor.into_inner().into_ptr() }
+    }
+}
+impl<T: Prm> From<SyncHostTensor<T>> for HostTensor<T> {
+    fn from(tensor: SyncHostTensor<T>) -> Self {
+        tensor.into_host()
+    }
+}
+
+impl<T: Prm> Tensor<T> for SyncHostTensor<T> {
+    type Buffer = HostBuffer<T>;
+
+    unsafe fn new_uninit_in(_: &(), shape: &Shape) -> Result<Self, Error> {
+        SyncInnerTensor::<T>::new_uninit_in(&(), shape).map(|inner| Self { inner })
+    }
+    fn new_empty_in(_: &(), shape: &Shape) -> Result<Self, Error> {
+        SyncInnerTensor::<T>::new_empty_in(&(), shape).map(|inner| Self { inner })
+    }
+    fn new_filled_in(_: &(), shape: &Shape, value: T) -> Result<Self, Error> {
+        SyncInnerTensor::<T>::new_filled_in(&(), shape, value).map(|inner| Self { inner })
+    }
+    fn new_zeroed_in(_: &(), shape: &Shape) -> Result<Self, Error> {
+        SyncInnerTensor::<T>::new_zeroed_in(&(), shape).map(|inner| Self { inner })
+    }
+
+    fn context(&self) -> &() {
+        self.inner.context()
+    }
+    fn shape(&self) -> &Shape {
+        self.inner.shape()
+    }
+    fn buffer_refcount(&self) -> usize {
+        self.inner.buffer_refcount()
+    }
+
+    fn view(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.view(shape).map(|inner| Self { inner })
+    }
+    fn reshape(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.reshape(shape).map(|inner| Self { inner })
+    }
+
+    fn resize(&self, shape: &[usize], fill: T) -> Result<Self, Error> {
+        self.inner.resize(shape, fill).map(|inner| Self { inner })
+    }
+
+    fn permute(&self, axes: &[usize]) -> Result<Self, Error> {
+        self.inner.permute(axes).map(|inner| Self { inner })
+    }
+    fn transpose(&self) -> Self {
+        Self { inner: self.inner.transpose() }
+    }
+    fn slice(&self, index: &[Index]) -> Result<Self, Error> {
+        self.inner.slice(index).map(|inner| Self { inner })
+    }
+    fn unfold(&self, axis: usize, size: usize, step: usize) -> Result<Self, Error> {
+        self.inner.unfold(axis, size, step).map(|inner| Self { inner })
+    }
+    fn broadcast_to(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.broadcast_to(shape).map(|inner| Self { inner })
+    }
+
+    fn clone_contiguous(&self) -> Result<Self, Error> {
+        self.inner.clone_contiguous().map(|inner| Self { inner })
+    }
+    fn compact(&self) -> Result<Self, Error> {
+        self.inner.compact().map(|inner| Self { inner })
+    }
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error> {
+        self.inner.copy_from(&src.inner)
+    }
+    fn assign_slice(&mut self, ranges: &[Range], src: &Self) -> Result<(), Error> {
+        self.inner.assign_slice(ranges, &src.inner)
+    }
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num {
+        self.inner.fill_iota(start, step)
+    }
+
+    fn load(&self, dst: &mut [T]) -> Result<(), Error> {
+        self.inner.load(dst)
+    }
+    fn store(&mut self, src: &[T]) -> Result<(), Error> {
+        self.inner.store(src)
+    }
+
+    fn as_slice(&self) -> Option<&[T]> {
+        self.inner.host_slice()
+    }
+    fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        Some(self.inner.host_mut_slice())
+    }
+    fn try_into_vec(self) -> Result<Vec<T>, Error> {
+        self.inner.into_host_vec()
+    }
+}
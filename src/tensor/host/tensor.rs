@@ -1,8 +1,13 @@
+use std::{
+    mem,
+    ops::{self, IndexMut},
+    hash::{Hash, Hasher},
+};
 use crate::{
-    Prm,
-    HostBuffer,
-    Shape, Tensor, CommonTensor,
-    HostTensorIter, HostTensorIterMut,
+    Prm, Num, Pod,
+    HostBuffer, check_len,
+    Shape, Tensor, CommonTensor, SharedPtr, Error, Index,
+    HostTensorIter, HostTensorIterMut, IndexIter, Range, TensorViewMut,
 };
 
 
@@ -11,28 +16,106 @@ type InnerTensor<T> = CommonTensor<T, HostBuffer<T>>;
 /// Tensor structure.
 /// It consists of a contiguous one-dimensional array and a shape.
 /// Tensor tries to reuse resources as long as possible and implements copy-on-write mechanism.
+///
+/// The buffer is shared via `Rc`, so the tensor cannot be sent to other threads,
+/// convert it to `SyncHostTensor` for that.
+#[derive(Clone)]
 pub struct HostTensor<T: Prm> {
     inner: InnerTensor<T>,
 }
 
 impl<T: Prm> HostTensor<T> {
     /// Create unitialized tensor
-    pub unsafe fn new_uninit(shape: &Shape) -> Self {
+    ///
+    /// # Safety
+    ///
+    /// Content of the tensor is uninitialized and must be written before reading.
+    /// See [`Tensor::new_uninit_in`] for details.
+    pub unsafe fn new_uninit(shape: &Shape) -> Result<Self, Error> {
         Self::new_uninit_in(&(), shape)
     }
+    /// Create tensor which content is not meant to be used, safe alternative to `new_uninit`.
+    pub fn new_empty(shape: &Shape) -> Result<Self, Error> {
+        Self::new_empty_in(&(), shape)
+    }
     /// Create tensor filled with value on the specified hardware
-    pub fn new_filled(shape: &Shape, value: T) -> Self {
+    pub fn new_filled(shape: &Shape, value: T) -> Result<Self, Error> {
         Self::new_filled_in(&(), shape, value)
     }
     /// Create tensor filled with zeros on the specified hardware
-    pub fn new_zeroed(shape: &Shape) -> Self {
+    pub fn new_zeroed(shape: &Shape) -> Result<Self, Error> {
         Self::new_zeroed_in(&(), shape)
     }
+    /// Create tensor by calling `f` with the multi-dimensional index of each element, see `Tensor::from_fn_in`.
+    pub fn from_fn<F: FnMut(&[usize]) -> T>(shape: &Shape, f: F) -> Result<Self, Error> {
+        Self::from_fn_in(&(), shape, f)
+    }
+    /// Create tensor from raw bytes in the native representation, see `Tensor::from_bytes_in`.
+    pub fn from_bytes(bytes: &[u8], shape: &Shape) -> Result<Self, Error> where T: Pod {
+        Self::from_bytes_in(&(), bytes, shape)
+    }
+
+    /// Create tensor of specified shape that takes ownership of the data without copying.
+    ///
+    /// Fails with `Error::BadSize` if the vector length isn't equal to the shape content.
+    pub fn from_vec(vec: Vec<T>, shape: &Shape) -> Result<Self, Error> {
+        check_len(shape.checked_content()?, vec.len())?;
+        Ok(Self { inner: InnerTensor::<T>::from_buffer(HostBuffer::from_vec(vec), shape) })
+    }
+
+    /// Create tensor from vector which length is known to be equal to the shape content.
+    pub(crate) fn from_plain_vec(vec: Vec<T>, shape: &Shape) -> Self {
+        Self { inner: InnerTensor::<T>::from_buffer(HostBuffer::from_vec(vec), shape) }
+    }
+
+    /// Create two-dimensional tensor from nested slices.
+    ///
+    /// Inner slices are placed along the first axis, so `from_2d(&[&[1, 2, 3], &[4, 5, 6]])` has shape `[3, 2]`
+    /// and its flattened data is `[1, 2, 3, 4, 5, 6]`.
+    /// Fails with `Error::BadSize` if inner slices have different lengths.
+    pub fn from_2d(data: &[&[T]]) -> Result<Self, Error> {
+        let len = data.first().map_or(0, |s| s.len());
+        let mut vec = Vec::with_capacity(len * data.len());
+        for s in data {
+            check_len(len, s.len())?;
+            vec.extend_from_slice(s);
+        }
+        Self::from_vec(vec, &Shape::from([len, data.len()]))
+    }
+    /// Create three-dimensional tensor from nested slices.
+    ///
+    /// The innermost slices are placed along the first axis, see `from_2d`.
+    /// Fails with `Error::BadSize` if slices of the same level have different lengths.
+    pub fn from_3d(data: &[&[&[T]]]) -> Result<Self, Error> {
+        let (len, sublen) = data.first().map_or((0, 0), |s| {
+            (s.len(), s.first().map_or(0, |ss| ss.len()))
+        });
+        let mut vec = Vec::with_capacity(sublen * len * data.len());
+        for s in data {
+            check_len(len, s.len())?;
+            for ss in s.iter() {
+                check_len(sublen, ss.len())?;
+                vec.extend_from_slice(ss);
+            }
+        }
+        Self::from_vec(vec, &Shape::from([sublen, len, data.len()]))
+    }
+
+    pub(crate) fn from_inner(inner: InnerTensor<T>) -> Self {
+        Self { inner }
+    }
+    pub(crate) fn into_inner(self) -> InnerTensor<T> {
+        self.inner
+    }
 
     /// Provides access to underlying buffer.
     pub(crate) fn buffer(&self) -> &HostBuffer<T> {
         self.inner.buffer()
     }
+    /// Buffer indices of tensor elements in flattened order.
+    pub(crate) fn indices(&self) -> IndexIter {
+        self.inner.indices()
+    }
     /// Provides mutable access to underlying buffer.
     pub(crate) fn buffer_mut(&mut self) -> &mut HostBuffer<T> {
         self.inner.buffer_mut()
@@ -44,36 +127,249 @@ impl<T: Prm> HostTensor<T> {
     }
     /// Mutable iterator over flatten tensor.
     pub fn iter_mut<'a>(&'a mut self) -> HostTensorIterMut<'a, T> {
-        // FIXME: Make tensor plain when using strides.
-        self.buffer_mut().as_mut_slice().iter_mut()
+        self.inner.host_mut_slice().iter_mut()
+    }
+
+    /// Returns element at specified position.
+    ///
+    /// Position may have any length, missing coordinates are considered to be zero.
+    /// Fails with `Error::BadIndex` if position is out of bounds.
+    pub fn get(&self, pos: &[usize]) -> Result<T, Error> {
+        self.inner.buffer_index(pos).map(|i| self.buffer().as_slice()[i])
+    }
+    /// Writes element at specified position.
+    ///
+    /// If elements of the tensor overlap in memory (e.g. it is broadcasted), the tensor is made plain first,
+    /// so that only the element at the position is changed.
+    /// Fails with `Error::BadIndex` if position is out of bounds.
+    pub fn set(&mut self, pos: &[usize], value: T) -> Result<(), Error> {
+        *self.element_mut(pos)? = value;
+        Ok(())
+    }
+    fn element_mut(&mut self, pos: &[usize]) -> Result<&mut T, Error> {
+        self.inner.buffer_index(pos)?;
+        // Broadcasted elements share memory, so they are separated before writing.
+        if self.inner.is_overlapping() {
+            self.inner.make_plain()?;
+        }
+        let i = self.inner.buffer_index(pos)?;
+        Ok(&mut self.buffer_mut().as_mut_slice()[i])
+    }
+
+    /// Mutable view of the sub-region selected by `ranges` along the leading axes, the rest of the axes are taken whole.
+    ///
+    /// Ranges are interpreted as in `slice`. The buffer is cloned first if it is shared with other tensors,
+    /// so writes through the view are visible only in `self`. If elements of the tensor overlap in memory
+    /// (e.g. it is broadcasted) or aren't contiguous, the tensor is made plain first.
+    /// Fails with `Error::BadArgument` if a range has zero step.
+    pub fn slice_mut(&mut self, ranges: &[Range]) -> Result<TensorViewMut<'_, T>, Error> {
+        if !self.inner.is_contiguous() {
+            self.inner.make_plain()?;
+        }
+        let index = ranges.iter().map(|&r| Index::Range(r)).collect::<Vec<_>>();
+        // The view holds a reference to the buffer, so it is dropped before the buffer is made unique.
+        let (shape, strides, offset) = {
+            let view = self.inner.slice(&index)?;
+            (view.shape.clone(), view.strides.clone(), view.offset)
+        };
+        Ok(TensorViewMut::new(self.buffer_mut().as_mut_slice(), shape, strides, offset))
+    }
+}
+
+/// Creates one-dimensional tensor from vector without copying.
+impl<T: Prm> From<Vec<T>> for HostTensor<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let shape = Shape::from([vec.len()]);
+        Self::from_vec(vec, &shape).unwrap()
+    }
+}
+/// Creates one-dimensional tensor with a copy of slice.
+impl<T: Prm> From<&[T]> for HostTensor<T> {
+    fn from(slice: &[T]) -> Self {
+        Self::from(slice.to_vec())
+    }
+}
+
+/// Access to a single element, e.g. `tensor[[1, 2]]`.
+///
+/// Panics if position is out of bounds, use `get` to handle this.
+impl<T: Prm, I: AsRef<[usize]>> ops::Index<I> for HostTensor<T> {
+    type Output = T;
+    fn index(&self, pos: I) -> &T {
+        let i = self.inner.buffer_index(pos.as_ref()).unwrap();
+        &self.buffer().as_slice()[i]
+    }
+}
+/// Mutable access to a single element, e.g. `tensor[[1, 2]] = x`.
+///
+/// Clones the buffer if it is shared and makes the tensor plain if its elements overlap in memory, as `set` does.
+/// Panics if position is out of bounds, use `set` to handle this.
+impl<T: Prm, I: AsRef<[usize]>> IndexMut<I> for HostTensor<T> {
+    fn index_mut(&mut self, pos: I) -> &mut T {
+        self.element_mut(pos.as_ref()).unwrap()
+    }
+}
+
+/// Tensors are equal if they have the same shape and elements, layout of elements in the buffer doesn't matter.
+///
+/// Floating-point elements are compared by value, so tensors containing NaNs are never equal,
+/// and `0.0` is equal to `-0.0`. That is why `Eq` and `Hash` are implemented only for integer-like types.
+impl<T: Prm> PartialEq for HostTensor<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape() == other.shape() && self.iter().eq(other.iter())
+    }
+}
+impl<T: Prm + Eq> Eq for HostTensor<T> {}
+/// Hashes shape and elements in flattened order, so it is consistent with `PartialEq`.
+///
+/// Takes time proportional to the tensor size, so it is better to hash tensors used as cache keys only once.
+impl<T: Prm + Eq + Hash> Hash for HostTensor<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.shape().hash(state);
+        for x in self.iter() {
+            x.hash(state);
+        }
+    }
+}
+
+impl<T: Prm> HostTensor<T> {
+    /// Reinterprets the memory of each element as an element of type `U` of the same size, e.g. `i32` as `u32`.
+    ///
+    /// The buffer is reused without copying if the tensor is plain, isn't shared and both types have the same alignment,
+    /// otherwise elements are copied bitwise to a new buffer.
+    /// Fails with `Error::BadSize` containing sizes of `T` and `U` in bytes if they are different.
+    ///
+    /// # Safety
+    ///
+    /// Every element must be a valid bit pattern of `U`, e.g. reinterpreting `u8` as `bool` is undefined behavior
+    /// unless all elements are `0` or `1`. There are no such restrictions for integers and floating-point numbers.
+    pub unsafe fn reinterpret<U: Prm>(self) -> Result<HostTensor<U>, Error> {
+        if mem::size_of::<T>() != mem::size_of::<U>() {
+            return Err(Error::BadSize { expected: mem::size_of::<T>(), found: mem::size_of::<U>() });
+        }
+        let shape = self.shape().clone();
+        let inner = if mem::align_of::<T>() == mem::align_of::<U>() {
+            match self.inner.try_into_buffer() {
+                Ok(buffer) => {
+                    let mut vec = mem::ManuallyDrop::new(buffer.into_vec());
+                    let vec = Vec::from_raw_parts(vec.as_mut_ptr() as *mut U, vec.len(), vec.capacity());
+                    return Ok(HostTensor::from_plain_vec(vec, &shape));
+                },
+                Err(inner) => inner,
+            }
+        } else {
+            self.inner
+        };
+        let vec = HostTensor::from_inner(inner).iter().map(|x| mem::transmute_copy(x)).collect();
+        Ok(HostTensor::from_plain_vec(vec, &shape))
+    }
+}
+
+impl<T: Prm, P: SharedPtr<HostBuffer<T>>> CommonTensor<T, HostBuffer<T>, P> {
+    pub(crate) fn into_host_vec(self) -> Result<Vec<T>, Error> {
+        match self.try_into_buffer() {
+            Ok(buffer) => Ok(buffer.into_vec()),
+            Err(inner) => inner.to_vec(),
+        }
+    }
+    pub(crate) fn host_slice(&self) -> Option<&[T]> {
+        if self.is_contiguous() {
+            Some(&self.buffer().as_slice()[self.offset..(self.offset + self.shape.content())])
+        } else {
+            None
+        }
+    }
+    pub(crate) fn host_mut_slice(&mut self) -> &mut [T] {
+        // Host buffer operations fail only on length mismatch which cannot happen here.
+        self.make_plain().unwrap();
+        self.buffer_mut().as_mut_slice()
     }
 }
 
 impl<T: Prm> Tensor<T> for HostTensor<T> {
     type Buffer = HostBuffer<T>;
 
-    unsafe fn new_uninit_in(_: &(), shape: &Shape) -> Self {
-        Self { inner: InnerTensor::<T>::new_uninit_in(&(), shape) }
+    unsafe fn new_uninit_in(_: &(), shape: &Shape) -> Result<Self, Error> {
+        InnerTensor::<T>::new_uninit_in(&(), shape).map(|inner| Self { inner })
     }
-    fn new_filled_in(_: &(), shape: &Shape, value: T) -> Self {
-        Self { inner: InnerTensor::<T>::new_filled_in(&(), shape, value) }
+    fn new_empty_in(_: &(), shape: &Shape) -> Result<Self, Error> {
+        InnerTensor::<T>::new_empty_in(&(), shape).map(|inner| Self { inner })
     }
-    fn new_zeroed_in(_: &(), shape: &Shape) -> Self {
-        Self { inner: InnerTensor::<T>::new_zeroed_in(&(), shape) }
+    fn new_filled_in(_: &(), shape: &Shape, value: T) -> Result<Self, Error> {
+        InnerTensor::<T>::new_filled_in(&(), shape, value).map(|inner| Self { inner })
+    }
+    fn new_zeroed_in(_: &(), shape: &Shape) -> Result<Self, Error> {
+        InnerTensor::<T>::new_zeroed_in(&(), shape).map(|inner| Self { inner })
     }
 
+    fn context(&self) -> &() {
+        self.inner.context()
+    }
     fn shape(&self) -> &Shape {
         self.inner.shape()
     }
+    fn buffer_refcount(&self) -> usize {
+        self.inner.buffer_refcount()
+    }
+
+    fn view(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.view(shape).map(|inner| Self { inner })
+    }
+    fn reshape(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.reshape(shape).map(|inner| Self { inner })
+    }
 
-    fn reshape(&self, shape: &Shape) -> Self {
-        Self { inner: self.inner.reshape(shape) }
+    fn resize(&self, shape: &[usize], fill: T) -> Result<Self, Error> {
+        self.inner.resize(shape, fill).map(|inner| Self { inner })
     }
 
-    fn load(&self, dst: &mut [T]) {
-        self.inner.load(dst);
+    fn permute(&self, axes: &[usize]) -> Result<Self, Error> {
+        self.inner.permute(axes).map(|inner| Self { inner })
+    }
+    fn transpose(&self) -> Self {
+        Self { inner: self.inner.transpose() }
+    }
+    fn slice(&self, index: &[Index]) -> Result<Self, Error> {
+        self.inner.slice(index).map(|inner| Self { inner })
+    }
+    fn unfold(&self, axis: usize, size: usize, step: usize) -> Result<Self, Error> {
+        self.inner.unfold(axis, size, step).map(|inner| Self { inner })
+    }
+    fn broadcast_to(&self, shape: &Shape) -> Result<Self, Error> {
+        self.inner.broadcast_to(shape).map(|inner| Self { inner })
+    }
+
+    fn clone_contiguous(&self) -> Result<Self, Error> {
+        self.inner.clone_contiguous().map(|inner| Self { inner })
+    }
+    fn compact(&self) -> Result<Self, Error> {
+        self.inner.compact().map(|inner| Self { inner })
+    }
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error> {
+        self.inner.copy_from(&src.inner)
+    }
+    /// Writes directly into the buffer through `slice_mut`, so the rest of the tensor isn't copied.
+    fn assign_slice(&mut self, ranges: &[Range], src: &Self) -> Result<(), Error> {
+        self.slice_mut(ranges)?.copy_from(src)
+    }
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num {
+        self.inner.fill_iota(start, step)
+    }
+
+    fn load(&self, dst: &mut [T]) -> Result<(), Error> {
+        self.inner.load(dst)
+    }
+    fn store(&mut self, src: &[T]) -> Result<(), Error> {
+        self.inner.store(src)
+    }
+
+    fn as_slice(&self) -> Option<&[T]> {
+        self.inner.host_slice()
+    }
+    fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        Some(self.inner.host_mut_slice())
     }
-    fn store(&mut self, src: &[T]) {
-        self.inner.store(src);
+    fn try_into_vec(self) -> Result<Vec<T>, Error> {
+        self.inner.into_host_vec()
     }
 }
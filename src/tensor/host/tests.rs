@@ -1,31 +1,29 @@
-use crate::{prelude::*, Shape, HostTensor as Tensor};
+use crate::{prelude::*, Shape, Error, Complex, Rng, f16, bf16, HostTensor as Tensor, SyncHostTensor, SparseTensor, RingTensor, TensorBuilder, InterpMode, MeshIndexing, RankedTensor, Vector, Matrix, Index, Range, ReduceOp, DynTensor, DType};
 
 #[test]
 fn new_filled() {
     let value: i32 = -123;
-    let a = Tensor::new_filled(&Shape::from([4, 3, 2].as_ref()), value);
+    let a = Tensor::new_filled(&Shape::from([4, 3, 2].as_ref()), value).unwrap();
 
-    let mut v = Vec::new();
-    v.resize(24, 0);
-    a.load(v.as_mut_slice());
+    let mut v = vec![0; 24];
+    a.load(v.as_mut_slice()).unwrap();
 
     assert!(v.iter().all(|&x| x == value));
 }
 
 #[test]
 fn new_zeroed() {
-    let a = Tensor::new_zeroed(&Shape::from([4, 3, 2].as_ref()));
+    let a = Tensor::new_zeroed(&Shape::from([4, 3, 2].as_ref())).unwrap();
 
-    let mut v = Vec::new();
-    v.resize(24, -1);
-    a.load(v.as_mut_slice());
+    let mut v = vec![-1; 24];
+    a.load(v.as_mut_slice()).unwrap();
 
     assert!(v.iter().all(|&x| x == 0));
 }
 
 #[test]
 fn iter() {
-    let mut a = Tensor::new_zeroed(&Shape::from([1, 2, 3, 4].as_ref()));
+    let mut a = Tensor::new_zeroed(&Shape::from([1, 2, 3, 4].as_ref())).unwrap();
     for i in 0..(2*3*4) {
         a.buffer_mut().as_mut_slice()[i] = i;
     }
@@ -37,7 +35,7 @@ fn iter() {
 
 #[test]
 fn iter_mut() {
-    let mut a = Tensor::new_zeroed(&Shape::from([1, 2, 3, 4].as_ref()));
+    let mut a = Tensor::new_zeroed(&Shape::from([1, 2, 3, 4].as_ref())).unwrap();
     for (i, v) in a.iter_mut().enumerate() {
         *v = i;
     }
@@ -46,3 +44,1790 @@ fn iter_mut() {
         assert_eq!(a.buffer().as_slice()[i], i);
     }
 }
+
+#[test]
+fn view() {
+    let mut a = Tensor::new_zeroed(&Shape::from([2, 3, 4].as_ref())).unwrap();
+    for (i, v) in a.iter_mut().enumerate() {
+        *v = i;
+    }
+
+    let b = a.view(&Shape::from([6, 4].as_ref())).unwrap();
+    assert_eq!(b.shape(), &Shape::from([6, 4].as_ref()));
+    for (i, v) in b.iter().enumerate() {
+        assert_eq!(*v, i);
+    }
+
+    assert!(a.view(&Shape::from([5, 4].as_ref())).is_err());
+}
+
+#[test]
+fn reshape() {
+    let a = Tensor::new_filled(&Shape::from([2, 3, 4].as_ref()), 1).unwrap();
+    let b = a.reshape(&Shape::from([4, 3, 2].as_ref())).unwrap();
+    assert_eq!(b.shape(), &Shape::from([4, 3, 2].as_ref()));
+    assert!(b.iter().all(|&x| x == 1));
+
+    assert!(a.reshape(&Shape::from([4, 3, 3].as_ref())).is_err());
+}
+
+#[test]
+fn transpose_load() {
+    let mut a = Tensor::new_zeroed(&Shape::from([3, 2].as_ref())).unwrap();
+    a.store(&[0, 1, 2, 3, 4, 5]).unwrap();
+
+    let b = a.transpose();
+    assert_eq!(b.shape(), &Shape::from([2, 3].as_ref()));
+    let mut v = vec![0; 6];
+    b.load(v.as_mut_slice()).unwrap();
+    assert_eq!(v, [0, 3, 1, 4, 2, 5]);
+    assert!(b.iter().cloned().eq(v.iter().cloned()));
+}
+
+#[test]
+fn permute_store() {
+    let a = Tensor::new_zeroed(&Shape::from([2, 3, 2].as_ref())).unwrap();
+    let mut b = a.permute(&[2, 0, 1]).unwrap();
+    b.store(&(0..12).collect::<Vec<_>>()).unwrap();
+
+    let mut v = vec![0; 12];
+    b.load(v.as_mut_slice()).unwrap();
+    assert!(v.iter().cloned().eq(0..12));
+
+    // Store must not affect the original tensor because of copy-on-write.
+    a.load(v.as_mut_slice()).unwrap();
+    assert!(v.iter().all(|&x| x == 0));
+
+    // Check that elements are placed in `b` according to permutation.
+    let c = b.permute(&[1, 2, 0]).unwrap();
+    c.load(v.as_mut_slice()).unwrap();
+    assert_eq!(v, [0, 2, 4, 6, 8, 10, 1, 3, 5, 7, 9, 11]);
+
+    assert!(a.permute(&[0, 2]).is_err());
+    assert!(a.permute(&[1, 1]).is_err());
+}
+
+#[test]
+fn view_transposed() {
+    let a = Tensor::new_filled(&Shape::from([3, 2].as_ref()), 0).unwrap().transpose();
+    assert!(a.view(&Shape::from([6].as_ref())).is_err());
+
+    let mut b = a.reshape(&Shape::from([6].as_ref())).unwrap();
+    assert!(b.view(&Shape::from([2, 3].as_ref())).is_ok());
+
+    b.iter_mut().for_each(|x| *x = 1);
+    assert!(b.iter().all(|&x| x == 1));
+}
+
+#[test]
+fn new_empty() {
+    let mut a = Tensor::<bool>::new_empty(&Shape::from([4, 3, 2].as_ref())).unwrap();
+    assert_eq!(a.iter().count(), 24);
+
+    a.store(&[true; 24]).unwrap();
+    assert!(a.iter().all(|&x| x));
+}
+
+#[test]
+fn new_uninit() {
+    let mut a = unsafe { Tensor::<bool>::new_uninit(&Shape::from([4, 3, 2].as_ref())).unwrap() };
+    a.store(&[true; 24]).unwrap();
+    assert!(a.iter().all(|&x| x));
+}
+
+#[test]
+fn new_overflow() {
+    assert!(matches!(
+        Tensor::<i32>::new_zeroed(&Shape::from([1 << 40, 1 << 40].as_ref())),
+        Err(Error::Overflow),
+    ));
+    assert!(matches!(
+        Tensor::<i32>::new_zeroed(&Shape::from([1 << 32, 1 << 30].as_ref())),
+        Err(Error::Overflow),
+    ));
+}
+
+#[test]
+fn load_store_bad_size() {
+    let a = Tensor::<i32>::new_zeroed(&Shape::from([3, 2].as_ref())).unwrap();
+    assert!(matches!(
+        a.load(&mut [0; 5]),
+        Err(Error::BadSize { expected: 6, found: 5 }),
+    ));
+    assert!(matches!(
+        a.transpose().store(&[0; 7]),
+        Err(Error::BadSize { expected: 6, found: 7 }),
+    ));
+}
+
+#[test]
+fn ranked() {
+    let mut a = Matrix::<Tensor<i32>>::new_zeroed_in(&(), [3, 2]).unwrap();
+    assert_eq!(a.shape(), [3, 2]);
+    a.store(&[0, 1, 2, 3, 4, 5]).unwrap();
+
+    let b = a.transpose();
+    assert_eq!(b.shape(), [2, 3]);
+    assert!(b.iter().cloned().eq([0, 3, 1, 4, 2, 5].iter().cloned()));
+
+    let c = b.reshape([6]).unwrap();
+    assert_eq!(c.shape(), [6]);
+    let d: Vector<_> = RankedTensor::new(c.into_inner()).unwrap();
+    assert_eq!(d.shape(), [6]);
+
+    assert!(matches!(
+        Vector::new(a.into_inner()),
+        Err(Error::BadRank { expected: 1, found: 2 }),
+    ));
+    let e = Tensor::new_zeroed(&Shape::from([6, 1].as_ref())).unwrap();
+    assert_eq!(Vector::<Tensor<i32>>::new(e).unwrap().shape(), [6]);
+
+    // Product of matrices is a matrix of the outer dimensions.
+    let mut x = Matrix::<Tensor<i32>>::new_zeroed_in(&(), [3, 2]).unwrap();
+    x.store(&[1, 2, 3, 4, 5, 6]).unwrap();
+    let mut y = Matrix::<Tensor<i32>>::new_zeroed_in(&(), [2, 3]).unwrap();
+    y.fill(1).unwrap();
+    let z = x.matmul(&y).unwrap();
+    assert_eq!(z.shape(), [2, 2]);
+    assert!(*z == Tensor::from_2d(&[&[6, 6], &[15, 15]]).unwrap());
+    assert!(matches!(x.matmul(&x).err().unwrap().root(), Error::ShapeMismatch(..)));
+    let mut w = z.transpose();
+    w.copy_from(&z).unwrap();
+    assert!(*w == *z && w.shape() == z.shape());
+}
+
+#[test]
+fn index() {
+    let mut a = Tensor::new_zeroed(&Shape::from([3, 2].as_ref())).unwrap();
+    a.store(&[0, 1, 2, 3, 4, 5]).unwrap();
+    assert_eq!(a[[1, 1]], 4);
+    assert_eq!(a[&[2]], 2);
+    assert_eq!(a[[2, 1, 0]], 5);
+
+    let mut b = a.transpose();
+    assert_eq!(b[[1, 2]], 5);
+    b[[0, 2]] = 7;
+    assert_eq!(b[[0, 2]], 7);
+    assert_eq!(a[[2, 0]], 2);
+}
+
+#[test]
+#[should_panic]
+fn index_out_of_bounds() {
+    let a = Tensor::<i32>::new_zeroed(&Shape::from([3, 2].as_ref())).unwrap();
+    let _ = a[[0, 0, 1]];
+}
+
+#[test]
+fn get_set() {
+    let mut a = Tensor::new_zeroed(&Shape::from([3, 2].as_ref())).unwrap();
+    a.set(&[2, 1], 5).unwrap();
+    assert_eq!(a.get(&[2, 1]).unwrap(), 5);
+    assert!(matches!(a.get(&[3, 0]), Err(Error::BadIndex(_))));
+    assert!(matches!(a.set(&[0, 2], 1), Err(Error::BadIndex(_))));
+
+    // Writes to a broadcasted tensor change only the element at the position.
+    let b = Tensor::from(vec![1, 2]).broadcast_to(&Shape::from([2, 3])).unwrap();
+    let mut c = b.clone();
+    c.set(&[1, 1], 7).unwrap();
+    c[[0, 2]] = 9;
+    assert!(c == Tensor::from_2d(&[&[1, 2], &[1, 7], &[9, 2]]).unwrap());
+    assert!(b == Tensor::from_2d(&[&[1, 2], &[1, 2], &[1, 2]]).unwrap());
+    assert!(matches!(b.clone().set(&[2, 0], 1), Err(Error::BadIndex(_))));
+}
+
+#[test]
+fn from_vec() {
+    let a: Tensor<f32> = vec![1.0, 2.0, 3.0].into();
+    assert_eq!(a.shape(), &Shape::from([3].as_ref()));
+    assert!(a.iter().cloned().eq([1.0, 2.0, 3.0].iter().cloned()));
+
+    let b = Tensor::from([4, 5].as_ref());
+    assert_eq!(b.shape(), &Shape::from([2].as_ref()));
+    assert_eq!(b[[1]], 5);
+
+    let c = Tensor::from_vec((0..6).collect(), &Shape::from([3, 2].as_ref())).unwrap();
+    assert_eq!(c[[2, 1]], 5);
+    assert!(matches!(
+        Tensor::from_vec(vec![0; 5], &Shape::from([3, 2].as_ref())),
+        Err(Error::BadSize { expected: 6, found: 5 }),
+    ));
+}
+
+#[test]
+fn from_nested() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    assert_eq!(a.shape(), &Shape::from([3, 2].as_ref()));
+    assert!(a.iter().cloned().eq(1..=6));
+    assert_eq!(a[[0, 1]], 4);
+    assert!(matches!(
+        Tensor::from_2d(&[&[1, 2, 3], &[4, 5]]),
+        Err(Error::BadSize { expected: 3, found: 2 }),
+    ));
+
+    let b = Tensor::from_3d(&[&[&[1, 2], &[3, 4], &[5, 6]], &[&[7, 8], &[9, 10], &[11, 12]]]).unwrap();
+    assert_eq!(b.shape(), &Shape::from([2, 3, 2].as_ref()));
+    assert!(b.iter().cloned().eq(1..=12));
+    assert!(Tensor::from_3d(&[&[&[1, 2], &[3, 4]], &[&[5, 6], &[7]]]).is_err());
+    assert!(Tensor::from_3d(&[&[&[1, 2], &[3, 4]], &[&[5, 6]]]).is_err());
+}
+
+#[test]
+fn maximum_minimum() {
+    let a = Tensor::from_2d(&[&[1, 5, 3], &[4, 2, 6]]).unwrap();
+    let b = Tensor::from(vec![3, 3, 3]);
+    assert!(a.maximum(&b).unwrap().iter().cloned().eq([3, 5, 3, 4, 3, 6].iter().cloned()));
+    assert!(a.minimum(&b).unwrap().iter().cloned().eq([1, 3, 3, 3, 2, 3].iter().cloned()));
+    assert_eq!(b.maximum(&a).unwrap().shape(), a.shape());
+
+    let c = Tensor::from(vec![0, 0]);
+    assert!(matches!(a.maximum(&c), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn zip_map_broadcast() {
+    let a = Tensor::from(vec![1, 2, 3]);
+    let b = Tensor::from_vec(vec![10, 20], &Shape::from([1, 2].as_ref())).unwrap();
+    let c = a.zip_map(&b, |x, y| x + y).unwrap();
+    assert_eq!(c.shape(), &Shape::from([3, 2].as_ref()));
+    assert!(c.iter().cloned().eq([11, 12, 13, 21, 22, 23].iter().cloned()));
+}
+
+#[test]
+fn rounding() {
+    let a = Tensor::from(vec![-1.5, -0.25, 0.0, 0.5, 2.5, 2.75]);
+    assert!(a.sign().iter().cloned().eq([-1.0, -1.0, 0.0, 1.0, 1.0, 1.0].iter().cloned()));
+    assert!(a.floor().iter().cloned().eq([-2.0, -1.0, 0.0, 0.0, 2.0, 2.0].iter().cloned()));
+    assert!(a.ceil().iter().cloned().eq([-1.0, -0.0, 0.0, 1.0, 3.0, 3.0].iter().cloned()));
+    assert!(a.round().iter().cloned().eq([-2.0, -0.0, 0.0, 1.0, 3.0, 3.0].iter().cloned()));
+    assert!(a.trunc().iter().cloned().eq([-1.0, -0.0, 0.0, 0.0, 2.0, 2.0].iter().cloned()));
+    assert!(a.fract().iter().cloned().eq([-0.5, -0.25, 0.0, 0.5, 0.5, 0.75].iter().cloned()));
+    assert!(Tensor::from(vec![f64::NAN]).sign()[[0]].is_nan());
+}
+
+#[test]
+fn trigonometric() {
+    let a = Tensor::from(vec![0.0f64, 0.5, 1.0]);
+    assert!(a.sin().iter().cloned().eq(a.iter().map(|x| x.sin())));
+    assert!(a.acos().iter().cloned().eq(a.iter().map(|x| x.acos())));
+    assert!(a.tanh().iter().cloned().eq(a.iter().map(|x| x.tanh())));
+
+    let y = Tensor::from(vec![1.0, -1.0]);
+    let x = Tensor::from_vec(vec![1.0, -1.0], &Shape::from([1, 2].as_ref())).unwrap();
+    let z = y.atan2(&x).unwrap();
+    assert_eq!(z.shape(), &Shape::from([2, 2].as_ref()));
+    assert!(z.iter().cloned().eq([1.0f64.atan2(1.0), (-1.0f64).atan2(1.0), 1.0f64.atan2(-1.0), (-1.0f64).atan2(-1.0)].iter().cloned()));
+}
+
+#[test]
+fn reciprocal() {
+    let a = Tensor::from(vec![4.0, 0.25, 0.0, -0.0]);
+    assert!(a.recip().iter().cloned().eq([0.25, 4.0, f64::INFINITY, f64::NEG_INFINITY].iter().cloned()));
+    assert!(a.rsqrt().iter().cloned().eq([0.5, 2.0, f64::INFINITY, f64::NEG_INFINITY].iter().cloned()));
+}
+
+/// Simple xorshift generator for tests.
+struct TestRng(u64);
+impl Rng for TestRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[test]
+fn dropout() {
+    let mut rng = TestRng(0x853c49e6748fea9b);
+    let a = Tensor::new_filled(&Shape::from([100, 10].as_ref()), 1.0).unwrap();
+    let b = a.dropout(0.25, true, &mut rng).unwrap();
+    assert_eq!(b.shape(), a.shape());
+    assert!(b.iter().all(|&x| x == 0.0 || x == 1.0 / 0.75));
+    let zeros = b.iter().filter(|&&x| x == 0.0).count();
+    assert!(zeros > 200 && zeros < 300);
+
+    assert!(a.dropout(0.25, false, &mut rng).unwrap().iter().all(|&x| x == 1.0));
+    assert!(a.dropout(0.0, true, &mut rng).unwrap().iter().all(|&x| x == 1.0));
+    assert!(matches!(a.dropout(1.0, true, &mut rng), Err(Error::BadArgument(_))));
+    assert!(matches!(a.dropout(-0.5, true, &mut rng), Err(Error::BadArgument(_))));
+    assert!(matches!(a.dropout(1.5, false, &mut rng), Err(Error::BadArgument(_))));
+    assert!(matches!(a.dropout(f64::NAN, false, &mut rng), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn layer_norm() {
+    let a = Tensor::from_2d(&[&[1.0, 2.0, 3.0, 4.0], &[2.0, 2.0, 2.0, 2.0]]).unwrap();
+    let b = a.layer_norm(&[4], None, None, 1e-6).unwrap();
+    assert_eq!(b.shape(), a.shape());
+    let s = (1.25f64 + 1e-6).sqrt();
+    let expected = [-1.5 / s, -0.5 / s, 0.5 / s, 1.5 / s, 0.0, 0.0, 0.0, 0.0];
+    assert!(b.iter().zip(expected.iter()).all(|(x, y)| (x - y).abs() < 1e-12));
+
+    let gamma = Tensor::from(vec![2.0, 2.0, 2.0, 2.0]);
+    let beta = Tensor::from(vec![1.0, 1.0, 1.0, 1.0]);
+    let c = a.layer_norm(&[4], Some(&gamma), Some(&beta), 1e-6).unwrap();
+    assert!(c.iter().zip(b.iter()).all(|(&y, &x)| (y - (2.0 * x + 1.0)).abs() < 1e-12));
+
+    let d = a.layer_norm(&[4, 2], None, None, 1e-6).unwrap();
+    assert!((d.iter().map(|x| x * x).sum::<f64>() - 8.0).abs() < 1e-4);
+
+    assert!(matches!(a.layer_norm(&[3], None, None, 1e-6), Err(Error::ShapeMismatch(..))));
+    assert!(matches!(a.layer_norm(&[4], Some(&beta.reshape(&Shape::from([2, 2].as_ref())).unwrap()), None, 1e-6), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn embedding() {
+    let weight = Tensor::from_2d(&[&[1, 2], &[3, 4], &[5, 6]]).unwrap();
+    let indices = Tensor::from_2d(&[&[2, 0], &[1, 1], &[0, 2]]).unwrap();
+    let a = Tensor::embedding(&weight, &indices).unwrap();
+    assert_eq!(a.shape(), &Shape::from([2, 2, 3].as_ref()));
+    assert!(a.iter().cloned().eq([5, 6, 1, 2, 3, 4, 3, 4, 1, 2, 5, 6].iter().cloned()));
+
+    let b = Tensor::embedding(&weight.transpose(), &Tensor::from(vec![1])).unwrap();
+    assert!(b.iter().cloned().eq([2, 4, 6].iter().cloned()));
+
+    assert!(matches!(Tensor::embedding(&weight, &Tensor::from(vec![3])), Err(Error::BadIndex(_))));
+    let cube = Tensor::new_zeroed(&Shape::from([2, 2, 2].as_ref())).unwrap();
+    assert!(matches!(Tensor::<i32>::embedding(&cube, &indices), Err(Error::BadRank { expected: 2, found: 3 })));
+}
+
+#[test]
+fn half_precision() {
+    let a = Tensor::from(vec![1.0f32, -0.5, 1.0 / 3.0]);
+    let b = a.to_f16();
+    assert_eq!(b[[0]], f16::from_f32(1.0));
+    assert!(b.to_f32().iter().zip(a.iter()).all(|(x, y)| (x - y).abs() < 1e-3));
+}
+
+#[test]
+fn bfloat16() {
+    let a = Tensor::from(vec![1.0f32, -0.5, 1.0 / 3.0, 1e30]);
+    let b = a.to_bf16();
+    assert_eq!(b[[0]], bf16::from_f32(1.0));
+    assert!(b.to_f32().iter().zip(a.iter()).all(|(x, y)| ((x - y) / y).abs() < 1e-2));
+}
+
+#[test]
+fn clone_contiguous() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    let mut b = a.transpose().clone_contiguous().unwrap();
+    assert_eq!(b.shape(), &Shape::from([2, 3].as_ref()));
+    assert!(b.iter().cloned().eq([1, 4, 2, 5, 3, 6].iter().cloned()));
+    b[[0, 0]] = 0;
+    assert_eq!(a[[0, 0]], 1);
+
+    let c = a.clone_contiguous().unwrap();
+    assert!(c.iter().eq(a.iter()));
+}
+
+#[test]
+fn resize() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    let b = a.resize(&[2, 3], 0).unwrap();
+    assert_eq!(b.shape(), &Shape::from([2, 3].as_ref()));
+    assert!(b.iter().cloned().eq([1, 2, 4, 5, 0, 0].iter().cloned()));
+
+    let c = a.transpose().resize(&[1, 4], 9).unwrap();
+    assert!(c.iter().cloned().eq([1, 2, 3, 9].iter().cloned()));
+
+    assert!(a.resize(&[3, 2], 0).unwrap().iter().eq(a.iter()));
+    assert!(matches!(a.resize(&[3], 0), Err(Error::BadRank { expected: 1, found: 2 })));
+}
+
+#[test]
+fn index_select() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    let b = a.index_select(0, &Tensor::from(vec![2, 0])).unwrap();
+    assert_eq!(b.shape(), &Shape::from([2, 2].as_ref()));
+    assert!(b.iter().cloned().eq([3, 1, 6, 4].iter().cloned()));
+
+    let c = a.index_select(1, &Tensor::from(vec![1, 1, 0])).unwrap();
+    assert_eq!(c.shape(), &Shape::from([3, 3].as_ref()));
+    assert!(c.iter().cloned().eq([4, 5, 6, 4, 5, 6, 1, 2, 3].iter().cloned()));
+
+    let d = a.index_select(2, &Tensor::from(vec![0, 0])).unwrap();
+    assert_eq!(d.shape(), &Shape::from([3, 2, 2].as_ref()));
+
+    assert!(matches!(a.index_select(1, &Tensor::from(vec![2])), Err(Error::BadIndex(_))));
+    assert!(matches!(a.index_select(0, &a.map(|x| x as usize)), Err(Error::BadRank { .. })));
+}
+
+#[test]
+fn repeat_interleave() {
+    let a = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    assert!(a.repeat_interleave(2, 0).unwrap().iter().cloned().eq([1, 1, 2, 2, 3, 3, 4, 4].iter().cloned()));
+    let b = a.repeat_interleave(2, 1).unwrap();
+    assert_eq!(b.shape(), &Shape::from([2, 4].as_ref()));
+    assert!(b.iter().cloned().eq([1, 2, 1, 2, 3, 4, 3, 4].iter().cloned()));
+    assert_eq!(a.repeat_interleave(0, 0).unwrap().shape(), &Shape::from([0, 2].as_ref()));
+
+    let c = a.repeat_interleave_by(&Tensor::from(vec![0, 3]), 0).unwrap();
+    assert!(c.iter().cloned().eq([2, 2, 2, 4, 4, 4].iter().cloned()));
+
+    assert!(matches!(a.repeat_interleave(2, 2), Err(Error::BadAxes(_))));
+    assert!(matches!(a.repeat_interleave_by(&Tensor::from(vec![1]), 0), Err(Error::BadSize { .. })));
+    assert!(matches!(a.repeat_interleave_by(&a.map(|x| x as usize), 0), Err(Error::BadRank { .. })));
+}
+
+#[test]
+fn segment_sum() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    let b = a.segment_sum(0, &Tensor::from(vec![1, 0, 1]), 3).unwrap();
+    assert_eq!(b.shape(), &Shape::from([3, 2].as_ref()));
+    assert!(b.iter().cloned().eq([2, 4, 0, 5, 10, 0].iter().cloned()));
+
+    let c = a.segment_sum(1, &Tensor::from(vec![0, 0]), 1).unwrap();
+    assert!(c.iter().cloned().eq([5, 7, 9].iter().cloned()));
+
+    assert!(matches!(a.segment_sum(0, &Tensor::from(vec![0, 3, 1]), 3), Err(Error::BadIndex(_))));
+    assert!(matches!(a.segment_sum(0, &Tensor::from(vec![0, 1]), 3), Err(Error::BadSize { .. })));
+    assert!(matches!(a.segment_sum(0, &a.map(|x| x as usize), 3), Err(Error::BadRank { .. })));
+}
+
+#[test]
+fn masked_reduce() {
+    let a = Tensor::from_2d(&[&[1.0f64, 2.0, 3.0], &[4.0, 5.0, 6.0]]).unwrap();
+    let mask = Tensor::from_2d(&[&[1, 1, 0], &[1, 0, 0]]).unwrap();
+    let s = a.masked_sum(&mask, 0, false).unwrap();
+    assert_eq!(s.shape(), &Shape::from([2].as_ref()));
+    assert!(s.iter().cloned().eq([3.0, 4.0].iter().cloned()));
+    let m = a.masked_mean(&mask, 0, false).unwrap();
+    assert!(m.iter().cloned().eq([1.5, 4.0].iter().cloned()));
+
+    let col = Tensor::from(vec![0, 1, 1]);
+    assert!(a.masked_sum(&col, 1, false).unwrap().iter().cloned().eq([0.0, 7.0, 9.0].iter().cloned()));
+    let m = a.masked_mean(&col, 1, false).unwrap();
+    assert!(m[[0]].is_nan());
+    assert!(m.iter().skip(1).cloned().eq([3.5, 4.5].iter().cloned()));
+
+    assert!(matches!(a.masked_sum(&Tensor::from(vec![1, 1]), 0, false), Err(Error::ShapeMismatch(..))));
+    let big = Tensor::from_vec(vec![1; 12], &Shape::from([3, 2, 2].as_ref())).unwrap();
+    assert!(matches!(a.masked_sum(&big, 0, false), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn sync_tensor() {
+    let a = SyncHostTensor::from(Tensor::from(vec![1, 2, 3, 4]));
+    let b = a.reshape(&Shape::from([2, 2].as_ref())).unwrap();
+    let handle = std::thread::spawn(move || {
+        let mut dst = vec![0; 4];
+        b.transpose().load(&mut dst).unwrap();
+        dst
+    });
+    assert_eq!(handle.join().unwrap(), vec![1, 3, 2, 4]);
+    let c = a.into_host();
+    assert!(c.iter().cloned().eq(1..5));
+}
+
+#[test]
+fn equality() {
+    use std::collections::HashSet;
+    let a = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    let b = Tensor::from_2d(&[&[1, 3], &[2, 4]]).unwrap().transpose();
+    assert!(a == b);
+    assert!(a != a.reshape(&Shape::from([4].as_ref())).unwrap());
+    let mut set = HashSet::new();
+    set.insert(a);
+    assert!(set.contains(&b));
+
+    let nan = Tensor::from(vec![1.0, f64::NAN]);
+    assert!(nan != nan.clone());
+}
+
+#[test]
+fn accumulate() {
+    let mut g = Tensor::from(vec![1, 2, 3]);
+    g.accumulate(&Tensor::from_2d(&[&[1, 1, 1], &[10, 20, 30]]).unwrap()).unwrap();
+    assert!(g.iter().cloned().eq([12, 23, 34].iter().cloned()));
+
+    let mut m = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    m.accumulate(&Tensor::from_2d(&[&[10], &[20]]).unwrap()).unwrap();
+    assert!(m.iter().cloned().eq([11, 12, 23, 24].iter().cloned()));
+
+    assert!(matches!(g.accumulate(&Tensor::from(vec![1, 2])), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn copy_from() {
+    let mut a = Tensor::new_zeroed(&Shape::from([2, 2].as_ref())).unwrap();
+    let b = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    a.copy_from(&b.transpose()).unwrap();
+    assert!(a.iter().cloned().eq([1, 3, 2, 4].iter().cloned()));
+    let c = a.clone();
+    a.copy_from(&b).unwrap();
+    assert!(a == b && c != b);
+    assert!(matches!(a.copy_from(&Tensor::from(vec![1, 2])), Err(Error::ShapeMismatch(..))));
+
+    // Elements of a broadcasted destination are separated, so each of them gets its own value.
+    let mut d = Tensor::from(vec![0]).broadcast_to(&Shape::from([3])).unwrap();
+    d.copy_from(&Tensor::from(vec![4, 5, 6])).unwrap();
+    assert!(d == Tensor::from(vec![4, 5, 6]));
+    let mut d = Tensor::from(vec![0]).broadcast_to(&Shape::from([3])).unwrap();
+    d.copy_from(&Tensor::from(vec![4, 5, 6, 7]).slice(&[Index::Range(Range { start: 0, end: 3, step: 1 })]).unwrap()).unwrap();
+    assert!(d == Tensor::from(vec![4, 5, 6]));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn reader_writer() {
+    let a = Tensor::from_2d(&[&[1i16, -2, 3], &[4, 5, -6]]).unwrap().transpose();
+    let mut bytes = Vec::new();
+    a.store_to_writer(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 12);
+    assert_eq!(bytes[..4], [1, 0, 4, 0]);
+    bytes.push(0xFF);
+    let mut reader = bytes.as_slice();
+    let b = Tensor::<i16>::load_from_reader(&mut reader, a.shape()).unwrap();
+    assert!(a == b);
+    assert_eq!(a.checksum().unwrap(), b.checksum().unwrap());
+    assert_eq!(reader, [0xFF]);
+
+    let mut short = &bytes[..5];
+    let r = Tensor::<f32>::load_from_reader(&mut short, &Shape::from([2].as_ref()));
+    assert!(matches!(r, Err(Error::BadSize { expected: 8, found: 5 })));
+
+    // Data longer than a chunk is written and read without losing elements at chunk boundaries.
+    let len = 2 * crate::CHUNK_LEN + 5;
+    let a = Tensor::from((0..len as u32).collect::<Vec<_>>());
+    let mut bytes = Vec::new();
+    a.store_to_writer(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 4 * len);
+    let b = Tensor::<u32>::load_from_reader(&mut bytes.as_slice(), a.shape()).unwrap();
+    assert!(a == b);
+    assert_eq!(a.checksum().unwrap(), Tensor::from(bytes).checksum().unwrap());
+}
+
+#[test]
+fn matmul() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    let b = Tensor::from_2d(&[&[1, 0], &[0, 1], &[1, 1]]).unwrap();
+    let c = a.matmul(&b).unwrap();
+    assert!(c == Tensor::from_2d(&[&[4, 5], &[10, 11]]).unwrap());
+    let err = a.matmul(&a).err().unwrap();
+    assert!(matches!(err.root(), Error::ShapeMismatch(..)));
+    assert!(err.to_string().starts_with("matmul: ShapeMismatch between"));
+
+    let k = 4096;
+    let x = Tensor::from_vec(vec![f16::from_f32(1.0); k], &Shape::from([k].as_ref())).unwrap();
+    let y = x.reshape(&Shape::from([1, k].as_ref())).unwrap();
+    // Half precision sum stops growing at 2048, but single precision one is exact.
+    assert_eq!(f32::from(x.matmul_mixed(&y).unwrap()[[0]]), 4096.0);
+}
+
+#[test]
+fn matmul_blocked() {
+    let (m, k, n) = (37, 70, 29);
+    let a = Tensor::from_vec((0..(m * k)).map(|i| (i % 13) as i64 - 6).collect(), &Shape::from([k, m].as_ref())).unwrap();
+    let b = Tensor::from_vec((0..(k * n)).map(|i| (i % 7) as i64 - 3).collect(), &Shape::from([n, k].as_ref())).unwrap();
+    let c = a.matmul_blocked(&b, k + n + m).unwrap();
+    for &block in [1, 5, 16, crate::MATMUL_BLOCK].iter() {
+        assert!(a.matmul_blocked(&b, block).unwrap() == c);
+    }
+    // Transposed views are handled too.
+    let ct = b.transpose().matmul(&a.transpose()).unwrap();
+    assert!(ct == c.transpose());
+    assert!(matches!(a.matmul_blocked(&b, 0), Err(Error::Context { op: "matmul_blocked", .. })));
+    assert!(matches!(a.matmul_blocked(&b, 0).err().unwrap().root(), Error::BadArgument(_)));
+}
+
+/// Naive product accumulating in the opposite order to the blocked one, as an external library could do.
+fn naive_gemm<T: crate::Num + Copy>(m: usize, k: usize, n: usize, a: &[T], b: &[T], c: &mut [T]) {
+    assert!(a.len() == m * k && b.len() == k * n && c.len() == m * n);
+    for (i, y) in c.iter_mut().enumerate() {
+        let (r, q) = (i / n, i % n);
+        *y = (0..k).rev().fold(T::zero(), |s, l| s + a[l + k * r] * b[q + n * l]);
+    }
+}
+
+#[test]
+fn matmul_gemm_hook() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let (m, k, n) = (70, 65, 67);
+    let a = Tensor::from_vec((0..(m * k)).map(|i| ((i % 13) as f32 - 6.0) / 7.0).collect(), &Shape::from([k, m].as_ref())).unwrap();
+    let b = Tensor::from_vec((0..(k * n)).map(|i| ((i % 11) as f32 - 5.0) / 3.0).collect(), &Shape::from([n, k].as_ref())).unwrap();
+    let reference = a.matmul_blocked(&b, crate::MATMUL_BLOCK).unwrap();
+    let c = a.matmul_hooked(&b, Some(naive_gemm), 0).unwrap();
+    assert_eq!(c.shape(), reference.shape());
+    assert!(c.iter().zip(reference.iter()).all(|(&x, &y)| (x - y).abs() <= 1e-4 * (1.0 + y.abs())));
+
+    // Products below the threshold aren't passed to the hook, integer values are summed exactly in any order.
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    fn counted(m: usize, k: usize, n: usize, a: &[f64], b: &[f64], c: &mut [f64]) {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        naive_gemm(m, k, n, a, b, c);
+    }
+    let (a, b) = (a.map(|x| (x * 7.0).round() as f64), b.map(|x| (x * 3.0).round() as f64));
+    let reference = a.matmul_blocked(&b, crate::MATMUL_BLOCK).unwrap();
+    assert!(a.matmul_hooked(&b, Some(counted), m * k * n + 1).unwrap() == reference);
+    assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+    assert!(a.matmul_hooked(&b, Some(counted), m * k * n).unwrap() == reference);
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+    // Only `f32` and `f64` products are computed by hooks.
+    assert!(super::matmul::gemm_hook::<i32>().is_none());
+}
+
+#[test]
+fn matmul_edge_shapes() {
+    // Dot product, outer product, matrix-vector and vector-matrix products, empty and large asymmetric matrices.
+    let cases = [(1, 9, 1), (7, 1, 5), (6, 4, 1), (1, 4, 6), (1, 1, 1), (0, 3, 2), (2, 0, 3), (3, 2, 0), (67, 3, 129), (5, 300, 2)];
+    for &(m, k, n) in cases.iter() {
+        let a = Tensor::from_vec((0..(m * k)).map(|i| (i % 11) as i64 - 5).collect(), &Shape::from([k, m])).unwrap();
+        let b = Tensor::from_vec((0..(k * n)).map(|i| (i % 7) as i64 - 3).collect(), &Shape::from([n, k])).unwrap();
+        let reference = Tensor::from_vec((0..(m * n)).map(|i| {
+            (0..k).map(|l| a[[l, i / n]] * b[[i % n, l]]).sum::<i64>()
+        }).collect(), &Shape::from([n, m])).unwrap();
+        let c = a.matmul(&b).unwrap();
+        assert_eq!(c.shape(), &Shape::from([n, m]), "{:?}", (m, k, n));
+        assert!(c == reference, "{:?}", (m, k, n));
+        assert!(a.matmul_blocked(&b, 2).unwrap() == reference, "{:?}", (m, k, n));
+        let (at, bt) = (a.transpose().clone_contiguous().unwrap(), b.transpose().clone_contiguous().unwrap());
+        assert!(at.matmul_flags(&bt, true, true).unwrap() == reference, "{:?}", (m, k, n));
+        assert!(bt.matmul(&at).unwrap() == reference.transpose(), "{:?}", (m, k, n));
+    }
+}
+
+#[test]
+fn count_nonzero() {
+    let a = Tensor::from_2d(&[&[0.0, 1.0, -0.0], &[f64::NAN, 2.0, 3.0]]).unwrap();
+    assert!(a.count_nonzero(Some(0), false).unwrap().iter().cloned().eq([1, 3].iter().cloned()));
+    assert!(a.count_nonzero(Some(1), false).unwrap().iter().cloned().eq([1, 2, 1].iter().cloned()));
+    let total = a.count_nonzero(None, false).unwrap();
+    assert!(total.shape().is_empty() && total[[]] == 4);
+
+    let m = Tensor::from_2d(&[&[1u8, 1], &[0, 2]]).unwrap();
+    assert!(m.all(Some(0), false).unwrap().iter().cloned().eq([true, false].iter().cloned()));
+    assert!(m.any(Some(1), false).unwrap().iter().cloned().eq([true, true].iter().cloned()));
+    assert!(!m.all(None, false).unwrap()[[]]);
+    let empty = Tensor::<u8>::new_zeroed(&Shape::from([0].as_ref())).unwrap();
+    assert!(empty.all(None, false).unwrap()[[]] && !empty.any(None, false).unwrap()[[]]);
+}
+
+#[test]
+fn clip_grad_norm() {
+    let mut grads = vec![Tensor::from(vec![3.0f64, 0.0]), Tensor::from(vec![0.0, 4.0])];
+    assert_eq!(Tensor::clip_grad_norm(&mut grads, 10.0).unwrap(), 5.0);
+    assert_eq!(grads[1][[1]], 4.0);
+    assert_eq!(Tensor::clip_grad_norm(&mut grads, 1.0).unwrap(), 5.0);
+    assert!((grads[0][[0]] - 0.6).abs() < 1e-12 && (grads[1][[1]] - 0.8).abs() < 1e-12);
+
+    let mut zeros = vec![Tensor::from(vec![0.0f64; 3])];
+    assert_eq!(Tensor::clip_grad_norm(&mut zeros, 0.0).unwrap(), 0.0);
+    assert!(zeros[0].iter().all(|&x| x == 0.0));
+    assert!(matches!(Tensor::clip_grad_norm(&mut grads, -1.0), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn slice() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    let row = a.slice(&[Index::Range(Range { start: 0, end: 3, step: 1 }), Index::Single(-1)]).unwrap();
+    assert!(row == Tensor::from(vec![4, 5, 6]));
+    let col = a.slice(&[Index::Single(1)]).unwrap();
+    assert!(col == Tensor::from(vec![2, 5]));
+
+    let rev = a.slice(&[Index::Range(Range { start: -1, end: isize::MIN, step: -2 })]).unwrap();
+    assert_eq!(rev.shape(), &Shape::from([2, 2].as_ref()));
+    assert!(rev.iter().cloned().eq([3, 1, 6, 4].iter().cloned()));
+    let empty = a.slice(&[Index::Range(Range { start: 2, end: 1, step: 1 })]).unwrap();
+    assert_eq!(empty.shape().content(), 0);
+
+    let b = a.slice(&[Index::NewAxis, Index::Range(Range { start: 1, end: 100, step: 1 })]).unwrap();
+    assert_eq!(b.shape(), &Shape::from([1, 2, 2].as_ref()));
+    assert!(b.iter().cloned().eq([2, 3, 5, 6].iter().cloned()));
+
+    assert!(matches!(a.slice(&[Index::Single(3)]), Err(Error::BadIndex(pos)) if pos == [3]));
+    assert!(matches!(a.slice(&[Index::Range(Range::new(None, None, 1)), Index::Single(-5)]), Err(Error::BadIndex(pos)) if pos == [0, 5]));
+    assert!(a.slice(&[Index::Single(-3)]).unwrap() == Tensor::from(vec![1, 4]));
+    assert!(matches!(a.slice(&[Index::Range(Range { start: 0, end: 1, step: 0 })]), Err(Error::BadArgument(_))));
+    assert!(matches!(a.slice(&[Index::Range(Range::new(None, None, 0))]), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn generic() {
+    fn fill_prefix<T: crate::Tensor<i32>>(t: &T) -> Result<Vec<i32>, Error> {
+        let mut t = t.clone_contiguous()?;
+        t.fill(7)?;
+        let mut v = t.to_vec()?;
+        v.truncate(t.numel() / 2);
+        Ok(v)
+    }
+    let a = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    assert_eq!(fill_prefix(&a).unwrap(), vec![7, 7]);
+    assert_eq!(fill_prefix(&SyncHostTensor::from(a.clone())).unwrap(), vec![7, 7]);
+    assert_eq!(a.to_vec().unwrap(), vec![1, 2, 3, 4]);
+    assert_eq!(a.context(), &());
+}
+
+#[test]
+fn as_slice() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    assert_eq!(a.as_slice().unwrap(), &[1, 2, 3, 4, 5, 6]);
+    let col = a.slice(&[Index::Single(1)]).unwrap();
+    assert!(col.as_slice().is_none());
+    let last = a.slice(&[Index::Range(Range { start: 0, end: 3, step: 1 }), Index::Single(1)]).unwrap();
+    assert_eq!(last.as_slice().unwrap(), &[4, 5, 6]);
+
+    let mut b = a.clone();
+    b.as_mut_slice().unwrap()[0] = 0;
+    assert_eq!(a.as_slice().unwrap()[0], 1);
+    assert_eq!(b.as_slice().unwrap()[0], 0);
+    let mut t = a.transpose();
+    assert_eq!(t.as_mut_slice().unwrap(), &[1, 4, 2, 5, 3, 6]);
+}
+
+#[test]
+fn reshape_infer() {
+    let a = Tensor::from_vec((0..12).collect(), &Shape::from([4, 3])).unwrap();
+    let b = a.reshape_infer(&[2, -1]).unwrap();
+    assert_eq!(b.shape(), &Shape::from([2, 6]));
+    assert!(b.iter().cloned().eq(0..12));
+    assert_eq!(a.transpose().reshape_infer(&[-1]).unwrap().shape(), &Shape::from([12]));
+    assert_eq!(a.reshape_infer(&[3, 4]).unwrap().shape(), &Shape::from([3, 4]));
+
+    assert!(matches!(a.reshape_infer(&[5, -1]), Err(Error::BadArgument(_))));
+    assert!(matches!(a.reshape_infer(&[-1, -1]), Err(Error::BadArgument(_))));
+    assert!(matches!(a.reshape_infer(&[-2, 6]), Err(Error::BadArgument(_))));
+    assert!(matches!(a.reshape_infer(&[0, -1]), Err(Error::BadArgument(_))));
+    assert!(matches!(a.reshape_infer(&[5, 2]), Err(Error::BadSize { .. })));
+}
+
+#[test]
+fn swapaxes() {
+    let a = Tensor::from_vec((0..24).collect(), &Shape::from([2, 3, 4])).unwrap();
+    let b = a.swapaxes(0, 2).unwrap();
+    assert_eq!(b.shape(), &Shape::from([4, 3, 2]));
+    assert_eq!(b[[3, 1, 0]], a[[0, 1, 3]]);
+    assert!(a.swapaxes(1, 0).unwrap() == a.transpose());
+    assert_eq!(Tensor::from(vec![1, 2]).swapaxes(0, 1).unwrap().shape(), &Shape::from([1, 2]));
+
+    let c = a.moveaxis(0, 2).unwrap();
+    assert_eq!(c.shape(), &Shape::from([3, 4, 2]));
+    assert_eq!(c[[2, 3, 1]], a[[1, 2, 3]]);
+    assert!(c.moveaxis(2, 0).unwrap() == a);
+    assert!(matches!(a.swapaxes(0, 3), Err(Error::BadAxes(_))));
+    assert!(matches!(a.moveaxis(3, 0), Err(Error::BadAxes(_))));
+}
+
+#[test]
+fn expand_dims() {
+    let a = Tensor::from_vec((0..6).collect(), &Shape::from([2, 3])).unwrap();
+    let b = a.expand_dims(&[2, 0]).unwrap();
+    assert_eq!(b.shape().as_slice(), &[1, 2, 1, 3]);
+    assert_eq!(b[[0, 1, 0, 2]], a[[1, 2]]);
+    assert_eq!(a.transpose().expand_dims(&[1]).unwrap().shape().as_slice(), &[3, 1, 2]);
+    assert!(a.expand_dims(&[]).unwrap() == a);
+    assert!(matches!(a.expand_dims(&[1, 1]), Err(Error::BadAxes(_))));
+    assert!(matches!(a.expand_dims(&[3]), Err(Error::BadAxes(_))));
+}
+
+#[test]
+fn isnan() {
+    let a = Tensor::from(vec![1.0, f64::NAN, f64::INFINITY, -f64::INFINITY, -0.0]);
+    assert!(a.isnan().iter().cloned().eq([0, 1, 0, 0, 0].iter().cloned()));
+    assert!(a.isinf().iter().cloned().eq([0, 0, 1, 1, 0].iter().cloned()));
+    assert!(a.isfinite().iter().cloned().eq([1, 0, 0, 0, 1].iter().cloned()));
+    assert!(a.any_nan());
+    assert!(!a.isfinite().map(f64::from).any_nan());
+}
+
+#[test]
+fn nan_to_num() {
+    let a = Tensor::from(vec![1.5f32, f32::NAN, f32::INFINITY, -f32::INFINITY]);
+    assert!(a.nan_to_num(-1.0, 10.0, -10.0).iter().cloned().eq([1.5, -1.0, 10.0, -10.0].iter().cloned()));
+    assert!(a.nan_to_num_default().iter().cloned().eq([1.5, 0.0, f32::MAX, f32::MIN].iter().cloned()));
+    assert!(!a.nan_to_num_default().any_nan());
+}
+
+#[test]
+fn copysign_hypot() {
+    let a = Tensor::from(vec![3.0f64, -5.0, 0.0]);
+    let s = Tensor::from(vec![-1.0f64]);
+    let c = a.copysign(&s).unwrap();
+    assert!(c.iter().cloned().eq([-3.0, -5.0, -0.0].iter().cloned()));
+    assert!(c[[2]].is_sign_negative());
+    let b = Tensor::from(vec![4.0f64, 12.0, 1e300]);
+    assert!(a.hypot(&b).unwrap().iter().cloned().eq([5.0, 13.0, 1e300].iter().cloned()));
+    assert!(matches!(a.hypot(&Tensor::from(vec![1.0, 2.0])), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn sparse() {
+    let a = Tensor::from_2d(&[&[0, 2, 0], &[3, 0, 0]]).unwrap();
+    let s = a.to_sparse();
+    assert_eq!(s.nnz(), 2);
+    assert!(s.indices().iter().cloned().eq([1, 0, 0, 1].iter().cloned()));
+    assert!(s.values().iter().cloned().eq([2, 3].iter().cloned()));
+    assert!(s.to_dense() == a);
+    assert!(a.transpose().to_sparse().to_dense() == a.transpose());
+
+    let indices = Tensor::from_2d(&[&[2, 1], &[2, 1], &[0, 0]]).unwrap();
+    let s = SparseTensor::new(&Shape::from([3, 2]), indices, Tensor::from(vec![1, 4, 5])).unwrap();
+    assert!(s.to_dense() == Tensor::from_2d(&[&[5, 0, 0], &[0, 0, 5]]).unwrap());
+
+    let bad = Tensor::from_2d(&[&[3, 0]]).unwrap();
+    assert!(matches!(SparseTensor::new(&Shape::from([3, 2]), bad, Tensor::from(vec![1])), Err(Error::BadIndex(_))));
+    let bad = Tensor::from(vec![0, 0]);
+    assert!(matches!(SparseTensor::new(&Shape::from([3, 2]), bad, Tensor::from(vec![1, 2])), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn unfold() {
+    let a = Tensor::from_vec((0..30).collect(), &Shape::from([10, 3])).unwrap();
+    let w = a.unfold(0, 4, 2).unwrap();
+    assert_eq!(w.shape(), &Shape::from([4, 3, 4]));
+    assert!((0..4).all(|i| (0..4).all(|j| w[[i, 1, j]] == a[[2 * i + j, 1]])));
+    let frames = Tensor::from(vec![1, 2, 3, 4, 5]).unfold(0, 2, 3).unwrap();
+    assert_eq!(frames.shape(), &Shape::from([2, 2]));
+    assert!(frames.iter().cloned().eq([1, 4, 2, 5].iter().cloned()));
+    // Windows of the rows.
+    assert_eq!(a.unfold(1, 3, 1).unwrap().shape(), &Shape::from([10, 1, 3]));
+
+    assert!(matches!(a.unfold(1, 4, 1), Err(Error::BadArgument(_))));
+    assert!(matches!(a.unfold(0, 0, 1), Err(Error::BadArgument(_))));
+    assert!(matches!(a.unfold(0, 2, 0), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn reinterpret() {
+    let a = Tensor::from(vec![-1i32, 0, 1]);
+    let ptr = a.as_slice().unwrap().as_ptr() as usize;
+    let b = unsafe { a.reinterpret::<u32>() }.unwrap();
+    assert!(b.iter().cloned().eq([u32::MAX, 0, 1].iter().cloned()));
+    assert_eq!(b.as_slice().unwrap().as_ptr() as usize, ptr);
+
+    let f = Tensor::from(vec![1.0f32, -2.0]).transpose();
+    let bits = unsafe { f.clone().reinterpret::<u32>() }.unwrap();
+    assert!(bits.iter().cloned().eq([0x3f800000, 0xc0000000].iter().cloned()));
+    assert!(f.transpose() == Tensor::from(vec![1.0f32, -2.0]));
+    assert!(matches!(unsafe { bits.reinterpret::<u8>() }, Err(Error::BadSize { expected: 4, found: 1 })));
+}
+
+#[test]
+fn keepdims() {
+    let a = Tensor::from_vec((0..6).map(f64::from).collect(), &Shape::from([3, 2])).unwrap();
+    let s = a.sum_axis(0, true);
+    assert_eq!(s.shape(), &Shape::from([1, 2]));
+    assert!(s.iter().cloned().eq([3.0, 12.0].iter().cloned()));
+    assert_eq!(a.sum_axis(0, false).shape(), &Shape::from([2]));
+    // Normalization over the first axis broadcasts the kept sum back.
+    let n = a.zip_map(&s, |x, y| x / y).unwrap();
+    assert!(n.sum_axis(0, false).iter().all(|&x| (x - 1.0).abs() < 1e-12));
+
+    let m = a.mean_axis(1, true);
+    assert_eq!(m.shape(), &Shape::from([3]));
+    assert!(m.iter().cloned().eq([1.5, 2.5, 3.5].iter().cloned()));
+    let mask = Tensor::from(vec![1u8, 0, 1]);
+    assert_eq!(a.masked_sum(&mask, 0, true).unwrap().shape(), &Shape::from([1, 2]));
+    assert_eq!(a.count_nonzero(Some(0), true).unwrap().shape(), &Shape::from([1, 2]));
+    assert_eq!(mask.all(Some(0), true).unwrap().shape(), &Shape::from([]));
+}
+
+#[test]
+fn sum_axes() {
+    let a = Tensor::from_vec((0..24).map(f64::from).collect(), &Shape::from([2, 3, 4])).unwrap();
+    let s = a.sum_axes(&[2, 0], false).unwrap();
+    assert!(s == a.sum_axis(2, false).sum_axis(0, false));
+    assert_eq!(s.shape(), &Shape::from([3]));
+    let m = a.mean_axes(&[0, 2], true).unwrap();
+    assert_eq!(m.shape(), &Shape::from([1, 3]));
+    assert!(m.iter().cloned().eq([9.5, 11.5, 13.5].iter().cloned()));
+    assert!(a.sum_axes(&[], false).unwrap() == a);
+    assert!(matches!(a.sum_axes(&[1, 1], false), Err(Error::BadAxes(_))));
+}
+
+#[test]
+fn builder() {
+    let a: Tensor<f32> = TensorBuilder::new(&[2, 3]).filled(1.5).build().unwrap();
+    assert_eq!(a.shape(), &Shape::from([2, 3]));
+    assert!(a.iter().all(|&x| x == 1.5));
+    let b = TensorBuilder::new(&[2]).location(&()).data(vec![1, 2]).build::<SyncHostTensor<i32>>().unwrap();
+    assert!(b.into_host() == Tensor::from(vec![1, 2]));
+    assert!(TensorBuilder::<u8>::new(&[3]).build::<Tensor<_>>().unwrap().iter().all(|&x| x == 0));
+
+    let r = TensorBuilder::new(&[3]).data(vec![1, 2]).build::<Tensor<i32>>();
+    assert!(matches!(r, Err(Error::BadSize { expected: 3, found: 2 })));
+    let r = TensorBuilder::new(&[2]).data(vec![1, 2]).filled(0).build::<Tensor<i32>>();
+    assert!(matches!(r, Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn like() {
+    let a = Tensor::from_vec((0..6).collect(), &Shape::from([2, 3])).unwrap().transpose();
+    let z = a.zeros_like().unwrap();
+    assert_eq!(z.shape(), a.shape());
+    assert!(z.iter().all(|&x| x == 0));
+    assert!(a.ones_like().unwrap().iter().all(|&x| x == 1));
+    assert!(a.full_like(7).unwrap() == Tensor::new_filled_in(&(), &Shape::from([3, 2]), 7).unwrap());
+
+    let mut scratch = a.empty_like().unwrap();
+    assert_eq!(scratch.shape(), a.shape());
+    scratch.copy_from(&a).unwrap();
+    assert!(scratch == a);
+}
+
+#[test]
+fn buffer_refcount() {
+    let a = Tensor::from(vec![1, 2, 3]);
+    assert_eq!(a.buffer_refcount(), 1);
+    let mut b = a.clone();
+    let t = a.transpose();
+    assert_eq!(a.buffer_refcount(), 3);
+    b.iter_mut().for_each(|x| *x += 1);
+    assert_eq!((a.buffer_refcount(), b.buffer_refcount()), (2, 1));
+    drop(t);
+    assert_eq!(SyncHostTensor::from(a).buffer_refcount(), 1);
+}
+
+#[test]
+fn split_at() {
+    let a = Tensor::from_vec((0..12).collect(), &Shape::from([3, 4])).unwrap();
+    let (q, kv) = a.split_at(1, 1).unwrap();
+    assert_eq!((q.shape(), kv.shape()), (&Shape::from([3]), &Shape::from([3, 3])));
+    assert!(q.iter().cloned().eq(0..3));
+    assert!(kv.iter().cloned().eq(3..12));
+    let (l, r) = a.split_at(0, 3).unwrap();
+    assert!(l == a && r.shape().content() == 0);
+    assert_eq!(a.split_at(0, 2).unwrap().1.shape(), &Shape::from([1, 4]));
+    assert!(matches!(a.split_at(1, 5), Err(Error::BadIndex(_))));
+}
+
+#[test]
+fn unflatten() {
+    let a = Tensor::from_vec((0..120).collect(), &Shape::from([2, 3, 4, 5])).unwrap();
+    let f = a.flatten_range(1, 3).unwrap();
+    assert_eq!(f.shape(), &Shape::from([2, 12, 5]));
+    assert!(f.iter().cloned().eq(0..120));
+    let u = f.unflatten(1, &[3, 4]).unwrap();
+    assert!(u == a);
+    assert_eq!(a.flatten_range(0, 4).unwrap().shape(), &Shape::from([120]));
+    assert_eq!(a.transpose().flatten_range(2, 2).unwrap().shape(), &Shape::from([3, 2, 1, 4, 5]));
+
+    assert!(matches!(f.unflatten(1, &[5, 2]), Err(Error::BadSize { expected: 12, found: 10 })));
+    assert!(matches!(f.unflatten(1, &[usize::MAX, 2, 0]), Err(Error::Overflow)));
+    assert!(matches!(a.flatten_range(2, 1), Err(Error::BadAxes(_))));
+}
+
+#[test]
+fn add_bias() {
+    let x = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    let b = Tensor::from(vec![10, 20, 30]);
+    let y = x.add_bias(&b).unwrap();
+    assert!(y == Tensor::from_2d(&[&[11, 22, 33], &[14, 25, 36]]).unwrap());
+    assert!(y == x.zip_map(&b, |x, b| x + b).unwrap());
+    assert!(x.transpose().add_bias(&Tensor::from(vec![1, 2])).unwrap() == Tensor::from_2d(&[&[2, 6], &[3, 7], &[4, 8]]).unwrap());
+
+    assert!(matches!(x.add_bias(&Tensor::from(vec![1, 2])), Err(Error::ShapeMismatch(..))));
+    assert!(matches!(x.add_bias(&x), Err(Error::BadRank { .. })));
+}
+
+#[test]
+fn interpolate2d() {
+    let a = Tensor::from_2d(&[&[1.0, 2.0], &[3.0, 4.0]]).unwrap();
+    let n = a.interpolate2d(4, 4, InterpMode::Nearest).unwrap();
+    assert_eq!(n.shape(), &Shape::from([4, 4]));
+    assert!(n.iter().cloned().eq([1.0, 1.0, 2.0, 2.0].iter().cycle().take(8).chain([3.0, 3.0, 4.0, 4.0].iter().cycle().take(8)).cloned()));
+    let b = a.interpolate2d(1, 4, InterpMode::Bilinear).unwrap();
+    assert!(b.iter().cloned().eq([2.0, 2.25, 2.75, 3.0].iter().cloned()));
+    assert!(a.interpolate2d(2, 2, InterpMode::Bilinear).unwrap() == a);
+
+    let batch = Tensor::new_filled_in(&(), &Shape::from([2, 2, 3, 2]), 1.0f32).unwrap();
+    assert_eq!(batch.interpolate2d(5, 3, InterpMode::Bilinear).unwrap().shape(), &Shape::from([3, 5, 3, 2]));
+    let big = Tensor::<f32>::new_zeroed_in(&(), &Shape::from([1, 1, 1, 1, 2])).unwrap();
+    assert!(matches!(big.interpolate2d(1, 1, InterpMode::Nearest), Err(Error::BadRank { .. })));
+}
+
+#[test]
+fn fft() {
+    let a = Tensor::from_2d(&[&[1.0f64, 2.0, 3.0, 4.0], &[1.0, 0.0, 0.0, 0.0]]).unwrap();
+    let (re, im) = a.fft(0).unwrap();
+    assert!(re.iter().zip([10.0, -2.0, -2.0, -2.0, 1.0, 1.0, 1.0, 1.0].iter()).all(|(x, y)| (x - y).abs() < 1e-12));
+    assert!(im.iter().zip([0.0, 2.0, 0.0, -2.0, 0.0, 0.0, 0.0, 0.0].iter()).all(|(x, y)| (x - y).abs() < 1e-12));
+    let (b, zero) = re.ifft(&im, 0).unwrap();
+    assert!(b.iter().zip(a.iter()).all(|(x, y)| (x - y).abs() < 1e-12));
+    assert!(zero.iter().all(|x| x.abs() < 1e-12));
+
+    // Circular convolution with a shifted delta is a rotation.
+    let x = Tensor::from_vec(vec![1.0f64, 2.0, 3.0, 4.0], &Shape::from([4])).unwrap();
+    let delta = Tensor::from_vec(vec![0.0f64, 1.0, 0.0, 0.0], &Shape::from([4])).unwrap();
+    let ((xre, xim), (dre, dim)) = (x.fft(0).unwrap(), delta.fft(0).unwrap());
+    let cre = xre.zip_map(&dre, |a, b| a * b).unwrap().zip_map(&xim.zip_map(&dim, |a, b| a * b).unwrap(), |a, b| a - b).unwrap();
+    let cim = xre.zip_map(&dim, |a, b| a * b).unwrap().zip_map(&xim.zip_map(&dre, |a, b| a * b).unwrap(), |a, b| a + b).unwrap();
+    let (c, _) = cre.ifft(&cim, 0).unwrap();
+    assert!(c.iter().zip([4.0, 1.0, 2.0, 3.0].iter()).all(|(x, y)| (x - y).abs() < 1e-12));
+
+    assert_eq!(a.fft(1).unwrap().0.shape(), a.shape());
+    assert!(matches!(Tensor::from_2d(&[&[1.0, 2.0, 3.0]]).unwrap().fft(0), Err(Error::BadArgument(_))));
+    assert!(matches!(a.ifft(&delta, 0), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn complex() {
+    let re = Tensor::from_vec(vec![3.0f64, 0.0, -1.0], &Shape::from([3])).unwrap();
+    let im = Tensor::from_vec(vec![4.0f64, 1.0, 0.0], &Shape::from([3])).unwrap();
+    let z = Tensor::from_parts(&re, &im).unwrap();
+    assert!(z.real() == re && z.imag() == im);
+    assert!(z.conj().imag().iter().cloned().eq([-4.0, -1.0, 0.0].iter().cloned()));
+    assert!(z.abs().iter().cloned().eq([5.0, 1.0, 1.0].iter().cloned()));
+    assert!(z.mul(&z.conj()).unwrap().real() == z.abs().map(|x| x * x));
+    let i = Tensor::from_vec(vec![Complex::new(0.0, 1.0)], &Shape::from([1])).unwrap();
+    assert!(z.mul(&i).unwrap().iter().cloned().eq([Complex::new(-4.0, 3.0), Complex::new(-1.0, 0.0), Complex::new(0.0, -1.0)].iter().cloned()));
+    assert!(z.add(&z).unwrap().real().iter().cloned().eq([6.0, 0.0, -2.0].iter().cloned()));
+    assert!(matches!(Tensor::from_parts(&re, &Tensor::new_zeroed(&Shape::from([2])).unwrap()), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn meshgrid() {
+    let (x, y) = (Tensor::from(vec![1, 2, 3]), Tensor::from(vec![4, 5]));
+    let ij = Tensor::meshgrid(&[&x, &y], MeshIndexing::Ij).unwrap();
+    assert_eq!(ij[0].shape(), &Shape::from([3, 2].as_ref()));
+    assert!(ij[0].iter().cloned().eq([1, 2, 3, 1, 2, 3].iter().cloned()));
+    assert!(ij[1].iter().cloned().eq([4, 4, 4, 5, 5, 5].iter().cloned()));
+
+    let xy = Tensor::meshgrid(&[&x, &y, &Tensor::from(vec![6])], MeshIndexing::Xy).unwrap();
+    assert_eq!(xy[2].shape(), &Shape::from([2, 3].as_ref()));
+    assert!(xy[0].iter().cloned().eq([1, 1, 2, 2, 3, 3].iter().cloned()));
+    assert!(xy[1].iter().cloned().eq([4, 5, 4, 5, 4, 5].iter().cloned()));
+
+    assert!(Tensor::<i32>::meshgrid(&[], MeshIndexing::Ij).unwrap().is_empty());
+    let m = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    assert!(matches!(Tensor::meshgrid(&[&x, &m], MeshIndexing::Ij), Err(Error::BadRank { .. })));
+}
+
+#[test]
+fn triangle() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6], &[7, 8, 9]]).unwrap();
+    assert!(a.triu(0).unwrap().iter().cloned().eq([1, 2, 3, 0, 5, 6, 0, 0, 9].iter().cloned()));
+    assert!(a.triu(1).unwrap().iter().cloned().eq([0, 2, 3, 0, 0, 6, 0, 0, 0].iter().cloned()));
+    assert!(a.tril(0).unwrap().iter().cloned().eq([1, 0, 0, 4, 5, 0, 7, 8, 9].iter().cloned()));
+    assert!(a.tril(-1).unwrap().iter().cloned().eq([0, 0, 0, 4, 0, 0, 7, 8, 0].iter().cloned()));
+    assert!(a.tril(isize::MAX).unwrap() == a && a.triu(isize::MAX).unwrap().iter().all(|&x| x == 0));
+
+    let row = Tensor::from(vec![1, 2, 3]);
+    assert!(row.triu(1).unwrap().iter().cloned().eq([0, 2, 3].iter().cloned()));
+    let batch = Tensor::new_filled(&Shape::from([2, 2, 2]), 1u8).unwrap();
+    assert!(matches!(batch.tril(0), Err(Error::BadRank { expected: 2, found: 3 })));
+    assert!(matches!(batch.triu(0), Err(Error::BadRank { expected: 2, found: 3 })));
+}
+
+#[test]
+fn ring_tensor() {
+    let mut ring = RingTensor::new(&Shape::from([2]), 3).unwrap();
+    assert!(ring.is_empty());
+    assert_eq!(ring.as_tensor().shape(), &Shape::from([2, 0].as_ref()));
+    for i in 0..2 {
+        ring.push(&Tensor::from(vec![i, 10 * i])).unwrap();
+    }
+    assert_eq!(ring.as_tensor().shape(), &Shape::from([2, 2].as_ref()));
+    assert!(ring.as_tensor().iter().cloned().eq([0, 0, 1, 10].iter().cloned()));
+    for i in 2..5 {
+        ring.push(&Tensor::from(vec![i, 10 * i])).unwrap();
+    }
+    assert!(ring.is_full());
+    assert!(ring.as_tensor().iter().cloned().eq([2, 20, 3, 30, 4, 40].iter().cloned()));
+
+    assert!(matches!(ring.push(&Tensor::from(vec![1, 2, 3])), Err(Error::ShapeMismatch(..))));
+    ring.clear();
+    assert_eq!(ring.len(), 0);
+    assert!(matches!(RingTensor::<i32>::new(&Shape::from([2]), 0), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn slice_mut() {
+    let mut a = Tensor::new_zeroed(&Shape::from([4, 3])).unwrap();
+    let b = a.clone();
+    {
+        let mut view = a.slice_mut(&[Range { start: 1, end: 3, step: 1 }, Range { start: -1, end: 3, step: 1 }]).unwrap();
+        assert_eq!(view.shape(), &Shape::from([2, 1].as_ref()));
+        view.fill(1);
+        view.set(&[1, 0], 2).unwrap();
+        assert!(matches!(view.set(&[0, 1], 2), Err(Error::BadIndex(_))));
+    }
+    assert!(a.iter().cloned().eq([0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 0].iter().cloned()));
+    // Buffer was shared with `b` and is cloned before writing.
+    assert!(b.iter().all(|&x| x == 0));
+
+    let patch = Tensor::from_2d(&[&[5, 6], &[7, 8]]).unwrap();
+    let mut view = a.slice_mut(&[Range { start: 0, end: 4, step: 2 }]).unwrap();
+    assert!(matches!(view.copy_from(&patch), Err(Error::ShapeMismatch(..))));
+    view.copy_from(&Tensor::from_2d(&[&[1, 2], &[3, 4], &[5, 6]]).unwrap()).unwrap();
+    assert!(view.to_tensor().iter().cloned().eq(1..7));
+    assert!(a.iter().cloned().eq([1, 0, 2, 0, 3, 0, 4, 0, 5, 1, 6, 0].iter().cloned()));
+
+    // Broadcasted elements share memory, so the tensor is made plain first.
+    let mut c = Tensor::from(vec![1, 2]).broadcast_to(&Shape::from([2, 2])).unwrap();
+    c.slice_mut(&[Range { start: 1, end: 0, step: -1 }]).unwrap().apply(|x| *x *= 10);
+    assert!(c.iter().cloned().eq([1, 20, 1, 20].iter().cloned()));
+    assert!(matches!(c.slice_mut(&[Range { start: 0, end: 1, step: 0 }]), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn assign_slice() {
+    let mut image = Tensor::new_zeroed(&Shape::from([4, 3])).unwrap();
+    let patch = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    image.assign_slice(&[Range { start: 2, end: 4, step: 1 }, Range { start: 1, end: 3, step: 1 }], &patch).unwrap();
+    assert!(image.iter().cloned().eq([0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 3, 4].iter().cloned()));
+    assert!(matches!(image.assign_slice(&[Range { start: 0, end: 1, step: 1 }], &patch), Err(Error::ShapeMismatch(..))));
+
+    let mut sync = SyncHostTensor::from(image.transpose());
+    let row = SyncHostTensor::from(Tensor::from(vec![7, 8, 9]));
+    sync.assign_slice(&[Range { start: 0, end: 3, step: 1 }, Range { start: -1, end: 4, step: 1 }], &row).unwrap();
+    let result = Tensor::from(sync);
+    assert!(result.iter().cloned().eq([0, 0, 0, 0, 0, 0, 0, 1, 3, 7, 8, 9].iter().cloned()));
+}
+
+#[test]
+fn strided_view_of_large_buffer() {
+    let range = |start, end, step| Index::Range(Range { start, end, step });
+    let big = Tensor::from_vec((0..100).collect(), &Shape::from([10, 10])).unwrap();
+    let mut view = big.slice(&[range(3, 9, 2), range(2, 5, 1)]).unwrap().transpose();
+    drop(big);
+    let expected = [23, 33, 43, 25, 35, 45, 27, 37, 47];
+    let mut dst = vec![0; 9];
+    view.load(&mut dst).unwrap();
+    assert_eq!(dst, expected);
+
+    // Only the elements of the view are written, the rest of the buffer stays intact.
+    view.store(&expected.iter().map(|&x| -x).collect::<Vec<_>>()).unwrap();
+    assert!(view.iter().cloned().eq(expected.iter().map(|&x| -x)));
+    assert!(view.buffer().as_slice().iter().enumerate().all(|(i, &x)| {
+        x == if expected.contains(&(i as i32)) { -(i as i32) } else { i as i32 }
+    }));
+
+    let mut rows = view.buffer().clone().into_vec();
+    rows.iter_mut().for_each(|x| *x = x.abs());
+    let mut rows = Tensor::from_vec(rows, &Shape::from([10, 10])).unwrap().slice(&[range(0, 10, 1), range(2, 8, 1)]).unwrap();
+    rows.assign_slice(&[Range { start: 1, end: 3, step: 1 }, Range { start: 1, end: 2, step: 1 }], &Tensor::from(vec![-1, -2])).unwrap();
+    assert!(rows.buffer().as_slice().iter().enumerate().all(|(i, &x)| {
+        x == match i { 31 => -1, 32 => -2, _ => i as i32 }
+    }));
+
+    // Elements of broadcasted views and overlapping windows share memory, so they are separated before storing.
+    let mut b = Tensor::from(vec![0]).broadcast_to(&Shape::from([3])).unwrap();
+    b.store(&[1, 2, 3]).unwrap();
+    assert!(b == Tensor::from(vec![1, 2, 3]));
+    let mut windows = Tensor::from((0..5).collect::<Vec<_>>()).unfold(0, 3, 1).unwrap();
+    windows.store(&(0..9).map(|x| -x).collect::<Vec<_>>()).unwrap();
+    assert!(windows.iter().cloned().eq((0..9).map(|x| -x)));
+}
+
+#[test]
+fn assign_slice_broadcast() {
+    let mut image = Tensor::new_zeroed(&Shape::from([4, 3])).unwrap();
+    image.assign_slice(&[Range { start: 0, end: 2, step: 1 }], &Tensor::new_filled(&Shape::from([]), 5).unwrap()).unwrap();
+    image.assign_slice(&[Range { start: 2, end: 4, step: 1 }, Range { start: 1, end: 3, step: 1 }], &Tensor::from(vec![1, 2])).unwrap();
+    assert!(image.iter().cloned().eq([5, 5, 0, 0, 5, 5, 1, 2, 5, 5, 1, 2].iter().cloned()));
+    let column = Tensor::from(vec![1, 2, 3]);
+    assert!(matches!(image.assign_slice(&[Range { start: 0, end: 2, step: 1 }], &column), Err(Error::ShapeMismatch(..))));
+
+    let mut view = image.slice_mut(&[Range { start: 0, end: 1, step: 1 }]).unwrap();
+    view.copy_from(&Tensor::new_filled(&Shape::from([]), 9).unwrap()).unwrap();
+    assert!(matches!(view.copy_from(&Tensor::from(vec![1, 2])), Err(Error::ShapeMismatch(..))));
+    assert!(image.iter().cloned().eq([9, 5, 0, 0, 9, 5, 1, 2, 9, 5, 1, 2].iter().cloned()));
+}
+
+#[test]
+fn lerp() {
+    let a = Tensor::from(vec![0.0f32, 2.0, 4.0]);
+    let b = Tensor::from(vec![10.0f32, 2.0, -4.0]);
+    assert_eq!(a.lerp(&b, 0.25).unwrap().to_vec().unwrap(), vec![2.5, 2.0, 2.0]);
+    assert!(a.lerp(&b, 0.0).unwrap() == a && a.lerp(&b, 1.0).unwrap() == b);
+    let rows = Tensor::from_2d(&[&[0.0f32, 2.0, 4.0], &[1.0, 1.0, 1.0]]).unwrap();
+    let w = Tensor::from_2d(&[&[0.5f32], &[1.0]]).unwrap();
+    let c = rows.lerp_by(&b, &w).unwrap();
+    assert_eq!(c.shape(), &Shape::from([3, 2]));
+    assert_eq!(c.to_vec().unwrap(), vec![5.0, 2.0, 0.0, 10.0, 2.0, -4.0]);
+    assert_eq!(Tensor::from(vec![2, 4]).lerp(&Tensor::from(vec![6, 8]), 2).unwrap().to_vec().unwrap(), vec![10, 12]);
+    assert!(matches!(a.lerp(&Tensor::from(vec![1.0, 2.0]), 0.5), Err(Error::ShapeMismatch(..))));
+    assert!(matches!(a.lerp_by(&b, &Tensor::from(vec![1.0, 2.0])), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn arithmetic() {
+    // Length isn't a multiple of the chunk size, so the tail is processed too.
+    let a = Tensor::from_vec((1..20).map(|i| i as f32).collect(), &Shape::from([19])).unwrap();
+    let b = a.map(|x| 2.0 * x);
+    assert!(a.add(&b).unwrap().iter().cloned().eq((1..20).map(|i| 3.0 * i as f32)));
+    assert!(b.sub(&a).unwrap() == a);
+    assert!(a.mul(&b).unwrap().iter().cloned().eq((1..20).map(|i| 2.0 * (i * i) as f32)));
+    assert!(b.div(&a).unwrap().iter().all(|&x| x == 2.0));
+
+    // Broadcasted and strided operands.
+    let m = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    assert!(m.transpose().add(&Tensor::from(vec![10, 20])).unwrap().iter().cloned().eq([11, 23, 12, 24].iter().cloned()));
+    assert!(matches!(m.mul(&Tensor::from(vec![1, 2, 3])), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn parallel_parts() {
+    use std::{collections::HashSet, sync::Mutex, thread};
+    use super::parallel::{split_parts, with_threads};
+
+    // Parts consist of whole units, the last one is shorter, each part is processed by its own thread.
+    let mut data = vec![0usize; 23];
+    let parts = Mutex::new(Vec::new());
+    split_parts(&mut data, 4, 3, |start, part| {
+        part.iter_mut().enumerate().for_each(|(i, x)| *x += start + i);
+        parts.lock().unwrap().push((start, part.len(), thread::current().id()));
+    });
+    assert!(data.iter().cloned().eq(0..23));
+    let mut parts = parts.into_inner().unwrap();
+    parts.sort_by_key(|&(start, ..)| start);
+    assert!(parts.iter().map(|&(start, len, _)| (start, len)).eq([(0, 8), (8, 8), (16, 7)].iter().cloned()));
+    assert_eq!(parts.iter().map(|&(.., id)| id).collect::<HashSet<_>>().len(), 3);
+
+    // Results don't depend on the number of threads, operations of this thread are split regardless of their size.
+    let (m, k, n) = (13, 7, 5);
+    let a = Tensor::from_vec((0..(m * k)).map(|i| (i % 5) as i64).collect(), &Shape::from([k, m])).unwrap();
+    let b = Tensor::from_vec((0..(k * n)).map(|i| (i % 3) as i64).collect(), &Shape::from([n, k])).unwrap();
+    let (c, s, p) = (a.matmul(&b).unwrap(), a.sum_axis(1, false), a.mul(&a).unwrap());
+    for &threads in [2, 3, 4].iter() {
+        with_threads(threads, || {
+            assert!(a.matmul(&b).unwrap() == c && a.sum_axis(1, false) == s && a.mul(&a).unwrap() == p);
+        });
+    }
+    assert!(c[[0, 0]] == (0..k).map(|l| a[[l, 0]] * b[[0, l]]).sum::<i64>());
+    assert_eq!(crate::parallel_threshold(), 1 << 16);
+}
+
+#[test]
+fn store_iter() {
+    let mut a = Tensor::new_zeroed(&Shape::from([3, 2])).unwrap();
+    let b = a.clone();
+    a.store_iter(1..7).unwrap();
+    assert!(a.iter().cloned().eq(1..7) && b.iter().all(|&x| x == 0));
+    let mut t = a.transpose();
+    t.store_iter((0..).map(|i| 10 * i).take(6)).unwrap();
+    assert!(t.iter().cloned().eq((0..6).map(|i| 10 * i)));
+
+    assert!(matches!(a.store_iter(0..5), Err(Error::BadSize { expected: 6, found: 5 })));
+    assert!(matches!(a.store_iter(0..), Err(Error::BadSize { expected: 6, found: 7 })));
+    let mut sync = SyncHostTensor::from(a);
+    sync.store_iter(std::iter::repeat_n(3, 6)).unwrap();
+    assert!(sync.to_vec().unwrap() == vec![3; 6]);
+}
+
+#[test]
+fn reduce() {
+    let a = Tensor::from_plain_vec(vec![3, -1, 4, 1, -5, 9], &Shape::from([3, 2]));
+    assert_eq!(a.reduce(0, ReduceOp::Sum), 11);
+    assert_eq!(a.reduce(1, ReduceOp::Prod), 540);
+    assert_eq!(a.transpose().reduce(i32::MIN, ReduceOp::Max), 9);
+    assert_eq!(a.reduce(0, ReduceOp::Min), -5);
+    assert_eq!(a.reduce(10, ReduceOp::Max), 10);
+    let empty = Tensor::<f32>::new_zeroed(&Shape::from([0, 2])).unwrap();
+    assert_eq!(empty.reduce(1.0, ReduceOp::Prod), 1.0);
+}
+
+#[test]
+fn prod() {
+    let a = Tensor::from_plain_vec(vec![3, -1, 4, 1, -5, 9], &Shape::from([3, 2]));
+    assert_eq!(a.prod(), 540);
+    assert_eq!(a.prod_axis(0, false).to_vec().unwrap(), vec![-12, -45]);
+    assert_eq!(a.prod_axis(1, true).shape(), &Shape::from([3, 1]));
+    assert_eq!(a.prod_axis(1, false).to_vec().unwrap(), vec![3, 5, 36]);
+    let empty = Tensor::<f32>::new_zeroed(&Shape::from([0, 2])).unwrap();
+    assert_eq!(empty.prod(), 1.0);
+    assert_eq!(empty.prod_axis(0, false).to_vec().unwrap(), vec![1.0, 1.0]);
+    // Product of many probabilities underflows to zero.
+    assert_eq!(Tensor::from(vec![1e-3f32; 20]).prod(), 0.0);
+}
+
+#[test]
+fn select() {
+    let cond = Tensor::from_plain_vec(vec![1u8, 0, 2], &Shape::from([3]));
+    let a = Tensor::from_plain_vec(vec![1, 2, 3, 4, 5, 6], &Shape::from([3, 2]));
+    let b = Tensor::new_filled(&Shape::from([]), -1).unwrap();
+    let c = Tensor::select(&cond, &a, &b).unwrap();
+    assert_eq!(c.shape(), &Shape::from([3, 2]));
+    assert_eq!(c.to_vec().unwrap(), vec![1, -1, 3, 4, -1, 6]);
+    let d = Tensor::select(&cond.reshape(&Shape::from([1, 3])).unwrap(), &b, &a.transpose()).unwrap();
+    assert_eq!(d.to_vec().unwrap(), vec![-1, -1, 2, 5, -1, -1]);
+    let bad = Tensor::from_plain_vec(vec![0; 2], &Shape::from([2]));
+    assert!(matches!(Tensor::select(&cond, &a, &bad), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn cdist() {
+    // Rows `[0, 0]`, `[3, 4]` and `[1, 1]`.
+    let a = Tensor::from_plain_vec(vec![0.0, 0.0, 3.0, 4.0], &Shape::from([2, 2]));
+    let b = Tensor::from_plain_vec(vec![3.0, 4.0, 1.0, 1.0, 0.0, 0.0], &Shape::from([2, 3]));
+    let close = |x: &Tensor<f64>, y: &[f64]| x.iter().zip(y).all(|(&x, &y)| (x - y).abs() < 1e-9) && x.shape().content() == y.len();
+    let d = a.cdist(&b, 2.0).unwrap();
+    assert_eq!(d.shape(), &Shape::from([3, 2]));
+    assert!(close(&d, &[5.0, 2.0f64.sqrt(), 0.0, 0.0, 13.0f64.sqrt(), 5.0]));
+    assert!(close(&a.cdist(&b, 1.0).unwrap(), &[7.0, 2.0, 0.0, 0.0, 5.0, 7.0]));
+    assert!(close(&a.cdist(&b, f64::INFINITY).unwrap(), &[4.0, 1.0, 0.0, 0.0, 3.0, 4.0]));
+    let p3 = a.cdist(&b, 3.0).unwrap();
+    assert!(close(&p3, &[91.0f64.cbrt(), 2.0f64.cbrt(), 0.0, 0.0, 35.0f64.cbrt(), 91.0f64.cbrt()]));
+    assert!(close(&p3, &a.transpose().transpose().cdist(&b, 3.0).unwrap().to_vec().unwrap()));
+
+    assert!(matches!(a.cdist(&b, 0.0), Err(Error::BadArgument(_))));
+    let c = Tensor::from_plain_vec(vec![0.0; 3], &Shape::from([3]));
+    assert!(matches!(a.cdist(&c, 2.0), Err(Error::ShapeMismatch(..))));
+    let e = Tensor::from_plain_vec(vec![0.0; 8], &Shape::from([2, 2, 2]));
+    assert!(matches!(e.cdist(&a, 2.0), Err(Error::BadRank { expected: 2, found: 3 })));
+}
+
+#[test]
+fn cosine_similarity() {
+    let a = Tensor::from_plain_vec(vec![1.0, 0.0, 3.0, 4.0, 0.0, 0.0], &Shape::from([2, 3]));
+    let b = Tensor::from_plain_vec(vec![1.0, 1.0], &Shape::from([2]));
+    let s = a.cosine_similarity(&b, 0).unwrap();
+    assert_eq!(s.shape(), &Shape::from([3]));
+    let expected = [0.5f64.sqrt(), 0.7 * 2.0f64.sqrt(), 0.0];
+    assert!(s.iter().zip(expected.iter()).all(|(&x, &y)| (x - y).abs() < 1e-9));
+    let t = a.transpose().cosine_similarity(&a.transpose(), 1).unwrap();
+    assert!(t.iter().zip([1.0, 1.0, 0.0].iter()).all(|(&x, &y)| (x - y).abs() < 1e-9));
+    assert!(matches!(a.cosine_similarity(&a.transpose(), 0), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn quantize_int8() {
+    let a = Tensor::from_plain_vec(vec![-1.0, 0.0, 0.6, 2.0, f32::NAN], &Shape::from([5]));
+    let (scale, zero_point) = a.slice(&[Index::Range(Range { start: 0, end: 4, step: 1 })]).unwrap().compute_quant_params();
+    assert!((scale - 3.0 / 255.0).abs() < 1e-7 && zero_point == -43);
+    let q = a.quantize_int8(scale, zero_point);
+    assert_eq!(q.to_vec().unwrap(), vec![-128, -43, 8, 127, 0]);
+    let d = Tensor::dequantize(&q, scale, zero_point);
+    assert!(d.iter().take(4).zip([-1.0, 0.0, 0.6, 2.0].iter()).all(|(&x, &y)| (x - y).abs() <= scale / 2.0));
+    assert_eq!(a.quantize_int8(0.01, 0).to_vec().unwrap()[3], 127);
+
+    let zeros = Tensor::<f64>::new_zeroed(&Shape::from([3])).unwrap();
+    assert_eq!(zeros.compute_quant_params(), (1.0, -128));
+    let positive = Tensor::from_plain_vec(vec![1.0, 2.55], &Shape::from([2]));
+    assert_eq!(positive.compute_quant_params().1, -128);
+}
+
+#[test]
+fn activation_backward() {
+    let x = Tensor::from_plain_vec(vec![-2.0, 0.0, 1.5], &Shape::from([3]));
+    let g = Tensor::from_plain_vec(vec![1.0, 2.0, 3.0], &Shape::from([3]));
+    assert_eq!(Tensor::relu_backward(&g, &x).unwrap().to_vec().unwrap(), vec![0.0, 0.0, 3.0]);
+    let close = |t: Tensor<f64>, y: &[f64]| t.iter().zip(y).all(|(&x, &y)| (x - y).abs() < 1e-12);
+    let sigmoid = |x: f64| 1.0 / (1.0 + (-x).exp());
+    let ds = [-2.0, 0.0, 1.5].iter().map(|&x| sigmoid(x) * (1.0 - sigmoid(x))).collect::<Vec<_>>();
+    assert!(close(Tensor::sigmoid_backward(&g, &x).unwrap(), &[ds[0], 2.0 * 0.25, 3.0 * ds[2]]));
+    assert!(close(Tensor::tanh_backward(&g, &x).unwrap(), &[1.0 - 2.0f64.tanh().powi(2), 2.0, 3.0 * (1.0 - 1.5f64.tanh().powi(2))]));
+    let b = Tensor::from_plain_vec(vec![1.0], &Shape::from([]));
+    assert!(matches!(Tensor::relu_backward(&b, &x), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn compare_report() {
+    let a = Tensor::from_plain_vec(vec![1.0, 2.0, 3.5, f64::NAN, f64::NAN, 0.0], &Shape::from([3, 2]));
+    let b = Tensor::from_plain_vec(vec![1.0, 2.001, 3.0, f64::NAN, 1.0, 0.0], &Shape::from([3, 2]));
+    let r = a.compare_report(&SyncHostTensor::from(b.clone()), 1e-2, 0.0).unwrap();
+    assert_eq!((r.mismatched, r.nan_mismatched, r.total()), (2, 1, 6));
+    assert_eq!(r.max_index, Some(vec![2, 0]));
+    assert!((r.max_abs_diff - 0.5).abs() < 1e-12 && (r.max_rel_diff - 0.5 / 3.0).abs() < 1e-12);
+    assert!(!r.is_close());
+    assert_eq!(r.to_string(), format!("2 of 6 elements of shape [3, 2] mismatch (1 by NaN), max abs diff {} at [2, 0], max rel diff {}", r.max_abs_diff, r.max_rel_diff));
+    assert!(b.compare_report(&b, 0.0, 0.0).unwrap().is_close());
+    assert!(matches!(a.compare_report(&a.transpose(), 0.0, 0.0), Err(Error::ShapeMismatch(..))));
+
+    // Infinite reference matches only the infinity of the same sign, whatever the tolerance is.
+    let inf = f64::INFINITY;
+    let a = Tensor::from(vec![1.0, inf, -inf, inf, -inf]);
+    let b = Tensor::from(vec![inf, inf, inf, 1.0, -inf]);
+    let r = a.compare_report(&b, 1e-2, 0.5).unwrap();
+    assert_eq!((r.mismatched, r.nan_mismatched), (3, 0));
+    assert!(a.compare_report(&a, 0.0, 0.0).unwrap().is_close());
+}
+
+#[test]
+fn fill_iota() {
+    let a = Tensor::from_plain_vec(vec![0u8; 6], &Shape::from([3, 2]));
+    let mut t = a.transpose();
+    t.fill_iota(1, 2).unwrap();
+    assert_eq!(t.shape(), &Shape::from([2, 3]));
+    assert_eq!(t.to_vec().unwrap(), vec![1, 3, 5, 7, 9, 11]);
+    assert!(a.iter().all(|&x| x == 0));
+}
+
+#[test]
+fn dyn_tensor() {
+    let t = DynTensor::from(Tensor::from_plain_vec(vec![1.0f32, 2.0, 3.0], &Shape::from([3])));
+    assert_eq!(t.dtype(), DType::F32);
+    assert_eq!(t.dtype().to_string(), "f32");
+    assert_eq!(t.shape(), &Shape::from([3]));
+    assert_eq!(DType::of::<bf16>(), Some(DType::Bf16));
+    assert_eq!(DType::of::<Complex<f32>>(), None);
+    assert!(t.downcast_ref::<f64>().is_none());
+    assert_eq!(t.downcast_ref::<f32>().unwrap().to_vec().unwrap(), vec![1.0, 2.0, 3.0]);
+    match t.clone().downcast::<i32>() {
+        Err(Error::BadArgument(msg)) => assert_eq!(msg, "tensor of type f32 cannot be downcast to i32"),
+        _ => panic!(),
+    }
+    assert_eq!(t.downcast::<f32>().unwrap().to_vec().unwrap(), vec![1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn inplace() {
+    let mut a = Tensor::from_plain_vec(vec![1.0, -2.0, 3.0, -4.0], &Shape::from([2, 2]));
+    let b = a.clone();
+    a.add_assign(&Tensor::from_plain_vec(vec![1.0, 2.0], &Shape::from([2]))).unwrap();
+    assert_eq!(a.to_vec().unwrap(), vec![2.0, 0.0, 4.0, -2.0]);
+    a.mul_assign(&Tensor::new_filled(&Shape::from([]), 2.0).unwrap()).unwrap();
+    a.sub_assign(&b).unwrap();
+    a.div_assign(&Tensor::new_filled(&Shape::from([]), 3.0).unwrap()).unwrap();
+    assert_eq!(a.to_vec().unwrap(), vec![1.0, 2.0 / 3.0, 5.0 / 3.0, 0.0]);
+    assert!(matches!(a.add_assign(&Tensor::new_zeroed(&Shape::from([2, 2, 2])).unwrap()), Err(Error::ShapeMismatch(..))));
+
+    let mut c = b.clone();
+    c.relu_assign();
+    assert_eq!(c.to_vec().unwrap(), b.relu().to_vec().unwrap());
+    assert_eq!(c.to_vec().unwrap(), vec![1.0, 0.0, 3.0, 0.0]);
+    c.clamp_assign(0.5, 2.0);
+    assert_eq!(c.to_vec().unwrap(), b.clamp(0.5, 2.0).to_vec().unwrap());
+    assert_eq!(c.to_vec().unwrap(), vec![1.0, 0.5, 2.0, 0.5]);
+    c.exp_assign();
+    assert_eq!(c.to_vec().unwrap(), vec![1.0f64.exp(), 0.5f64.exp(), 2.0f64.exp(), 0.5f64.exp()]);
+    c.fill_zero().unwrap();
+    assert!(c.iter().all(|&x| x == 0.0));
+    assert_eq!(b.to_vec().unwrap(), vec![1.0, -2.0, 3.0, -4.0]);
+}
+
+#[test]
+fn rem() {
+    let a = Tensor::from_plain_vec(vec![7, -7, 7, -7], &Shape::from([4]));
+    let b = Tensor::from_plain_vec(vec![3, 3, -3, -3], &Shape::from([4]));
+    assert_eq!(a.rem(&b).unwrap().to_vec().unwrap(), vec![1, -1, 1, -1]);
+    let x = Tensor::from_plain_vec(vec![5.5, -5.5], &Shape::from([2]));
+    let y = Tensor::new_filled(&Shape::from([]), 2.0).unwrap();
+    assert_eq!(x.rem(&y).unwrap().to_vec().unwrap(), vec![1.5, -1.5]);
+    assert!(matches!(a.rem(&x.map(|v| v as i32)), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn matmul_flags() {
+    let a = Tensor::from_plain_vec((0..6).collect(), &Shape::from([3, 2]));
+    let b = Tensor::from_plain_vec((0..12).collect(), &Shape::from([4, 3]));
+    let c = a.matmul(&b).unwrap();
+    let (at, bt) = (a.transpose().clone_contiguous().unwrap(), b.transpose().clone_contiguous().unwrap());
+    assert_eq!(at.matmul_flags(&b, true, false).unwrap().to_vec().unwrap(), c.to_vec().unwrap());
+    assert_eq!(a.matmul_flags(&bt, false, true).unwrap().to_vec().unwrap(), c.to_vec().unwrap());
+    assert_eq!(at.matmul_flags(&bt, true, true).unwrap().to_vec().unwrap(), c.to_vec().unwrap());
+    assert!(matches!(a.matmul_flags(&b, true, false).err().unwrap().root(), Error::ShapeMismatch(..)));
+}
+
+#[test]
+fn pad_to_multiple() {
+    let a = Tensor::from_plain_vec((1..=6).collect(), &Shape::from([3, 2]));
+    let p = a.pad_to_multiple(0, 4, 0).unwrap();
+    assert_eq!(p.shape(), &Shape::from([4, 2]));
+    assert_eq!(p.to_vec().unwrap(), vec![1, 2, 3, 0, 4, 5, 6, 0]);
+    let q = a.pad_to_multiple(1, 3, -1).unwrap();
+    assert_eq!(q.to_vec().unwrap(), vec![1, 2, 3, 4, 5, 6, -1, -1, -1]);
+    assert_eq!(a.pad_to_multiple(0, 3, 0).unwrap().buffer_refcount(), 2);
+    assert!(matches!(a.pad_to_multiple(2, 4, 0), Err(Error::BadAxes(_))));
+    assert!(matches!(a.pad_to_multiple(0, 0, 0), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn from_fn() {
+    let a = Tensor::from_fn(&Shape::from([3, 2]), |i| i[0] + 10 * i[1]).unwrap();
+    assert_eq!(a.shape(), &Shape::from([3, 2]));
+    assert_eq!(a.to_vec().unwrap(), vec![0, 1, 2, 10, 11, 12]);
+    let s = Tensor::from_fn(&Shape::from([]), |i| i.len()).unwrap();
+    assert_eq!(s.to_vec().unwrap(), vec![0]);
+    assert_eq!(Tensor::<i32>::from_fn(&Shape::from([2, 0]), |_| unreachable!()).unwrap().numel(), 0);
+}
+
+#[test]
+fn checked_saturating() {
+    let a = Tensor::<u8>::from_plain_vec(vec![250, 10, 0], &Shape::from([3]));
+    let b = Tensor::new_filled(&Shape::from([]), 10u8).unwrap();
+    assert_eq!(a.saturating_add(&b).unwrap().to_vec().unwrap(), vec![255, 20, 10]);
+    assert_eq!(a.saturating_sub(&b).unwrap().to_vec().unwrap(), vec![240, 0, 0]);
+    assert_eq!(a.saturating_mul(&b).unwrap().to_vec().unwrap(), vec![255, 100, 0]);
+    assert!(matches!(a.checked_add(&b), Err(Error::Overflow)));
+    assert!(matches!(a.checked_sub(&b), Err(Error::Overflow)));
+    assert!(matches!(a.checked_mul(&b), Err(Error::Overflow)));
+    let c = Tensor::<i32>::from_plain_vec(vec![5, -7], &Shape::from([2]));
+    let d = Tensor::from_plain_vec(vec![3, 2], &Shape::from([2]));
+    assert_eq!(c.checked_add(&d).unwrap().to_vec().unwrap(), vec![8, -5]);
+    assert_eq!(c.checked_mul(&d).unwrap().to_vec().unwrap(), vec![15, -14]);
+    assert!(matches!(c.checked_add(&Tensor::from_plain_vec(vec![0, 0, 0], &Shape::from([3]))), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn compact() {
+    let a = Tensor::from_plain_vec((0..12).collect(), &Shape::from([4, 3]));
+    let all = Index::Range(Range { start: 0, end: 4, step: 1 });
+    let row = a.slice(&[all, Index::Single(1)]).unwrap();
+    let c = row.compact().unwrap();
+    assert_eq!(c.buffer_refcount(), 1);
+    assert_eq!(c.to_vec().unwrap(), vec![4, 5, 6, 7]);
+    let half = a.slice(&[all, Index::Range(Range { start: 1, end: 3, step: 1 })]).unwrap();
+    assert_eq!(half.compact().unwrap().buffer_refcount(), a.buffer_refcount());
+}
+
+#[test]
+fn try_into_vec() {
+    let vec = (0..6).collect::<Vec<i32>>();
+    let ptr = vec.as_ptr();
+    let a = Tensor::from_vec(vec, &Shape::from([3, 2])).unwrap();
+    let b = a.transpose();
+    assert_eq!(b.try_into_vec().unwrap(), vec![0, 3, 1, 4, 2, 5]);
+    let moved = a.try_into_vec().unwrap();
+    assert_eq!(moved.as_ptr(), ptr);
+    let c = Tensor::from(moved);
+    let d = c.clone();
+    assert_eq!(c.try_into_vec().unwrap(), d.try_into_vec().unwrap());
+    let s = SyncHostTensor::from(Tensor::from(vec![1, 2, 3]));
+    assert_eq!(s.try_into_vec().unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn image_layout() {
+    // Two rows of three RGB pixels.
+    let bytes = (0..18).collect::<Vec<u8>>();
+    let img = Tensor::from_image_bytes(&bytes, 2, 3, 3).unwrap();
+    assert_eq!(img.shape(), &Shape::from([3, 3, 2]));
+    let chw = img.to_chw().unwrap();
+    assert_eq!(chw.shape(), &Shape::from([3, 2, 3]));
+    assert_eq!(chw.to_vec().unwrap()[..6], [0, 3, 6, 9, 12, 15]);
+    assert_eq!(chw.to_hwc().unwrap().to_vec().unwrap(), bytes);
+    assert!(matches!(Tensor::from_image_bytes(&bytes, 2, 3, 2), Err(Error::BadSize { expected: 12, found: 18 })));
+    assert!(matches!(Tensor::<u8>::new_zeroed(&Shape::from([1, 1, 1, 2])).unwrap().to_chw(), Err(Error::BadRank { .. })));
+
+    let norm = chw.normalize_image(&[0.0, 0.5, 1.0], &[1.0, 0.5, 2.0]).unwrap();
+    let v = norm.to_vec().unwrap();
+    assert_eq!(v[1], 3.0 / 255.0);
+    assert_eq!(v[6], (1.0 / 255.0 - 0.5) / 0.5);
+    assert_eq!(v[12], (2.0 / 255.0 - 1.0) / 2.0);
+    assert!(matches!(chw.normalize_image(&[0.0; 2], &[1.0; 2]), Err(Error::BadSize { .. })));
+    assert!(matches!(chw.normalize_image(&[0.0; 3], &[1.0, 0.0, 1.0]), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn conv2d() {
+    // 3x3 single-channel image and 2x2 kernel.
+    let x = Tensor::from_plain_vec((1..=9).collect::<Vec<i64>>(), &Shape::from([3, 3]));
+    let w = Tensor::from_plain_vec(vec![1, 0, 0, -1], &Shape::from([2, 2]));
+    let y = x.conv2d(&w, 1, 0).unwrap();
+    assert_eq!(y.shape(), &Shape::from([2, 2]));
+    assert_eq!(y.to_vec().unwrap(), vec![-4; 4]);
+    let p = x.conv2d(&w, 2, 1).unwrap();
+    assert_eq!(p.shape(), &Shape::from([2, 2]));
+    assert_eq!(p.to_vec().unwrap(), vec![-1, -3, -7, -4]);
+
+    // Gradients are adjoint to the forward pass: <conv(x, w), g> = <x, dx(g)> = <w, dw(g)>.
+    let x = Tensor::from_fn(&Shape::from([5, 4, 2, 2]), |i| (i[0] * 7 + i[1] * 3 + i[2] * 5 + i[3]) as i64 % 11 - 5).unwrap();
+    let w = Tensor::from_fn(&Shape::from([3, 2, 2, 3]), |i| (i[0] + 2 * i[1] + 3 * i[2] + 5 * i[3]) as i64 % 7 - 3).unwrap();
+    let y = x.conv2d(&w, 2, 1).unwrap();
+    assert_eq!(y.shape(), &Shape::from([3, 3, 3, 2]));
+    let g = Tensor::from_fn(y.shape(), |i| (i[0] + 3 * i[1] + i[2] * i[3]) as i64 % 5 - 2).unwrap();
+    let dot = |a: &Tensor<i64>, b: &Tensor<i64>| a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<i64>();
+    let dx = Tensor::conv2d_backward_input(&g, &x, &w, 2, 1).unwrap();
+    let dw = Tensor::conv2d_backward_weight(&g, &x, &w, 2, 1).unwrap();
+    assert_eq!(dx.shape(), x.shape());
+    assert_eq!(dw.shape(), w.shape());
+    assert_eq!(dot(&y, &g), dot(&x, &dx));
+    assert_eq!(dot(&y, &g), dot(&w, &dw));
+
+    assert!(matches!(Tensor::conv2d_backward_input(&x, &x, &w, 2, 1), Err(Error::ShapeMismatch(..))));
+    assert!(matches!(x.conv2d(&w.view(&Shape::from([3, 2, 1, 6])).unwrap(), 1, 0), Err(Error::ShapeMismatch(..))));
+    assert!(matches!(x.conv2d(&w, 0, 0), Err(Error::BadArgument(_))));
+    assert!(matches!(x.conv2d(&Tensor::new_zeroed(&Shape::from([6, 1, 2])).unwrap(), 1, 0), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn gemm() {
+    let a = Tensor::from_plain_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &Shape::from([3, 2]));
+    let b = Tensor::from_plain_vec(vec![1.0, 0.0, -1.0, 2.0, 1.0, 0.0], &Shape::from([2, 3]));
+    let p = a.matmul(&b).unwrap().to_vec().unwrap();
+    let mut c = Tensor::from_plain_vec(vec![1.0, 2.0, 3.0, 4.0], &Shape::from([2, 2]));
+    a.gemm(&b, &mut c, 2.0, 0.5).unwrap();
+    assert_eq!(c.to_vec().unwrap(), vec![2.0 * p[0] + 0.5, 2.0 * p[1] + 1.0, 2.0 * p[2] + 1.5, 2.0 * p[3] + 2.0]);
+    let mut nan = Tensor::new_filled(&Shape::from([2, 2]), f64::NAN).unwrap();
+    a.gemm(&b, &mut nan, 1.0, 0.0).unwrap();
+    assert_eq!(nan.to_vec().unwrap(), p);
+    let mut bad = Tensor::new_zeroed(&Shape::from([3, 2])).unwrap();
+    assert!(matches!(a.gemm(&b, &mut bad, 1.0, 1.0).err().unwrap().root(), Error::ShapeMismatch(..)));
+}
+
+#[test]
+fn checksum() {
+    // Reference values of 64-bit FNV-1a.
+    assert_eq!(Tensor::<u8>::from(vec![]).checksum().unwrap(), 0xcbf29ce484222325);
+    assert_eq!(Tensor::from(b"a".to_vec()).checksum().unwrap(), 0xaf63dc4c8601ec8c);
+    let a = Tensor::from_2d(&[&[1u16, 2], &[3, 4]]).unwrap();
+    let bytes = a.to_vec().unwrap().into_iter().flat_map(|x| x.to_le_bytes().to_vec()).collect::<Vec<_>>();
+    assert_eq!(a.checksum().unwrap(), Tensor::from(bytes).checksum().unwrap());
+    assert_ne!(a.checksum().unwrap(), a.transpose().checksum().unwrap());
+    assert_eq!(a.checksum().unwrap(), a.reshape(&Shape::from([4])).unwrap().checksum().unwrap());
+}
+
+#[test]
+fn slice_negative_step() {
+    let a = Tensor::from((0..8).collect::<Vec<i32>>());
+    let s = |start, end, step| a.slice(&[Index::Range(Range::new(start, end, step))]).unwrap().to_vec().unwrap();
+    assert_eq!(s(None, None, -1), vec![7, 6, 5, 4, 3, 2, 1, 0]);
+    assert_eq!(s(None, None, -2), vec![7, 5, 3, 1]);
+    assert_eq!(s(Some(5), Some(1), -1), vec![5, 4, 3, 2]);
+    assert_eq!(s(Some(-2), None, -3), vec![6, 3, 0]);
+    assert_eq!(s(None, Some(-3), -1), vec![7, 6]);
+    assert_eq!(s(Some(100), Some(-100), -4), vec![7, 3]);
+    assert!(s(Some(1), Some(5), -1).is_empty());
+    assert_eq!(s(None, None, 3), vec![0, 3, 6]);
+    let rev = a.slice(&[Index::Range(Range::new(None, None, -1))]).unwrap();
+    assert_eq!(rev.slice(&[Index::Range(Range::new(None, None, -1))]).unwrap().to_vec().unwrap(), a.to_vec().unwrap());
+    let empty = Tensor::<i32>::from(vec![]);
+    assert_eq!(empty.slice(&[Index::Range(Range::new(None, None, -1))]).unwrap().numel(), 0);
+
+    // Extreme steps select at most one element instead of overflowing, along an axis with a non-unit stride too.
+    let r = |start, end, step| Index::Range(Range { start, end, step });
+    let t = |index| a.slice(&[index]).unwrap().to_vec().unwrap();
+    assert_eq!(t(r(0, isize::MAX, isize::MAX)), vec![0]);
+    assert_eq!(t(r(-1, isize::MIN, isize::MIN)), vec![7]);
+    assert_eq!(t(r(isize::MIN, isize::MAX, isize::MIN)), vec![]);
+    let m = Tensor::from_plain_vec((0..6).collect::<Vec<i32>>(), &Shape::from([3, 2]));
+    assert_eq!(m.slice(&[Index::Range(Range::new(None, None, 1)), r(-1, isize::MIN, isize::MIN)]).unwrap().to_vec().unwrap(), vec![3, 4, 5]);
+    assert_eq!(m.slice(&[Index::Range(Range::new(None, None, 1)), r(0, isize::MAX, isize::MAX)]).unwrap().to_vec().unwrap(), vec![0, 1, 2]);
+}
+
+#[test]
+fn apply_along_axis() {
+    let a = Tensor::from_2d(&[&[1, 2, 3], &[4, 5, 6]]).unwrap();
+    let cumsum = |x: &[i32]| x.iter().scan(0, |s, &v| { *s += v; Some(*s) }).collect::<Vec<_>>();
+    assert_eq!(a.apply_along_axis(0, cumsum).unwrap().to_vec().unwrap(), vec![1, 3, 6, 4, 9, 15]);
+    let b = a.apply_along_axis(1, |x| vec![x.iter().sum(), x.iter().product()]).unwrap();
+    assert_eq!(b.shape(), &Shape::from([3, 2]));
+    assert_eq!(b.to_vec().unwrap(), vec![5, 7, 9, 4, 10, 18]);
+    let c = a.apply_along_axis(0, |x| vec![*x.iter().max().unwrap()]).unwrap();
+    assert_eq!(c.shape(), &Shape::from([1, 2]));
+    assert_eq!(c.to_vec().unwrap(), vec![3, 6]);
+    let mut n = 0;
+    assert!(matches!(a.apply_along_axis(0, |x| { n += 1; x[..n].to_vec() }), Err(Error::BadSize { expected: 1, found: 2 })));
+    assert!(matches!(a.apply_along_axis(2, |x| x.to_vec()), Err(Error::BadAxes(_))));
+}
+
+#[test]
+fn raw_bytes() {
+    let a = Tensor::from_2d(&[&[1.0f32, -2.0], &[0.5, 8.0]]).unwrap();
+    let bytes = a.as_bytes().unwrap();
+    assert_eq!(bytes.len(), 16);
+    assert_eq!(&bytes[4..8], &(-2.0f32).to_ne_bytes());
+    let b = Tensor::<f32>::from_bytes(bytes, a.shape()).unwrap();
+    assert!(b == a);
+    assert!(a.transpose().as_bytes().is_none());
+    assert!(matches!(Tensor::<f32>::from_bytes(&bytes[..12], a.shape()), Err(Error::BadSize { expected: 16, found: 12 })));
+    // Unaligned bytes are copied, so they give the same elements as aligned ones.
+    let words = Tensor::from(vec![0x04030201u32, 0x08070605, 0x0c0b0a09]);
+    let unaligned = &words.as_bytes().unwrap()[1..9];
+    let expected = unaligned.chunks(4).map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]])).collect::<Vec<_>>();
+    assert_eq!(Tensor::<u32>::from_bytes(unaligned, &Shape::from([2])).unwrap().to_vec().unwrap(), expected);
+    assert_eq!(Tensor::<u8>::from_bytes(unaligned, &Shape::from([8])).unwrap().to_vec().unwrap(), unaligned);
+}
+
+#[test]
+fn unique() {
+    let a = Tensor::from_2d(&[&[3, 1, 3], &[2, 1, 3]]).unwrap();
+    assert_eq!(a.unique().to_vec().unwrap(), vec![1, 2, 3]);
+    let (values, counts) = a.unique_counts();
+    assert_eq!(values.to_vec().unwrap(), vec![1, 2, 3]);
+    assert_eq!(counts.to_vec().unwrap(), vec![2, 1, 3]);
+    let (values, inverse) = a.unique_inverse();
+    assert_eq!(inverse.shape(), a.shape());
+    assert_eq!(inverse.to_vec().unwrap(), vec![2, 0, 2, 1, 0, 2]);
+    assert!(values.index_select(0, &inverse.reshape(&Shape::from([6])).unwrap()).unwrap().iter().eq(a.iter()));
+    assert_eq!(Tensor::<f32>::new_zeroed(&Shape::from([0])).unwrap().unique().shape(), &Shape::from([0]));
+
+    // Each NaN is distinct and goes after the numbers, which are still sorted and counted as without NaNs.
+    let nan = f64::NAN;
+    let b = Tensor::from(vec![3.0, nan, 1.0, 3.0, 2.0, nan, 1.0]);
+    let (values, counts) = b.unique_counts();
+    let values = values.to_vec().unwrap();
+    assert_eq!(values[..3], [1.0, 2.0, 3.0]);
+    assert!(values.len() == 5 && values[3..].iter().all(|x| x.is_nan()));
+    assert_eq!(counts.to_vec().unwrap(), vec![2, 1, 2, 1, 1]);
+    assert_eq!(b.unique_inverse().1.to_vec().unwrap(), vec![2, 3, 0, 2, 1, 4, 0]);
+}
+
+#[test]
+fn bincount() {
+    let labels = Tensor::from_2d(&[&[0, 2, 2], &[3, 0, 2]]).unwrap();
+    assert_eq!(labels.bincount(0).unwrap().to_vec().unwrap(), vec![2, 0, 3, 1]);
+    assert_eq!(labels.map(|x| x as u8).bincount(6).unwrap().to_vec().unwrap(), vec![2, 0, 3, 1, 0, 0]);
+    assert_eq!(Tensor::<i64>::new_zeroed(&Shape::from([0])).unwrap().bincount(2).unwrap().to_vec().unwrap(), vec![0, 0]);
+    assert!(matches!(Tensor::from(vec![1, -1]).bincount(0), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn confusion_matrix() {
+    let predictions = Tensor::from(vec![0, 1, 1, 2, 2]);
+    let targets = Tensor::from(vec![0, 1, 2, 2, 2]);
+    let m = Tensor::confusion_matrix(&predictions, &targets, 3).unwrap();
+    assert_eq!(m.shape(), &Shape::from([3, 3]));
+    assert_eq!(m.to_vec().unwrap(), vec![1, 0, 0, 0, 1, 0, 0, 1, 2]);
+    assert_eq!(m.get(&[1, 2]).unwrap(), 1);
+    assert!(matches!(Tensor::confusion_matrix(&predictions, &targets, 2), Err(Error::BadIndex(_))));
+    assert!(matches!(Tensor::confusion_matrix(&predictions, &Tensor::from(vec![0, 1]), 3), Err(Error::ShapeMismatch(..))));
+}
+
+#[test]
+fn median_partition() {
+    let a = Tensor::from_2d(&[&[3, 1, 2, 10], &[7, 5, 8, 6]]).unwrap();
+    let m = a.median_axis(0).unwrap();
+    assert_eq!(m.shape(), &Shape::from([2]));
+    assert_eq!(m.to_vec().unwrap(), vec![2, 6]);
+    assert_eq!(a.median_axis(1).unwrap().to_vec().unwrap(), vec![3, 1, 2, 6]);
+    assert_eq!(a.map(|x| x as f32).median_axis(0).unwrap().to_vec().unwrap(), vec![2.5, 6.5]);
+    assert_eq!(Tensor::from(vec![5, 1, 4]).median_axis(0).unwrap().to_vec().unwrap(), vec![4]);
+    assert!(matches!(a.median_axis(2), Err(Error::BadAxes(_))));
+    assert!(matches!(Tensor::<f32>::new_zeroed(&Shape::from([0, 2])).unwrap().median_axis(0), Err(Error::BadArgument(_))));
+
+    let p = a.partition(1, 0).unwrap();
+    assert_eq!(p.shape(), a.shape());
+    assert_eq!(p.get(&[1, 0]).unwrap(), 2);
+    assert_eq!(p.get(&[0, 0]).unwrap(), 1);
+    assert_eq!(p.get(&[1, 1]).unwrap(), 6);
+    assert!(p.get(&[2, 1]).unwrap() >= 6 && p.get(&[3, 1]).unwrap() >= 6);
+    assert!(matches!(a.partition(4, 0), Err(Error::BadIndex(_))));
+}
+
+#[test]
+fn diff() {
+    let a = Tensor::from(vec![1, 2, 4, 7, 0]);
+    assert_eq!(a.diff(1, 0).unwrap().to_vec().unwrap(), vec![1, 2, 3, -7]);
+    assert_eq!(a.diff(2, 0).unwrap().to_vec().unwrap(), vec![1, 1, -10]);
+    assert_eq!(a.diff(0, 0).unwrap().to_vec().unwrap(), a.to_vec().unwrap());
+    assert_eq!(a.diff(5, 0).unwrap().numel(), 0);
+    let b = Tensor::from_2d(&[&[1.0, 2.0, 4.0], &[3.0, 7.0, 8.0]]).unwrap();
+    let d = b.diff(1, 1).unwrap();
+    assert_eq!(d.shape(), &Shape::from([3]));
+    assert_eq!(d.to_vec().unwrap(), vec![2.0, 5.0, 4.0]);
+    assert_eq!(b.diff(2, 0).unwrap().to_vec().unwrap(), vec![1.0, -3.0]);
+    assert!(matches!(b.diff(1, 2), Err(Error::BadAxes(_))));
+    assert!(matches!(a.diff(6, 0), Err(Error::BadArgument(_))));
+}
+
+#[test]
+fn clamp_with_mask() {
+    let a = Tensor::from(vec![-2.0, -1.0, 0.5, 1.0, 3.0, f64::NAN]);
+    let (c, mask) = a.clamp_with_mask(-1.0, 1.0);
+    assert_eq!(c.to_vec().unwrap()[..5], [-1.0, -1.0, 0.5, 1.0, 1.0]);
+    assert!(c.to_vec().unwrap()[5].is_nan());
+    assert_eq!(mask.to_vec().unwrap(), vec![1, 0, 0, 0, 1, 0]);
+    let (c, mask) = Tensor::from(vec![0u8, 5, 200]).clamp_with_mask(1, 100);
+    assert_eq!(c.to_vec().unwrap(), vec![1, 5, 100]);
+    assert_eq!(mask.to_vec().unwrap(), vec![1, 0, 1]);
+}
+
+#[test]
+fn kron() {
+    let a = Tensor::from_2d(&[&[1, 2], &[3, 4]]).unwrap();
+    let b = Tensor::from_2d(&[&[0, 5, 1]]).unwrap();
+    let k = a.kron(&b).unwrap();
+    assert_eq!(k.shape(), &Shape::from([6, 2]));
+    assert_eq!(k.to_vec().unwrap(), vec![0, 5, 1, 0, 10, 2, 0, 15, 3, 0, 20, 4]);
+    let i = Tensor::from_2d(&[&[1, 0], &[0, 1]]).unwrap();
+    let k = i.kron(&a).unwrap();
+    assert_eq!(k.shape(), &Shape::from([4, 4]));
+    assert_eq!(k.to_vec().unwrap(), vec![1, 2, 0, 0, 3, 4, 0, 0, 0, 0, 1, 2, 0, 0, 3, 4]);
+    let v = Tensor::from(vec![1, -1]);
+    assert_eq!(v.kron(&v).unwrap().to_vec().unwrap(), vec![1, -1, -1, 1]);
+    assert!(matches!(a.kron(&Tensor::new_zeroed(&Shape::from([1, 1, 2])).unwrap()), Err(Error::BadRank { .. })));
+}
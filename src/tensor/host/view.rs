@@ -0,0 +1,81 @@
+use crate::{
+    Prm, Error,
+    Shape, Tensor, HostTensor, IndexIter,
+};
+
+
+/// Mutable view of a sub-region of a host tensor, created by `HostTensor::slice_mut`.
+///
+/// Writes through the view modify the tensor it was taken from, so it can be used
+/// to assemble an output block by block without copying it around.
+/// The view borrows the tensor mutably, so the tensor cannot be read or cloned while the view exists.
+pub struct TensorViewMut<'a, T: Prm> {
+    /// The whole buffer of the tensor.
+    data: &'a mut [T],
+    shape: Shape,
+    strides: Vec<isize>,
+    offset: usize,
+}
+
+impl<'a, T: Prm> TensorViewMut<'a, T> {
+    pub(crate) fn new(data: &'a mut [T], shape: Shape, strides: Vec<isize>, offset: usize) -> Self {
+        Self { data, shape, strides, offset }
+    }
+
+    /// Shape of the sub-region.
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+    fn indices(&self) -> IndexIter {
+        IndexIter::new(&self.shape, &self.strides, self.offset)
+    }
+    fn buffer_index(&self, pos: &[usize]) -> Result<usize, Error> {
+        let mut index = self.offset as isize;
+        for (i, &x) in pos.iter().enumerate() {
+            if x >= self.shape[i] {
+                return Err(Error::BadIndex(pos.to_vec()));
+            }
+            index += x as isize * self.strides.get(i).cloned().unwrap_or(0);
+        }
+        Ok(index as usize)
+    }
+
+    /// Returns element at specified position of the view, see `HostTensor::get`.
+    pub fn get(&self, pos: &[usize]) -> Result<T, Error> {
+        self.buffer_index(pos).map(|i| self.data[i])
+    }
+    /// Writes element at specified position of the view.
+    ///
+    /// Fails with `Error::BadIndex` if position is out of bounds.
+    pub fn set(&mut self, pos: &[usize], value: T) -> Result<(), Error> {
+        let i = self.buffer_index(pos)?;
+        self.data[i] = value;
+        Ok(())
+    }
+
+    /// Fills the sub-region with the `value`.
+    pub fn fill(&mut self, value: T) {
+        self.apply(|x| *x = value);
+    }
+    /// Copies `src` into the sub-region, a scalar or a lower-rank `src` is broadcasted over it.
+    ///
+    /// Fails with `Error::ShapeMismatch` if `src` cannot be broadcasted to the shape of the view.
+    pub fn copy_from(&mut self, src: &HostTensor<T>) -> Result<(), Error> {
+        let src = src.broadcast_to(&self.shape)
+            .map_err(|_| Error::ShapeMismatch(self.shape.clone(), src.shape().clone()))?;
+        let mut values = src.iter();
+        self.apply(|x| *x = *values.next().unwrap());
+        Ok(())
+    }
+    /// Calls `f` for each element of the sub-region in flattened order.
+    pub fn apply<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        for i in self.indices() {
+            f(&mut self.data[i]);
+        }
+    }
+
+    /// Copy of the sub-region as a new tensor.
+    pub fn to_tensor(&self) -> HostTensor<T> {
+        HostTensor::from_plain_vec(self.indices().map(|i| self.data[i]).collect(), &self.shape)
+    }
+}
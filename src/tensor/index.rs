@@ -0,0 +1,98 @@
+use crate::Shape;
+
+
+/// Strides of contiguous tensor of specified shape.
+pub fn contiguous_strides(shape: &Shape) -> Vec<isize> {
+    shape.iter().scan(1, |stride, &len| {
+        let cur = *stride;
+        *stride *= len as isize;
+        Some(cur)
+    }).collect()
+}
+
+/// Iterator over buffer indices of tensor elements taken in flattened order.
+///
+/// The first axis is the fastest one.
+#[derive(Clone, Debug)]
+pub struct IndexIter {
+    shape: Vec<usize>,
+    strides: Vec<isize>,
+    position: Vec<usize>,
+    index: isize,
+    remaining: usize,
+}
+
+impl IndexIter {
+    /// Create iterator over tensor elements with specified shape, strides and offset.
+    ///
+    /// Stride of axis that is missing from `strides` is considered to be zero.
+    pub fn new(shape: &Shape, strides: &[isize], offset: usize) -> Self {
+        let shape = shape.as_slice().to_vec();
+        let strides = (0..shape.len())
+            .map(|i| strides.get(i).cloned().unwrap_or(0))
+            .collect::<Vec<_>>();
+        Self {
+            remaining: shape.iter().product(),
+            position: vec![0; shape.len()],
+            shape,
+            strides,
+            index: offset as isize,
+        }
+    }
+}
+
+impl Iterator for IndexIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let index = self.index as usize;
+        self.remaining -= 1;
+        if self.remaining > 0 {
+            for i in 0..self.shape.len() {
+                self.position[i] += 1;
+                self.index += self.strides[i];
+                if self.position[i] < self.shape[i] {
+                    break;
+                }
+                self.index -= self.strides[i] * self.shape[i] as isize;
+                self.position[i] = 0;
+            }
+        }
+        Some(index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for IndexIter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape;
+
+    #[test]
+    fn contiguous() {
+        let shape = shape![2, 3, 4];
+        let strides = contiguous_strides(&shape);
+        assert_eq!(strides, [1, 2, 6]);
+        assert!(IndexIter::new(&shape, &strides, 0).eq(0..24));
+    }
+
+    #[test]
+    fn strided() {
+        let iter = IndexIter::new(&shape![2, 3], &[3, 1], 1);
+        assert_eq!(iter.collect::<Vec<_>>(), [1, 4, 2, 5, 3, 6]);
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(IndexIter::new(&shape![2, 0, 3], &[1, 2, 0], 0).count(), 0);
+        assert_eq!(IndexIter::new(&shape![], &[], 5).collect::<Vec<_>>(), [5]);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{Shape, Error};
+
+
+/// Interpolation mode of `interpolate2d`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterpMode {
+    /// Each output pixel takes the value of the source pixel it falls into.
+    Nearest,
+    /// Each output pixel is a weighted mean of the four nearest source pixels by their centers.
+    Bilinear,
+}
+
+/// Layout of 2D interpolation of images of shape `[w, h, ...]` where the rest axes enumerate planes.
+pub(crate) struct InterpLayout {
+    pub w: usize,
+    pub h: usize,
+    pub out_w: usize,
+    pub out_h: usize,
+    /// Number of planes, e.g. `channels * batch`.
+    pub planes: usize,
+    /// Shape of the result.
+    pub shape: Shape,
+}
+
+impl InterpLayout {
+    /// Fails with `Error::BadRank` if the shape has more than four dimensions and
+    /// with `Error::BadArgument` if the output isn't empty but the source is.
+    pub(crate) fn new(shape: &Shape, out_h: usize, out_w: usize) -> Result<Self, Error> {
+        if shape.len() > 4 {
+            return Err(Error::BadRank { expected: 4, found: shape.len() });
+        }
+        let (w, h) = (shape[0], shape[1]);
+        if (w == 0 || h == 0) && out_w * out_h != 0 {
+            return Err(Error::BadArgument(format!("cannot interpolate empty image of size {}x{}", h, w)));
+        }
+        let planes = shape.slice(2..).content();
+        let out = Shape::from([out_w, out_h, shape[2], shape[3]]);
+        out.checked_content()?;
+        Ok(Self { w, h, out_w, out_h, planes, shape: out })
+    }
+}
+
+/// Position of the nearest source pixel for output pixel `i` along an axis of length `len` resized to `out`.
+pub(crate) fn nearest_source(i: usize, len: usize, out: usize) -> usize {
+    i * len / out
+}
+
+/// Two source pixels for output pixel `i` and the weight of the second one, pixel centers are aligned.
+pub(crate) fn bilinear_source(i: usize, len: usize, out: usize) -> (usize, usize, f64) {
+    let x = ((i as f64 + 0.5) * len as f64 / out as f64 - 0.5).max(0.0);
+    let a = usize::min(x as usize, len - 1);
+    (a, usize::min(a + 1, len - 1), x - a as f64)
+}
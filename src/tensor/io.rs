@@ -0,0 +1,57 @@
+use std::io::{self, Read, Write};
+use crate::{LeBytes, Error};
+
+
+/// Number of elements read or written at once.
+pub(crate) const CHUNK_LEN: usize = 1 << 16;
+
+/// Reads bytes until the buffer is full or the end of data is reached, returns number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut pos = 0;
+    while pos < buf.len() {
+        match reader.read(&mut buf[pos..]) {
+            Ok(0) => break,
+            Ok(n) => pos += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(pos)
+}
+
+/// Reads `len` little-endian elements by chunks of `CHUNK_LEN`, passes offset of each chunk and its elements to `f`.
+///
+/// Fails with `Error::BadSize` (in bytes) if data ends before `len` elements are read.
+pub(crate) fn read_chunks<T: LeBytes, R: Read, F>(reader: &mut R, len: usize, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(usize, &[T]) -> Result<(), Error>,
+{
+    let mut bytes = vec![0; usize::min(len, CHUNK_LEN) * T::SIZE];
+    let mut chunk = Vec::with_capacity(usize::min(len, CHUNK_LEN));
+    let mut offset = 0;
+    while offset < len {
+        let n = usize::min(len - offset, CHUNK_LEN);
+        let size = read_full(reader, &mut bytes[..n * T::SIZE])?;
+        if size < n * T::SIZE {
+            return Err(Error::BadSize { expected: len * T::SIZE, found: offset * T::SIZE + size });
+        }
+        chunk.clear();
+        chunk.extend(bytes[..size].chunks_exact(T::SIZE).map(T::read_le));
+        f(offset, &chunk)?;
+        offset += n;
+    }
+    Ok(())
+}
+
+/// Writes elements in little-endian representation by chunks of `CHUNK_LEN`.
+pub(crate) fn write_elements<T: LeBytes, W: Write, I: Iterator<Item = T>>(writer: &mut W, mut data: I) -> Result<(), Error> {
+    let mut bytes = vec![0; CHUNK_LEN * T::SIZE];
+    loop {
+        // Chunk of bytes goes first, so that no element is taken from `data` when the buffer is full.
+        let n = bytes.chunks_exact_mut(T::SIZE).zip(data.by_ref()).map(|(b, x)| x.write_le(b)).count();
+        if n == 0 {
+            return Ok(());
+        }
+        writer.write_all(&bytes[..n * T::SIZE])?;
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{Float, Shape, Error};
+
+
+/// Dimensions of matrix product.
+///
+/// Matrix of `m` rows and `k` columns has shape `[k, m]` (rows are placed along the first axis as in `from_2d`),
+/// so its element at row `r` and column `l` has flat index `l + k * r`.
+pub(crate) struct MatmulLayout {
+    /// Number of rows of the left operand and the result.
+    pub m: usize,
+    /// Number of columns of the left operand and rows of the right one.
+    pub k: usize,
+    /// Number of columns of the right operand and the result.
+    pub n: usize,
+}
+
+impl MatmulLayout {
+    /// Checks that operands are matrices and the number of columns of `a` equals the number of rows of `b`.
+    pub(crate) fn new(a: &Shape, b: &Shape) -> Result<Self, Error> {
+        if let Some(s) = [a, b].iter().find(|s| s.len() > 2) {
+            return Err(Error::BadRank { expected: 2, found: s.len() });
+        }
+        if a[0] != b[1] {
+            return Err(Error::ShapeMismatch(a.clone(), b.clone()));
+        }
+        Ok(Self { m: a[1], k: a[0], n: b[0] })
+    }
+    /// Layout of the product of operands transposed according to the flags, see `new`.
+    ///
+    /// Shapes in `Error::ShapeMismatch` are the shapes of the transposed operands.
+    #[cfg(feature = "device")]
+    pub(crate) fn with_flags(a: &Shape, b: &Shape, trans_a: bool, trans_b: bool) -> Result<Self, Error> {
+        if let Some(s) = [a, b].iter().find(|s| s.len() > 2) {
+            return Err(Error::BadRank { expected: 2, found: s.len() });
+        }
+        let swap = |s: &Shape, t: bool| if t { Shape::from([s[1], s[0]]) } else { s.clone() };
+        Self::new(&swap(a, trans_a), &swap(b, trans_b))
+    }
+    /// Shape of the result.
+    pub(crate) fn shape(&self) -> Shape {
+        Shape::from([self.n, self.m])
+    }
+}
+
+/// Dimensions of pairwise distances between rows of two matrices, see `MatmulLayout` for the matrix layout.
+pub(crate) struct CdistLayout {
+    /// Number of rows of the left operand, and of the result.
+    pub n: usize,
+    /// Number of features, i.e. columns of both operands.
+    pub d: usize,
+    /// Number of rows of the right operand, and columns of the result.
+    pub m: usize,
+}
+
+impl CdistLayout {
+    /// Checks that operands are matrices with the same number of columns.
+    pub(crate) fn new(a: &Shape, b: &Shape) -> Result<Self, Error> {
+        if let Some(s) = [a, b].iter().find(|s| s.len() > 2) {
+            return Err(Error::BadRank { expected: 2, found: s.len() });
+        }
+        if a[0] != b[0] {
+            return Err(Error::ShapeMismatch(a.clone(), b.clone()));
+        }
+        Ok(Self { n: a[1], d: a[0], m: b[1] })
+    }
+    /// Shape of the result.
+    pub(crate) fn shape(&self) -> Shape {
+        Shape::from([self.m, self.n])
+    }
+}
+
+/// Dimensions of Kronecker product, see `MatmulLayout` for the matrix layout.
+///
+/// Element at row `i1 * m2 + i2` and column `j1 * n2 + j2` of the result is the product of elements
+/// at row `i1` and column `j1` of the left operand and at row `i2` and column `j2` of the right one.
+pub(crate) struct KronLayout {
+    /// Number of rows and columns of the left operand.
+    pub m1: usize,
+    pub n1: usize,
+    /// Number of rows and columns of the right operand.
+    pub m2: usize,
+    pub n2: usize,
+}
+
+impl KronLayout {
+    /// Checks that operands are matrices and the size of the result doesn't overflow.
+    pub(crate) fn new(a: &Shape, b: &Shape) -> Result<Self, Error> {
+        if let Some(s) = [a, b].iter().find(|s| s.len() > 2) {
+            return Err(Error::BadRank { expected: 2, found: s.len() });
+        }
+        let layout = Self { m1: a[1], n1: a[0], m2: b[1], n2: b[0] };
+        match (layout.n1.checked_mul(layout.n2), layout.m1.checked_mul(layout.m2)) {
+            (Some(n), Some(m)) => Shape::from([n, m]).checked_content()?,
+            _ => return Err(Error::Overflow),
+        };
+        Ok(layout)
+    }
+    /// Shape of the result.
+    pub(crate) fn shape(&self) -> Shape {
+        Shape::from([self.n1 * self.n2, self.m1 * self.m2])
+    }
+    /// Flat indices of elements of the left and right operands multiplied to get the element `i` of the result.
+    pub(crate) fn sources(&self, i: usize) -> (usize, usize) {
+        let n = self.n1 * self.n2;
+        let (row, col) = (i / n, i % n);
+        let (i1, i2, j1, j2) = (row / self.m2, row % self.m2, col / self.n2, col % self.n2);
+        (j1 + self.n1 * i1, j2 + self.n2 * i2)
+    }
+}
+
+/// Checks the order `p` of the distance, it must be positive (possibly infinite).
+pub(crate) fn check_cdist_p<T: Float>(p: T) -> Result<(), Error> {
+    if p > T::zero() {
+        Ok(())
+    } else {
+        Err(Error::BadArgument("cdist order must be positive".into()))
+    }
+}
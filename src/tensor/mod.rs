@@ -1,12 +1,46 @@
 mod shape;
 pub use shape::*;
 
+#[allow(clippy::module_inception)]
 mod tensor;
 pub use tensor::*;
 
+mod ranked;
+pub use ranked::*;
+
+mod builder;
+pub use builder::*;
+
+mod index;
+pub(crate) use index::*;
+
 mod common;
 pub(crate) use common::*;
 
+mod nn;
+pub(crate) use nn::*;
+
+mod select;
+pub(crate) use select::*;
+
+mod reduce;
+pub(crate) use reduce::*;
+pub use reduce::ReduceOp;
+
+mod matmul;
+pub(crate) use matmul::*;
+
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "std")]
+pub(crate) use io::*;
+
+mod interp;
+pub use interp::*;
+
+mod compare;
+pub use compare::*;
+
 mod host;
 pub use host::*;
 
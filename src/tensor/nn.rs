@@ -0,0 +1,187 @@
+use num_traits::NumCast;
+use crate::{Prm, Float, Shape, Tensor, Error};
+
+
+/// Checks arguments of layer normalization and returns the number of elements in each normalized group.
+pub(crate) fn check_layer_norm<T: Prm, X: Tensor<T>>(
+    x: &X, normalized_shape: &[usize], gamma: Option<&X>, beta: Option<&X>,
+) -> Result<usize, Error> {
+    let shape = Shape::from(normalized_shape);
+    if x.shape().slice(..normalized_shape.len()) != shape {
+        return Err(Error::ShapeMismatch(x.shape().clone(), shape));
+    }
+    for t in gamma.iter().chain(beta.iter()) {
+        if *t.shape() != shape {
+            return Err(Error::ShapeMismatch(t.shape().clone(), shape));
+        }
+    }
+    Ok(shape.content())
+}
+
+/// Checks that the tensor is a matrix, returns the numbers of its columns and rows.
+pub(crate) fn check_triangle(shape: &Shape) -> Result<(usize, usize), Error> {
+    if shape.len() > 2 {
+        return Err(Error::BadRank { expected: 2, found: shape.len() });
+    }
+    Ok((shape[0], shape[1]))
+}
+
+/// Whether the element at `col` and `row` of a matrix is kept by `triu` if `upper` is set or by `tril` otherwise.
+pub(crate) fn in_triangle(col: usize, row: usize, diagonal: isize, upper: bool) -> bool {
+    let d = col as isize - row as isize;
+    if upper { d >= diagonal } else { d <= diagonal }
+}
+
+/// Checks that `bias` is a vector of the size of the first axis of `x`, returns that size.
+pub(crate) fn check_bias(x: &Shape, bias: &Shape) -> Result<usize, Error> {
+    if bias.len() > 1 {
+        return Err(Error::BadRank { expected: 1, found: bias.len() });
+    }
+    if bias[0] != x[0] {
+        return Err(Error::ShapeMismatch(x.clone(), bias.clone()));
+    }
+    Ok(bias[0])
+}
+
+/// Checks shapes of embedding operands.
+///
+/// Returns embedding size, number of embeddings and shape of the result.
+pub(crate) fn embedding_shape(weight: &Shape, indices: &Shape) -> Result<(usize, usize, Shape), Error> {
+    if weight.len() > 2 {
+        return Err(Error::BadRank { expected: 2, found: weight.len() });
+    }
+    let (dim, num) = (weight[0], weight[1]);
+    let shape = Shape::from(Some(dim).into_iter().chain(indices.iter().cloned()).collect::<Vec<_>>());
+    shape.checked_content()?;
+    Ok((dim, num, shape))
+}
+/// Checks that indices of embeddings are in bounds.
+pub(crate) fn check_embedding_indices<'a, I: IntoIterator<Item = &'a usize>>(indices: I, num: usize) -> Result<(), Error> {
+    match indices.into_iter().find(|&&i| i >= num) {
+        Some(&i) => Err(Error::BadIndex(vec![0, i])),
+        None => Ok(()),
+    }
+}
+
+/// Factor to scale gradients of global L2 `norm` down to `max_norm`, `None` if they don't need scaling.
+///
+/// Fails with `Error::BadArgument` if `max_norm` is negative or NaN.
+pub(crate) fn clip_scale<T: Float>(norm: T, max_norm: T) -> Result<Option<T>, Error> {
+    if max_norm.is_nan() || max_norm < T::zero() {
+        return Err(Error::BadArgument("max_norm must be non-negative".into()));
+    }
+    Ok(if norm > max_norm { Some(max_norm / norm) } else { None })
+}
+
+/// Checks that the gradient of an activation has the same shape as its input.
+pub(crate) fn check_grad(grad: &Shape, input: &Shape) -> Result<(), Error> {
+    if grad != input {
+        return Err(Error::ShapeMismatch(grad.clone(), input.clone()));
+    }
+    Ok(())
+}
+
+/// Scale and zero point of affine int8 quantization that maps `[min, max]` extended to include zero onto `[-128, 127]`.
+///
+/// Zero is then represented exactly, and the scale of the range `[0, 0]` is `1`.
+pub(crate) fn quant_params<T: Float>(min: T, max: T) -> (T, i32) {
+    let (min, max) = (min.min(T::zero()), max.max(T::zero()));
+    let scale = (max - min) / T::from(255).unwrap();
+    let scale = if scale > T::zero() { scale } else { T::one() };
+    let zero_point = (T::from(-128).unwrap() - min / scale).round().max(T::from(-128).unwrap()).min(T::from(127).unwrap());
+    (scale, zero_point.to_i32().unwrap())
+}
+
+/// Affine quantization of a value, NaN is mapped to zero as by OpenCL `convert_char_sat`.
+pub(crate) fn quantize_int8<T: Float>(x: T, scale: T, zero_point: i32) -> i8 {
+    let q = (x / scale).round() + T::from(zero_point).unwrap();
+    if q.is_nan() {
+        0
+    } else {
+        NumCast::from(q.max(T::from(i8::MIN).unwrap()).min(T::from(i8::MAX).unwrap())).unwrap()
+    }
+}
+
+/// Layout of 2D convolution of input of shape `[w, h, c_in, n]` with weight of shape `[kw, kh, c_in, c_out]`.
+///
+/// The output has shape `[out_w, out_h, c_out, n]`, these are NCHW and OIHW layouts in row-major terms.
+/// Input is padded with `padding` zeros on each side and the kernel moves by `stride` pixels.
+pub(crate) struct Conv2dLayout {
+    pub w: usize,
+    pub h: usize,
+    pub c_in: usize,
+    pub n: usize,
+    pub kw: usize,
+    pub kh: usize,
+    pub c_out: usize,
+    pub out_w: usize,
+    pub out_h: usize,
+    pub stride: usize,
+    pub padding: usize,
+}
+
+impl Conv2dLayout {
+    /// Fails with `Error::BadRank` if input or weight has more than four dimensions,
+    /// with `Error::ShapeMismatch` if their numbers of input channels differ
+    /// and with `Error::BadArgument` if `stride` is zero or the kernel is larger than the padded input.
+    pub(crate) fn new(input: &Shape, weight: &Shape, stride: usize, padding: usize) -> Result<Self, Error> {
+        for s in [input, weight] {
+            if s.len() > 4 {
+                return Err(Error::BadRank { expected: 4, found: s.len() });
+            }
+        }
+        if input[2] != weight[2] {
+            return Err(Error::ShapeMismatch(input.clone(), weight.clone()));
+        }
+        if stride == 0 {
+            return Err(Error::BadArgument("conv2d stride must not be zero".into()));
+        }
+        let (w, h) = (input[0] + 2 * padding, input[1] + 2 * padding);
+        let (kw, kh) = (weight[0], weight[1]);
+        if kw == 0 || kh == 0 || kw > w || kh > h {
+            return Err(Error::BadArgument(format!("cannot convolve {}x{} padded input with {}x{} kernel", h, w, kh, kw)));
+        }
+        let layout = Self {
+            w: input[0], h: input[1], c_in: input[2], n: input[3],
+            kw, kh, c_out: weight[3],
+            out_w: (w - kw) / stride + 1, out_h: (h - kh) / stride + 1,
+            stride, padding,
+        };
+        layout.output_shape().checked_content()?;
+        Ok(layout)
+    }
+    pub(crate) fn output_shape(&self) -> Shape {
+        Shape::from([self.out_w, self.out_h, self.c_out, self.n])
+    }
+    /// Calls `f` with flat indices of the output, input and weight elements for each product of the convolution.
+    ///
+    /// Products with padding are skipped.
+    pub(crate) fn for_each_tap<F: FnMut(usize, usize, usize)>(&self, mut f: F) {
+        for b in 0..self.n {
+            for co in 0..self.c_out {
+                for oy in 0..self.out_h {
+                    for ox in 0..self.out_w {
+                        let out = ox + self.out_w * (oy + self.out_h * (co + self.c_out * b));
+                        for ci in 0..self.c_in {
+                            for j in 0..self.kh {
+                                let y = (oy * self.stride + j).wrapping_sub(self.padding);
+                                if y >= self.h {
+                                    continue;
+                                }
+                                for i in 0..self.kw {
+                                    let x = (ox * self.stride + i).wrapping_sub(self.padding);
+                                    if x >= self.w {
+                                        continue;
+                                    }
+                                    let input = x + self.w * (y + self.h * (ci + self.c_in * b));
+                                    let weight = i + self.kw * (j + self.kh * (ci + self.c_in * co));
+                                    f(out, input, weight);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    ops::Deref,
+};
+use crate::{
+    Prm, Num, Buffer, Shape, Tensor, HostTensor, Error, Range,
+};
+#[cfg(feature = "device")]
+use crate::{Interop, DeviceTensor};
+
+
+/// Tensor with number of dimensions known at compile time.
+///
+/// It's a wrapper over dynamic tensor `X` which guarantees that the tensor has at most `N` dimensions
+/// (remember that trailing axes of size `1` are ignored, so e.g. a vector is also a matrix of a single column).
+/// Operations that require specific rank could take ranked tensor to catch rank errors at compile time.
+///
+/// Methods of the dynamic tensor are available through `Deref`. Mutable access is provided only by the methods
+/// that can't change the shape, so the rank and the shape of the tensor always agree.
+pub struct RankedTensor<X, const N: usize> {
+    tensor: X,
+    shape: [usize; N],
+}
+
+/// Tensor of rank 1.
+pub type Vector<X> = RankedTensor<X, 1>;
+/// Tensor of rank 2.
+pub type Matrix<X> = RankedTensor<X, 2>;
+
+fn shape_array<const N: usize>(shape: &Shape) -> Result<[usize; N], Error> {
+    if shape.len() > N {
+        return Err(Error::BadRank { expected: N, found: shape.len() });
+    }
+    let mut array = [1; N];
+    for (i, x) in array.iter_mut().enumerate() {
+        *x = shape[i];
+    }
+    Ok(array)
+}
+
+impl<X, const N: usize> RankedTensor<X, N> {
+    /// Wraps a dynamic tensor.
+    /// Fails with `Error::BadRank` if the tensor has more than `N` dimensions.
+    pub fn new<T: Prm>(tensor: X) -> Result<Self, Error> where X: Tensor<T> {
+        shape_array(tensor.shape()).map(|shape| Self { tensor, shape })
+    }
+
+    /// Create tensor filled with value on the specified hardware.
+    pub fn new_filled_in<T: Prm>(
+        context: &<X::Buffer as Buffer<T>>::Context, shape: [usize; N], value: T,
+    ) -> Result<Self, Error> where X: Tensor<T> {
+        X::new_filled_in(context, &Shape::from(shape), value).and_then(Self::new)
+    }
+    /// Create tensor filled with zeros on the specified hardware.
+    pub fn new_zeroed_in<T: Prm>(
+        context: &<X::Buffer as Buffer<T>>::Context, shape: [usize; N],
+    ) -> Result<Self, Error> where X: Tensor<T> {
+        X::new_zeroed_in(context, &Shape::from(shape)).and_then(Self::new)
+    }
+
+    /// Dimensions of the tensor.
+    pub fn shape(&self) -> [usize; N] {
+        self.shape
+    }
+
+    /// Returns a new tensor with the same data but other shape, see `Tensor::reshape`.
+    pub fn reshape<T: Prm, const M: usize>(&self, shape: [usize; M]) -> Result<RankedTensor<X, M>, Error> where X: Tensor<T> {
+        self.tensor.reshape(&Shape::from(shape)).and_then(RankedTensor::new)
+    }
+
+    /// Returns underlying dynamic tensor.
+    pub fn into_inner(self) -> X {
+        self.tensor
+    }
+
+    /// Store data from slice to the tensor, see `Tensor::store`.
+    pub fn store<T: Prm>(&mut self, src: &[T]) -> Result<(), Error> where X: Tensor<T> {
+        self.tensor.store(src)
+    }
+    /// Fills the tensor with the value, see `Tensor::fill`.
+    pub fn fill<T: Prm>(&mut self, value: T) -> Result<(), Error> where X: Tensor<T> {
+        self.tensor.fill(value)
+    }
+    /// Overwrites elements of the tensor with elements of `src` of the same rank, see `Tensor::copy_from`.
+    pub fn copy_from<T: Prm>(&mut self, src: &Self) -> Result<(), Error> where X: Tensor<T> {
+        self.tensor.copy_from(&src.tensor)
+    }
+    /// Overwrites the sub-region selected by `ranges` with elements of `src`, see `Tensor::assign_slice`.
+    pub fn assign_slice<T: Prm>(&mut self, ranges: &[Range], src: &X) -> Result<(), Error> where X: Tensor<T> {
+        self.tensor.assign_slice(ranges, src)
+    }
+}
+
+impl<X> RankedTensor<X, 2> {
+    /// Returns a transposed matrix view.
+    pub fn transpose<T: Prm>(&self) -> Self where X: Tensor<T> {
+        Self {
+            tensor: self.tensor.transpose(),
+            shape: [self.shape[1], self.shape[0]],
+        }
+    }
+}
+
+impl<T: Prm + Num + 'static> Matrix<HostTensor<T>> {
+    /// Matrix product, see `HostTensor::matmul`.
+    ///
+    /// Both operands are matrices, so only their inner dimensions are checked at runtime.
+    pub fn matmul(&self, other: &Self) -> Result<Self, Error> {
+        self.tensor.matmul(&other.tensor).and_then(Self::new)
+    }
+}
+#[cfg(feature = "device")]
+impl<T: Prm + Interop + Num + PartialOrd> Matrix<DeviceTensor<T>> {
+    /// Matrix product, see `DeviceTensor::matmul`.
+    pub fn matmul(&self, other: &Self) -> Result<Self, Error> {
+        self.tensor.matmul(&other.tensor).and_then(Self::new)
+    }
+}
+
+impl<X, const N: usize> Deref for RankedTensor<X, N> {
+    type Target = X;
+    fn deref(&self) -> &X {
+        &self.tensor
+    }
+}
@@ -0,0 +1,139 @@
+use num_traits::PrimInt;
+use crate::{Shape, Error};
+
+
+/// Associative operation of a full reduction, see `HostTensor::reduce`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReduceOp {
+    /// Sum, its identity is zero.
+    Sum,
+    /// Product, its identity is one.
+    Prod,
+    /// Result is unspecified if any of elements is NaN, as for `maximum`.
+    Max,
+    /// Result is unspecified if any of elements is NaN, as for `minimum`.
+    Min,
+}
+
+impl ReduceOp {
+    /// Combines two values.
+    pub(crate) fn apply<T: num_traits::Num + PartialOrd>(self, a: T, b: T) -> T {
+        match self {
+            ReduceOp::Sum => a + b,
+            ReduceOp::Prod => a * b,
+            ReduceOp::Max => if b > a { b } else { a },
+            ReduceOp::Min => if b < a { b } else { a },
+        }
+    }
+    /// OpenCL C expression combining `a` and `b`.
+    #[cfg(feature = "device")]
+    pub(crate) fn dev_expr(self) -> &'static str {
+        match self {
+            ReduceOp::Sum => "a + b",
+            ReduceOp::Prod => "a * b",
+            ReduceOp::Max => "max(a, b)",
+            ReduceOp::Min => "min(a, b)",
+        }
+    }
+}
+
+/// Layout of reduction along an axis.
+///
+/// Elements of the source in flattened order are indexed as `j + inner * (k + len * o)`,
+/// where `k` is the position along the axis, and the result element is `j + inner * o`.
+pub(crate) struct ReduceLayout {
+    /// Number of elements in a slice of the source along the axis.
+    pub inner: usize,
+    /// Source length along the axis.
+    pub len: usize,
+    /// Shape of the result, the axis is removed or kept as `1`.
+    pub shape: Shape,
+}
+
+impl ReduceLayout {
+    /// Layout of reduction along the `axis`, it is kept in the result shape as `1` if `keepdims` is set.
+    pub(crate) fn new(shape: &Shape, axis: usize, keepdims: bool) -> Self {
+        let mut dims = shape.as_slice().to_vec();
+        if axis < dims.len() {
+            if keepdims {
+                dims[axis] = 1;
+            } else {
+                dims.remove(axis);
+            }
+        }
+        Self { inner: shape.slice(..axis).content(), len: shape[axis], shape: Shape::from(dims) }
+    }
+
+    /// Flat indices in the source of elements reduced into `i`-th element of the result.
+    pub(crate) fn source_indices(&self, i: usize) -> impl Iterator<Item = usize> {
+        let (j, o, inner, len) = (i % self.inner, i / self.inner, self.inner, self.len);
+        (0..len).map(move |k| j + inner * (k + len * o))
+    }
+}
+
+/// Lower bound of the product of norms in `cosine_similarity`, so that zero vectors have zero similarity instead of NaN.
+pub(crate) const COSINE_EPS: f64 = 1e-8;
+
+/// Axes of multi-axis reduction in descending order, so that removing one doesn't shift the others.
+///
+/// Fails with `Error::BadAxes` if axes are repeated.
+pub(crate) fn descending_axes(axes: &[usize]) -> Result<Vec<usize>, Error> {
+    let mut sorted = axes.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    sorted.dedup();
+    if sorted.len() != axes.len() {
+        return Err(Error::BadAxes(axes.to_vec()));
+    }
+    Ok(sorted)
+}
+
+/// Checks that mask could be broadcasted to the shape of the tensor.
+pub(crate) fn check_mask(shape: &Shape, mask: &Shape) -> Result<(), Error> {
+    if shape.broadcast(mask)? != *shape {
+        return Err(Error::ShapeMismatch(shape.clone(), mask.clone()));
+    }
+    Ok(())
+}
+
+/// Reduction of a tensor to accumulate it into a tensor of the `shape`.
+///
+/// Returns axes along which the tensor should be summed, in descending order so that removing one doesn't shift the others,
+/// and the shape of the sum with these axes kept as `1`.
+/// Fails with `Error::ShapeMismatch` if shapes aren't broadcast-compatible.
+pub(crate) fn accumulate_layout(shape: &Shape, other: &Shape) -> Result<(Vec<usize>, Shape), Error> {
+    shape.broadcast(other)?;
+    let axes = (0..other.len()).rev().filter(|&i| shape[i] == 1 && other[i] != 1).collect::<Vec<_>>();
+    let mut kept = other.clone();
+    for &i in axes.iter() {
+        kept[i] = 1;
+    }
+    Ok((axes, kept))
+}
+
+/// Length of the result of `bincount` of a tensor with `count` elements in range from `min` to `max`.
+///
+/// Fails with `Error::BadArgument` if there are negative values and `Error::Overflow` if the maximum doesn't fit `usize`.
+pub(crate) fn bincount_len<T: PrimInt>(count: usize, min: T, max: T, minlength: usize) -> Result<usize, Error> {
+    if count == 0 {
+        return Ok(minlength);
+    }
+    if min < T::zero() {
+        return Err(Error::BadArgument("bincount of negative values".to_string()));
+    }
+    let max = max.to_usize().and_then(|m| m.checked_add(1)).ok_or(Error::Overflow)?;
+    Ok(usize::max(max, minlength))
+}
+
+/// Checks arguments of `confusion_matrix` given the greatest predicted and target classes,
+/// returns the number of elements of the matrix.
+///
+/// Fails with `Error::ShapeMismatch` if shapes differ and `Error::BadIndex` if any class isn't less than `num_classes`.
+pub(crate) fn check_confusion(predictions: &Shape, targets: &Shape, max: usize, num_classes: usize) -> Result<usize, Error> {
+    if predictions != targets {
+        return Err(Error::ShapeMismatch(predictions.clone(), targets.clone()));
+    }
+    if predictions.content() > 0 && max >= num_classes {
+        return Err(Error::BadIndex(vec![max]));
+    }
+    num_classes.checked_mul(num_classes).ok_or(Error::Overflow)
+}
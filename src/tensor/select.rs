@@ -0,0 +1,129 @@
+use crate::{Prm, Shape, Tensor, Index, Range, Error};
+
+
+/// Layout of `index_select` operation.
+pub(crate) struct SelectLayout {
+    /// Number of elements in a slice of the source along the axis.
+    pub inner: usize,
+    /// Source length along the axis.
+    pub len: usize,
+    /// Shape of the result.
+    pub shape: Shape,
+}
+
+impl SelectLayout {
+    /// Checks arguments of `index_select` of tensor with specified shape.
+    ///
+    /// Indices must be a vector of values less than the length of the axis.
+    pub(crate) fn new(shape: &Shape, axis: usize, indices_shape: &Shape, indices: &[usize]) -> Result<Self, Error> {
+        if indices_shape.len() > 1 {
+            return Err(Error::BadRank { expected: 1, found: indices_shape.len() });
+        }
+        let len = shape[axis];
+        if let Some(&i) = indices.iter().find(|&&i| i >= len) {
+            let mut pos = vec![0; axis + 1];
+            pos[axis] = i;
+            return Err(Error::BadIndex(pos));
+        }
+        let mut dims = shape.as_slice().to_vec();
+        dims.resize(usize::max(dims.len(), axis + 1), 1);
+        dims[axis] = indices.len();
+        let result = Shape::from(dims);
+        result.checked_content()?;
+        Ok(Self { inner: shape.slice(..axis).content(), len, shape: result })
+    }
+
+    /// Flat index in the source of `i`-th element of the result.
+    pub(crate) fn source_index(&self, i: usize, indices: &[usize]) -> usize {
+        let (j, r) = (i % self.inner, i / self.inner);
+        let (k, o) = (r % indices.len(), r / indices.len());
+        j + self.inner * (indices[k] + self.len * o)
+    }
+}
+
+/// Indices of `index_select` that repeat `k`-th slice along the `axis` consecutively `repeats[k]` times.
+///
+/// Fails with `Error::BadAxes` if the axis isn't less than the rank, `Error::BadRank` if repeats isn't a vector
+/// and `Error::BadSize` if the number of repeats differs from the length of the axis.
+pub(crate) fn interleave_indices(shape: &Shape, axis: usize, repeats_shape: &Shape, repeats: &[usize]) -> Result<Vec<usize>, Error> {
+    if axis >= shape.len() {
+        return Err(Error::BadAxes(vec![axis]));
+    }
+    if repeats_shape.len() > 1 {
+        return Err(Error::BadRank { expected: 1, found: repeats_shape.len() });
+    }
+    if repeats.len() != shape[axis] {
+        return Err(Error::BadSize { expected: shape[axis], found: repeats.len() });
+    }
+    repeats.iter().try_fold(0usize, |n, &r| n.checked_add(r)).ok_or(Error::Overflow)?;
+    Ok(repeats.iter().enumerate().flat_map(|(k, &r)| std::iter::repeat_n(k, r)).collect())
+}
+
+/// Checks that the `axis` of `diff` exists and is long enough for `n` differences.
+///
+/// Fails with `Error::BadAxes` if the axis isn't less than the rank and with `Error::BadArgument` if `n` exceeds its length.
+pub(crate) fn check_diff(shape: &Shape, n: usize, axis: usize) -> Result<(), Error> {
+    if axis >= shape.len() {
+        return Err(Error::BadAxes(vec![axis]));
+    }
+    if n > shape[axis] {
+        return Err(Error::BadArgument(format!("cannot take {} differences along axis {} of size {}", n, axis, shape[axis])));
+    }
+    Ok(())
+}
+/// Views of the tensor without the first and without the last element along the `axis`, their difference is `diff`.
+pub(crate) fn diff_views<T: Prm, X: Tensor<T>>(x: &X, axis: usize) -> Result<(X, X), Error> {
+    let mut index = vec![Index::Range(Range::new(None, None, 1)); axis + 1];
+    index[axis] = Index::Range(Range::new(Some(1), None, 1));
+    let hi = x.slice(&index)?;
+    index[axis] = Index::Range(Range::new(None, Some(x.shape()[axis] as isize - 1), 1));
+    Ok((hi, x.slice(&index)?))
+}
+
+/// Layout of `segment_sum` operation.
+///
+/// Elements of the source are indexed as `j + inner * (k + len * o)`,
+/// and each of them is added to the element `j + inner * (ids[k] + segments * o)` of the result.
+pub(crate) struct SegmentLayout {
+    /// Number of elements in a slice of the source along the axis.
+    pub inner: usize,
+    /// Source length along the axis.
+    pub len: usize,
+    /// Number of segments, that is the result length along the axis.
+    pub segments: usize,
+    /// Shape of the result.
+    pub shape: Shape,
+}
+
+impl SegmentLayout {
+    /// Checks arguments of `segment_sum` of tensor with specified shape.
+    ///
+    /// Segment ids must be a vector with an id for each position along the axis, all ids must be less than `segments`.
+    pub(crate) fn new(shape: &Shape, axis: usize, ids_shape: &Shape, ids: &[usize], segments: usize) -> Result<Self, Error> {
+        if ids_shape.len() > 1 {
+            return Err(Error::BadRank { expected: 1, found: ids_shape.len() });
+        }
+        let len = shape[axis];
+        if ids.len() != len {
+            return Err(Error::BadSize { expected: len, found: ids.len() });
+        }
+        if let Some(&i) = ids.iter().find(|&&i| i >= segments) {
+            let mut pos = vec![0; axis + 1];
+            pos[axis] = i;
+            return Err(Error::BadIndex(pos));
+        }
+        let mut dims = shape.as_slice().to_vec();
+        dims.resize(usize::max(dims.len(), axis + 1), 1);
+        dims[axis] = segments;
+        let result = Shape::from(dims);
+        result.checked_content()?;
+        Ok(Self { inner: shape.slice(..axis).content(), len, segments, shape: result })
+    }
+
+    /// Flat index in the result of `i`-th element of the source.
+    pub(crate) fn target_index(&self, i: usize, ids: &[usize]) -> usize {
+        let (j, r) = (i % self.inner, i / self.inner);
+        let (k, o) = (r % self.len, r / self.len);
+        j + self.inner * (ids[k] + self.segments * o)
+    }
+}
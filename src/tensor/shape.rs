@@ -1,8 +1,10 @@
 use std::{
-    cmp,
+    cmp, fmt,
+    hash::{Hash, Hasher},
     ops::{Index, IndexMut, RangeBounds, Bound},
     slice::{Iter, IterMut},
 };
+use crate::Error;
 
 #[macro_export]
 macro_rules! shape {
@@ -43,14 +45,19 @@ impl From<Vec<usize>> for Shape {
 }
 impl From<&[usize]> for Shape {
     fn from(slice: &[usize]) -> Self {
-        Self::from(slice.iter().cloned().collect::<Vec<_>>())
+        Self::from(slice.to_vec())
+    }
+}
+impl<const N: usize> From<[usize; N]> for Shape {
+    fn from(array: [usize; N]) -> Self {
+        Self::from(array.as_ref())
     }
 }
 
-impl Into<Vec<usize>> for Shape {
-    fn into(mut self) -> Vec<usize> {
-        trim_vec(&mut self.vec);
-        self.vec
+impl From<Shape> for Vec<usize> {
+    fn from(mut shape: Shape) -> Self {
+        trim_vec(&mut shape.vec);
+        shape.vec
     }
 }
 
@@ -59,12 +66,23 @@ impl PartialEq<Shape> for Shape {
         trim_slice(self.vec.as_slice()) == trim_slice(other.vec.as_slice())
     }
 }
+impl Eq for Shape {}
+/// Trailing `1`s are ignored as in `PartialEq`.
+impl Hash for Shape {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state)
+    }
+}
 
 impl Shape {
     /// Count of dimensions without trailing `1`s in the end.
     pub fn len(&self) -> usize {
         count_non_one(self.vec.as_slice())
     }
+    /// Whether the shape has no dimensions (i.e. it is a shape of a scalar).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
     /// Slice of dimension sizes.
     pub fn as_slice(&self) -> &[usize] {
@@ -76,16 +94,26 @@ impl Shape {
     }
 
     /// Iterator over dimension sizes.
-    pub fn iter(&self) -> Iter<usize> {
+    pub fn iter(&self) -> Iter<'_, usize> {
         trim_slice(self.vec.as_slice()).iter()
     }
     /// Mutable iterator over dimension sizes.
-    pub fn iter_mut(&mut self) -> IterMut<usize> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, usize> {
         trim_mut_slice(self.vec.as_mut_slice()).iter_mut()
     }
     /// Number of items in tensor of this shape.
     pub fn content(&self) -> usize {
-        self.iter().fold(1, |a, &x| a * x )
+        self.iter().product()
+    }
+    /// Number of items in tensor of this shape, fails with `Error::Overflow` if it doesn't fit in `usize`.
+    pub fn checked_content(&self) -> Result<usize, Error> {
+        self.iter().try_fold(1usize, |a, &x| a.checked_mul(x)).ok_or(Error::Overflow)
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_slice())
     }
 }
 
@@ -131,6 +159,54 @@ impl Shape {
     }
 }
 
+impl Shape {
+    /// Shape of the result of elementwise operation between tensors of shapes `self` and `other`.
+    ///
+    /// Shapes are compatible if each pair of their dimensions are equal or one of them is `1`.
+    /// Because of trailing axes of size `1` shapes are aligned by the first axis,
+    /// e.g. shapes `(x, y)` and `(x,)` are compatible.
+    /// Fails with `Error::ShapeMismatch` if shapes aren't compatible.
+    pub fn broadcast(&self, other: &Shape) -> Result<Shape, Error> {
+        let len = cmp::max(self.len(), other.len());
+        let mut vec = Vec::with_capacity(len);
+        for i in 0..len {
+            let (a, b) = (self[i], other[i]);
+            if a == b || b == 1 {
+                vec.push(a);
+            } else if a == 1 {
+                vec.push(b);
+            } else {
+                return Err(Error::ShapeMismatch(self.clone(), other.clone()));
+            }
+        }
+        Ok(Shape::from(vec))
+    }
+
+    /// Checks that the shape matches the pattern, where `None` matches any size.
+    ///
+    /// Pattern is extended with trailing `Some(1)`, so the shape must not have more dimensions than the pattern.
+    /// Fails with `Error::BadRank` if the shape has too many dimensions
+    /// and with `Error::BadArgument` describing the first mismatching axis otherwise.
+    pub fn check_pattern(&self, pattern: &[Option<usize>]) -> Result<(), Error> {
+        if self.len() > pattern.len() {
+            return Err(Error::BadRank { expected: pattern.len(), found: self.len() });
+        }
+        for (i, p) in pattern.iter().enumerate() {
+            match *p {
+                Some(n) if n != self[i] => {
+                    let pattern = pattern.iter().map(|p| p.map_or("_".to_string(), |n| n.to_string())).collect::<Vec<_>>();
+                    return Err(Error::BadArgument(format!(
+                        "axis {} of shape {} has size {}, expected {} by pattern [{}]",
+                        i, self, self[i], n, pattern.join(", "),
+                    )));
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -207,4 +283,30 @@ mod tests {
     fn content() {
         assert_eq!(shape![1, 2, 3].content(), 6);
     }
+
+    #[test]
+    fn broadcast() {
+        assert_eq!(shape![3, 1, 2].broadcast(&shape![3, 4]).unwrap(), shape![3, 4, 2]);
+        assert_eq!(shape![].broadcast(&shape![2, 5]).unwrap(), shape![2, 5]);
+        assert!(shape![3, 2].broadcast(&shape![2, 3]).is_err());
+    }
+
+    #[test]
+    fn check_pattern() {
+        assert!(shape![2, 3].check_pattern(&[None, Some(3)]).is_ok());
+        assert!(shape![2].check_pattern(&[Some(2), Some(1), None]).is_ok());
+        assert!(matches!(shape![2, 4].check_pattern(&[None, Some(3)]), Err(Error::BadArgument(_))));
+        assert!(matches!(shape![2, 3, 4].check_pattern(&[None, Some(3)]), Err(Error::BadRank { expected: 2, found: 3 })));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", shape![2, 3, 1]), "[2, 3]");
+    }
+
+    #[test]
+    fn checked_content() {
+        assert_eq!(shape![1, 2, 3].checked_content().unwrap(), 6);
+        assert!(shape![1 << 40, 1 << 40].checked_content().is_err());
+    }
 }
@@ -1,8 +1,13 @@
+use std::{mem, ptr, slice};
 use crate::{
-    Prm, Buffer, Shape,
+    Prm, Num, Float, LeBytes, Pod, Buffer, Shape, Error, ComparisonReport,
 };
 
 
+/// Parameters of 64-bit FNV-1a hash used by `Tensor::checksum`.
+const FNV1A_BASIS: u64 = 0xcbf29ce484222325;
+const FNV1A_PRIME: u64 = 0x100000001b3;
+
 /// Struture representing range for one dimension for tensor slicing operation.
 ///
 /// Both `begin` and `end` indices can be negative that means indexing from the end (e.g. `-1` means last element).
@@ -17,6 +22,19 @@ pub struct Range {
     pub step: isize,
 }
 
+impl Range {
+    /// Range with Python slice defaults for missing bounds, e.g. `Range::new(None, None, -1)` is `[::-1]`.
+    ///
+    /// Missing `start` and `end` are the ends of the axis in the direction of `step`: the first and past the last element
+    /// for a positive step, and the last and before the first element for a negative one, so `[5:1:-1]` is
+    /// `Range::new(Some(5), Some(1), -1)` and takes elements `5, 4, 3, 2`.
+    /// Zero step isn't checked here, slicing with it fails with `Error::BadArgument`.
+    pub fn new(start: Option<isize>, end: Option<isize>, step: isize) -> Self {
+        let (first, last) = if step < 0 { (-1, isize::MIN) } else { (0, isize::MAX) };
+        Self { start: start.unwrap_or(first), end: end.unwrap_or(last), step }
+    }
+}
+
 /// Index for one dimension for tensor slicing operaion.
 #[derive(Clone, Copy, Debug)]
 pub enum Index {
@@ -30,27 +48,512 @@ pub enum Index {
     NewAxis,
 }
 
+/// Correspondence between inputs and output axes of `Tensor::meshgrid`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshIndexing {
+    /// Input `k` varies along the output axis `k`, so the output shape is `[n0, n1, n2, ...]`.
+    Ij,
+    /// The first two inputs are swapped, so the output shape is `[n1, n0, n2, ...]` and `x` is the fastest axis.
+    Xy,
+}
+
+/// Checks axes for `swapaxes` and `moveaxis`, returns the number of axes to permute.
+fn permuted_rank(shape: &Shape, axes: &[usize]) -> Result<usize, Error> {
+    let rank = usize::max(shape.len(), 2);
+    if axes.iter().any(|&a| a >= rank) {
+        return Err(Error::BadAxes(axes.to_vec()));
+    }
+    Ok(rank)
+}
+
+/// Checks that the shape is at most three-dimensional for `to_chw` and `to_hwc`.
+fn check_image_rank(shape: &Shape) -> Result<(), Error> {
+    if shape.len() > 3 {
+        return Err(Error::BadRank { expected: 3, found: shape.len() });
+    }
+    Ok(())
+}
+
 /// Tensor a.k.a. N-dimensional array.
+///
+/// It is the interface shared by host and device tensors, so code written against it works with both of them.
+/// Backend-specific operations (e.g. elementwise arithmetic, which has different bounds on element type)
+/// remain inherent methods of the tensor types.
+///
+/// Implementors must provide constructors, `context`, `shape`, the views, copying and `load`/`store`.
+/// Methods `numel`, `assert_shape`, `to_vec` and `fill` are provided on top of them,
+/// `as_slice` and `as_mut_slice` return `None` unless overridden by the host tensors.
 pub trait Tensor<T: Prm>: Sized {
     /// Inner buffer type.
     type Buffer : Buffer<T>;
 
     /// Create unitialized tensor
-    unsafe fn new_uninit_in(context: &<Self::Buffer as Buffer<T>>::Context, shape: &Shape) -> Self;
+    ///
+    /// Useful for allocation of storage that is completely overwritten by a subsequent operation.
+    /// If the cost of initialization doesn't matter use safe `new_empty_in` instead.
+    ///
+    /// # Safety
+    ///
+    /// Content of the tensor is uninitialized and must be written before reading.
+    /// Reading uninitialized element is undefined behavior (e.g. it may contain invalid `bool` value).
+    unsafe fn new_uninit_in(context: &<Self::Buffer as Buffer<T>>::Context, shape: &Shape) -> Result<Self, Error>;
+    /// Create tensor which content is not meant to be used, safe alternative to `new_uninit_in`.
+    ///
+    /// The memory is zero-filled, so reading it is always defined.
+    fn new_empty_in(context: &<Self::Buffer as Buffer<T>>::Context, shape: &Shape) -> Result<Self, Error>;
     /// Create tensor filled with value on the specified hardware
-    fn new_filled_in(context: &<Self::Buffer as Buffer<T>>::Context, shape: &Shape, value: T) -> Self;
+    fn new_filled_in(context: &<Self::Buffer as Buffer<T>>::Context, shape: &Shape, value: T) -> Result<Self, Error>;
     /// Create tensor filled with zeros on the specified hardware
-    fn new_zeroed_in(context: &<Self::Buffer as Buffer<T>>::Context, shape: &Shape) -> Self;
+    ///
+    /// All constructors fail with `Error::Overflow` if the number of elements or the size of memory it occupies is too large.
+    fn new_zeroed_in(context: &<Self::Buffer as Buffer<T>>::Context, shape: &Shape) -> Result<Self, Error>;
+
+    /// Create tensor for scratch data of the same shape and on the same hardware as `self`, see `new_empty_in`.
+    ///
+    /// Contents are zeroed, so it is safe, but they are not meant to be read before being overwritten,
+    /// e.g. by `copy_from` or `store` in a loop that reuses the same scratch tensor.
+    fn empty_like(&self) -> Result<Self, Error> {
+        Self::new_empty_in(self.context(), self.shape())
+    }
+    /// Create tensor filled with zeros of the same shape and on the same hardware as `self`.
+    fn zeros_like(&self) -> Result<Self, Error> {
+        Self::new_zeroed_in(self.context(), self.shape())
+    }
+    /// Create tensor filled with ones of the same shape and on the same hardware as `self`.
+    fn ones_like(&self) -> Result<Self, Error> {
+        self.full_like(T::one())
+    }
+    /// Create tensor filled with value of the same shape and on the same hardware as `self`.
+    fn full_like(&self, value: T) -> Result<Self, Error> {
+        Self::new_filled_in(self.context(), self.shape(), value)
+    }
+    /// Create tensor on the specified hardware from raw bytes in the native representation, e.g. obtained by `as_bytes`
+    /// from shared memory or a memory-mapped file.
+    ///
+    /// The bytes are reinterpreted as elements without decoding and copied into the tensor once,
+    /// unaligned bytes are copied to an aligned temporary buffer first.
+    /// Fails with `Error::BadSize` if the number of bytes doesn't match the shape.
+    fn from_bytes_in(context: &<Self::Buffer as Buffer<T>>::Context, bytes: &[u8], shape: &Shape) -> Result<Self, Error> where T: Pod {
+        let len = shape.checked_content()?;
+        let size = len.checked_mul(mem::size_of::<T>()).ok_or(Error::Overflow)?;
+        if bytes.len() != size {
+            return Err(Error::BadSize { expected: size, found: bytes.len() });
+        }
+        let mut tensor = Self::new_empty_in(context, shape)?;
+        if len == 0 {
+            return Ok(tensor);
+        }
+        if (bytes.as_ptr() as *const T).is_aligned() {
+            // Bytes are aligned, have the size of `len` elements, and any bit pattern is valid for `Pod`.
+            tensor.store(unsafe { slice::from_raw_parts(bytes.as_ptr() as *const T, len) })?;
+        } else {
+            let mut data = vec![T::zero(); len];
+            // Buffer of `len` elements has the size of the bytes, and any bit pattern is valid for `Pod`.
+            unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), data.as_mut_ptr() as *mut u8, size) };
+            tensor.store(&data)?;
+        }
+        Ok(tensor)
+    }
+    /// Create tensor on the specified hardware by calling `f` with the multi-dimensional index of each element,
+    /// e.g. `|i| i[0] + 10 * i[1]`.
+    ///
+    /// Indices have `shape.len()` components and are visited in flattened order (the first axis is the fastest).
+    /// Elements are always computed on the host because closures can't be run on the device,
+    /// so for device tensors the whole content is built in the host memory and then uploaded.
+    fn from_fn_in<F: FnMut(&[usize]) -> T>(
+        context: &<Self::Buffer as Buffer<T>>::Context,
+        shape: &Shape,
+        mut f: F,
+    ) -> Result<Self, Error> {
+        let len = shape.checked_content()?;
+        let mut index = vec![0; shape.len()];
+        let mut tensor = Self::new_empty_in(context, shape)?;
+        tensor.store_iter((0..len).map(|_| {
+            let x = f(&index);
+            for (i, n) in index.iter_mut().zip(shape.iter()) {
+                *i += 1;
+                if *i < *n {
+                    break;
+                }
+                *i = 0;
+            }
+            x
+        }))?;
+        Ok(tensor)
+    }
 
+    /// Context of the hardware where the tensor is located, `()` for the host.
+    fn context(&self) -> &<Self::Buffer as Buffer<T>>::Context;
     /// Shape of the tensor - a slice containing all tensor dimensions.
     fn shape(&self) -> &Shape;
+    /// Number of elements in the tensor.
+    fn numel(&self) -> usize {
+        self.shape().content()
+    }
+    /// Number of tensors sharing the buffer with this one, including itself.
+    ///
+    /// It is a diagnostic aid for the copy-on-write: if it is greater than `1`,
+    /// modification of the tensor will copy the buffer first. Views returned by `view`, `slice`, `transpose`, etc.
+    /// share the buffer, so their buffers are also counted.
+    fn buffer_refcount(&self) -> usize;
+    /// Checks that the shape matches the pattern, where `None` matches any size.
+    ///
+    /// E.g. `t.assert_shape(&[None, Some(3)])` checks that the tensor has at most two dimensions and the second one is `3`.
+    /// See `Shape::check_pattern` for details.
+    fn assert_shape(&self, pattern: &[Option<usize>]) -> Result<(), Error> {
+        self.shape().check_pattern(pattern)
+    }
 
     /// Returns a new tensor that shares the same data but has other shape.
-    /// Failed if the product of all shape dimensions is not equal to buffer size.
-    fn reshape(&self, shape: &Shape) -> Self;
+    ///
+    /// Never copies the data. Fails if the product of all shape dimensions is not equal to tensor content
+    /// or if the data cannot be represented in a new shape without copying.
+    fn view(&self, shape: &Shape) -> Result<Self, Error>;
+    /// Returns a new tensor with the same data but other shape.
+    ///
+    /// Unlike `view` it may copy the data if it cannot be shared.
+    /// Fails if the product of all shape dimensions is not equal to tensor content.
+    fn reshape(&self, shape: &Shape) -> Result<Self, Error>;
+    /// Same as `reshape` but a single dimension could be `-1`, then it is inferred from the number of elements.
+    ///
+    /// E.g. tensor of shape `[4, 3]` reshaped to `[2, -1]` has shape `[2, 6]`.
+    /// Fails with `Error::BadArgument` if there are several `-1` or other negative dimensions,
+    /// or if the number of elements isn't divisible by the product of the other dimensions.
+    fn reshape_infer(&self, shape: &[isize]) -> Result<Self, Error> {
+        let mut known = 1usize;
+        let mut inferred = None;
+        for (i, &len) in shape.iter().enumerate() {
+            match len {
+                -1 if inferred.is_none() => inferred = Some(i),
+                -1 => return Err(Error::BadArgument(format!("only one dimension of {:?} could be inferred", shape))),
+                len if len < 0 => return Err(Error::BadArgument(format!("dimension {} of {:?} is negative", i, shape))),
+                len => known = known.checked_mul(len as usize).ok_or(Error::Overflow)?,
+            }
+        }
+        let mut dims = shape.iter().map(|&len| len as usize).collect::<Vec<_>>();
+        if let Some(i) = inferred {
+            let numel = self.numel();
+            if known == 0 || numel % known != 0 {
+                return Err(Error::BadArgument(format!("{} elements cannot be reshaped to {:?}", numel, shape)));
+            }
+            dims[i] = numel / known;
+        }
+        self.reshape(&Shape::from(dims))
+    }
+    /// Merges axes in the range `start..end` into a single axis at `start`, see `reshape`.
+    ///
+    /// E.g. merging axes `1..3` of `[2, 3, 4, 5]` gives `[2, 12, 5]`.
+    /// Fails with `Error::BadAxes` if `start` is greater than `end`.
+    fn flatten_range(&self, start: usize, end: usize) -> Result<Self, Error> {
+        if start > end {
+            return Err(Error::BadAxes(vec![start, end]));
+        }
+        let shape = self.shape();
+        let dims = (0..start).map(|i| shape[i])
+            .chain(Some(shape.slice(start..end).content()))
+            .chain((end..shape.len()).map(|i| shape[i]))
+            .collect::<Vec<_>>();
+        self.reshape(&Shape::from(dims))
+    }
+    /// Splits the `axis` into several axes of specified sizes, the inverse of `flatten_range`.
+    ///
+    /// E.g. unflattening axis `1` of `[2, 12]` into `[3, 4]` gives `[2, 3, 4]`.
+    /// Fails with `Error::BadSize` if the product of `sizes` isn't equal to the axis length
+    /// and with `Error::Overflow` if the product overflows.
+    fn unflatten(&self, axis: usize, sizes: &[usize]) -> Result<Self, Error> {
+        let shape = self.shape();
+        let (len, found) = (shape[axis], Shape::from(sizes).checked_content()?);
+        if len != found {
+            return Err(Error::BadSize { expected: len, found });
+        }
+        let dims = (0..axis).map(|i| shape[i])
+            .chain(sizes.iter().cloned())
+            .chain(((axis + 1)..shape.len()).map(|i| shape[i]))
+            .collect::<Vec<_>>();
+        self.reshape(&Shape::from(dims))
+    }
+
+    /// Returns a new tensor of other shape that contains the data of the original one aligned at the origin.
+    ///
+    /// Axes that shrink are cropped and axes that grow are padded with `fill`.
+    /// Fails with `Error::BadRank` if `shape` has fewer dimensions than the tensor.
+    fn resize(&self, shape: &[usize], fill: T) -> Result<Self, Error>;
+    /// Pads `axis` at the end with `value` up to the next multiple of `multiple`, e.g. a sequence length to a multiple of 8, see `resize`.
+    ///
+    /// Returns a view of the tensor if the length is already a multiple.
+    /// Fails with `Error::BadAxes` if `axis` isn't less than the number of dimensions,
+    /// with `Error::BadArgument` if `multiple` is zero and with `Error::Overflow` if the padded length doesn't fit in `usize`.
+    fn pad_to_multiple(&self, axis: usize, multiple: usize, value: T) -> Result<Self, Error> {
+        if axis >= self.shape().len() {
+            return Err(Error::BadAxes(vec![axis]));
+        }
+        if multiple == 0 {
+            return Err(Error::BadArgument("pad multiple must not be zero".into()));
+        }
+        let len = self.shape()[axis];
+        if len % multiple == 0 {
+            return self.slice(&[]);
+        }
+        let mut dims = self.shape().as_slice().to_vec();
+        dims[axis] = (len / multiple + 1).checked_mul(multiple).ok_or(Error::Overflow)?;
+        self.resize(&dims, value)
+    }
+
+    /// Returns a view of the tensor with permuted axes.
+    ///
+    /// Axis `i` of the resulting tensor is the axis `axes[i]` of the original one.
+    /// Axes that are not mentioned remain on their places.
+    /// Fails if `axes` is not a permutation of `0..axes.len()`.
+    fn permute(&self, axes: &[usize]) -> Result<Self, Error>;
+    /// Returns a view of the tensor with first two axes swapped.
+    fn transpose(&self) -> Self;
+    /// Returns a view of the tensor with axes `a` and `b` swapped, see `permute`.
+    ///
+    /// Axes must be less than the number of dimensions, though the first two axes are always valid as in `transpose`.
+    /// Fails with `Error::BadAxes` otherwise.
+    fn swapaxes(&self, a: usize, b: usize) -> Result<Self, Error> {
+        let mut axes = (0..permuted_rank(self.shape(), &[a, b])?).collect::<Vec<_>>();
+        axes.swap(a, b);
+        self.permute(&axes)
+    }
+    /// Returns a view of the tensor with axis `src` moved to position `dst`, the order of other axes is kept.
+    ///
+    /// Axes are validated as in `swapaxes`.
+    fn moveaxis(&self, src: usize, dst: usize) -> Result<Self, Error> {
+        let mut axes = (0..permuted_rank(self.shape(), &[src, dst])?).filter(|&i| i != src).collect::<Vec<_>>();
+        axes.insert(dst, src);
+        self.permute(&axes)
+    }
+    /// Returns a view of an interleaved image of shape `[c, w, h]` (HWC in row-major terms) as a planar one of shape `[w, h, c]`
+    /// (CHW in row-major terms), which is the layout expected by `interpolate2d`.
+    ///
+    /// Never copies the data, use `clone_contiguous` to place planes one after another in memory.
+    /// Fails with `Error::BadRank` if the tensor has more than three dimensions.
+    fn to_chw(&self) -> Result<Self, Error> {
+        check_image_rank(self.shape())?;
+        self.permute(&[1, 2, 0])
+    }
+    /// Returns a view of a planar image of shape `[w, h, c]` as an interleaved one of shape `[c, w, h]`, inverse of `to_chw`.
+    fn to_hwc(&self) -> Result<Self, Error> {
+        check_image_rank(self.shape())?;
+        self.permute(&[2, 0, 1])
+    }
+    /// Returns a view of a part of the tensor, never copies the data.
+    ///
+    /// Each index corresponds to an axis of the tensor (except `Index::NewAxis` which inserts a new one),
+    /// remaining axes are taken as a whole. E.g. `[Index::Single(0)]` takes the first slice along the first axis
+    /// and removes this axis, and `[Index::Range(Range { start: -1, end: isize::MIN, step: -1 })]` reverses it.
+    /// Out of bounds ranges are clamped, so they may be empty.
+    /// Fails with `Error::BadIndex` if a single index is out of bounds (a negative one is reported by its magnitude)
+    /// and with `Error::BadArgument` if a step is zero.
+    fn slice(&self, index: &[Index]) -> Result<Self, Error>;
+    /// Returns a view of sliding windows of length `size` taken along `axis` with `step` between their starts.
+    ///
+    /// The axis length becomes the number of windows `(len - size) / step + 1`
+    /// and a new axis of length `size` is appended after the last axis, e.g. `[10, 3]` unfolded along axis `0`
+    /// with `size = 4` and `step = 2` gives `[4, 3, 4]`. Windows share memory, the data is never copied.
+    /// Fails with `Error::BadArgument` if `size` or `step` is zero or if `size` is greater than the axis length.
+    fn unfold(&self, axis: usize, size: usize, step: usize) -> Result<Self, Error>;
+    /// Splits the tensor along `axis` into two views, the first one contains `mid` leading elements of the axis.
+    ///
+    /// Fails with `Error::BadIndex` if `mid` is greater than the axis length.
+    fn split_at(&self, axis: usize, mid: usize) -> Result<(Self, Self), Error> {
+        let len = self.shape()[axis];
+        if mid > len {
+            let mut pos = vec![0; axis + 1];
+            pos[axis] = mid;
+            return Err(Error::BadIndex(pos));
+        }
+        let whole = Index::Range(Range { start: 0, end: isize::MAX, step: 1 });
+        let mut index = vec![whole; axis + 1];
+        index[axis] = Index::Range(Range { start: 0, end: mid as isize, step: 1 });
+        let head = self.slice(&index)?;
+        index[axis] = Index::Range(Range { start: mid as isize, end: len as isize, step: 1 });
+        Ok((head, self.slice(&index)?))
+    }
+    /// Returns a view of the tensor with new axes of size `1` inserted at the specified positions.
+    ///
+    /// Positions are given in the axes of the result, e.g. expanding a tensor of shape `[2, 3]` at `[0, 2]` gives `[1, 2, 1, 3]`.
+    /// Fails with `Error::BadAxes` if positions are repeated or not less than the resulting number of dimensions.
+    fn expand_dims(&self, axes: &[usize]) -> Result<Self, Error> {
+        let rank = self.shape().len() + axes.len();
+        let mut sorted = axes.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if sorted.len() != axes.len() || sorted.last().is_some_and(|&a| a >= rank) {
+            return Err(Error::BadAxes(axes.to_vec()));
+        }
+        let whole = Index::Range(Range { start: 0, end: isize::MAX, step: 1 });
+        let index = (0..rank).map(|i| if sorted.binary_search(&i).is_ok() { Index::NewAxis } else { whole }).collect::<Vec<_>>();
+        self.slice(&index)
+    }
+    /// Returns a view of the tensor broadcasted to specified shape, see `Shape::broadcast`.
+    ///
+    /// Elements along broadcasted axes share the same memory.
+    /// Fails with `Error::ShapeMismatch` if the tensor cannot be broadcasted to the shape.
+    fn broadcast_to(&self, shape: &Shape) -> Result<Self, Error>;
+    /// Coordinate grids built from vectors, one grid per input, all of them have the same shape determined by `indexing`.
+    ///
+    /// Grids are broadcasted views of the inputs, make a contiguous copy to modify them independently.
+    /// Fails with `Error::BadRank` if any input isn't a vector.
+    fn meshgrid(xs: &[&Self], indexing: MeshIndexing) -> Result<Vec<Self>, Error> {
+        if let Some(x) = xs.iter().find(|x| x.shape().len() > 1) {
+            return Err(Error::BadRank { expected: 1, found: x.shape().len() });
+        }
+        let mut axes = (0..xs.len()).collect::<Vec<_>>();
+        if indexing == MeshIndexing::Xy && xs.len() >= 2 {
+            axes.swap(0, 1);
+        }
+        let mut dims = vec![1; xs.len()];
+        for (x, &a) in xs.iter().zip(axes.iter()) {
+            dims[a] = x.shape()[0];
+        }
+        let shape = Shape::from(dims);
+        xs.iter().zip(axes).map(|(x, a)| {
+            let mut dims = vec![1; a + 1];
+            dims[a] = x.shape()[0];
+            x.reshape(&Shape::from(dims))?.broadcast_to(&shape)
+        }).collect()
+    }
+
+    /// Returns a copy of the tensor in a freshly allocated buffer in flattened order.
+    ///
+    /// Unlike `clone`, which is cheap because the buffer is shared until one of the tensors is modified,
+    /// it always copies the data. Prefer it to deliberately break the sharing or
+    /// to release the memory of a large buffer when only a small view of it is needed.
+    fn clone_contiguous(&self) -> Result<Self, Error>;
+    /// Copies the tensor to a right-sized buffer if it uses only a small part of its buffer, see `clone_contiguous`.
+    ///
+    /// A small view, e.g. a row sliced from a large batch, keeps the whole buffer alive.
+    /// The tensor is copied if it has fewer than half as many elements as the buffer, otherwise a cheap clone is returned,
+    /// so calling it after slicing doesn't copy views that would save little memory.
+    /// The large buffer is freed when no other tensor refers to it.
+    fn compact(&self) -> Result<Self, Error>;
+    /// Overwrites elements of the tensor with elements of `src` without reallocation if the buffer isn't shared.
+    ///
+    /// If elements of the tensor overlap in memory (e.g. it is broadcasted), the tensor is made plain first.
+    /// Device tensors could be copied between different contexts.
+    /// Fails with `Error::ShapeMismatch` if tensors have different shapes.
+    fn copy_from(&mut self, src: &Self) -> Result<(), Error>;
+    /// Overwrites the sub-region selected by `ranges` along the leading axes with elements of `src`, e.g. to place a patch into an image.
+    ///
+    /// Ranges are interpreted as in `slice`. The buffer is cloned first if it is shared with other tensors.
+    /// `src` is broadcasted to the sub-region, so it could be a scalar or a lower-rank tensor, e.g. a row repeated over rows.
+    /// Fails with `Error::ShapeMismatch` if `src` cannot be broadcasted to the shape of the sub-region.
+    fn assign_slice(&mut self, ranges: &[Range], src: &Self) -> Result<(), Error>;
+    /// Fills the tensor with arithmetic sequence `start, start + step, ...` in flattened order.
+    ///
+    /// The tensor is made plain first, so views and broadcasted tensors get their own buffer.
+    /// See `Buffer::fill_iota` for the overflow behavior.
+    fn fill_iota(&mut self, start: T, step: T) -> Result<(), Error> where T: Num;
 
     /// Load flattened data from tensor to slice.
-    fn load(&self, dst: &mut [T]);
+    /// Fails with `Error::BadSize` if the slice length isn't equal to the tensor content.
+    fn load(&self, dst: &mut [T]) -> Result<(), Error>;
     /// Store data from slice to a tensor in a flattened manner.
-    fn store(&mut self, src: &[T]);
+    /// If elements of the tensor overlap in memory (e.g. it is broadcasted), the tensor is made plain first.
+    /// Fails with `Error::BadSize` if the slice length isn't equal to the tensor content.
+    fn store(&mut self, src: &[T]) -> Result<(), Error>;
+    /// Store elements produced by the iterator to a tensor in a flattened manner.
+    ///
+    /// Host tensors are written directly through `as_mut_slice`, the others collect the elements and `store` them.
+    /// At most one element beyond the tensor content is taken from the iterator, so it may be infinite.
+    /// Fails with `Error::BadSize` if the iterator yields a different number of elements,
+    /// `found` is limited to the content plus one then, and the tensor content is unspecified.
+    fn store_iter<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Result<(), Error> {
+        let len = self.numel();
+        let mut iter = iter.into_iter();
+        let found = match self.as_mut_slice() {
+            Some(dst) => dst.iter_mut().zip(&mut iter).map(|(d, x)| *d = x).count(),
+            None => {
+                let src = (&mut iter).take(len).collect::<Vec<_>>();
+                if src.len() == len {
+                    self.store(&src)?;
+                }
+                src.len()
+            }
+        };
+        let found = found + iter.next().map_or(0, |_| 1);
+        if found != len {
+            return Err(Error::BadSize { expected: len, found });
+        }
+        Ok(())
+    }
+
+    /// Elements of the tensor in flattened order as a slice without copying.
+    ///
+    /// Returns `None` for device tensors, whose memory isn't accessible from the host,
+    /// and for host tensors which elements aren't contiguous in memory (e.g. transposed views).
+    fn as_slice(&self) -> Option<&[T]> {
+        None
+    }
+    /// Elements of the tensor in flattened order as raw bytes in the native representation without copying,
+    /// e.g. to put them into shared memory for another process.
+    ///
+    /// Byte order is the native one of the platform, unlike `store_to_writer` which always writes little-endian.
+    /// Returns `None` in the same cases as `as_slice`.
+    fn as_bytes(&self) -> Option<&[u8]> where T: Pod {
+        // `Pod` types have no padding, so all the bytes are initialized.
+        self.as_slice().map(|s| unsafe { slice::from_raw_parts(s.as_ptr() as *const u8, mem::size_of_val(s)) })
+    }
+    /// Mutable slice of tensor elements in flattened order.
+    ///
+    /// Host tensors always return `Some`, the buffer is cloned first if it is shared with other tensors
+    /// or if the elements aren't contiguous, so modification never affects other tensors.
+    /// Returns `None` for device tensors.
+    fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        None
+    }
+
+    /// Loads flattened data from tensor to a new vector.
+    fn to_vec(&self) -> Result<Vec<T>, Error> {
+        let mut dst = vec![T::zero(); self.numel()];
+        self.load(&mut dst)?;
+        Ok(dst)
+    }
+    /// Moves flattened data out of the tensor to a vector, see `to_vec`.
+    ///
+    /// Host tensors that are plain and don't share the buffer with other tensors give their buffer away without copying,
+    /// the others, including device tensors, are copied to a new vector as in `to_vec`.
+    fn try_into_vec(self) -> Result<Vec<T>, Error> {
+        self.to_vec()
+    }
+    /// Sets all elements of the tensor to `value`.
+    fn fill(&mut self, value: T) -> Result<(), Error> {
+        self.store(&vec![value; self.numel()])
+    }
+    /// Sets all elements of the tensor to zero.
+    fn fill_zero(&mut self) -> Result<(), Error> {
+        self.fill(T::zero())
+    }
+
+    /// 64-bit FNV-1a hash of the elements in little-endian representation in flattened order, e.g. to verify saved data.
+    ///
+    /// Unlike `Hash`, the value is stable: it doesn't depend on the platform, the run or the crate version,
+    /// and it is the hash of the bytes written by `store_to_writer`, so it could be computed for the file as well.
+    /// The shape isn't hashed, so tensors of different shapes with the same elements have the same checksum.
+    /// Device tensors are copied to the host first.
+    fn checksum(&self) -> Result<u64, Error> where T: LeBytes {
+        let mut hash = FNV1A_BASIS;
+        let mut bytes = vec![0; T::SIZE];
+        for x in self.to_vec()? {
+            x.write_le(&mut bytes);
+            for &b in bytes.iter() {
+                hash = (hash ^ b as u64).wrapping_mul(FNV1A_PRIME);
+            }
+        }
+        Ok(hash)
+    }
+
+    /// Detailed comparison with the `reference` of the same shape, e.g. of a device result with a host one.
+    ///
+    /// Unlike a boolean check it reports the largest differences and where they are, see `ComparisonReport` for tolerances.
+    /// Both tensors are loaded to the host, so they may be of different kinds.
+    /// Fails with `Error::ShapeMismatch` if shapes are different.
+    fn compare_report<R: Tensor<T>>(&self, reference: &R, atol: T, rtol: T) -> Result<ComparisonReport<T>, Error> where T: Float {
+        if self.shape() != reference.shape() {
+            return Err(Error::ShapeMismatch(self.shape().clone(), reference.shape().clone()));
+        }
+        Ok(ComparisonReport::new(self.shape(), &self.to_vec()?, &reference.to_vec()?, atol, rtol))
+    }
 }